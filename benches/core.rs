@@ -0,0 +1,149 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use nestify::core::bus::Bus;
+use nestify::core::cartridge::Cartridge;
+use nestify::core::clock::Clock;
+use nestify::core::cpu::Cpu;
+use nestify::core::emulator::Emulator;
+use nestify::core::memory::Memory;
+use nestify::core::ppu::{Mirroring, Ppu};
+use nestify::core::trace::TraceSink;
+
+const INSTRUCTION_COUNT: usize = 10_000;
+const DOTS_PER_FRAME: usize = 341 * 262;
+
+fn load_program(bus: &Rc<RefCell<Bus>>, program: &[u8]) {
+    for (address, byte) in program.iter().enumerate() {
+        bus.borrow_mut()
+            .cpu_memory_map()
+            .write(address as u16, *byte);
+    }
+}
+
+fn cpu_loop(c: &mut Criterion) {
+    let cartridge = Cartridge::empty();
+    let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+    let ppu = Rc::new(RefCell::new(Ppu::new(&bus, cartridge.get_mirroring())));
+    let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
+    let mut cpu = Cpu::new(&bus, &clock);
+
+    // LDX #$00; INX; STX $10; LDA $10; ADC #$01; BNE $0002; JMP $0000
+    load_program(&bus, &[
+        0xA2, 0x00, 0xE8, 0x86, 0x10, 0xA5, 0x10, 0x69,
+        0x01, 0xD0, 0xF7, 0x4C, 0x00, 0x00,
+    ]);
+
+    // Reported as instructions per second
+    let mut group = c.benchmark_group("cpu");
+    group.throughput(Throughput::Elements(INSTRUCTION_COUNT as u64));
+    group.bench_function("cpu_10k_instructions", |b| {
+        b.iter(|| {
+            cpu.set_program_counter(0x0000);
+
+            for _ in 0..INSTRUCTION_COUNT {
+                cpu.fetch();
+            }
+        })
+    });
+
+    // Same loop with trace lines formatted and dropped, the gap is what tracing costs
+    cpu.use_disassembler_with(TraceSink::callback(|line| {
+        black_box(line);
+    }));
+    group.bench_function("cpu_10k_instructions_traced", |b| {
+        b.iter(|| {
+            cpu.set_program_counter(0x0000);
+
+            for _ in 0..INSTRUCTION_COUNT {
+                cpu.fetch();
+            }
+        })
+    });
+    group.finish();
+}
+
+// Internal RAM is the hottest path of the CPU's memory map
+fn cpu_ram_loop(c: &mut Criterion) {
+    let cartridge = Cartridge::empty();
+    let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+    let ppu = Rc::new(RefCell::new(Ppu::new(&bus, cartridge.get_mirroring())));
+    let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
+    let mut cpu = Cpu::new(&bus, &clock);
+
+    // LDX #$00; LDA $0300,X; STA $0400,X; INC $10; DEX; BNE $0002; JMP $0000
+    load_program(&bus, &[
+        0xA2, 0x00, 0xBD, 0x00, 0x03, 0x9D, 0x00, 0x04,
+        0xE6, 0x10, 0xCA, 0xD0, 0xF5, 0x4C, 0x00, 0x00,
+    ]);
+
+    let mut group = c.benchmark_group("cpu");
+    group.throughput(Throughput::Elements(INSTRUCTION_COUNT as u64));
+    group.bench_function("cpu_ram_10k_instructions", |b| {
+        b.iter(|| {
+            cpu.set_program_counter(0x0000);
+
+            for _ in 0..INSTRUCTION_COUNT {
+                cpu.fetch();
+            }
+        })
+    });
+    group.finish();
+}
+
+fn ppu_frame(c: &mut Criterion) {
+    let cartridge = Cartridge::empty();
+    let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+    let mut ppu = Ppu::new(&bus, Mirroring::Horizontal);
+
+    ppu.write_address(0x20);
+    ppu.write_address(0x00);
+    for tile in 0..0x3C0 {
+        ppu.write_data(tile as u8);
+    }
+
+    ppu.write_address(0x3F);
+    ppu.write_address(0x00);
+    for color in 0..0x20 {
+        ppu.write_data(color);
+    }
+
+    // Show background and sprites, including the leftmost 8 pixels
+    ppu.write_mask(0x1E);
+
+    c.bench_function("ppu_tick_frame", |b| {
+        b.iter(|| {
+            for _ in 0..DOTS_PER_FRAME {
+                ppu.tick(1);
+            }
+
+            black_box(ppu.get_screen_buffer());
+        })
+    });
+}
+
+fn emulator_frame(c: &mut Criterion) {
+    c.bench_function("emulator_run_frame", |b| {
+        b.iter_batched(
+            || {
+                let cartridge = Cartridge::empty();
+                let emulator = Emulator::new(&cartridge);
+
+                // LDA #$80; STA $2000; JMP $0005
+                load_program(emulator.bus(), &[0xA9, 0x80, 0x8D, 0x00, 0x20, 0x4C, 0x05, 0x00]);
+
+                emulator
+            },
+            |mut emulator| {
+                emulator.run_frame();
+                emulator
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, cpu_loop, cpu_ram_loop, ppu_frame, emulator_frame);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use nestify::core::cartridge::Cartridge;
+use nestify::core::emulator::Emulator;
+use nestify::gui::videobuffer::VideoBuffer;
+
+fn frame_to_rgb(c: &mut Criterion) {
+    let cartridge = Cartridge::empty();
+    let emulator = Emulator::new(&cartridge);
+    let frame = emulator.frame();
+    let mut videobuffer = VideoBuffer::new(frame.width(), frame.height());
+
+    c.bench_function("frame_to_rgb", |b| {
+        b.iter(|| {
+            videobuffer.draw_frame(black_box(&frame));
+            black_box(videobuffer.get());
+        })
+    });
+}
+
+criterion_group!(benches, frame_to_rgb);
+criterion_main!(benches);
@@ -0,0 +1,114 @@
+// Volume of the pulse and noise channels, either constant or decaying from 15
+#[derive(Clone)]
+pub struct Envelope {
+    start: bool,
+    looping: bool,
+    constant_volume: bool,
+    // Constant volume or the divider period, depending on the flag
+    period: u8,
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    pub fn new() -> Self {
+        Self {
+            start: false,
+            looping: false,
+            constant_volume: false,
+            period: 0,
+            divider: 0,
+            decay: 0,
+        }
+    }
+
+    // Takes the first register of the channel, bit 5 doubles as the length counter halt flag
+    pub fn write(&mut self, data: u8) {
+        self.looping = data & 0x20 != 0;
+        self.constant_volume = data & 0x10 != 0;
+        self.period = data & 0x0F;
+    }
+
+    // Writing the length of the channel restarts the decay on the next quarter frame
+    pub fn restart(&mut self) {
+        self.start = true;
+    }
+
+    // Clocked every quarter frame, the decay level drops once every `period + 1` clocks
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.period;
+            return;
+        }
+
+        if self.divider > 0 {
+            self.divider -= 1;
+            return;
+        }
+
+        self.divider = self.period;
+
+        if self.decay > 0 {
+            self.decay -= 1;
+        } else if self.looping {
+            self.decay = 15;
+        }
+    }
+
+    pub fn volume(&self) -> u8 {
+        if self.constant_volume {
+            self.period
+        } else {
+            self.decay
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volumes(envelope: &mut Envelope, clocks: usize) -> Vec<u8> {
+        (0..clocks).map(|_| {
+            envelope.clock();
+            envelope.volume()
+        }).collect()
+    }
+
+    #[test]
+    fn test_envelope_decays_every_period_plus_one_clocks() {
+        let mut envelope = Envelope::new();
+        envelope.write(0x03);
+        envelope.restart();
+
+        let volumes = volumes(&mut envelope, 1 + 4 * 16);
+
+        assert_eq!(volumes[0], 15, "Restarted envelope should start at 15!");
+        assert_eq!(volumes[1..5], [15, 15, 15, 14], "Decay should drop once every 4 clocks!");
+        assert_eq!(volumes[60], 0, "Decay should reach 0 after 15 steps!");
+        assert_eq!(volumes[64], 0, "Decay should stay at 0 without looping!");
+    }
+
+    #[test]
+    fn test_envelope_loops_back_to_15() {
+        let mut envelope = Envelope::new();
+        envelope.write(0x20);
+        envelope.restart();
+
+        let volumes = volumes(&mut envelope, 18);
+
+        assert_eq!(volumes[15], 0, "Period 0 should drop the decay every clock!");
+        assert_eq!(volumes[16], 15, "Looping envelope should wrap back to 15!");
+    }
+
+    #[test]
+    fn test_constant_volume_ignores_decay() {
+        let mut envelope = Envelope::new();
+        envelope.write(0x17);
+        envelope.restart();
+
+        assert_eq!(volumes(&mut envelope, 20), [7; 20], "Constant volume should come from the low bits!");
+    }
+}
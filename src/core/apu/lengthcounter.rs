@@ -0,0 +1,97 @@
+// Half frames a channel plays for, indexed by the top 5 bits of its last register
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14,
+    12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+// Silences a channel once it counted down, unless halted
+#[derive(Clone)]
+pub struct LengthCounter {
+    enabled: bool,
+    halted: bool,
+    value: u8,
+}
+
+impl LengthCounter {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            halted: false,
+            value: 0,
+        }
+    }
+
+    // Driven by $4015, disabling clears the counter and ignores loads until enabled again
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+
+        if !enabled {
+            self.value = 0;
+        }
+    }
+
+    pub fn set_halted(&mut self, halted: bool) {
+        self.halted = halted;
+    }
+
+    pub fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.value = LENGTH_TABLE[index as usize & 0x1F];
+        }
+    }
+
+    // Clocked every half frame
+    pub fn clock(&mut self) {
+        if !self.halted && self.value > 0 {
+            self.value -= 1;
+        }
+    }
+
+    pub fn is_silenced(&self) -> bool {
+        self.value == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_counter_counts_down() {
+        let mut length_counter = LengthCounter::new();
+        length_counter.set_enabled(true);
+        length_counter.load(0x03);
+
+        length_counter.clock();
+        assert!(!length_counter.is_silenced(), "Index 3 should last 2 half frames!");
+
+        length_counter.clock();
+        assert!(length_counter.is_silenced(), "Counter should silence the channel at 0!");
+
+        length_counter.clock();
+        assert!(length_counter.is_silenced(), "Counter should not wrap below 0!");
+    }
+
+    #[test]
+    fn test_halted_length_counter_keeps_value() {
+        let mut length_counter = LengthCounter::new();
+        length_counter.set_enabled(true);
+        length_counter.set_halted(true);
+        length_counter.load(0x03);
+
+        (0..10).for_each(|_| length_counter.clock());
+        assert!(!length_counter.is_silenced(), "Halted counter should not count down!");
+    }
+
+    #[test]
+    fn test_disabled_length_counter_ignores_loads() {
+        let mut length_counter = LengthCounter::new();
+        length_counter.load(0x01);
+        assert!(length_counter.is_silenced(), "Loads should be ignored while disabled!");
+
+        length_counter.set_enabled(true);
+        length_counter.load(0x01);
+        length_counter.set_enabled(false);
+        assert!(length_counter.is_silenced(), "Disabling should clear the counter!");
+    }
+}
@@ -0,0 +1,272 @@
+mod envelope;
+mod lengthcounter;
+mod pulse;
+mod sweep;
+mod triangle;
+mod waveform;
+
+pub use self::waveform::{WaveformChannel, Waveforms, WAVEFORM_SAMPLES};
+
+use self::pulse::{Pulse, PulseChannel};
+use self::triangle::Triangle;
+
+// CPU cycles of the 4-step frame sequence, every step clocks the envelopes
+// and the second and last also clock the length counters
+const FRAME_QUARTER_STEPS: [usize; 2] = [7457, 22371];
+const FRAME_HALF_STEPS: [usize; 2] = [14913, 29829];
+const FRAME_SEQUENCE_CYCLES: usize = 29830;
+
+/// Audio processing unit of the 2A03, noise and DMC aren't emulated yet.
+#[derive(Clone)]
+pub struct Apu {
+    pulse1: Pulse,
+    pulse2: Pulse,
+    triangle: Triangle,
+    frame_cycles: usize,
+    // Channel timers run at half the CPU rate
+    odd_cycle: bool,
+    // Only recorded while the waveform view is open
+    waveforms: Option<Box<Waveforms>>,
+}
+
+impl Apu {
+    pub fn new() -> Self {
+        Self {
+            pulse1: Pulse::new(PulseChannel::One),
+            pulse2: Pulse::new(PulseChannel::Two),
+            triangle: Triangle::new(),
+            frame_cycles: 0,
+            odd_cycle: false,
+            waveforms: None,
+        }
+    }
+
+    /// Handles a CPU write to $4000-$4017, registers of channels that aren't emulated are ignored.
+    pub fn write_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x4000..=0x4003 => self.pulse1.write_register(address - 0x4000, data),
+            0x4004..=0x4007 => self.pulse2.write_register(address - 0x4004, data),
+            0x4008..=0x400B => self.triangle.write_register(address - 0x4008, data),
+            0x4015 => {
+                self.pulse1.set_enabled(data & 0x01 != 0);
+                self.pulse2.set_enabled(data & 0x02 != 0);
+                self.triangle.set_enabled(data & 0x04 != 0);
+            },
+            _ => (),
+        }
+    }
+
+    pub fn tick(&mut self, cpu_cycles: usize) {
+        for _ in 0..cpu_cycles {
+            self.step_cycle();
+        }
+    }
+
+    fn step_cycle(&mut self) {
+        self.triangle.clock_timer();
+
+        if self.odd_cycle {
+            self.pulse1.clock_timer();
+            self.pulse2.clock_timer();
+        }
+
+        self.odd_cycle = !self.odd_cycle;
+        self.frame_cycles += 1;
+
+        if FRAME_QUARTER_STEPS.contains(&self.frame_cycles) {
+            self.clock_quarter_frame();
+        }
+
+        if FRAME_HALF_STEPS.contains(&self.frame_cycles) {
+            self.clock_quarter_frame();
+            self.clock_half_frame();
+        }
+
+        if self.frame_cycles == FRAME_SEQUENCE_CYCLES {
+            self.frame_cycles = 0;
+        }
+
+        if let Some(waveforms) = self.waveforms.as_mut() {
+            if waveforms.tick() {
+                let (pulse1, pulse2, triangle) = (self.pulse1.output(), self.pulse2.output(), self.triangle.output());
+
+                waveforms.record([
+                    pulse1 as f32 / 15.0,
+                    pulse2 as f32 / 15.0,
+                    triangle as f32 / 15.0,
+                    mix(pulse1, pulse2, triangle) / mix(15, 15, 15),
+                ]);
+            }
+        }
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulse1.clock_quarter_frame();
+        self.pulse2.clock_quarter_frame();
+        self.triangle.clock_quarter_frame();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.pulse1.clock_half_frame();
+        self.pulse2.clock_half_frame();
+        self.triangle.clock_half_frame();
+    }
+
+    // Opening the view starts from empty traces, closing it frees them
+    pub fn set_waveform_taps(&mut self, enabled: bool) {
+        self.waveforms = enabled.then(|| Box::new(Waveforms::new()));
+    }
+
+    pub fn waveforms(&self) -> Option<&Waveforms> {
+        self.waveforms.as_deref()
+    }
+
+    /// Current output of the mixer, between 0.0 and about 0.5 with the emulated channels.
+    pub fn sample(&self) -> f32 {
+        mix(self.pulse1.output(), self.pulse2.output(), self.triangle.output())
+    }
+}
+
+// Non-linear DAC of the 2A03, the pulses share one output and the other channels another
+fn mix(pulse1: u8, pulse2: u8, triangle: u8) -> f32 {
+    let pulse = (pulse1 + pulse2) as f32;
+    let triangle = triangle as f32;
+
+    let pulse_out = if pulse > 0.0 {
+        95.88 / (8128.0 / pulse + 100.0)
+    } else {
+        0.0
+    };
+
+    let tnd_out = if triangle > 0.0 {
+        159.79 / (1.0 / (triangle / 8227.0) + 100.0)
+    } else {
+        0.0
+    };
+
+    pulse_out + tnd_out
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Loudest pulse output over one full sequencer period
+    fn loudest_sample(apu: &mut Apu) -> u8 {
+        (0..18 * 8).map(|_| {
+            apu.tick(1);
+            apu.pulse1.output() + apu.pulse2.output()
+        }).max().unwrap()
+    }
+
+    #[test]
+    fn test_frame_sequence_clocks_pulse_length_counter() {
+        let mut apu = Apu::new();
+        apu.write_register(0x4015, 0x01);
+        apu.write_register(0x4000, 0xDF);
+        apu.write_register(0x4002, 0x08);
+        // Length index 3 lasts 2 half frames
+        apu.write_register(0x4003, 0x18);
+
+        apu.tick(FRAME_HALF_STEPS[0]);
+        assert_eq!(loudest_sample(&mut apu), 15, "Pulse should still play after the first half frame!");
+
+        apu.tick(FRAME_HALF_STEPS[1] - FRAME_HALF_STEPS[0]);
+        assert_eq!(loudest_sample(&mut apu), 0, "Pulse should be silenced after the second half frame!");
+    }
+
+    #[test]
+    fn test_disabled_pulse_is_silent() {
+        let mut apu = Apu::new();
+        apu.write_register(0x4000, 0xDF);
+        apu.write_register(0x4002, 0x08);
+        apu.write_register(0x4003, 0x18);
+
+        assert_eq!(loudest_sample(&mut apu), 0, "Pulse should stay silent until enabled through $4015!");
+    }
+
+    #[test]
+    fn test_pulse_2_plays_through_its_own_registers() {
+        let mut apu = Apu::new();
+        apu.write_register(0x4015, 0x02);
+        apu.write_register(0x4000, 0xDF);
+        apu.write_register(0x4002, 0x08);
+        apu.write_register(0x4003, 0x18);
+        assert_eq!(loudest_sample(&mut apu), 0, "Pulse 1 should stay silent with only pulse 2 enabled!");
+
+        apu.write_register(0x4004, 0xD7);
+        apu.write_register(0x4006, 0x08);
+        apu.write_register(0x4007, 0x18);
+        assert_eq!(loudest_sample(&mut apu), 7, "Pulse 2 should play at its own volume!");
+    }
+
+    #[test]
+    fn test_half_frames_clock_the_sweep() {
+        let mut apu = Apu::new();
+        apu.write_register(0x4015, 0x01);
+        apu.write_register(0x4000, 0xFF);
+        apu.write_register(0x4002, 0xF0);
+        apu.write_register(0x4003, 0x03);
+        apu.write_register(0x4001, 0x81);
+        assert_eq!(loudest_sample(&mut apu), 15, "Period $3F0 should play while its target is in range!");
+
+        // The first half frame raises the period to $5E8, whose target is out of range
+        apu.tick(FRAME_HALF_STEPS[0]);
+        assert_eq!(loudest_sample(&mut apu), 0, "Sweep should mute once the target passes $7FF!");
+    }
+
+    #[test]
+    fn test_triangle_is_mixed_with_the_pulses() {
+        let mut apu = Apu::new();
+        apu.write_register(0x4015, 0x04);
+        apu.write_register(0x4008, 0x81);
+        apu.write_register(0x400A, 0x10);
+        apu.write_register(0x400B, 0x08);
+
+        assert_eq!(apu.sample(), mix(0, 0, 15), "Triangle should start at level 15!");
+
+        // Timer runs every CPU cycle, once the linear counter is loaded
+        apu.tick(FRAME_QUARTER_STEPS[0] + 17 * 4);
+        assert_eq!(apu.triangle.output(), 11, "Triangle should step down with period $10!");
+        assert_eq!(apu.sample(), mix(0, 0, 11), "Mixer should follow the triangle level!");
+    }
+
+    #[test]
+    fn test_waveform_taps_only_record_while_enabled() {
+        let mut apu = Apu::new();
+        apu.write_register(0x4015, 0x01);
+        apu.write_register(0x4000, 0xDF);
+        apu.write_register(0x4002, 0x08);
+        apu.write_register(0x4003, 0x18);
+
+        apu.tick(1000);
+        assert!(apu.waveforms().is_none(), "Taps should start disabled!");
+
+        apu.set_waveform_taps(true);
+        let is_silent = |apu: &Apu, channel| apu.waveforms().unwrap().samples(channel).iter().all(|level| *level == 0.0);
+        assert!(is_silent(&apu, WaveformChannel::Pulse1), "Nothing should be recorded before the taps are enabled!");
+
+        apu.tick(1000);
+        assert!(!is_silent(&apu, WaveformChannel::Pulse1), "Enabled taps should record pulse 1!");
+        assert!(!is_silent(&apu, WaveformChannel::Mix), "Enabled taps should record the mix!");
+        assert!(is_silent(&apu, WaveformChannel::Pulse2), "Silent pulse 2 should record a flat trace!");
+
+        apu.set_waveform_taps(false);
+        apu.tick(1000);
+        assert!(apu.waveforms().is_none(), "Disabled taps should not record!");
+    }
+
+    #[test]
+    fn test_mixer_levels() {
+        assert_eq!(mix(0, 0, 0), 0.0, "Silence should mix to 0!");
+        assert!((mix(15, 15, 0) - 0.2585).abs() < 0.001, "Both pulses at full volume should mix to about 0.26!");
+        assert!((mix(0, 0, 15) - 0.2464).abs() < 0.001, "Full triangle should mix to about 0.25!");
+        assert!(mix(15, 0, 0) * 2.0 > mix(15, 15, 0), "Pulse mixing should be non-linear!");
+    }
+}
@@ -0,0 +1,124 @@
+use super::envelope::Envelope;
+use super::lengthcounter::LengthCounter;
+use super::sweep::Sweep;
+
+// Waveforms of the four duty cycles in the order they are played, the last one is 25% negated
+const DUTY_SEQUENCES: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PulseChannel {
+    One,
+    Two,
+}
+
+// Square wave channel, $4000-$4003 for pulse 1 and $4004-$4007 for pulse 2
+#[derive(Clone)]
+pub struct Pulse {
+    duty: u8,
+    duty_step: u8,
+    sweep: Sweep,
+    timer_period: u16,
+    timer: u16,
+    envelope: Envelope,
+    length_counter: LengthCounter,
+}
+
+impl Pulse {
+    pub fn new(channel: PulseChannel) -> Self {
+        Self {
+            duty: 0,
+            duty_step: 0,
+            sweep: Sweep::new(channel == PulseChannel::One),
+            timer_period: 0,
+            timer: 0,
+            envelope: Envelope::new(),
+            length_counter: LengthCounter::new(),
+        }
+    }
+
+    // `register` is the offset from the first register of the channel
+    pub fn write_register(&mut self, register: u16, data: u8) {
+        match register & 0x03 {
+            0 => {
+                self.duty = data >> 6;
+                self.length_counter.set_halted(data & 0x20 != 0);
+                self.envelope.write(data);
+            },
+            1 => self.sweep.write(data),
+            2 => self.timer_period = (self.timer_period & 0x0700) | data as u16,
+            _ => {
+                self.timer_period = (self.timer_period & 0x00FF) | ((data as u16 & 0x07) << 8);
+                self.length_counter.load(data >> 3);
+                self.duty_step = 0;
+                self.envelope.restart();
+            },
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    // Clocked every other CPU cycle, the sequencer steps when the timer wraps
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.timer_period;
+        self.duty_step = (self.duty_step + 1) % 8;
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+        self.timer_period = self.sweep.clock(self.timer_period);
+    }
+
+    pub fn output(&self) -> u8 {
+        let is_muted = self.length_counter.is_silenced() || self.sweep.is_muting(self.timer_period);
+
+        if is_muted || DUTY_SEQUENCES[self.duty as usize][self.duty_step as usize] == 0 {
+            0
+        } else {
+            self.envelope.volume()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Output at each sequencer step, with the timer stepping every 9 clocks
+    fn waveform(duty: u8) -> Vec<u8> {
+        let mut pulse = Pulse::new(PulseChannel::One);
+        pulse.set_enabled(true);
+        pulse.write_register(0, (duty << 6) | 0x3F);
+        pulse.write_register(2, 0x08);
+        pulse.write_register(3, 0x08);
+
+        (0..8).map(|_| {
+            let output = pulse.output();
+            (0..9).for_each(|_| pulse.clock_timer());
+            output
+        }).collect()
+    }
+
+    #[test]
+    fn test_duty_waveforms() {
+        assert_eq!(waveform(0), [0, 15, 0, 0, 0, 0, 0, 0], "Duty 0 should be high 12.5% of the time!");
+        assert_eq!(waveform(1), [0, 15, 15, 0, 0, 0, 0, 0], "Duty 1 should be high 25% of the time!");
+        assert_eq!(waveform(2), [0, 15, 15, 15, 15, 0, 0, 0], "Duty 2 should be high 50% of the time!");
+        assert_eq!(waveform(3), [15, 0, 0, 15, 15, 15, 15, 15], "Duty 3 should be high 75% of the time!");
+    }
+}
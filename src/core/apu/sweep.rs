@@ -0,0 +1,116 @@
+// Bends the timer period of a pulse channel up or down every few half frames
+#[derive(Clone)]
+pub struct Sweep {
+    enabled: bool,
+    period: u8,
+    negate: bool,
+    shift: u8,
+    divider: u8,
+    reload: bool,
+    // Pulse 1 negates with one's complement, so it subtracts one more than pulse 2
+    ones_complement: bool,
+}
+
+impl Sweep {
+    pub fn new(ones_complement: bool) -> Self {
+        Self {
+            enabled: false,
+            period: 0,
+            negate: false,
+            shift: 0,
+            divider: 0,
+            reload: false,
+            ones_complement,
+        }
+    }
+
+    pub fn write(&mut self, data: u8) {
+        self.enabled = data & 0x80 != 0;
+        self.period = (data >> 4) & 0x07;
+        self.negate = data & 0x08 != 0;
+        self.shift = data & 0x07;
+        self.reload = true;
+    }
+
+    fn target_period(&self, timer_period: u16) -> u16 {
+        let change = timer_period >> self.shift;
+
+        match (self.negate, self.ones_complement) {
+            (false, _) => timer_period + change,
+            (true, false) => timer_period - change,
+            (true, true) => timer_period.saturating_sub(change + 1),
+        }
+    }
+
+    // Checked all the time, even with the sweep disabled
+    pub fn is_muting(&self, timer_period: u16) -> bool {
+        timer_period < 8 || self.target_period(timer_period) > 0x7FF
+    }
+
+    // Clocked every half frame, returns the new timer period of the channel
+    pub fn clock(&mut self, timer_period: u16) -> u16 {
+        let mut timer_period = timer_period;
+
+        if self.divider == 0 && self.enabled && self.shift > 0 && !self.is_muting(timer_period) {
+            timer_period = self.target_period(timer_period);
+        }
+
+        if self.divider == 0 || self.reload {
+            self.divider = self.period;
+            self.reload = false;
+        } else {
+            self.divider -= 1;
+        }
+
+        timer_period
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trajectory(sweep: &mut Sweep, timer_period: u16, clocks: usize) -> Vec<u16> {
+        let mut timer_period = timer_period;
+
+        (0..clocks).map(|_| {
+            timer_period = sweep.clock(timer_period);
+            timer_period
+        }).collect()
+    }
+
+    #[test]
+    fn test_sweep_raises_period_every_divider_period() {
+        let mut sweep = Sweep::new(false);
+        sweep.write(0x92);
+
+        // Period 1 updates on every other half frame, adding a quarter of the period
+        assert_eq!(trajectory(&mut sweep, 0x100, 6), [0x140, 0x140, 0x190, 0x190, 0x1F4, 0x1F4], "Period should grow by a quarter!");
+    }
+
+    #[test]
+    fn test_sweep_negate_differs_between_pulses() {
+        let mut pulse1 = Sweep::new(true);
+        let mut pulse2 = Sweep::new(false);
+        pulse1.write(0x89);
+        pulse2.write(0x89);
+
+        assert_eq!(trajectory(&mut pulse1, 0x100, 3), [0x7F, 0x3F, 0x1F], "Pulse 1 should subtract one more!");
+        assert_eq!(trajectory(&mut pulse2, 0x100, 3), [0x80, 0x40, 0x20], "Pulse 2 should halve the period!");
+    }
+
+    #[test]
+    fn test_sweep_mutes_out_of_range_periods() {
+        let mut sweep = Sweep::new(false);
+
+        assert!(sweep.is_muting(0x07), "Periods below 8 should mute!");
+        assert!(!sweep.is_muting(0x08), "Period 8 should play!");
+
+        sweep.write(0x01);
+        assert!(sweep.is_muting(0x600), "Target above $7FF should mute even with the sweep disabled!");
+        assert!(!sweep.is_muting(0x500), "Target up to $7FF should play!");
+
+        sweep.write(0x81);
+        assert_eq!(trajectory(&mut sweep, 0x600, 3), [0x600; 3], "Muting sweep should leave the period alone!");
+    }
+}
@@ -0,0 +1,167 @@
+use super::lengthcounter::LengthCounter;
+
+// Output levels of the 32 sequencer steps, down from 15 and back up
+const TRIANGLE_SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0,
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+// Triangle wave channel, $4008-$400B
+#[derive(Clone)]
+pub struct Triangle {
+    // Also halts the length counter
+    control: bool,
+    linear_counter_period: u8,
+    linear_counter: u8,
+    linear_counter_reload: bool,
+    step: u8,
+    timer_period: u16,
+    timer: u16,
+    length_counter: LengthCounter,
+}
+
+impl Triangle {
+    pub fn new() -> Self {
+        Self {
+            control: false,
+            linear_counter_period: 0,
+            linear_counter: 0,
+            linear_counter_reload: false,
+            step: 0,
+            timer_period: 0,
+            timer: 0,
+            length_counter: LengthCounter::new(),
+        }
+    }
+
+    // `register` is the offset from $4008, $4009 isn't connected
+    pub fn write_register(&mut self, register: u16, data: u8) {
+        match register & 0x03 {
+            0 => {
+                self.control = data & 0x80 != 0;
+                self.linear_counter_period = data & 0x7F;
+                self.length_counter.set_halted(self.control);
+            },
+            1 => (),
+            2 => self.timer_period = (self.timer_period & 0x0700) | data as u16,
+            _ => {
+                self.timer_period = (self.timer_period & 0x00FF) | ((data as u16 & 0x07) << 8);
+                self.length_counter.load(data >> 3);
+                self.linear_counter_reload = true;
+            },
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    // Clocked every CPU cycle. Periods 0 and 1 step at ultrasonic rates instead of going silent,
+    // which some games use to pop the output to a new level.
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.timer_period;
+
+        if self.linear_counter > 0 && !self.length_counter.is_silenced() {
+            self.step = (self.step + 1) % 32;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        if self.linear_counter_reload {
+            self.linear_counter = self.linear_counter_period;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+
+        if !self.control {
+            self.linear_counter_reload = false;
+        }
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+    }
+
+    // A stopped sequencer keeps its level instead of dropping to 0
+    pub fn output(&self) -> u8 {
+        TRIANGLE_SEQUENCE[self.step as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_triangle(linear_control: u8) -> Triangle {
+        let mut triangle = Triangle::new();
+        triangle.set_enabled(true);
+        triangle.write_register(0, linear_control);
+        triangle.write_register(2, 0x00);
+        triangle.write_register(3, 0x08);
+
+        triangle
+    }
+
+    fn linear_counters(triangle: &mut Triangle, clocks: usize) -> Vec<u8> {
+        (0..clocks).map(|_| {
+            triangle.clock_quarter_frame();
+            triangle.linear_counter
+        }).collect()
+    }
+
+    #[test]
+    fn test_linear_counter_reloads_once_without_control() {
+        let mut triangle = create_triangle(0x03);
+
+        assert_eq!(linear_counters(&mut triangle, 5), [3, 2, 1, 0, 0], "Counter should reload once and count down!");
+
+        triangle.write_register(3, 0x08);
+        assert_eq!(linear_counters(&mut triangle, 2), [3, 2], "Writing $400B should reload the counter again!");
+    }
+
+    #[test]
+    fn test_linear_counter_keeps_reloading_with_control() {
+        let mut triangle = create_triangle(0x83);
+
+        assert_eq!(linear_counters(&mut triangle, 4), [3, 3, 3, 3], "Control should keep the reload flag set!");
+
+        triangle.write_register(0, 0x03);
+        assert_eq!(linear_counters(&mut triangle, 3), [3, 2, 1], "Clearing control should let the counter run down!");
+    }
+
+    #[test]
+    fn test_sequencer_needs_both_counters() {
+        let mut triangle = create_triangle(0x03);
+
+        (0..4).for_each(|_| triangle.clock_timer());
+        assert_eq!(triangle.step, 0, "Sequencer should wait for the linear counter!");
+
+        triangle.clock_quarter_frame();
+        (0..4).for_each(|_| triangle.clock_timer());
+        assert_eq!(triangle.step, 4, "Sequencer should step on every clock with period 0!");
+
+        triangle.set_enabled(false);
+        (0..4).for_each(|_| triangle.clock_timer());
+        assert_eq!(triangle.step, 4, "Sequencer should stop with the length counter at 0!");
+        assert_eq!(triangle.output(), 11, "Stopped sequencer should hold its level!");
+    }
+
+    #[test]
+    fn test_ultrasonic_periods_keep_stepping() {
+        let mut triangle = create_triangle(0x83);
+        triangle.clock_quarter_frame();
+
+        let outputs = (0..32).map(|_| {
+            triangle.clock_timer();
+            triangle.output()
+        }).collect::<Vec<_>>();
+
+        assert_eq!(outputs[..4], [14, 13, 12, 11], "Period 0 should step on every CPU cycle!");
+        assert_eq!(outputs[31], 15, "Sequencer should wrap after 32 steps!");
+    }
+}
@@ -0,0 +1,97 @@
+/// Samples kept per trace, about 50 ms of output at `TAP_INTERVAL_CYCLES`.
+pub const WAVEFORM_SAMPLES: usize = 1024;
+
+// CPU cycles between recorded samples, 1024 * 87 cycles at 1.79 MHz is 49.8 ms
+const TAP_INTERVAL_CYCLES: usize = 87;
+
+/// Traces recorded by the waveform view, noise and DMC aren't emulated yet.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WaveformChannel {
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Mix,
+}
+
+impl WaveformChannel {
+    pub const ALL: [Self; 4] = [Self::Pulse1, Self::Pulse2, Self::Triangle, Self::Mix];
+}
+
+/// Ring buffers of the last `WAVEFORM_SAMPLES` levels of each channel, between 0.0 and 1.0.
+#[derive(Clone)]
+pub struct Waveforms {
+    traces: [[f32; WAVEFORM_SAMPLES]; 4],
+    // Index the next sample is written to, also the oldest sample
+    position: usize,
+    cycles: usize,
+}
+
+impl Waveforms {
+    pub fn new() -> Self {
+        Self {
+            traces: [[0.0; WAVEFORM_SAMPLES]; 4],
+            position: 0,
+            cycles: 0,
+        }
+    }
+
+    // Counts one CPU cycle, true when a sample is due
+    pub(super) fn tick(&mut self) -> bool {
+        self.cycles += 1;
+
+        if self.cycles == TAP_INTERVAL_CYCLES {
+            self.cycles = 0;
+            return true;
+        }
+
+        false
+    }
+
+    // Levels in `WaveformChannel::ALL` order
+    pub(super) fn record(&mut self, levels: [f32; 4]) {
+        for (trace, level) in self.traces.iter_mut().zip(levels) {
+            trace[self.position] = level;
+        }
+
+        self.position = (self.position + 1) % WAVEFORM_SAMPLES;
+    }
+
+    /// Samples of one trace, oldest first.
+    pub fn samples(&self, channel: WaveformChannel) -> Vec<f32> {
+        let trace = &self.traces[channel as usize];
+        [&trace[self.position..], &trace[..self.position]].concat()
+    }
+}
+
+impl Default for Waveforms {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_are_oldest_first() {
+        let mut waveforms = Waveforms::new();
+
+        for sample in 0..WAVEFORM_SAMPLES + 2 {
+            waveforms.record([sample as f32, 0.0, 0.0, 0.0]);
+        }
+
+        let samples = waveforms.samples(WaveformChannel::Pulse1);
+        assert_eq!(samples.len(), WAVEFORM_SAMPLES, "Trace should keep a fixed number of samples!");
+        assert_eq!(samples[0], 2.0, "Oldest samples should be overwritten first!");
+        assert_eq!(samples[WAVEFORM_SAMPLES - 1], (WAVEFORM_SAMPLES + 1) as f32, "Newest sample should come last!");
+    }
+
+    #[test]
+    fn test_samples_are_due_every_interval() {
+        let mut waveforms = Waveforms::new();
+        let due = (0..TAP_INTERVAL_CYCLES * 3).filter(|_| waveforms.tick()).count();
+
+        assert_eq!(due, 3, "One sample should be due every interval!");
+    }
+}
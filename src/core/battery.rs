@@ -0,0 +1,168 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::emulator::Emulator;
+
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Where battery-backed PRG-RAM is kept between runs.
+pub trait SaveStorage {
+    fn load(&mut self) -> io::Result<Option<Vec<u8>>>;
+
+    fn store(&mut self, data: &[u8]) -> io::Result<()>;
+}
+
+/// `.sav` file next to the ROM, holding the raw PRG-RAM.
+pub struct SaveFile {
+    path: PathBuf,
+}
+
+impl SaveFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+        }
+    }
+}
+
+impl SaveStorage for SaveFile {
+    fn load(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(data) => Ok(Some(data)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    // Written next to the save and renamed over it, so a crash mid-write keeps the old save
+    fn store(&mut self, data: &[u8]) -> io::Result<()> {
+        let temporary_path = self.path.with_extension("sav.tmp");
+
+        fs::write(&temporary_path, data)?;
+        fs::rename(&temporary_path, &self.path)
+    }
+}
+
+/// Writes PRG-RAM to storage every `interval` while the game keeps changing it.
+pub struct Autosave<S: SaveStorage> {
+    storage: S,
+    interval: Duration,
+    last_check: Instant,
+}
+
+impl<S: SaveStorage> Autosave<S> {
+    pub fn new(storage: S, interval: Duration, now: Instant) -> Self {
+        Self {
+            storage,
+            interval,
+            last_check: now,
+        }
+    }
+
+    pub fn load(&mut self, emulator: &mut Emulator) -> io::Result<()> {
+        if let Some(data) = self.storage.load()? {
+            emulator.load_battery_ram(&data);
+        }
+
+        Ok(())
+    }
+
+    // Returns whether the save was written, RAM that didn't change since the last save is skipped
+    pub fn update(&mut self, emulator: &mut Emulator, now: Instant) -> io::Result<bool> {
+        if now.duration_since(self.last_check) < self.interval {
+            return Ok(false);
+        }
+
+        self.last_check = now;
+        self.flush(emulator)
+    }
+
+    pub fn flush(&mut self, emulator: &mut Emulator) -> io::Result<bool> {
+        match emulator.take_dirty_battery_ram() {
+            Some(data) => self.storage.store(&data).map(|_| true),
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::cartridge::Cartridge;
+    use crate::core::memory::Memory;
+    use super::*;
+
+    #[derive(Default)]
+    struct MemoryStorage {
+        data: Option<Vec<u8>>,
+        writes: usize,
+    }
+
+    impl SaveStorage for &mut MemoryStorage {
+        fn load(&mut self) -> io::Result<Option<Vec<u8>>> {
+            Ok(self.data.clone())
+        }
+
+        fn store(&mut self, data: &[u8]) -> io::Result<()> {
+            self.data = Some(data.to_vec());
+            self.writes += 1;
+            Ok(())
+        }
+    }
+
+    // MMC1 board with 8KB of PRG-RAM
+    fn create_emulator() -> Emulator {
+        let mut rom = b"NES\x1A\x02\x01\x12\x00".to_vec();
+        rom.resize(16 + 2 * 0x4000 + 0x2000, 0x00);
+
+        Emulator::new(&Cartridge::new(rom))
+    }
+
+    fn write_ram(emulator: &Emulator, address: u16, data: u8) {
+        emulator.bus().borrow_mut().cpu_memory_map().write(address, data);
+    }
+
+    #[test]
+    fn test_autosave_writes_dirty_ram_once_per_interval() {
+        let mut emulator = create_emulator();
+        let mut storage = MemoryStorage::default();
+        let start = Instant::now();
+        let mut autosave = Autosave::new(&mut storage, AUTOSAVE_INTERVAL, start);
+
+        write_ram(&emulator, 0x6000, 0x42);
+        assert!(!autosave.update(&mut emulator, start + Duration::from_secs(10)).unwrap(), "Save should wait for the interval!");
+        assert!(autosave.update(&mut emulator, start + Duration::from_secs(30)).unwrap(), "Dirty RAM should be saved after the interval!");
+        assert!(!autosave.update(&mut emulator, start + Duration::from_secs(60)).unwrap(), "Unchanged RAM should not be saved again!");
+
+        assert_eq!(storage.writes, 1, "Autosave should write exactly once!");
+        assert_eq!(storage.data.as_ref().unwrap()[0], 0x42, "Save should hold the PRG-RAM!");
+    }
+
+    #[test]
+    fn test_autosave_flush_writes_pending_ram() {
+        let mut emulator = create_emulator();
+        let mut storage = MemoryStorage::default();
+        let mut autosave = Autosave::new(&mut storage, AUTOSAVE_INTERVAL, Instant::now());
+
+        assert!(!autosave.flush(&mut emulator).unwrap(), "Clean RAM should not be flushed!");
+        write_ram(&emulator, 0x7FFF, 0x24);
+        assert!(autosave.flush(&mut emulator).unwrap(), "Shutdown should flush dirty RAM!");
+
+        assert_eq!(storage.data.as_ref().unwrap()[0x1FFF], 0x24, "Flushed save should hold the last write!");
+    }
+
+    #[test]
+    fn test_autosave_loads_saved_ram() {
+        let mut emulator = create_emulator();
+        let mut storage = MemoryStorage {
+            data: Some(vec![0x99; 0x2000]),
+            writes: 0,
+        };
+        let mut autosave = Autosave::new(&mut storage, AUTOSAVE_INTERVAL, Instant::now());
+
+        autosave.load(&mut emulator).unwrap();
+        assert!(!autosave.flush(&mut emulator).unwrap(), "Loading a save should not mark RAM dirty!");
+        assert_eq!(emulator.bus().borrow_mut().cpu_memory_map().read(0x6000), 0x99, "Loaded save should be visible to the CPU!");
+    }
+}
@@ -1,19 +1,55 @@
+use std::fmt;
+
+use super::apu::Apu;
 use super::cartridge::Cartridge;
+use super::joypad::Joypad;
+use super::mappers::Mapper;
+use super::memory::Memory;
 use super::memorymap::CpuMemoryMap;
 use super::memorymap::PpuMemoryMap;
+use super::ppu::Mirroring;
+
+pub use super::memorymap::RamInitPattern;
+
+/// Write of a program into memory that can't hold it, `address` is the first such byte.
+#[derive(Debug, PartialEq)]
+pub struct LoadError {
+    pub address: u16,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "address ${:04X} is not writable", self.address)
+    }
+}
+
+impl std::error::Error for LoadError {}
 
+#[derive(Clone)]
 pub struct Bus {
     cpu_memory_map: Box<CpuMemoryMap>,
     ppu_memory_map: Box<PpuMemoryMap>,
     nmi_interrupt: Option<()>,
+    nmi_line: bool,
+    irq: bool,
+    joypads: [Joypad; 2],
+    apu: Apu,
 }
 
 impl Bus {
     pub fn new(cartridge: &Cartridge) -> Self {
+        Self::with_ram_init(cartridge, RamInitPattern::AllZeros)
+    }
+
+    pub fn with_ram_init(cartridge: &Cartridge, ram_init: RamInitPattern) -> Self {
         Self {
-            cpu_memory_map: Box::new(CpuMemoryMap::new(cartridge.get_mapper())),
+            cpu_memory_map: Box::new(CpuMemoryMap::with_ram_init(cartridge.get_mapper(), ram_init)),
             ppu_memory_map: Box::new(PpuMemoryMap::new(cartridge.get_mapper())),
             nmi_interrupt: None,
+            nmi_line: false,
+            irq: false,
+            joypads: [Joypad::new(), Joypad::new()],
+            apu: Apu::new(),
         }
     }
 
@@ -25,6 +61,98 @@ impl Bus {
         &mut self.ppu_memory_map
     }
 
+    pub fn read_ram(&self, address: u16) -> u8 {
+        self.cpu_memory_map.read_ram(address)
+    }
+
+    pub fn write_ram(&mut self, address: u16, data: u8) {
+        self.cpu_memory_map.write_ram(address, data);
+    }
+
+    /// Writes `bytes` from `start` through the CPU memory map, so RAM mirrors apply.
+    /// Nothing is written when the range touches the PPU and APU registers or PRG-ROM.
+    pub fn load(&mut self, start: u16, bytes: &[u8]) -> Result<(), LoadError> {
+        let addresses = (0..bytes.len()).map(|offset| start.wrapping_add(offset as u16));
+
+        for address in addresses.clone() {
+            let is_register = (0x2000..0x4020).contains(&address);
+            let is_prg_rom = self.cpu_memory_map.mapper().borrow().prg_rom_address(address).is_some();
+
+            if is_register || is_prg_rom {
+                return Err(LoadError { address });
+            }
+        }
+
+        for (address, &byte) in addresses.zip(bytes) {
+            self.cpu_memory_map.write(address, byte);
+        }
+
+        Ok(())
+    }
+
+    pub fn joypad(&mut self, index: usize) -> &mut Joypad {
+        &mut self.joypads[index]
+    }
+
+    pub fn apu(&mut self) -> &mut Apu {
+        &mut self.apu
+    }
+
+    pub fn tick_mapper(&mut self, cycles: usize) {
+        self.cpu_memory_map.mapper().borrow_mut().tick(cycles);
+    }
+
+    // Mirroring the mapper switched to, None when the cartridge header decides
+    pub fn current_mirroring(&self) -> Option<Mirroring> {
+        self.cpu_memory_map.mapper().borrow().mirroring()
+    }
+
+    pub fn clock_mapper_a12(&mut self) {
+        self.cpu_memory_map.mapper().borrow_mut().a12_rising_edge();
+    }
+
+    pub fn has_mapper_interrupt(&self) -> bool {
+        self.cpu_memory_map.mapper().borrow().irq_pending()
+    }
+
+    pub fn switch_disk_side(&mut self) {
+        self.cpu_memory_map.mapper().borrow_mut().switch_disk_side();
+    }
+
+    pub fn load_battery_ram(&mut self, data: &[u8]) {
+        self.cpu_memory_map.mapper().borrow_mut().load_battery_ram(data);
+    }
+
+    // Copy of PRG-RAM if it changed since the last call
+    pub fn take_dirty_battery_ram(&mut self) -> Option<Vec<u8>> {
+        let mut mapper = self.cpu_memory_map.mapper().borrow_mut();
+
+        if !mapper.is_battery_ram_dirty() {
+            return None;
+        }
+
+        mapper.clear_battery_ram_dirty();
+        mapper.battery_ram().map(<[u8]>::to_vec)
+    }
+
+    pub(crate) fn save_ram(&self) -> [u8; 0x800] {
+        self.cpu_memory_map.save_ram()
+    }
+
+    // Cloned buses share the live RAM like the mapper, so it is restored in place too
+    pub(crate) fn load_ram(&self, bytes: &[u8; 0x800]) {
+        self.cpu_memory_map.load_ram(bytes);
+    }
+
+    pub(crate) fn save_mapper(&self) -> Box<dyn Mapper> {
+        self.cpu_memory_map.mapper().borrow().clone_mapper()
+    }
+
+    // Cloned buses share the live mapper, so its contents are restored in place
+    pub(crate) fn load_mapper(&self, mapper: &dyn Mapper) {
+        *self.cpu_memory_map.mapper().borrow_mut() = mapper.clone_mapper();
+    }
+
     pub fn set_interrupt(&mut self, interrupt: Option<()>) {
         self.nmi_interrupt = interrupt;
     }
@@ -36,4 +164,70 @@ impl Bus {
     pub fn poll_interrupt(&mut self) -> Option<()> {
         self.nmi_interrupt.take()
     }
+
+    // NMI is edge triggered, the rising edge is latched until the CPU services it,
+    // so a pulse that is already over when the CPU polls still counts
+    pub fn set_nmi_line(&mut self, level: bool) {
+        if level && !self.nmi_line {
+            self.nmi_interrupt = Some(());
+        }
+
+        self.nmi_line = level;
+    }
+
+    // IRQ is level triggered, it stays asserted until its source acknowledges it
+    pub fn set_irq(&mut self, irq: bool) {
+        self.irq = irq;
+    }
+
+    pub fn poll_irq(&self) -> bool {
+        self.irq || self.has_mapper_interrupt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_applies_ram_mirroring() {
+        let mut bus = Bus::new(&Cartridge::empty());
+
+        assert_eq!(bus.load(0x0A00, &[0xA9, 0x42]), Ok(()), "RAM mirror should be writable!");
+        assert_eq!((bus.read_ram(0x0200), bus.read_ram(0x0201)), (0xA9, 0x42), "Load should write through the RAM mirror!");
+
+        assert_eq!(bus.load(0x6000, &[0x01]), Ok(()), "PRG-RAM should be writable!");
+        assert_eq!(bus.cpu_memory_map().read(0x6000), 0x01, "Load should reach the mapper's PRG-RAM!");
+    }
+
+    #[test]
+    fn test_load_refuses_prg_rom() {
+        let mut bus = Bus::new(&Cartridge::empty());
+
+        assert_eq!(bus.load(0x7FFE, &[0x01, 0x02, 0x03]), Err(LoadError { address: 0x8000 }), "PRG-ROM should be refused!");
+        assert_eq!(bus.cpu_memory_map().read(0x7FFE), 0x00, "Refused load should not write anything!");
+    }
+
+    #[test]
+    fn test_nmi_line_latches_rising_edge() {
+        let mut bus = Bus::new(&Cartridge::empty());
+
+        bus.set_nmi_line(true);
+        bus.set_nmi_line(false);
+        assert_eq!(bus.poll_interrupt(), Some(()), "NMI pulse should stay latched after the line drops!");
+
+        bus.set_nmi_line(true);
+        bus.poll_interrupt();
+        bus.set_nmi_line(true);
+        assert_eq!(bus.poll_interrupt(), None, "Line held high should trigger a single NMI!");
+    }
+
+    #[test]
+    fn test_load_refuses_registers() {
+        let mut bus = Bus::new(&Cartridge::empty());
+
+        assert_eq!(bus.load(0x1FFF, &[0x01, 0x02]), Err(LoadError { address: 0x2000 }), "PPU registers should be refused!");
+        assert_eq!(bus.read_ram(0x1FFF), 0x00, "Refused load should not write anything!");
+        assert_eq!(LoadError { address: 0x2000 }.to_string(), "address $2000 is not writable", "Error should name the address!");
+    }
 }
@@ -1,28 +1,37 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use super::ppu::Mirroring;
+use super::ppu::{Mirroring, Region};
 use super::mappers::Mapper;
 use super::mappers::Mapper000;
+use super::mappers::Mapper001;
+use super::mappers::MapperFds;
 
 pub struct Cartridge {
     mirroring: Mirroring,
+    region: Region,
+    has_battery: bool,
     mapper: Rc<RefCell<Box<dyn Mapper>>>,
 }
 
 impl Cartridge {
     pub fn new(rom: Vec<u8>) -> Self {
-        rom.get(0..16).expect("Unable to parse NES Header, possibly wrong file!");
+        Self::try_new(rom).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    // Like `new`, but reports a broken or unsupported ROM instead of panicking
+    pub fn try_new(rom: Vec<u8>) -> Result<Self, String> {
+        rom.get(0..16).ok_or("Unable to parse NES Header, possibly wrong file!")?;
 
         if &rom[0..4] != b"NES\x1A" {
-            panic!("This ROM is not iNES format!");
+            return Err("This ROM is not iNES format!".to_string());
         }
 
         let prg_rom_size = rom[4] as u16;
         let chr_rom_size = rom[5] as u16;
 
         let flag6_metadata = rom[6];
-        let (mirroring, mapper_lower_nybble, has_trainer, _has_batterybacked_prg_ram) = {
+        let (mirroring, mapper_lower_nybble, has_trainer, has_batterybacked_prg_ram) = {
             let four_screen_mirroring = (flag6_metadata >> 3) & 0x1 == 0x1;
             let mirroring = if four_screen_mirroring {
                 Mirroring::FourScreen
@@ -43,7 +52,7 @@ impl Cartridge {
 
         // If it's iNES 2.0 format, flags 8-15 are in NES 2.0 format
         let flag7_metadata = rom[7];
-        let (_mapper_upper_nybble, _is_nes20_format, _is_playchoice10, _is_vsunisystem) = {
+        let (mapper_upper_nybble, is_nes20_format, _is_playchoice10, _is_vsunisystem) = {
             let mapper_upper_nybble = flag7_metadata >> 4;
             let is_nes20_format = (flag7_metadata >> 2) & 0x3 == 0x2;
             let is_playchoice10 = (flag7_metadata >> 1) & 0x1 == 0x1;
@@ -52,8 +61,6 @@ impl Cartridge {
             (mapper_upper_nybble, is_nes20_format, is_playchoice10, is_vsunisystem)
         };
 
-        let _prg_ram_size = rom[8];
-
         let (prg_rom_begin, prg_rom_end) = {
             let begin = if has_trainer { 16 + 512 } else { 16 };
             let end = begin + prg_rom_size * 16 * 1024;
@@ -70,41 +77,109 @@ impl Cartridge {
 
         let prg_rom = rom
             .get(prg_rom_begin..prg_rom_end)
-            .clone()
-            .expect("Unable to get PRG-ROM!")
+            .ok_or("Unable to get PRG-ROM!")?
             .to_vec();
 
-        let chr_rom = rom
-            .get(chr_rom_begin..chr_rom_end)
-            .clone()
-            .expect("Unable to get CHR-ROM!")
-            .to_vec();
+        // Cartridges without CHR-ROM have 8KB of CHR-RAM instead
+        let chr_rom = if chr_rom_size == 0 {
+            vec![0; 0x2000]
+        } else {
+            rom
+                .get(chr_rom_begin..chr_rom_end)
+                .ok_or("Unable to get CHR-ROM!")?
+                .to_vec()
+        };
 
         // TODO: add more mappers later
-        let mapper: Box<dyn Mapper> = match mapper_lower_nybble {
+        // Old iNES dumps may have garbage like "DiskDude!" in bytes 7-15
+        let has_dirty_header = !is_nes20_format && rom[12..16].iter().any(|byte| *byte != 0);
+        let mapper_number = if has_dirty_header {
+            mapper_lower_nybble
+        } else {
+            (mapper_upper_nybble << 4) | mapper_lower_nybble
+        };
+
+        // NES 2.0 keeps the volatile and battery-backed sizes as 64 << shift, iNES counts 8KB units
+        let prg_ram_size = if is_nes20_format {
+            let shift_size = |shift: u8| if shift == 0 { 0 } else { 64 << shift };
+            shift_size(rom[10] & 0x0F) + shift_size(rom[10] >> 4)
+        } else if has_dirty_header {
+            0
+        } else {
+            rom[8] as usize * 8 * 1024
+        };
+
+        // NES 2.0 keeps the timing in byte 12, iNES in bit 0 of byte 9. Multi-region
+        // and Dendy carts run as NTSC
+        let region = match (is_nes20_format, has_dirty_header) {
+            (true, _) if rom[12] & 0x03 == 0x01 => Region::Pal,
+            (false, false) if rom[9] & 0x01 == 0x01 => Region::Pal,
+            _ => Region::Ntsc,
+        };
+
+        let mapper: Box<dyn Mapper> = match mapper_number {
             0 => Box::new(Mapper000::new(prg_rom, chr_rom)),
-            _ => panic!("Unsupported mapper!"),
+            1 => Box::new(Mapper001::new(prg_rom, chr_rom, prg_ram_size)),
+            _ => return Err(format!("Unsupported mapper {}!", mapper_number)),
         };
         
-        Self {
+        Ok(Self {
             mirroring,
+            region,
+            has_battery: has_batterybacked_prg_ram,
+            mapper: Rc::new(RefCell::new(mapper)),
+        })
+    }
+
+    // Famicom Disk System images run on the RAM adapter with the BIOS mapped at $E000
+    pub fn from_fds(image: Vec<u8>, bios: Vec<u8>) -> Self {
+        let mapper = Box::new(MapperFds::new(bios, &image));
+
+        Self {
+            mirroring: Mirroring::Horizontal,
+            region: Region::Ntsc,
+            has_battery: false,
             mapper: Rc::new(RefCell::new(mapper)),
         }
     }
 
+    pub fn is_fds_image(rom: &[u8]) -> bool {
+        rom.starts_with(b"FDS\x1A") || rom.get(1..15) == Some(b"*NINTENDO-HVC*")
+    }
+
     pub fn get_mirroring(&self) -> Mirroring {
         self.mirroring
     }
 
+    pub fn get_region(&self) -> Region {
+        self.region
+    }
+
     pub fn empty() -> Self {
         let mapper = Box::new(Mapper000::new(vec![0; 0x8000], vec![0; 0x2000]));
 
         Self {
             mirroring: Mirroring::Horizontal,
+            region: Region::Ntsc,
+            has_battery: false,
             mapper: Rc::new(RefCell::new(mapper)),
         }
     }
 
+    #[cfg(test)]
+    pub(crate) fn from_mapper(mapper: Box<dyn Mapper>) -> Self {
+        Self {
+            mirroring: Mirroring::Horizontal,
+            region: Region::Ntsc,
+            has_battery: false,
+            mapper: Rc::new(RefCell::new(mapper)),
+        }
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.has_battery
+    }
+
     pub fn get_mapper(&self) -> &Rc<RefCell<Box<dyn Mapper>>> {
         &self.mapper
     }
@@ -0,0 +1,85 @@
+// FCEUX layout: bit 0 code, bit 1 data, bits 2-3 the 8KB window of $8000-$FFFF the
+// byte was last accessed through. Bit 7 is free there, operands use it until export.
+const CODE: u8 = 0x01;
+const DATA: u8 = 0x02;
+const BANK_MASK: u8 = 0x0C;
+const OPERAND: u8 = 0x80;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CdlAccess {
+    Opcode,
+    Operand,
+    Data,
+}
+
+/// Records how each PRG-ROM byte was accessed, for reverse-engineering tools.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeDataLogger {
+    entries: Vec<u8>,
+}
+
+impl CodeDataLogger {
+    pub fn new(prg_rom_len: usize) -> Self {
+        Self {
+            entries: vec![0; prg_rom_len],
+        }
+    }
+
+    pub fn log(&mut self, prg_rom_address: usize, cpu_address: u16, access: CdlAccess) {
+        let Some(entry) = self.entries.get_mut(prg_rom_address) else {
+            return;
+        };
+
+        let kind = match access {
+            CdlAccess::Opcode => CODE,
+            CdlAccess::Operand => OPERAND,
+            CdlAccess::Data => DATA,
+        };
+        let bank = ((cpu_address >> 11) as u8) & BANK_MASK;
+
+        *entry = (*entry & !BANK_MASK) | kind | bank;
+    }
+
+    pub fn is_opcode(&self, prg_rom_address: usize) -> bool {
+        self.entries[prg_rom_address] & CODE != 0
+    }
+
+    pub fn is_operand(&self, prg_rom_address: usize) -> bool {
+        self.entries[prg_rom_address] & OPERAND != 0
+    }
+
+    pub fn is_data(&self, prg_rom_address: usize) -> bool {
+        self.entries[prg_rom_address] & DATA != 0
+    }
+
+    /// Contents of a `.cdl` file for the PRG-ROM, operands count as code there.
+    pub fn export(&self) -> Vec<u8> {
+        self.entries
+            .iter()
+            .map(|entry| match entry & OPERAND {
+                0 => *entry,
+                _ => (entry & !OPERAND) | CODE,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_uses_fceux_layout() {
+        let mut logger = CodeDataLogger::new(0x8000);
+        logger.log(0x0000, 0x8000, CdlAccess::Opcode);
+        logger.log(0x0001, 0x8001, CdlAccess::Operand);
+        logger.log(0x6000, 0xE000, CdlAccess::Data);
+        logger.log(0x6000, 0xE000, CdlAccess::Operand);
+
+        let cdl = logger.export();
+        assert_eq!(cdl[0x0000], 0x01, "Opcode should be exported as code!");
+        assert_eq!(cdl[0x0001], 0x01, "Operand should be exported as code!");
+        assert_eq!(cdl[0x6000], 0x0F, "Byte read both ways should keep both bits and its bank!");
+        assert_eq!(cdl[0x0002], 0x00, "Untouched byte should stay clear!");
+    }
+}
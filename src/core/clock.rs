@@ -1,12 +1,23 @@
 use std::rc::Rc;
 use std::cell::RefCell;
 
-use super::ppu::Ppu;
+use super::ppu::{Ppu, Region};
+
+const RESET_CYCLES: usize = 7;
+
+/// Clock state that isn't part of the CPU's, for savestates. The cycle count is saved with the CPU.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "savestate", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClockState {
+    pub dot_remainder: usize,
+}
 
 pub struct Clock {
     ppu: Rc<RefCell<Ppu>>,
     render_callback: Box<dyn FnMut(&Ppu)>,
     cycles: usize,
+    // PAL runs 16 PPU dots every 5 CPU cycles, this carries the leftover fifths
+    dot_remainder: usize,
 }
 
 impl Clock {
@@ -16,26 +27,44 @@ impl Clock {
         Self {
             ppu: ppu.clone(),
             render_callback: Box::new(render_callback),
-            cycles: 7,
+            cycles: 0,
+            dot_remainder: 0,
         }
     }
 
+    // Restarts the cycle count and runs the reset sequence, the PPU keeps running through it
     pub fn reset(&mut self) {
-        self.cycles = 7;
+        self.cycles = 0;
+        self.tick(RESET_CYCLES);
     }
 
     pub fn tick(&mut self, amount: usize) {
         self.cycles += amount;
-        let nmi_interrupt_before = self.ppu.borrow().has_interrupt();
 
-        for _ in 0..(amount * 3) {
+        let dots = match self.ppu.borrow().get_region() {
+            Region::Ntsc => amount * 3,
+            Region::Pal => {
+                let fifths = amount * 16 + self.dot_remainder;
+                self.dot_remainder = fifths % 5;
+
+                fifths / 5
+            },
+        };
+
+        for _ in 0..dots {
             self.ppu.borrow_mut().tick(1);
         }
 
-        let nmi_interrupt_after = self.ppu.borrow().has_interrupt();
+        {
+            let ppu = self.ppu.borrow();
+            let mut bus = ppu.bus().borrow_mut();
+
+            bus.tick_mapper(amount);
+            bus.apu().tick(amount);
+        }
 
-        if !nmi_interrupt_before && nmi_interrupt_after {
-            (*self.render_callback)(&*self.ppu.borrow());
+        if self.ppu.borrow_mut().take_frame_complete() {
+            (*self.render_callback)(&self.ppu.borrow());
         }
     }
 
@@ -43,7 +72,89 @@ impl Clock {
         self.cycles
     }
 
+    pub fn set_cycles(&mut self, cycles: usize) {
+        self.cycles = cycles;
+    }
+
     pub fn ppu(&self) -> &Rc<RefCell<Ppu>> {
         &self.ppu
     }
+
+    pub fn save_state(&self) -> ClockState {
+        ClockState {
+            dot_remainder: self.dot_remainder,
+        }
+    }
+
+    pub fn restore_state(&mut self, state: &ClockState) {
+        self.dot_remainder = state.dot_remainder;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use crate::core::bus::Bus;
+    use crate::core::cartridge::Cartridge;
+    use super::*;
+
+    #[test]
+    fn test_pal_ticks_16_dots_every_5_cycles() {
+        let cartridge = Cartridge::empty();
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, cartridge.get_mirroring())));
+        ppu.borrow_mut().set_region(Region::Pal);
+        let mut clock = Clock::new(&ppu, |_| {});
+
+        let dots = (0..10).map(|_| {
+            clock.tick(1);
+            ppu.borrow().get_cycles()
+        }).collect::<Vec<_>>();
+
+        assert_eq!(dots, [3, 6, 9, 12, 16, 19, 22, 25, 28, 32], "PAL should tick 3.2 dots per CPU cycle!");
+    }
+
+    #[test]
+    fn test_restored_state_keeps_pal_dot_carry() {
+        let cartridge = Cartridge::empty();
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, cartridge.get_mirroring())));
+        ppu.borrow_mut().set_region(Region::Pal);
+        let mut clock = Clock::new(&ppu, |_| {});
+
+        clock.tick(3);
+        let state = clock.save_state();
+        let dots = ppu.borrow().get_cycles();
+
+        clock.tick(2);
+        let expected = ppu.borrow().get_cycles() - dots;
+
+        clock.tick(1);
+        clock.restore_state(&state);
+        let dots = ppu.borrow().get_cycles();
+        clock.tick(2);
+        assert_eq!(ppu.borrow().get_cycles() - dots, expected, "Restored clock should carry the same fraction of a dot!");
+    }
+
+    #[test]
+    fn test_frames_render_without_nmi() {
+        let cartridge = Cartridge::empty();
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, cartridge.get_mirroring())));
+        let frames = Rc::new(Cell::new(0));
+        let mut clock = {
+            let frames = frames.clone();
+
+            Clock::new(&ppu, move |_| frames.set(frames.get() + 1))
+        };
+
+        // One NTSC frame is 89342 dots, a little under 29781 CPU cycles
+        for _ in 0..29781 {
+            clock.tick(1);
+        }
+
+        assert!(!ppu.borrow().has_interrupt(), "NMI should stay disabled!");
+        assert_eq!(frames.get(), 1, "Render callback should fire once per frame!");
+    }
 }
@@ -0,0 +1,427 @@
+use std::fmt;
+use std::path::Path;
+
+use super::bus::RamInitPattern;
+use super::hash::crc32;
+use super::emulator::MAX_RUN_AHEAD_FRAMES;
+use super::joypad::JoypadButton;
+use super::ppu::MAX_EXTRA_VBLANK_SCANLINES;
+
+pub const GAMEDB_PATH: &str = "gamedb.toml";
+
+const RAM_INIT_NAMES: [(&str, RamInitPattern); 3] = [
+    ("zeros", RamInitPattern::AllZeros),
+    ("ones", RamInitPattern::AllOnes),
+    ("00ff", RamInitPattern::Pattern00FF),
+];
+
+const BUTTON_NAMES: [(&str, JoypadButton); 8] = [
+    ("a", JoypadButton::A),
+    ("b", JoypadButton::B),
+    ("select", JoypadButton::Select),
+    ("start", JoypadButton::Start),
+    ("up", JoypadButton::Up),
+    ("down", JoypadButton::Down),
+    ("left", JoypadButton::Left),
+    ("right", JoypadButton::Right),
+];
+
+#[derive(Debug, PartialEq)]
+pub struct ConfigError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+}
+
+struct Entry {
+    line: usize,
+    section: String,
+    key: String,
+    value: Value,
+}
+
+/// Settings the emulator runs a game with.
+#[derive(Default)]
+pub struct Config {
+    pub extra_vblank_scanlines: usize,
+    pub run_ahead_frames: usize,
+    // `None` keeps the RAM the emulator was created with
+    pub ram_init: Option<RamInitPattern>,
+    pub key_bindings: Vec<(JoypadButton, String)>,
+}
+
+impl Config {
+    pub fn merge(&mut self, overrides: &ConfigOverrides) {
+        if let Some(scanlines) = overrides.extra_vblank_scanlines {
+            self.extra_vblank_scanlines = scanlines;
+        }
+
+        if let Some(frames) = overrides.run_ahead_frames {
+            self.run_ahead_frames = frames;
+        }
+
+        if let Some(ram_init) = overrides.ram_init {
+            self.ram_init = Some(ram_init);
+        }
+
+        for (button, key) in &overrides.key_bindings {
+            self.key_bindings.retain(|(bound, _)| *bound as u8 != *button as u8);
+            self.key_bindings.push((*button, key.clone()));
+        }
+    }
+
+    // Precedence is CLI > `<rom>.toml` sidecar > `gamedb.toml` entry for the ROM's CRC32 > defaults.
+    // `sidecar` is the file name and its contents, `is_key_name` tells which key names the frontend knows.
+    pub fn resolve(
+        gamedb: Option<&str>,
+        sidecar: Option<(&str, &str)>,
+        rom: &[u8],
+        cli: &ConfigOverrides,
+        is_key_name: &dyn Fn(&str) -> bool,
+    ) -> Result<(Self, Vec<String>), ConfigError> {
+        let mut config = Self::default();
+        let mut warnings = Vec::new();
+
+        if let Some(gamedb) = gamedb {
+            let section = format!("{:08X}", crc32(rom));
+            let (overrides, gamedb_warnings) = ConfigOverrides::parse(gamedb, &section, is_key_name)?;
+
+            config.merge(&overrides);
+            warnings.extend(gamedb_warnings.into_iter().map(|warning| format!("{}: {}", GAMEDB_PATH, warning)));
+        }
+
+        if let Some((sidecar_name, sidecar)) = sidecar {
+            let (overrides, sidecar_warnings) = ConfigOverrides::parse(sidecar, "", is_key_name)?;
+
+            config.merge(&overrides);
+            warnings.extend(sidecar_warnings.into_iter().map(|warning| format!("{}: {}", sidecar_name, warning)));
+        }
+
+        config.merge(cli);
+
+        Ok((config, warnings))
+    }
+
+    // Reads the sidecar next to the ROM and the global game database, whichever exist.
+    // Called whenever a ROM is loaded, so swapping cartridges picks up edited files.
+    pub fn load(
+        rom_path: &Path,
+        rom: &[u8],
+        cli: &ConfigOverrides,
+        is_key_name: &dyn Fn(&str) -> bool,
+    ) -> Result<(Self, Vec<String>), ConfigError> {
+        let gamedb = std::fs::read_to_string(GAMEDB_PATH).ok();
+        let sidecar_path = rom_path.with_extension("toml");
+        let sidecar_name = sidecar_path.display().to_string();
+        let sidecar = std::fs::read_to_string(&sidecar_path).ok();
+
+        Self::resolve(gamedb.as_deref(), sidecar.as_deref().map(|sidecar| (sidecar_name.as_str(), sidecar)), rom, cli, is_key_name)
+    }
+}
+
+/// Values set by one configuration source, `None` keeps what a lower source set.
+#[derive(Default)]
+pub struct ConfigOverrides {
+    pub extra_vblank_scanlines: Option<usize>,
+    pub run_ahead_frames: Option<usize>,
+    pub ram_init: Option<RamInitPattern>,
+    pub key_bindings: Vec<(JoypadButton, String)>,
+}
+
+impl ConfigOverrides {
+    // Shared by the `overclock` key and `--overclock`, so both reject the same values
+    pub fn set_extra_vblank_scanlines(&mut self, scanlines: i64) -> Result<(), String> {
+        if scanlines < 0 || scanlines as usize > MAX_EXTRA_VBLANK_SCANLINES {
+            return Err(format!("`overclock` should be between 0 and {} scanlines", MAX_EXTRA_VBLANK_SCANLINES));
+        }
+
+        self.extra_vblank_scanlines = Some(scanlines as usize);
+        Ok(())
+    }
+
+    // Shared by the `run_ahead` key and `--run-ahead`, so both reject the same values
+    pub fn set_run_ahead_frames(&mut self, frames: i64) -> Result<(), String> {
+        if frames < 0 || frames as usize > MAX_RUN_AHEAD_FRAMES {
+            return Err(format!("`run_ahead` should be between 0 and {} frames", MAX_RUN_AHEAD_FRAMES));
+        }
+
+        self.run_ahead_frames = Some(frames as usize);
+        Ok(())
+    }
+
+    // Reads the keys of `section` and its `input` subsection, other sections are skipped.
+    // Bindings to key names `is_key_name` rejects are skipped with a warning.
+    pub fn parse(source: &str, section: &str, is_key_name: &dyn Fn(&str) -> bool) -> Result<(Self, Vec<String>), ConfigError> {
+        let input_section = if section.is_empty() {
+            "input".to_string()
+        } else {
+            format!("{}.input", section)
+        };
+
+        let mut overrides = Self::default();
+        let mut warnings = Vec::new();
+
+        for entry in parse_entries(source)? {
+            let Entry { line, key, value, .. } = &entry;
+            let error = |message: &str| ConfigError { line: *line, message: message.to_string() };
+
+            if entry.section.eq_ignore_ascii_case(section) {
+                match (key.as_str(), value) {
+                    ("overclock", Value::Integer(scanlines)) => {
+                        overrides.set_extra_vblank_scanlines(*scanlines).map_err(|message| error(&message))?;
+                    },
+                    ("overclock", _) => return Err(error("`overclock` should be an integer")),
+                    ("run_ahead", Value::Integer(frames)) => {
+                        overrides.set_run_ahead_frames(*frames).map_err(|message| error(&message))?;
+                    },
+                    ("run_ahead", _) => return Err(error("`run_ahead` should be an integer")),
+                    // An integer is the seed of random RAM
+                    ("ram_init", Value::Integer(seed)) => {
+                        overrides.ram_init = Some(RamInitPattern::Random(*seed as u64));
+                    },
+                    ("ram_init", Value::String(name)) => {
+                        let pattern = RAM_INIT_NAMES
+                            .iter()
+                            .find(|(known, _)| known == name)
+                            .map(|(_, pattern)| *pattern)
+                            .ok_or_else(|| error("`ram_init` should be \"zeros\", \"ones\", \"00ff\" or a random seed"))?;
+
+                        overrides.ram_init = Some(pattern);
+                    },
+                    ("ram_init", _) => return Err(error("`ram_init` should be \"zeros\", \"ones\", \"00ff\" or a random seed")),
+                    _ => warnings.push(format!("unknown key `{}` on line {}", key, line)),
+                }
+            } else if entry.section.eq_ignore_ascii_case(&input_section) {
+                let button = BUTTON_NAMES
+                    .iter()
+                    .find(|(name, _)| name == key)
+                    .map(|(_, button)| *button);
+
+                match (button, value) {
+                    (Some(_), Value::String(scancode)) if !is_key_name(scancode) => {
+                        warnings.push(format!("unknown key name `{}` for `{}` on line {}", scancode, key, line));
+                    },
+                    (Some(button), Value::String(scancode)) => {
+                        overrides.key_bindings.push((button, scancode.clone()));
+                    },
+                    (Some(_), _) => return Err(error("key bindings should be key names in quotes")),
+                    (None, _) => warnings.push(format!("unknown button `{}` on line {}", key, line)),
+                }
+            }
+        }
+
+        Ok((overrides, warnings))
+    }
+}
+
+// Supports the subset of TOML configs need: `[section]` headers, comments and
+// `key = value` pairs with integer, boolean or basic string values
+fn parse_entries(source: &str) -> Result<Vec<Entry>, ConfigError> {
+    let mut entries = Vec::new();
+    let mut section = String::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: &str| ConfigError { line: line_number, message: message.to_string() };
+        let line = strip_comment(line).trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            section = header
+                .strip_suffix(']')
+                .ok_or_else(|| error("section header is missing `]`"))?
+                .trim()
+                .to_string();
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected `key = value`"))?;
+
+        let value = value.trim();
+        let value = if let Some(string) = value.strip_prefix('"') {
+            let string = string
+                .strip_suffix('"')
+                .ok_or_else(|| error("string is missing closing quote"))?;
+
+            Value::String(string.to_string())
+        } else {
+            match value {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                _ => Value::Integer(value.replace('_', "").parse().map_err(|_| error("invalid value"))?),
+            }
+        };
+
+        entries.push(Entry {
+            line: line_number,
+            section: section.clone(),
+            key: key.trim().to_string(),
+            value,
+        });
+    }
+
+    Ok(entries)
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => (),
+        }
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM: &[u8] = b"NES\x1A";
+
+    fn is_key_name(name: &str) -> bool {
+        ["X", "Z", "J"].contains(&name)
+    }
+
+    fn gamedb() -> String {
+        format!(
+            "# Game database\n[{other:08X}]\noverclock = 1\n\n[{rom:08X}]\noverclock = 10\n\n[{rom:08X}.input]\na = \"X\"\nb = \"Z\"\n",
+            other = crc32(b"other"),
+            rom = crc32(ROM),
+        )
+    }
+
+    fn bound_key(config: &Config, button: JoypadButton) -> Option<&str> {
+        config.key_bindings
+            .iter()
+            .find(|(bound, _)| *bound as u8 == button as u8)
+            .map(|(_, key)| key.as_str())
+    }
+
+    #[test]
+    fn test_config_defaults_without_sources() {
+        let (config, warnings) = Config::resolve(None, None, ROM, &ConfigOverrides::default(), &is_key_name).unwrap();
+
+        assert_eq!(config.extra_vblank_scanlines, 0, "Default should not overclock!");
+        assert!(config.key_bindings.is_empty(), "Default should keep the window key bindings!");
+        assert!(warnings.is_empty(), "Defaults should not warn!");
+    }
+
+    #[test]
+    fn test_config_gamedb_matches_rom_crc() {
+        let (config, _) = Config::resolve(Some(&gamedb()), None, ROM, &ConfigOverrides::default(), &is_key_name).unwrap();
+
+        assert_eq!(config.extra_vblank_scanlines, 10, "Game database entry for the ROM should apply!");
+        assert_eq!(bound_key(&config, JoypadButton::A), Some("X"), "Game database input section should apply!");
+    }
+
+    #[test]
+    fn test_config_precedence() {
+        let sidecar = "overclock = 20\n[input]\na = \"J\" # jump\n";
+
+        let (config, _) = Config::resolve(Some(&gamedb()), Some(("game.toml", sidecar)), ROM, &ConfigOverrides::default(), &is_key_name).unwrap();
+        assert_eq!(config.extra_vblank_scanlines, 20, "Sidecar should override the game database!");
+        assert_eq!(bound_key(&config, JoypadButton::A), Some("J"), "Sidecar binding should override the game database!");
+        assert_eq!(bound_key(&config, JoypadButton::B), Some("Z"), "Bindings missing from the sidecar should stay!");
+
+        let cli = ConfigOverrides {
+            extra_vblank_scanlines: Some(0),
+            ..Default::default()
+        };
+        let (config, _) = Config::resolve(Some(&gamedb()), Some(("game.toml", sidecar)), ROM, &cli, &is_key_name).unwrap();
+        assert_eq!(config.extra_vblank_scanlines, 0, "CLI should override the sidecar!");
+    }
+
+    #[test]
+    fn test_config_ram_init() {
+        let ram_init = |sidecar: &str| {
+            ConfigOverrides::parse(sidecar, "", &is_key_name).map(|(overrides, _)| overrides.ram_init)
+        };
+
+        assert_eq!(ram_init(""), Ok(None), "RAM init should default to the emulator's!");
+        assert_eq!(ram_init("ram_init = \"00ff\""), Ok(Some(RamInitPattern::Pattern00FF)), "Named pattern should be parsed!");
+        assert_eq!(ram_init("ram_init = 1234"), Ok(Some(RamInitPattern::Random(1234))), "Integer should seed random RAM!");
+        assert!(ram_init("ram_init = \"garbage\"").is_err(), "Unknown pattern should be rejected!");
+    }
+
+    #[test]
+    fn test_config_warns_about_unknown_keys() {
+        let (config, warnings) = Config::resolve(None, Some(("game.toml", "overclock = 5\nturbo = true\n")), ROM, &ConfigOverrides::default(), &is_key_name).unwrap();
+
+        assert_eq!(config.extra_vblank_scanlines, 5, "Known keys should still apply!");
+        assert_eq!(warnings, ["game.toml: unknown key `turbo` on line 2"], "Unknown key should produce a warning!");
+    }
+
+    #[test]
+    fn test_config_skips_unknown_key_names() {
+        let sidecar = "[input]\na = \"Lft Shift\"\nb = \"J\"\n";
+        let (config, warnings) = Config::resolve(Some(&gamedb()), Some(("game.toml", sidecar)), ROM, &ConfigOverrides::default(), &is_key_name).unwrap();
+
+        assert_eq!(
+            warnings,
+            ["game.toml: unknown key name `Lft Shift` for `a` on line 2"],
+            "Unknown key name should produce a warning naming the file and key!"
+        );
+        assert_eq!(bound_key(&config, JoypadButton::A), Some("X"), "Unknown key name should not replace the binding!");
+        assert_eq!(bound_key(&config, JoypadButton::B), Some("J"), "Other bindings should still apply!");
+    }
+
+    #[test]
+    fn test_config_rejects_invalid_values() {
+        let error = ConfigOverrides::parse("overclock = 101", "", &is_key_name).err().unwrap();
+        assert_eq!(error.line, 1, "Error should point to the invalid line!");
+
+        assert!(ConfigOverrides::parse("overclock = \"fast\"", "", &is_key_name).is_err(), "String overclock should be rejected!");
+        assert!(ConfigOverrides::parse("run_ahead = 3", "", &is_key_name).is_err(), "Run-ahead above the maximum should be rejected!");
+        assert!(ConfigOverrides::parse("[input]\na = 1", "", &is_key_name).is_err(), "Key binding should be a string!");
+        assert!(ConfigOverrides::parse("[input", "", &is_key_name).is_err(), "Unclosed section should be rejected!");
+    }
+
+    #[test]
+    fn test_config_cli_run_ahead_is_range_checked() {
+        let mut overrides = ConfigOverrides::default();
+
+        assert_eq!(overrides.set_run_ahead_frames(2), Ok(()), "Maximum run-ahead should be accepted!");
+        assert_eq!(overrides.run_ahead_frames, Some(2), "Accepted run-ahead should be set!");
+
+        let toml_error = ConfigOverrides::parse("run_ahead = 3", "", &is_key_name).err().unwrap();
+        assert_eq!(overrides.set_run_ahead_frames(3), Err(toml_error.message), "CLI and TOML should reject with the same message!");
+        assert_eq!(overrides.run_ahead_frames, Some(2), "Rejected run-ahead should keep the previous value!");
+    }
+
+    #[test]
+    fn test_config_cli_overclock_is_range_checked() {
+        let mut overrides = ConfigOverrides::default();
+
+        assert_eq!(overrides.set_extra_vblank_scanlines(100), Ok(()), "Maximum overclock should be accepted!");
+        assert_eq!(overrides.extra_vblank_scanlines, Some(100), "Accepted overclock should be set!");
+
+        let toml_error = ConfigOverrides::parse("overclock = 101", "", &is_key_name).err().unwrap();
+        assert_eq!(overrides.set_extra_vblank_scanlines(101), Err(toml_error.message), "CLI and TOML should reject with the same message!");
+        assert!(overrides.set_extra_vblank_scanlines(-1).is_err(), "Negative overclock should be rejected!");
+        assert_eq!(overrides.extra_vblank_scanlines, Some(100), "Rejected overclock should keep the previous value!");
+    }
+}
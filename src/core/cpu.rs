@@ -1,11 +1,20 @@
-use std::cell::RefCell;
+use std::cell::{Ref, RefCell};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::io;
+use std::ops::RangeInclusive;
 use std::rc::Rc;
 
-use super::bus::Bus;
+use super::bus::{Bus, LoadError};
+use super::cdl::{CdlAccess, CodeDataLogger};
+use super::disasm::{self, DisassembledLine};
 use super::clock::Clock;
 use super::registers::Register;
 use super::registers::cpu::status::{CpuStatusRegister, CpuStatusRegisterFlags};
 use super::memory::Memory;
+use super::memorymap::InternalRam;
+use super::symbols::SymbolTable;
+use super::trace::TraceSink;
 
 #[derive(Copy, Clone)]
 pub enum AddressingMode {
@@ -24,288 +33,598 @@ pub enum AddressingMode {
     IndirectIndexed,
 }
 
-pub struct Instruction<'a> {
+impl AddressingMode {
+    // Bytes following the opcode
+    pub const fn operand_length(self) -> u16 {
+        match self {
+            AddressingMode::Implicit | AddressingMode::Accumulator => 0,
+            AddressingMode::Absolute
+                | AddressingMode::AbsoluteX
+                | AddressingMode::AbsoluteY
+                | AddressingMode::Indirect => 2,
+            _ => 1,
+        }
+    }
+}
+
+// Operation of an instruction, the mnemonic is only needed by the disassembler
+#[derive(Copy, Clone, PartialEq)]
+pub enum Opcode {
+    Adc,
+    Ahx,
+    Anc,
+    And,
+    Arr,
+    Asl,
+    Alr,
+    Axs,
+    Bcc,
+    Bcs,
+    Beq,
+    Bit,
+    Bmi,
+    Bne,
+    Bpl,
+    Brk,
+    Bvc,
+    Bvs,
+    Clc,
+    Cld,
+    Cli,
+    Clv,
+    Cmp,
+    Cpx,
+    Cpy,
+    Dcp,
+    Dec,
+    Dex,
+    Dey,
+    Eor,
+    Inc,
+    Inx,
+    Iny,
+    Isc,
+    Jmp,
+    Jsr,
+    Kil,
+    Las,
+    Lax,
+    Lda,
+    Ldx,
+    Ldy,
+    Lsr,
+    Nop,
+    Ora,
+    Pha,
+    Php,
+    Pla,
+    Plp,
+    Rla,
+    Rol,
+    Ror,
+    Rra,
+    Rti,
+    Rts,
+    Sax,
+    Sbc,
+    Sec,
+    Sed,
+    Sei,
+    Shx,
+    Shy,
+    Slo,
+    Sre,
+    Sta,
+    Stx,
+    Sty,
+    Tas,
+    Tax,
+    Tay,
+    Tsx,
+    Txa,
+    Txs,
+    Tya,
+    Xaa,
+}
+
+impl Opcode {
+    pub const fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Adc => "ADC",
+            Opcode::Ahx => "AHX",
+            Opcode::Anc => "ANC",
+            Opcode::And => "AND",
+            Opcode::Arr => "ARR",
+            Opcode::Asl => "ASL",
+            Opcode::Alr => "ALR",
+            Opcode::Axs => "AXS",
+            Opcode::Bcc => "BCC",
+            Opcode::Bcs => "BCS",
+            Opcode::Beq => "BEQ",
+            Opcode::Bit => "BIT",
+            Opcode::Bmi => "BMI",
+            Opcode::Bne => "BNE",
+            Opcode::Bpl => "BPL",
+            Opcode::Brk => "BRK",
+            Opcode::Bvc => "BVC",
+            Opcode::Bvs => "BVS",
+            Opcode::Clc => "CLC",
+            Opcode::Cld => "CLD",
+            Opcode::Cli => "CLI",
+            Opcode::Clv => "CLV",
+            Opcode::Cmp => "CMP",
+            Opcode::Cpx => "CPX",
+            Opcode::Cpy => "CPY",
+            Opcode::Dcp => "DCP",
+            Opcode::Dec => "DEC",
+            Opcode::Dex => "DEX",
+            Opcode::Dey => "DEY",
+            Opcode::Eor => "EOR",
+            Opcode::Inc => "INC",
+            Opcode::Inx => "INX",
+            Opcode::Iny => "INY",
+            Opcode::Isc => "ISC",
+            Opcode::Jmp => "JMP",
+            Opcode::Jsr => "JSR",
+            Opcode::Kil => "KIL",
+            Opcode::Las => "LAS",
+            Opcode::Lax => "LAX",
+            Opcode::Lda => "LDA",
+            Opcode::Ldx => "LDX",
+            Opcode::Ldy => "LDY",
+            Opcode::Lsr => "LSR",
+            Opcode::Nop => "NOP",
+            Opcode::Ora => "ORA",
+            Opcode::Pha => "PHA",
+            Opcode::Php => "PHP",
+            Opcode::Pla => "PLA",
+            Opcode::Plp => "PLP",
+            Opcode::Rla => "RLA",
+            Opcode::Rol => "ROL",
+            Opcode::Ror => "ROR",
+            Opcode::Rra => "RRA",
+            Opcode::Rti => "RTI",
+            Opcode::Rts => "RTS",
+            Opcode::Sax => "SAX",
+            Opcode::Sbc => "SBC",
+            Opcode::Sec => "SEC",
+            Opcode::Sed => "SED",
+            Opcode::Sei => "SEI",
+            Opcode::Shx => "SHX",
+            Opcode::Shy => "SHY",
+            Opcode::Slo => "SLO",
+            Opcode::Sre => "SRE",
+            Opcode::Sta => "STA",
+            Opcode::Stx => "STX",
+            Opcode::Sty => "STY",
+            Opcode::Tas => "TAS",
+            Opcode::Tax => "TAX",
+            Opcode::Tay => "TAY",
+            Opcode::Tsx => "TSX",
+            Opcode::Txa => "TXA",
+            Opcode::Txs => "TXS",
+            Opcode::Tya => "TYA",
+            Opcode::Xaa => "XAA",
+        }
+    }
+}
+
+pub struct Instruction {
     opcode: u8,
-    name: &'a str,
-    bytes: u8,
+    pub(crate) operation: Opcode,
+    pub(crate) bytes: u8,
     cycles: u8,
-    addressing_mode: AddressingMode,
+    pub(crate) addressing_mode: AddressingMode,
+    // Reads through indexed addressing take one more cycle when the index crosses a page,
+    // stores and read-modify-write instructions always take the fixed count
+    page_cross_cycle: bool,
 }
 
-impl Instruction<'_> {
-    pub const fn new(opcode: u8, name: &'static str, bytes: u8, cycles: u8, addressing_mode: AddressingMode) -> Self {
+impl Instruction {
+    pub const fn new(opcode: u8, operation: Opcode, bytes: u8, cycles: u8, addressing_mode: AddressingMode) -> Self {
         Self {
             opcode,
-            name,
+            operation,
             bytes,
             cycles,
-            addressing_mode
+            addressing_mode,
+            page_cross_cycle: false,
+        }
+    }
+
+    pub const fn with_page_cross_cycle(mut self) -> Self {
+        self.page_cross_cycle = true;
+        self
+    }
+
+    // Opcodes missing from the official 6502 documentation, traces mark them with `*`
+    pub const fn is_unofficial(&self) -> bool {
+        match self.operation {
+            Opcode::Nop => self.opcode != 0xEA,
+            Opcode::Sbc => self.opcode == 0xEB,
+            Opcode::Lax | Opcode::Sax | Opcode::Dcp | Opcode::Isc | Opcode::Slo | Opcode::Rla
+                | Opcode::Sre | Opcode::Rra | Opcode::Kil | Opcode::Anc | Opcode::Alr | Opcode::Arr
+                | Opcode::Axs | Opcode::Ahx | Opcode::Las | Opcode::Shx | Opcode::Shy | Opcode::Tas
+                | Opcode::Xaa => true,
+            _ => false,
         }
     }
 }
 
-static INSTRUCTIONS: [Instruction; 256] = [
-        Instruction::new(0x00, "BRK", 1, 7, AddressingMode::Implicit),
-        Instruction::new(0x01, "ORA", 2, 6, AddressingMode::IndexedIndirect),
-        Instruction::new(0x02, "KIL", 1, 1, AddressingMode::Implicit),
-        Instruction::new(0x03, "SLO", 2, 8, AddressingMode::IndexedIndirect),
-        Instruction::new(0x04, "NOP", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0x05, "ORA", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0x06, "ASL", 2, 5, AddressingMode::ZeroPage),
-        Instruction::new(0x07, "SLO", 2, 5, AddressingMode::ZeroPage),
-        Instruction::new(0x08, "PHP", 1, 3, AddressingMode::Implicit),
-        Instruction::new(0x09, "ORA", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0x0A, "ASL", 1, 2, AddressingMode::Accumulator),
-        Instruction::new(0x0B, "ANC", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0x0C, "NOP", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0x0D, "ORA", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0x0E, "ASL", 3, 6, AddressingMode::Absolute),
-        Instruction::new(0x0F, "SLO", 3, 6, AddressingMode::Absolute),
-        Instruction::new(0x10, "BPL", 2, 2, AddressingMode::Relative),
-        Instruction::new(0x11, "ORA", 2, 5, AddressingMode::IndirectIndexed),
-        Instruction::new(0x12, "KIL", 1, 1, AddressingMode::Implicit),
-        Instruction::new(0x13, "SLO", 2, 8, AddressingMode::IndirectIndexed),
-        Instruction::new(0x14, "NOP", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0x15, "ORA", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0x16, "ASL", 2, 6, AddressingMode::ZeroPageX),
-        Instruction::new(0x17, "SLO", 2, 6, AddressingMode::ZeroPageX),
-        Instruction::new(0x18, "CLC", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0x19, "ORA", 3, 4, AddressingMode::AbsoluteY),
-        Instruction::new(0x1A, "NOP", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0x1B, "SLO", 3, 7, AddressingMode::AbsoluteY),
-        Instruction::new(0x1C, "NOP", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0x1D, "ORA", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0x1E, "ASL", 3, 7, AddressingMode::AbsoluteX),
-        Instruction::new(0x1F, "SLO", 3, 7, AddressingMode::AbsoluteX),
-        Instruction::new(0x20, "JSR", 3, 6, AddressingMode::Absolute),
-        Instruction::new(0x21, "AND", 2, 6, AddressingMode::IndexedIndirect),
-        Instruction::new(0x22, "KIL", 1, 1, AddressingMode::Implicit),
-        Instruction::new(0x23, "RLA", 2, 8, AddressingMode::IndexedIndirect),
-        Instruction::new(0x24, "BIT", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0x25, "AND", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0x26, "ROL", 2, 5, AddressingMode::ZeroPage),
-        Instruction::new(0x27, "RLA", 2, 5, AddressingMode::ZeroPage),
-        Instruction::new(0x28, "PLP", 1, 4, AddressingMode::Implicit),
-        Instruction::new(0x29, "AND", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0x2A, "ROL", 1, 2, AddressingMode::Accumulator),
-        Instruction::new(0x2B, "ANC", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0x2C, "BIT", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0x2D, "AND", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0x2E, "ROL", 3, 6, AddressingMode::Absolute),
-        Instruction::new(0x2F, "RLA", 3, 6, AddressingMode::Absolute),
-        Instruction::new(0x30, "BMI", 2, 2, AddressingMode::Relative),
-        Instruction::new(0x31, "AND", 2, 5, AddressingMode::IndirectIndexed),
-        Instruction::new(0x32, "KIL", 1, 1, AddressingMode::Implicit),
-        Instruction::new(0x33, "RLA", 2, 8, AddressingMode::IndirectIndexed),
-        Instruction::new(0x34, "NOP", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0x35, "AND", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0x36, "ROL", 2, 6, AddressingMode::ZeroPageX),
-        Instruction::new(0x37, "RLA", 2, 6, AddressingMode::ZeroPageX),
-        Instruction::new(0x38, "SEC", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0x39, "AND", 3, 4, AddressingMode::AbsoluteY),
-        Instruction::new(0x3A, "NOP", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0x3B, "RLA", 3, 7, AddressingMode::AbsoluteY),
-        Instruction::new(0x3C, "NOP", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0x3D, "AND", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0x3E, "ROL", 3, 7, AddressingMode::AbsoluteX),
-        Instruction::new(0x3F, "RLA", 3, 7, AddressingMode::AbsoluteX),
-        Instruction::new(0x40, "RTI", 1, 6, AddressingMode::Implicit),
-        Instruction::new(0x41, "EOR", 2, 6, AddressingMode::IndexedIndirect),
-        Instruction::new(0x42, "KIL", 1, 1, AddressingMode::Implicit),
-        Instruction::new(0x43, "SRE", 2, 8, AddressingMode::IndexedIndirect),
-        Instruction::new(0x44, "NOP", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0x45, "EOR", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0x46, "LSR", 2, 5, AddressingMode::ZeroPage),
-        Instruction::new(0x47, "SRE", 2, 5, AddressingMode::ZeroPage),
-        Instruction::new(0x48, "PHA", 1, 3, AddressingMode::Implicit),
-        Instruction::new(0x49, "EOR", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0x4A, "LSR", 1, 2, AddressingMode::Accumulator),
-        Instruction::new(0x4B, "ASR", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0x4C, "JMP", 3, 3, AddressingMode::Absolute),
-        Instruction::new(0x4D, "EOR", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0x4E, "LSR", 3, 6, AddressingMode::Absolute),
-        Instruction::new(0x4F, "SRE", 3, 6, AddressingMode::Absolute),
-        Instruction::new(0x50, "BVC", 2, 2, AddressingMode::Relative),
-        Instruction::new(0x51, "EOR", 2, 5, AddressingMode::IndirectIndexed),
-        Instruction::new(0x52, "KIL", 1, 1, AddressingMode::Implicit),
-        Instruction::new(0x53, "SRE", 2, 8, AddressingMode::IndirectIndexed),
-        Instruction::new(0x54, "NOP", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0x55, "EOR", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0x56, "LSR", 2, 6, AddressingMode::ZeroPageX),
-        Instruction::new(0x57, "SRE", 2, 6, AddressingMode::ZeroPageX),
-        Instruction::new(0x58, "CLI", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0x59, "EOR", 3, 4, AddressingMode::AbsoluteY),
-        Instruction::new(0x5A, "NOP", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0x5B, "SRE", 3, 7, AddressingMode::AbsoluteY),
-        Instruction::new(0x5C, "NOP", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0x5D, "EOR", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0x5E, "LSR", 3, 7, AddressingMode::AbsoluteX),
-        Instruction::new(0x5F, "SRE", 3, 7, AddressingMode::AbsoluteX),
-        Instruction::new(0x60, "RTS", 1, 6, AddressingMode::Implicit),
-        Instruction::new(0x61, "ADC", 2, 6, AddressingMode::IndexedIndirect),
-        Instruction::new(0x62, "KIL", 1, 1, AddressingMode::Implicit),
-        Instruction::new(0x63, "RRA", 2, 8, AddressingMode::IndexedIndirect),
-        Instruction::new(0x64, "NOP", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0x65, "ADC", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0x66, "ROR", 2, 5, AddressingMode::ZeroPage),
-        Instruction::new(0x67, "RRA", 2, 5, AddressingMode::ZeroPage),
-        Instruction::new(0x68, "PLA", 1, 4, AddressingMode::Implicit),
-        Instruction::new(0x69, "ADC", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0x6A, "ROR", 1, 2, AddressingMode::Accumulator),
-        Instruction::new(0x6B, "ARR", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0x6C, "JMP", 3, 5, AddressingMode::Indirect),
-        Instruction::new(0x6D, "ADC", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0x6E, "ROR", 3, 6, AddressingMode::Absolute),
-        Instruction::new(0x6F, "RRA", 3, 6, AddressingMode::Absolute),
-        Instruction::new(0x70, "BVS", 2, 2, AddressingMode::Relative),
-        Instruction::new(0x71, "ADC", 2, 5, AddressingMode::IndirectIndexed),
-        Instruction::new(0x72, "KIL", 1, 1, AddressingMode::Implicit),
-        Instruction::new(0x73, "RRA", 2, 8, AddressingMode::IndirectIndexed),
-        Instruction::new(0x74, "NOP", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0x75, "ADC", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0x76, "ROR", 2, 6, AddressingMode::ZeroPageX),
-        Instruction::new(0x77, "RRA", 2, 6, AddressingMode::ZeroPageX),
-        Instruction::new(0x78, "SEI", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0x79, "ADC", 3, 4, AddressingMode::AbsoluteY),
-        Instruction::new(0x7A, "NOP", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0x7B, "RRA", 3, 7, AddressingMode::AbsoluteY),
-        Instruction::new(0x7C, "NOP", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0x7D, "ADC", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0x7E, "ROR", 3, 7, AddressingMode::AbsoluteX),
-        Instruction::new(0x7F, "RRA", 3, 7, AddressingMode::AbsoluteX),
-        Instruction::new(0x80, "NOP", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0x81, "STA", 2, 6, AddressingMode::IndexedIndirect),
-        Instruction::new(0x82, "NOP", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0x83, "SAX", 2, 6, AddressingMode::IndexedIndirect),
-        Instruction::new(0x84, "STY", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0x85, "STA", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0x86, "STX", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0x87, "SAX", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0x88, "DEY", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0x89, "NOP", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0x8A, "TXA", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0x8B, "XAA", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0x8C, "STY", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0x8D, "STA", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0x8E, "STX", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0x8F, "SAX", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0x90, "BCC", 2, 2, AddressingMode::Relative),
-        Instruction::new(0x91, "STA", 2, 6, AddressingMode::IndirectIndexed),
-        Instruction::new(0x92, "KIL", 1, 1, AddressingMode::Implicit),
-        Instruction::new(0x93, "AHX", 2, 6, AddressingMode::IndirectIndexed),
-        Instruction::new(0x94, "STY", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0x95, "STA", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0x96, "STX", 2, 4, AddressingMode::ZeroPageY),
-        Instruction::new(0x97, "SAX", 2, 4, AddressingMode::ZeroPageY),
-        Instruction::new(0x98, "TYA", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0x99, "STA", 3, 5, AddressingMode::AbsoluteY),
-        Instruction::new(0x9A, "TXS", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0x9B, "TAS", 3, 5, AddressingMode::AbsoluteY),
-        Instruction::new(0x9C, "SHY", 3, 5, AddressingMode::AbsoluteX),
-        Instruction::new(0x9D, "STA", 3, 5, AddressingMode::AbsoluteX),
-        Instruction::new(0x9E, "SHX", 3, 5, AddressingMode::AbsoluteY),
-        Instruction::new(0x9F, "AHX", 3, 5, AddressingMode::AbsoluteY),
-        Instruction::new(0xA0, "LDY", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0xA1, "LDA", 2, 6, AddressingMode::IndexedIndirect),
-        Instruction::new(0xA2, "LDX", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0xA3, "LAX", 2, 6, AddressingMode::IndexedIndirect),
-        Instruction::new(0xA4, "LDY", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0xA5, "LDA", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0xA6, "LDX", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0xA7, "LAX", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0xA8, "TAY", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0xA9, "LDA", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0xAA, "TAX", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0xAB, "LAX", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0xAC, "LDY", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0xAD, "LDA", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0xAE, "LDX", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0xAF, "LAX", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0xB0, "BCS", 2, 2, AddressingMode::Relative),
-        Instruction::new(0xB1, "LDA", 2, 5, AddressingMode::IndirectIndexed),
-        Instruction::new(0xB2, "KIL", 1, 1, AddressingMode::Implicit),
-        Instruction::new(0xB3, "LAX", 2, 5, AddressingMode::IndirectIndexed),
-        Instruction::new(0xB4, "LDY", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0xB5, "LDA", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0xB6, "LDX", 2, 4, AddressingMode::ZeroPageY),
-        Instruction::new(0xB7, "LAX", 2, 4, AddressingMode::ZeroPageY),
-        Instruction::new(0xB8, "CLV", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0xB9, "LDA", 3, 4, AddressingMode::AbsoluteY),
-        Instruction::new(0xBA, "TSX", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0xBB, "LAS", 3, 4, AddressingMode::AbsoluteY),
-        Instruction::new(0xBC, "LDY", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0xBD, "LDA", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0xBE, "LDX", 3, 4, AddressingMode::AbsoluteY),
-        Instruction::new(0xBF, "LAX", 3, 4, AddressingMode::AbsoluteY),
-        Instruction::new(0xC0, "CPY", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0xC1, "CMP", 2, 6, AddressingMode::IndexedIndirect),
-        Instruction::new(0xC2, "NOP", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0xC3, "DCP", 2, 8, AddressingMode::IndexedIndirect),
-        Instruction::new(0xC4, "CPY", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0xC5, "CMP", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0xC6, "DEC", 2, 5, AddressingMode::ZeroPage),
-        Instruction::new(0xC7, "DCP", 2, 5, AddressingMode::ZeroPage),
-        Instruction::new(0xC8, "INY", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0xC9, "CMP", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0xCA, "DEX", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0xCB, "AXS", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0xCC, "CPY", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0xCD, "CMP", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0xCE, "DEC", 3, 6, AddressingMode::Absolute),
-        Instruction::new(0xCF, "DCP", 3, 6, AddressingMode::Absolute),
-        Instruction::new(0xD0, "BNE", 2, 2, AddressingMode::Relative),
-        Instruction::new(0xD1, "CMP", 2, 5, AddressingMode::IndirectIndexed),
-        Instruction::new(0xD2, "KIL", 1, 1, AddressingMode::Implicit),
-        Instruction::new(0xD3, "DCP", 2, 8, AddressingMode::IndirectIndexed),
-        Instruction::new(0xD4, "NOP", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0xD5, "CMP", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0xD6, "DEC", 2, 6, AddressingMode::ZeroPageX),
-        Instruction::new(0xD7, "DCP", 2, 6, AddressingMode::ZeroPageX),
-        Instruction::new(0xD8, "CLD", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0xD9, "CMP", 3, 4, AddressingMode::AbsoluteY),
-        Instruction::new(0xDA, "NOP", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0xDB, "DCP", 3, 7, AddressingMode::AbsoluteY),
-        Instruction::new(0xDC, "NOP", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0xDD, "CMP", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0xDE, "DEC", 3, 7, AddressingMode::AbsoluteX),
-        Instruction::new(0xDF, "DCP", 3, 7, AddressingMode::AbsoluteX),
-        Instruction::new(0xE0, "CPX", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0xE1, "SBC", 2, 6, AddressingMode::IndexedIndirect),
-        Instruction::new(0xE2, "NOP", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0xE3, "ISC", 2, 8, AddressingMode::IndexedIndirect),
-        Instruction::new(0xE4, "CPX", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0xE5, "SBC", 2, 3, AddressingMode::ZeroPage),
-        Instruction::new(0xE6, "INC", 2, 5, AddressingMode::ZeroPage),
-        Instruction::new(0xE7, "ISC", 2, 5, AddressingMode::ZeroPage),
-        Instruction::new(0xE8, "INX", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0xE9, "SBC", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0xEA, "NOP", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0xEB, "SBC", 2, 2, AddressingMode::Immediate),
-        Instruction::new(0xEC, "CPX", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0xED, "SBC", 3, 4, AddressingMode::Absolute),
-        Instruction::new(0xEE, "INC", 3, 6, AddressingMode::Absolute),
-        Instruction::new(0xEF, "ISC", 3, 6, AddressingMode::Absolute),
-        Instruction::new(0xF0, "BEQ", 2, 2, AddressingMode::Relative),
-        Instruction::new(0xF1, "SBC", 2, 5, AddressingMode::IndirectIndexed),
-        Instruction::new(0xF2, "KIL", 1, 1, AddressingMode::Implicit),
-        Instruction::new(0xF3, "ISC", 2, 8, AddressingMode::IndirectIndexed),
-        Instruction::new(0xF4, "NOP", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0xF5, "SBC", 2, 4, AddressingMode::ZeroPageX),
-        Instruction::new(0xF6, "INC", 2, 6, AddressingMode::ZeroPageX),
-        Instruction::new(0xF7, "ISC", 2, 6, AddressingMode::ZeroPageX),
-        Instruction::new(0xF8, "SED", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0xF9, "SBC", 3, 4, AddressingMode::AbsoluteY),
-        Instruction::new(0xFA, "NOP", 1, 2, AddressingMode::Implicit),
-        Instruction::new(0xFB, "ISC", 3, 7, AddressingMode::AbsoluteY),
-        Instruction::new(0xFC, "NOP", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0xFD, "SBC", 3, 4, AddressingMode::AbsoluteX),
-        Instruction::new(0xFE, "INC", 3, 7, AddressingMode::AbsoluteX),
-        Instruction::new(0xFF, "ISC", 3, 7, AddressingMode::AbsoluteX),
+pub(crate) static INSTRUCTIONS: [Instruction; 256] = [
+        Instruction::new(0x00, Opcode::Brk, 1, 7, AddressingMode::Implicit),
+        Instruction::new(0x01, Opcode::Ora, 2, 6, AddressingMode::IndexedIndirect),
+        Instruction::new(0x02, Opcode::Kil, 1, 1, AddressingMode::Implicit),
+        Instruction::new(0x03, Opcode::Slo, 2, 8, AddressingMode::IndexedIndirect),
+        Instruction::new(0x04, Opcode::Nop, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0x05, Opcode::Ora, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0x06, Opcode::Asl, 2, 5, AddressingMode::ZeroPage),
+        Instruction::new(0x07, Opcode::Slo, 2, 5, AddressingMode::ZeroPage),
+        Instruction::new(0x08, Opcode::Php, 1, 3, AddressingMode::Implicit),
+        Instruction::new(0x09, Opcode::Ora, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0x0A, Opcode::Asl, 1, 2, AddressingMode::Accumulator),
+        Instruction::new(0x0B, Opcode::Anc, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0x0C, Opcode::Nop, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0x0D, Opcode::Ora, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0x0E, Opcode::Asl, 3, 6, AddressingMode::Absolute),
+        Instruction::new(0x0F, Opcode::Slo, 3, 6, AddressingMode::Absolute),
+        Instruction::new(0x10, Opcode::Bpl, 2, 2, AddressingMode::Relative),
+        Instruction::new(0x11, Opcode::Ora, 2, 5, AddressingMode::IndirectIndexed).with_page_cross_cycle(),
+        Instruction::new(0x12, Opcode::Kil, 1, 1, AddressingMode::Implicit),
+        Instruction::new(0x13, Opcode::Slo, 2, 8, AddressingMode::IndirectIndexed),
+        Instruction::new(0x14, Opcode::Nop, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0x15, Opcode::Ora, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0x16, Opcode::Asl, 2, 6, AddressingMode::ZeroPageX),
+        Instruction::new(0x17, Opcode::Slo, 2, 6, AddressingMode::ZeroPageX),
+        Instruction::new(0x18, Opcode::Clc, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0x19, Opcode::Ora, 3, 4, AddressingMode::AbsoluteY).with_page_cross_cycle(),
+        Instruction::new(0x1A, Opcode::Nop, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0x1B, Opcode::Slo, 3, 7, AddressingMode::AbsoluteY),
+        Instruction::new(0x1C, Opcode::Nop, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0x1D, Opcode::Ora, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0x1E, Opcode::Asl, 3, 7, AddressingMode::AbsoluteX),
+        Instruction::new(0x1F, Opcode::Slo, 3, 7, AddressingMode::AbsoluteX),
+        Instruction::new(0x20, Opcode::Jsr, 3, 6, AddressingMode::Absolute),
+        Instruction::new(0x21, Opcode::And, 2, 6, AddressingMode::IndexedIndirect),
+        Instruction::new(0x22, Opcode::Kil, 1, 1, AddressingMode::Implicit),
+        Instruction::new(0x23, Opcode::Rla, 2, 8, AddressingMode::IndexedIndirect),
+        Instruction::new(0x24, Opcode::Bit, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0x25, Opcode::And, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0x26, Opcode::Rol, 2, 5, AddressingMode::ZeroPage),
+        Instruction::new(0x27, Opcode::Rla, 2, 5, AddressingMode::ZeroPage),
+        Instruction::new(0x28, Opcode::Plp, 1, 4, AddressingMode::Implicit),
+        Instruction::new(0x29, Opcode::And, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0x2A, Opcode::Rol, 1, 2, AddressingMode::Accumulator),
+        Instruction::new(0x2B, Opcode::Anc, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0x2C, Opcode::Bit, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0x2D, Opcode::And, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0x2E, Opcode::Rol, 3, 6, AddressingMode::Absolute),
+        Instruction::new(0x2F, Opcode::Rla, 3, 6, AddressingMode::Absolute),
+        Instruction::new(0x30, Opcode::Bmi, 2, 2, AddressingMode::Relative),
+        Instruction::new(0x31, Opcode::And, 2, 5, AddressingMode::IndirectIndexed).with_page_cross_cycle(),
+        Instruction::new(0x32, Opcode::Kil, 1, 1, AddressingMode::Implicit),
+        Instruction::new(0x33, Opcode::Rla, 2, 8, AddressingMode::IndirectIndexed),
+        Instruction::new(0x34, Opcode::Nop, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0x35, Opcode::And, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0x36, Opcode::Rol, 2, 6, AddressingMode::ZeroPageX),
+        Instruction::new(0x37, Opcode::Rla, 2, 6, AddressingMode::ZeroPageX),
+        Instruction::new(0x38, Opcode::Sec, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0x39, Opcode::And, 3, 4, AddressingMode::AbsoluteY).with_page_cross_cycle(),
+        Instruction::new(0x3A, Opcode::Nop, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0x3B, Opcode::Rla, 3, 7, AddressingMode::AbsoluteY),
+        Instruction::new(0x3C, Opcode::Nop, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0x3D, Opcode::And, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0x3E, Opcode::Rol, 3, 7, AddressingMode::AbsoluteX),
+        Instruction::new(0x3F, Opcode::Rla, 3, 7, AddressingMode::AbsoluteX),
+        Instruction::new(0x40, Opcode::Rti, 1, 6, AddressingMode::Implicit),
+        Instruction::new(0x41, Opcode::Eor, 2, 6, AddressingMode::IndexedIndirect),
+        Instruction::new(0x42, Opcode::Kil, 1, 1, AddressingMode::Implicit),
+        Instruction::new(0x43, Opcode::Sre, 2, 8, AddressingMode::IndexedIndirect),
+        Instruction::new(0x44, Opcode::Nop, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0x45, Opcode::Eor, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0x46, Opcode::Lsr, 2, 5, AddressingMode::ZeroPage),
+        Instruction::new(0x47, Opcode::Sre, 2, 5, AddressingMode::ZeroPage),
+        Instruction::new(0x48, Opcode::Pha, 1, 3, AddressingMode::Implicit),
+        Instruction::new(0x49, Opcode::Eor, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0x4A, Opcode::Lsr, 1, 2, AddressingMode::Accumulator),
+        Instruction::new(0x4B, Opcode::Alr, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0x4C, Opcode::Jmp, 3, 3, AddressingMode::Absolute),
+        Instruction::new(0x4D, Opcode::Eor, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0x4E, Opcode::Lsr, 3, 6, AddressingMode::Absolute),
+        Instruction::new(0x4F, Opcode::Sre, 3, 6, AddressingMode::Absolute),
+        Instruction::new(0x50, Opcode::Bvc, 2, 2, AddressingMode::Relative),
+        Instruction::new(0x51, Opcode::Eor, 2, 5, AddressingMode::IndirectIndexed).with_page_cross_cycle(),
+        Instruction::new(0x52, Opcode::Kil, 1, 1, AddressingMode::Implicit),
+        Instruction::new(0x53, Opcode::Sre, 2, 8, AddressingMode::IndirectIndexed),
+        Instruction::new(0x54, Opcode::Nop, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0x55, Opcode::Eor, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0x56, Opcode::Lsr, 2, 6, AddressingMode::ZeroPageX),
+        Instruction::new(0x57, Opcode::Sre, 2, 6, AddressingMode::ZeroPageX),
+        Instruction::new(0x58, Opcode::Cli, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0x59, Opcode::Eor, 3, 4, AddressingMode::AbsoluteY).with_page_cross_cycle(),
+        Instruction::new(0x5A, Opcode::Nop, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0x5B, Opcode::Sre, 3, 7, AddressingMode::AbsoluteY),
+        Instruction::new(0x5C, Opcode::Nop, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0x5D, Opcode::Eor, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0x5E, Opcode::Lsr, 3, 7, AddressingMode::AbsoluteX),
+        Instruction::new(0x5F, Opcode::Sre, 3, 7, AddressingMode::AbsoluteX),
+        Instruction::new(0x60, Opcode::Rts, 1, 6, AddressingMode::Implicit),
+        Instruction::new(0x61, Opcode::Adc, 2, 6, AddressingMode::IndexedIndirect),
+        Instruction::new(0x62, Opcode::Kil, 1, 1, AddressingMode::Implicit),
+        Instruction::new(0x63, Opcode::Rra, 2, 8, AddressingMode::IndexedIndirect),
+        Instruction::new(0x64, Opcode::Nop, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0x65, Opcode::Adc, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0x66, Opcode::Ror, 2, 5, AddressingMode::ZeroPage),
+        Instruction::new(0x67, Opcode::Rra, 2, 5, AddressingMode::ZeroPage),
+        Instruction::new(0x68, Opcode::Pla, 1, 4, AddressingMode::Implicit),
+        Instruction::new(0x69, Opcode::Adc, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0x6A, Opcode::Ror, 1, 2, AddressingMode::Accumulator),
+        Instruction::new(0x6B, Opcode::Arr, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0x6C, Opcode::Jmp, 3, 5, AddressingMode::Indirect),
+        Instruction::new(0x6D, Opcode::Adc, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0x6E, Opcode::Ror, 3, 6, AddressingMode::Absolute),
+        Instruction::new(0x6F, Opcode::Rra, 3, 6, AddressingMode::Absolute),
+        Instruction::new(0x70, Opcode::Bvs, 2, 2, AddressingMode::Relative),
+        Instruction::new(0x71, Opcode::Adc, 2, 5, AddressingMode::IndirectIndexed).with_page_cross_cycle(),
+        Instruction::new(0x72, Opcode::Kil, 1, 1, AddressingMode::Implicit),
+        Instruction::new(0x73, Opcode::Rra, 2, 8, AddressingMode::IndirectIndexed),
+        Instruction::new(0x74, Opcode::Nop, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0x75, Opcode::Adc, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0x76, Opcode::Ror, 2, 6, AddressingMode::ZeroPageX),
+        Instruction::new(0x77, Opcode::Rra, 2, 6, AddressingMode::ZeroPageX),
+        Instruction::new(0x78, Opcode::Sei, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0x79, Opcode::Adc, 3, 4, AddressingMode::AbsoluteY).with_page_cross_cycle(),
+        Instruction::new(0x7A, Opcode::Nop, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0x7B, Opcode::Rra, 3, 7, AddressingMode::AbsoluteY),
+        Instruction::new(0x7C, Opcode::Nop, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0x7D, Opcode::Adc, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0x7E, Opcode::Ror, 3, 7, AddressingMode::AbsoluteX),
+        Instruction::new(0x7F, Opcode::Rra, 3, 7, AddressingMode::AbsoluteX),
+        Instruction::new(0x80, Opcode::Nop, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0x81, Opcode::Sta, 2, 6, AddressingMode::IndexedIndirect),
+        Instruction::new(0x82, Opcode::Nop, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0x83, Opcode::Sax, 2, 6, AddressingMode::IndexedIndirect),
+        Instruction::new(0x84, Opcode::Sty, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0x85, Opcode::Sta, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0x86, Opcode::Stx, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0x87, Opcode::Sax, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0x88, Opcode::Dey, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0x89, Opcode::Nop, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0x8A, Opcode::Txa, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0x8B, Opcode::Xaa, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0x8C, Opcode::Sty, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0x8D, Opcode::Sta, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0x8E, Opcode::Stx, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0x8F, Opcode::Sax, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0x90, Opcode::Bcc, 2, 2, AddressingMode::Relative),
+        Instruction::new(0x91, Opcode::Sta, 2, 6, AddressingMode::IndirectIndexed),
+        Instruction::new(0x92, Opcode::Kil, 1, 1, AddressingMode::Implicit),
+        Instruction::new(0x93, Opcode::Ahx, 2, 6, AddressingMode::IndirectIndexed),
+        Instruction::new(0x94, Opcode::Sty, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0x95, Opcode::Sta, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0x96, Opcode::Stx, 2, 4, AddressingMode::ZeroPageY),
+        Instruction::new(0x97, Opcode::Sax, 2, 4, AddressingMode::ZeroPageY),
+        Instruction::new(0x98, Opcode::Tya, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0x99, Opcode::Sta, 3, 5, AddressingMode::AbsoluteY),
+        Instruction::new(0x9A, Opcode::Txs, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0x9B, Opcode::Tas, 3, 5, AddressingMode::AbsoluteY),
+        Instruction::new(0x9C, Opcode::Shy, 3, 5, AddressingMode::AbsoluteX),
+        Instruction::new(0x9D, Opcode::Sta, 3, 5, AddressingMode::AbsoluteX),
+        Instruction::new(0x9E, Opcode::Shx, 3, 5, AddressingMode::AbsoluteY),
+        Instruction::new(0x9F, Opcode::Ahx, 3, 5, AddressingMode::AbsoluteY),
+        Instruction::new(0xA0, Opcode::Ldy, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0xA1, Opcode::Lda, 2, 6, AddressingMode::IndexedIndirect),
+        Instruction::new(0xA2, Opcode::Ldx, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0xA3, Opcode::Lax, 2, 6, AddressingMode::IndexedIndirect),
+        Instruction::new(0xA4, Opcode::Ldy, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0xA5, Opcode::Lda, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0xA6, Opcode::Ldx, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0xA7, Opcode::Lax, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0xA8, Opcode::Tay, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0xA9, Opcode::Lda, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0xAA, Opcode::Tax, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0xAB, Opcode::Lax, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0xAC, Opcode::Ldy, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0xAD, Opcode::Lda, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0xAE, Opcode::Ldx, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0xAF, Opcode::Lax, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0xB0, Opcode::Bcs, 2, 2, AddressingMode::Relative),
+        Instruction::new(0xB1, Opcode::Lda, 2, 5, AddressingMode::IndirectIndexed).with_page_cross_cycle(),
+        Instruction::new(0xB2, Opcode::Kil, 1, 1, AddressingMode::Implicit),
+        Instruction::new(0xB3, Opcode::Lax, 2, 5, AddressingMode::IndirectIndexed).with_page_cross_cycle(),
+        Instruction::new(0xB4, Opcode::Ldy, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0xB5, Opcode::Lda, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0xB6, Opcode::Ldx, 2, 4, AddressingMode::ZeroPageY),
+        Instruction::new(0xB7, Opcode::Lax, 2, 4, AddressingMode::ZeroPageY),
+        Instruction::new(0xB8, Opcode::Clv, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0xB9, Opcode::Lda, 3, 4, AddressingMode::AbsoluteY).with_page_cross_cycle(),
+        Instruction::new(0xBA, Opcode::Tsx, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0xBB, Opcode::Las, 3, 4, AddressingMode::AbsoluteY).with_page_cross_cycle(),
+        Instruction::new(0xBC, Opcode::Ldy, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0xBD, Opcode::Lda, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0xBE, Opcode::Ldx, 3, 4, AddressingMode::AbsoluteY).with_page_cross_cycle(),
+        Instruction::new(0xBF, Opcode::Lax, 3, 4, AddressingMode::AbsoluteY).with_page_cross_cycle(),
+        Instruction::new(0xC0, Opcode::Cpy, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0xC1, Opcode::Cmp, 2, 6, AddressingMode::IndexedIndirect),
+        Instruction::new(0xC2, Opcode::Nop, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0xC3, Opcode::Dcp, 2, 8, AddressingMode::IndexedIndirect),
+        Instruction::new(0xC4, Opcode::Cpy, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0xC5, Opcode::Cmp, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0xC6, Opcode::Dec, 2, 5, AddressingMode::ZeroPage),
+        Instruction::new(0xC7, Opcode::Dcp, 2, 5, AddressingMode::ZeroPage),
+        Instruction::new(0xC8, Opcode::Iny, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0xC9, Opcode::Cmp, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0xCA, Opcode::Dex, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0xCB, Opcode::Axs, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0xCC, Opcode::Cpy, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0xCD, Opcode::Cmp, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0xCE, Opcode::Dec, 3, 6, AddressingMode::Absolute),
+        Instruction::new(0xCF, Opcode::Dcp, 3, 6, AddressingMode::Absolute),
+        Instruction::new(0xD0, Opcode::Bne, 2, 2, AddressingMode::Relative),
+        Instruction::new(0xD1, Opcode::Cmp, 2, 5, AddressingMode::IndirectIndexed).with_page_cross_cycle(),
+        Instruction::new(0xD2, Opcode::Kil, 1, 1, AddressingMode::Implicit),
+        Instruction::new(0xD3, Opcode::Dcp, 2, 8, AddressingMode::IndirectIndexed),
+        Instruction::new(0xD4, Opcode::Nop, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0xD5, Opcode::Cmp, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0xD6, Opcode::Dec, 2, 6, AddressingMode::ZeroPageX),
+        Instruction::new(0xD7, Opcode::Dcp, 2, 6, AddressingMode::ZeroPageX),
+        Instruction::new(0xD8, Opcode::Cld, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0xD9, Opcode::Cmp, 3, 4, AddressingMode::AbsoluteY).with_page_cross_cycle(),
+        Instruction::new(0xDA, Opcode::Nop, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0xDB, Opcode::Dcp, 3, 7, AddressingMode::AbsoluteY),
+        Instruction::new(0xDC, Opcode::Nop, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0xDD, Opcode::Cmp, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0xDE, Opcode::Dec, 3, 7, AddressingMode::AbsoluteX),
+        Instruction::new(0xDF, Opcode::Dcp, 3, 7, AddressingMode::AbsoluteX),
+        Instruction::new(0xE0, Opcode::Cpx, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0xE1, Opcode::Sbc, 2, 6, AddressingMode::IndexedIndirect),
+        Instruction::new(0xE2, Opcode::Nop, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0xE3, Opcode::Isc, 2, 8, AddressingMode::IndexedIndirect),
+        Instruction::new(0xE4, Opcode::Cpx, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0xE5, Opcode::Sbc, 2, 3, AddressingMode::ZeroPage),
+        Instruction::new(0xE6, Opcode::Inc, 2, 5, AddressingMode::ZeroPage),
+        Instruction::new(0xE7, Opcode::Isc, 2, 5, AddressingMode::ZeroPage),
+        Instruction::new(0xE8, Opcode::Inx, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0xE9, Opcode::Sbc, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0xEA, Opcode::Nop, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0xEB, Opcode::Sbc, 2, 2, AddressingMode::Immediate),
+        Instruction::new(0xEC, Opcode::Cpx, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0xED, Opcode::Sbc, 3, 4, AddressingMode::Absolute),
+        Instruction::new(0xEE, Opcode::Inc, 3, 6, AddressingMode::Absolute),
+        Instruction::new(0xEF, Opcode::Isc, 3, 6, AddressingMode::Absolute),
+        Instruction::new(0xF0, Opcode::Beq, 2, 2, AddressingMode::Relative),
+        Instruction::new(0xF1, Opcode::Sbc, 2, 5, AddressingMode::IndirectIndexed).with_page_cross_cycle(),
+        Instruction::new(0xF2, Opcode::Kil, 1, 1, AddressingMode::Implicit),
+        Instruction::new(0xF3, Opcode::Isc, 2, 8, AddressingMode::IndirectIndexed),
+        Instruction::new(0xF4, Opcode::Nop, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0xF5, Opcode::Sbc, 2, 4, AddressingMode::ZeroPageX),
+        Instruction::new(0xF6, Opcode::Inc, 2, 6, AddressingMode::ZeroPageX),
+        Instruction::new(0xF7, Opcode::Isc, 2, 6, AddressingMode::ZeroPageX),
+        Instruction::new(0xF8, Opcode::Sed, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0xF9, Opcode::Sbc, 3, 4, AddressingMode::AbsoluteY).with_page_cross_cycle(),
+        Instruction::new(0xFA, Opcode::Nop, 1, 2, AddressingMode::Implicit),
+        Instruction::new(0xFB, Opcode::Isc, 3, 7, AddressingMode::AbsoluteY),
+        Instruction::new(0xFC, Opcode::Nop, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0xFD, Opcode::Sbc, 3, 4, AddressingMode::AbsoluteX).with_page_cross_cycle(),
+        Instruction::new(0xFE, Opcode::Inc, 3, 7, AddressingMode::AbsoluteX),
+        Instruction::new(0xFF, Opcode::Isc, 3, 7, AddressingMode::AbsoluteX),
 ];
 
+const BRK_NMI_HIJACK_CYCLES: usize = 4;
+const OAM_DMA_CYCLES: usize = 513;
+const HISTORY_CAPACITY: usize = 256;
+
+/// Constant XAA mixes into register A, chips and even temperatures disagree on it.
+/// 0xEE matches most NMOS 2A03s and is what test ROMs usually expect.
+pub const DEFAULT_UNSTABLE_MAGIC: u8 = 0xEE;
+
+/// Behaviour of the unstable opcodes, pinned so tests don't depend on the modeled chip.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnstableOpcodes {
+    // Constant XAA mixes into register A
+    pub magic: u8,
+    // SHX, SHY, AHX and TAS AND the value with the high byte of the base address plus one, and
+    // on page cross store it at that high byte. Off they store the plain value at the indexed
+    // address, like when a DMA halts the CPU right before the write
+    pub high_byte_and: bool,
+}
+
+impl Default for UnstableOpcodes {
+    fn default() -> Self {
+        Self {
+            magic: DEFAULT_UNSTABLE_MAGIC,
+            high_byte_and: true,
+        }
+    }
+}
+
+/// What the CPU does with the KIL opcodes, which lock up real hardware.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IllegalOpcodePolicy {
+    Panic,
+    // Skips the opcode with the bytes and cycles of the instruction table
+    TreatAsNop,
+    // Jams like real hardware until reset
+    Halt,
+}
+
+/// Registers, cycle counter and interrupt latches of the CPU, for debuggers, tests and emulator snapshots.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "savestate", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuState {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub p: u8,
+    pub sp: u8,
+    pub pc: u16,
+    pub cycles: usize,
+    pub halted: bool,
+    // I flag the next interrupt poll sees after CLI, SEI or PLP
+    pub delayed_interrupt_disable: Option<bool>,
+    // Raised by `Cpu::irq` and not serviced yet
+    pub irq_requested: bool,
+    // NMI edge latched on the bus and not serviced yet
+    pub nmi_pending: bool,
+}
+
+/// Instruction the CPU executed, with the registers before it ran.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub pc: u16,
+    pub opcode: u8,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub p: u8,
+    pub sp: u8,
+}
+
+impl fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let instruction = &INSTRUCTIONS[self.opcode as usize];
+        let marker = if instruction.is_unofficial() { '*' } else { ' ' };
+
+        write!(
+            f,
+            "{:04X}  {:02X}  {}{}  A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+            self.pc, self.opcode, marker, instruction.operation.mnemonic(),
+            self.a, self.x, self.y, self.p, self.sp
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WatchKind {
+    Read,
+    Write,
+}
+
+/// Access to a watched address, `pc` is the instruction that made it.
+#[derive(Debug, PartialEq)]
+pub struct WatchpointHit {
+    pub pc: u16,
+    pub address: u16,
+    pub value: u8,
+    pub kind: WatchKind,
+}
+
+/// Outcome of `Cpu::step`.
+#[derive(Debug, PartialEq)]
+pub enum StepResult {
+    Executed(usize),
+    // The instruction at the address wasn't executed, the next step runs it
+    BreakpointHit(u16),
+    // The instruction completed in `cycles`, only its first watched access is reported
+    WatchpointHit { hit: WatchpointHit, cycles: usize },
+    // The opcode at `pc` was fetched from where the mapper has nothing mapped and ran in `cycles`, reported once
+    PcOutOfRom { pc: u16, cycles: usize },
+}
+
 struct InternalState {
-    current_instruction: String,
+    current_instruction: Opcode,
     args_length: u8,
+    unofficial: bool,
 }
 
 pub struct Cpu {
@@ -317,8 +636,40 @@ pub struct Cpu {
     program_counter: u16,
     internal_state: Option<InternalState>,
     bus: Rc<RefCell<Bus>>,
+    // Same RAM the bus maps at $0000-$1FFF, held here so RAM accesses skip the bus borrow
+    ram: InternalRam,
     clock: Rc<RefCell<Clock>>,
     use_disassembler: bool,
+    page_crossed: bool,
+    trace_sink: Option<TraceSink>,
+    // First write error of the trace sink, tracing stops there
+    trace_error: Option<io::Error>,
+    halted: bool,
+    unstable_opcodes: UnstableOpcodes,
+    breakpoints: HashSet<u16>,
+    resume_from_breakpoint: bool,
+    watchpoints: Vec<(RangeInclusive<u16>, WatchKind)>,
+    watched_access: RefCell<Option<(u16, u8, WatchKind)>>,
+    // Cycles the current instruction ticked before completing, so they aren't ticked twice
+    cycles_ticked: usize,
+    bcd_enabled: bool,
+    oam_dma_pending: bool,
+    // CLI, SEI and PLP change the I flag after the IRQ poll, the next instruction still sees the old one
+    delayed_interrupt_disable: Option<bool>,
+    // Raised by `irq`, held until the IRQ is serviced
+    irq_requested: bool,
+    code_data_logger: Option<RefCell<CodeDataLogger>>,
+    symbols: SymbolTable,
+    // Tells the logger which reads fetch the current instruction
+    instruction_address: u16,
+    #[cfg(feature = "flat-memory")]
+    flat_memory: Option<Box<dyn Memory>>,
+    illegal_opcode_policy: IllegalOpcodePolicy,
+    // Armed until the first opcode fetch from unmapped cartridge space
+    detect_pc_out_of_rom: bool,
+    pc_out_of_rom: Option<u16>,
+    // Last executed instructions, oldest first
+    history: Option<VecDeque<HistoryEntry>>,
 }
 
 impl Cpu {
@@ -332,17 +683,334 @@ impl Cpu {
             program_counter: 0x8000,
             internal_state: None,
             bus: bus.clone(),
+            ram: bus.borrow_mut().cpu_memory_map().internal_ram().clone(),
             clock: clock.clone(),
             use_disassembler: false,
+            page_crossed: false,
+            trace_sink: None,
+            trace_error: None,
+            halted: false,
+            unstable_opcodes: UnstableOpcodes::default(),
+            breakpoints: HashSet::new(),
+            resume_from_breakpoint: false,
+            watchpoints: Vec::new(),
+            watched_access: RefCell::new(None),
+            cycles_ticked: 0,
+            bcd_enabled: false,
+            oam_dma_pending: false,
+            delayed_interrupt_disable: None,
+            irq_requested: false,
+            code_data_logger: None,
+            symbols: SymbolTable::default(),
+            instruction_address: 0x0000,
+            #[cfg(feature = "flat-memory")]
+            flat_memory: None,
+            illegal_opcode_policy: IllegalOpcodePolicy::Halt,
+            detect_pc_out_of_rom: false,
+            pc_out_of_rom: None,
+            history: None,
         }
     }
 
+    // Traces to buffered stdout unless a sink was installed before
     pub fn use_disassembler(&mut self, active: bool) {
-        if active {
-            self.use_disassembler = true;
-        } else {
-            self.use_disassembler = false;
+        if active && self.trace_sink.is_none() {
+            self.trace_sink = Some(TraceSink::stdout());
+        }
+
+        self.use_disassembler = active;
+    }
+
+    pub fn use_disassembler_with(&mut self, sink: TraceSink) {
+        self.trace_sink = Some(sink);
+        self.trace_error = None;
+        self.use_disassembler = true;
+    }
+
+    // A failed write turns the disassembler off, e.g. when the reader of piped stdout quits
+    pub fn take_trace_error(&mut self) -> Option<io::Error> {
+        self.trace_error.take()
+    }
+
+    pub fn flush_trace(&mut self) -> io::Result<()> {
+        match self.trace_sink.as_mut() {
+            Some(sink) => sink.flush(),
+            None => Ok(()),
+        }
+    }
+
+    // Labels replace absolute addresses in the trace
+    pub fn set_symbols(&mut self, symbols: SymbolTable) {
+        self.symbols = symbols;
+    }
+
+    pub fn set_unstable_opcodes(&mut self, unstable_opcodes: UnstableOpcodes) {
+        self.unstable_opcodes = unstable_opcodes;
+    }
+
+    /// Replaces the NES memory map with a flat 64KB address space, so CPU test
+    /// vectors can put code and data anywhere. The PPU and mappers are no longer reachable.
+    #[cfg(feature = "flat-memory")]
+    pub fn set_flat_memory(&mut self, memory: Box<dyn Memory>) {
+        self.flat_memory = Some(memory);
+    }
+
+    #[cfg(feature = "flat-memory")]
+    fn has_flat_memory(&self) -> bool {
+        self.flat_memory.is_some()
+    }
+
+    // Without the feature every access goes through the memory map
+    #[cfg(not(feature = "flat-memory"))]
+    fn has_flat_memory(&self) -> bool {
+        false
+    }
+
+    // The 2A03 has no decimal mode, a plain 6502 honors the D flag in ADC and SBC
+    pub fn set_bcd_enabled(&mut self, enabled: bool) {
+        self.bcd_enabled = enabled;
+    }
+
+    pub fn set_illegal_opcode_policy(&mut self, policy: IllegalOpcodePolicy) {
+        self.illegal_opcode_policy = policy;
+    }
+
+    /// Makes `step` return `StepResult::PcOutOfRom` the first time an opcode is fetched
+    /// from an address the mapper reports as unmapped, instead of running off into open bus.
+    pub fn enable_pc_out_of_rom_detection(&mut self) {
+        self.detect_pc_out_of_rom = true;
+    }
+
+    fn check_pc_out_of_rom(&mut self) {
+        let address = self.program_counter;
+
+        if !self.detect_pc_out_of_rom || self.has_flat_memory() || address < 0x4020 {
+            return;
+        }
+
+        if !self.bus.borrow_mut().cpu_memory_map().mapper().borrow().is_prg_mapped(address) {
+            self.detect_pc_out_of_rom = false;
+            self.pc_out_of_rom = Some(address);
+        }
+    }
+
+    /// Keeps the last 256 executed instructions, they are printed when the emulator panics.
+    pub fn enable_history(&mut self) {
+        self.history = Some(VecDeque::with_capacity(HISTORY_CAPACITY));
+    }
+
+    pub fn history(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.history.iter().flatten()
+    }
+
+    // One line per instruction, oldest first, in the layout of the trace log
+    pub fn format_history(&self) -> String {
+        self.history()
+            .map(HistoryEntry::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn record_history(&mut self, opcode: u8) {
+        let entry = HistoryEntry {
+            pc: self.instruction_address,
+            opcode,
+            a: self.register_a,
+            x: self.register_x,
+            y: self.register_y,
+            p: self.status.get(),
+            sp: self.stack_pointer,
+        };
+
+        if let Some(history) = &mut self.history {
+            if history.len() == HISTORY_CAPACITY {
+                history.pop_front();
+            }
+
+            history.push_back(entry);
+        }
+    }
+
+    fn is_decimal_mode(&self) -> bool {
+        self.bcd_enabled && self.status.get_flag(CpuStatusRegisterFlags::DecimalMode)
+    }
+
+    /// Starts recording how the cartridge's PRG-ROM gets accessed, see `CodeDataLogger`.
+    pub fn enable_code_data_logger(&mut self) {
+        let prg_rom_len = self.bus.borrow_mut().cpu_memory_map().mapper().borrow().prg_rom_len();
+        self.code_data_logger = Some(RefCell::new(CodeDataLogger::new(prg_rom_len)));
+    }
+
+    pub fn code_data_logger(&self) -> Option<Ref<'_, CodeDataLogger>> {
+        self.code_data_logger.as_ref().map(RefCell::borrow)
+    }
+
+    // Bytes of the current instruction are its opcode and operand, anything else is data
+    fn access_kind(&self, address: u16) -> CdlAccess {
+        let args_length = self.internal_state.as_ref().map_or(0, |state| state.args_length) as u16;
+
+        match address.wrapping_sub(self.instruction_address) {
+            0 => CdlAccess::Opcode,
+            offset if offset <= args_length => CdlAccess::Operand,
+            _ => CdlAccess::Data,
+        }
+    }
+
+    fn log_access(&self, logger: &RefCell<CodeDataLogger>, address: u16) {
+        if self.has_flat_memory() || address < 0x4020 {
+            return;
         }
+
+        let access = self.access_kind(address);
+        let prg_rom_address = self.bus.borrow_mut().cpu_memory_map().mapper().borrow().prg_rom_address(address);
+
+        if let Some(prg_rom_address) = prg_rom_address {
+            logger.borrow_mut().log(prg_rom_address, address, access);
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.breakpoints.remove(&address);
+    }
+
+    pub fn add_watchpoint(&mut self, range: RangeInclusive<u16>, kind: WatchKind) {
+        self.watchpoints.push((range, kind));
+    }
+
+    pub fn remove_watchpoint(&mut self, range: RangeInclusive<u16>, kind: WatchKind) {
+        self.watchpoints.retain(|watchpoint| *watchpoint != (range.clone(), kind));
+    }
+
+    // Read watchpoints only match data, not the instruction's own opcode and operand
+    fn watch_access(&self, address: u16, value: u8, kind: WatchKind) {
+        if kind == WatchKind::Read && self.access_kind(address) != CdlAccess::Data {
+            return;
+        }
+
+        let is_watched = self.watchpoints
+            .iter()
+            .any(|(range, watched_kind)| *watched_kind == kind && range.contains(&address));
+
+        if is_watched {
+            self.watched_access.borrow_mut().get_or_insert((address, value, kind));
+        }
+    }
+
+    /// Signals an NMI edge, it's serviced before the next instruction. Repeated calls
+    /// before that still trigger a single NMI.
+    pub fn nmi(&mut self) {
+        self.bus.borrow_mut().set_interrupt(Some(()));
+    }
+
+    /// Requests an IRQ, it stays pending while InterruptDisable is set and is
+    /// dropped once serviced.
+    pub fn irq(&mut self) {
+        self.irq_requested = true;
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            a: self.register_a,
+            x: self.register_x,
+            y: self.register_y,
+            p: self.status.get(),
+            sp: self.stack_pointer,
+            pc: self.program_counter,
+            cycles: self.clock.borrow().get_cycles(),
+            halted: self.halted,
+            delayed_interrupt_disable: self.delayed_interrupt_disable,
+            irq_requested: self.irq_requested,
+            nmi_pending: self.bus.borrow().get_interrupt().is_some(),
+        }
+    }
+
+    pub fn load_state(&mut self, state: CpuState) {
+        self.register_a = state.a;
+        self.register_x = state.x;
+        self.register_y = state.y;
+        self.status.set(state.p);
+        self.stack_pointer = state.sp;
+        self.program_counter = state.pc;
+        self.clock.borrow_mut().set_cycles(state.cycles);
+        self.halted = state.halted;
+        self.delayed_interrupt_disable = state.delayed_interrupt_disable;
+        self.irq_requested = state.irq_requested;
+        self.bus.borrow_mut().set_interrupt(state.nmi_pending.then_some(()));
+    }
+
+    /// Architectural state for savestates, taken between instructions, with the pending
+    /// interrupt latches. The bus and clock handles and the mapper's IRQ line aren't part of it.
+    pub fn save_state(&self) -> CpuState {
+        self.state()
+    }
+
+    /// Restores a `save_state` snapshot and drops what the CPU latched for the
+    /// instruction it was in, like a pending breakpoint resume or OAM DMA.
+    pub fn restore_state(&mut self, state: &CpuState) {
+        self.load_state(state.clone());
+        self.resume_from_breakpoint = false;
+        self.oam_dma_pending = false;
+        self.watched_access.borrow_mut().take();
+    }
+
+    pub fn get_a(&self) -> u8 {
+        self.register_a
+    }
+
+    pub fn set_a(&mut self, value: u8) {
+        self.register_a = value;
+    }
+
+    pub fn get_x(&self) -> u8 {
+        self.register_x
+    }
+
+    pub fn set_x(&mut self, value: u8) {
+        self.register_x = value;
+    }
+
+    pub fn get_y(&self) -> u8 {
+        self.register_y
+    }
+
+    pub fn set_y(&mut self, value: u8) {
+        self.register_y = value;
+    }
+
+    pub fn get_sp(&self) -> u8 {
+        self.stack_pointer
+    }
+
+    pub fn set_sp(&mut self, value: u8) {
+        self.stack_pointer = value;
+    }
+
+    // Break reads as clear and Unused as set, they only exist in pushed copies
+    pub fn get_status_byte(&self) -> u8 {
+        self.status.get()
+    }
+
+    pub fn set_status_byte(&mut self, value: u8) {
+        self.status.set(value);
+    }
+
+    pub fn get_program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    // Read-modify-write instructions write the unmodified value back before the result,
+    // which memory-mapped registers see as two writes
+    fn write_modified(&mut self, address: u16, original: u8, result: u8) {
+        self.write(address, original);
+        self.write(address, result);
     }
 
     fn push_stack(&mut self, value: u8) {
@@ -369,13 +1037,22 @@ impl Cpu {
         u16::from_le_bytes([lo, hi])
     }
 
+    // Status comes up with InterruptDisable set and Unused reading as 1, like after a real reset
     pub fn reset(&mut self) {
+        let entry_point = self.read_u16(0xFFFC);
+        self.reset_to(entry_point);
+    }
+
+    /// Resets like `reset` but starts at `entry_point` instead of the reset vector,
+    /// e.g. $C000 for nestest's automated mode.
+    pub fn reset_to(&mut self, entry_point: u16) {
         self.register_a = 0x00;
         self.register_x = 0x00;
         self.register_y = 0x00;
         self.status = CpuStatusRegister::new();
         self.stack_pointer = 0xFD;
-        self.program_counter = self.read_u16(0xFFFC);
+        self.halted = false;
+        self.program_counter = entry_point;
         self.clock.borrow_mut().reset();
     }
 
@@ -383,7 +1060,9 @@ impl Cpu {
         (page1 & 0xFF00) != (page2 & 0xFF00)
     }
 
-    pub fn get_memory_data(&self, addressing_mode: &AddressingMode) -> Option<(u16, bool)> {
+    // Decodes the operand at PC and moves PC to the next instruction, so handlers
+    // that jump only have to overwrite it
+    pub fn get_memory_data(&mut self, addressing_mode: &AddressingMode) -> Option<(u16, bool)> {
         let mut instruction_info = String::new();
         let result = match addressing_mode {
             AddressingMode::Implicit => {
@@ -400,7 +1079,7 @@ impl Cpu {
                 let memory_pointer = self.program_counter;
 
                 if self.use_disassembler {
-                    instruction_info = format!("#${:02X}", self.read(memory_pointer));
+                    instruction_info = format!("#${:02X}", self.peek(memory_pointer));
                 }
 
                 Some((memory_pointer, false))
@@ -411,7 +1090,7 @@ impl Cpu {
                 if self.use_disassembler {
                     instruction_info = format!(
                         "${:02X} = {:02X}",
-                        memory_pointer, self.read(memory_pointer)
+                        memory_pointer, self.peek(memory_pointer)
                     );
                 }
 
@@ -421,45 +1100,42 @@ impl Cpu {
                 let pointer = self
                     .read(self.program_counter);
 
-                let memory_pointer = pointer 
+                // Wraps inside page zero, so there is never a page cross penalty
+                let memory_pointer = pointer
                     .wrapping_add(self.register_x);
 
-                let is_page_cross = self.is_page_cross(pointer as u16, memory_pointer as u16);
-
                 if self.use_disassembler {
                     instruction_info = format!(
                         "${:02X},X @ {:02X} = {:02X}",
-                        pointer, memory_pointer, self.read(memory_pointer as u16)
+                        pointer, memory_pointer, self.peek(memory_pointer as u16)
                     );
                 }
 
-                Some((memory_pointer as u16, is_page_cross))
+                Some((memory_pointer as u16, false))
             },
             AddressingMode::ZeroPageY => {
                 let pointer = self
                     .read(self.program_counter);
 
+                // Wraps inside page zero, so there is never a page cross penalty
                 let memory_pointer = pointer
                     .wrapping_add(self.register_y);
 
-                let is_page_cross = self.is_page_cross(pointer as u16, memory_pointer as u16);
-
                 if self.use_disassembler {
                     instruction_info = format!(
                         "${:02X},Y @ {:02X} = {:02X}",
-                        pointer, memory_pointer, self.read(memory_pointer as u16)
+                        pointer, memory_pointer, self.peek(memory_pointer as u16)
                     );
                 }
 
-                Some((memory_pointer as u16, is_page_cross))
+                Some((memory_pointer as u16, false))
             },
             AddressingMode::Relative => {
                 let offset = self.read(self.program_counter) as u16;
 
                 if self.use_disassembler {
                     let jump_offset = offset as i8;
-                    instruction_info = format!(
-                        "${:04X}", 
+                    instruction_info = self.symbols.format_address(
                         (self.program_counter.wrapping_add(1) as i16)
                             .wrapping_add(jump_offset as i16) as u16
                     );
@@ -474,7 +1150,7 @@ impl Cpu {
                     let value = if let 0x2000..=0x3FFF = memory_pointer {
                         0x00
                     } else {
-                        self.read(memory_pointer)
+                        self.peek(memory_pointer)
                     };
 
                     let current_instruction = &self.internal_state
@@ -482,15 +1158,14 @@ impl Cpu {
                         .unwrap()
                         .current_instruction;
 
-                    if let "JSR" | "JMP" = current_instruction.as_str() {
-                        instruction_info = format!(
-                            "${:04X}",
-                            memory_pointer
-                        );
+                    let address = self.symbols.format_address(memory_pointer);
+
+                    if let Opcode::Jsr | Opcode::Jmp = current_instruction {
+                        instruction_info = address;
                     } else {
                         instruction_info = format!(
-                            "${:04X} = {:02X}",
-                            memory_pointer, value
+                            "{} = {:02X}",
+                            address, value
                         );
                     }
                 }
@@ -508,8 +1183,8 @@ impl Cpu {
 
                 if self.use_disassembler {
                     instruction_info = format!(
-                        "${:04X},X @ {:04X} = {:02X}",
-                        pointer, memory_pointer, self.read(memory_pointer)
+                        "{},X @ {:04X} = {:02X}",
+                        self.symbols.format_address(pointer), memory_pointer, self.peek(memory_pointer)
                     );
                 }
 
@@ -526,8 +1201,8 @@ impl Cpu {
 
                 if self.use_disassembler {
                     instruction_info = format!(
-                        "${:04X},Y @ {:04X} = {:02X}",
-                        pointer, memory_pointer, self.read(memory_pointer)
+                        "{},Y @ {:04X} = {:02X}",
+                        self.symbols.format_address(pointer), memory_pointer, self.peek(memory_pointer)
                     );
                 }
 
@@ -551,8 +1226,8 @@ impl Cpu {
 
                 if self.use_disassembler {
                     instruction_info = format!(
-                        "(${:04X}) = {:04X}",
-                        pointer, memory_pointer
+                        "({}) = {:04X}",
+                        self.symbols.format_address(pointer), memory_pointer
                     );
                 }
 
@@ -570,7 +1245,7 @@ impl Cpu {
                 if self.use_disassembler {
                     instruction_info = format!(
                         "(${:02X},X) @ {:02X} = {:04X} = {:02X}",
-                        pointer.wrapping_sub(self.register_x as u16), pointer, memory_pointer, self.read(memory_pointer)
+                        pointer.wrapping_sub(self.register_x as u16), pointer, memory_pointer, self.peek(memory_pointer)
                     );
                 }
 
@@ -587,12 +1262,12 @@ impl Cpu {
                 let memory_pointer = deref_pointer
                     .wrapping_add(self.register_y as u16);
 
-                let is_page_cross = self.is_page_cross(deref_pointer, memory_pointer as u16);
+                let is_page_cross = self.is_page_cross(deref_pointer, memory_pointer);
 
                 if self.use_disassembler {
                     instruction_info = format!(
                         "(${:02X}),Y = {:04X} @ {:04X} = {:02X}",
-                        pointer, deref_pointer, memory_pointer, self.read(memory_pointer)
+                        pointer, deref_pointer, memory_pointer, self.peek(memory_pointer)
                     );
                 }
 
@@ -603,24 +1278,50 @@ impl Cpu {
         if self.use_disassembler {
             let InternalState { 
                 current_instruction, 
-                args_length
+                args_length,
+                unofficial,
             } = self.internal_state.as_ref().unwrap();
 
-            let hexdump = (0..*args_length + 1).into_iter()
+            let hexdump = (0..*args_length + 1)
                 .map(|offset| {
-                    format!("{:02X}", self.read(self.program_counter.wrapping_sub(1).wrapping_add(offset as u16)))
+                    format!("{:02X}", self.peek(self.program_counter.wrapping_sub(1).wrapping_add(offset as u16)))
                 })
                 .collect::<Vec<_>>()
                 .join(" ");
 
-            println!(
-                "{:<47} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
-                format!("{:04X}  {:<9} {} {}", self.program_counter.wrapping_sub(1), hexdump, current_instruction, instruction_info),
+            // Same columns as nestest.log, the pre-render scanline is shown as the last one
+            let clock = self.clock.borrow();
+            let ppu = clock.ppu().borrow();
+            let scanlines = ppu.get_region().scanlines();
+            let scanline = (ppu.get_scanline() + scanlines) % scanlines;
+            let marker = if *unofficial { '*' } else { ' ' };
+
+            let mut line = format!(
+                "{:<47} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+                format!("{:04X}  {:<8} {}{} {}", self.program_counter.wrapping_sub(1), hexdump, marker, current_instruction.mnemonic(), instruction_info),
                 self.register_a, self.register_x, self.register_y, self.status.get(), self.stack_pointer,
-                self.clock.borrow().get_cycles()
+                scanline, ppu.get_cycles(), clock.get_cycles()
             );
+
+            // Comments of the instruction's own address go after the nestest columns
+            if let Some(comment) = self.symbols.get(self.program_counter.wrapping_sub(1)).and_then(|symbol| symbol.comment.as_ref()) {
+                line.push_str(" ; ");
+                line.push_str(comment);
+            }
+
+            drop(ppu);
+            drop(clock);
+
+            if let Some(Err(error)) = self.trace_sink.as_mut().map(|sink| sink.write_line(&line)) {
+                self.trace_error = Some(error);
+                self.trace_sink = None;
+                self.use_disassembler = false;
+            }
         }
 
+        self.program_counter = self.program_counter.wrapping_add(addressing_mode.operand_length());
+        self.page_crossed = matches!(result, Some((_, true)));
+
         result
     }
 
@@ -629,7 +1330,7 @@ impl Cpu {
     }
 
     fn execute_adc(&mut self, addressing_mode: &AddressingMode) {
-        let (memory_pointer, additional_cycle) = self.get_memory_data(addressing_mode)
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
             .expect("Invalid Addressing mode for ADC instruction!");
 
         let a = self.register_a as u16;
@@ -644,13 +1345,53 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Overflow, overflow);
         self.register_a = result as u8;
 
-        if additional_cycle {
-            self.clock.borrow_mut().tick(1);
+        if self.is_decimal_mode() {
+            self.add_decimal(a as u8, m as u8, c as u8);
+        }
+    }
+
+    // Like the NMOS 6502, Zero keeps the binary result while Negative and Overflow
+    // come from the sum before the high nibble is adjusted
+    fn add_decimal(&mut self, a: u8, m: u8, c: u8) {
+        let mut lo = (a & 0x0F) + (m & 0x0F) + c;
+
+        if lo > 0x09 {
+            lo += 0x06;
+        }
+
+        let mut hi = (a >> 4) + (m >> 4) + (lo > 0x0F) as u8;
+        let unadjusted = hi << 4;
+
+        self.status.set_flag(CpuStatusRegisterFlags::Negative, unadjusted & 0x80 == 0x80);
+        self.status.set_flag(CpuStatusRegisterFlags::Overflow, (a ^ unadjusted) & !(a ^ m) & 0x80 == 0x80);
+
+        if hi > 0x09 {
+            hi += 0x06;
+        }
+
+        self.status.set_flag(CpuStatusRegisterFlags::Carry, hi > 0x0F);
+        self.register_a = (hi << 4) | (lo & 0x0F);
+    }
+
+    // Only register A is adjusted, every flag keeps the binary result
+    fn subtract_decimal(&mut self, a: u8, m: u8, c: u8) {
+        let mut lo = (a & 0x0F) as i16 - (m & 0x0F) as i16 + c as i16 - 1;
+
+        if lo < 0 {
+            lo = ((lo - 0x06) & 0x0F) - 0x10;
+        }
+
+        let mut result = (a & 0xF0) as i16 - (m & 0xF0) as i16 + lo;
+
+        if result < 0 {
+            result -= 0x60;
         }
+
+        self.register_a = result as u8;
     }
 
     fn execute_and(&mut self, addressing_mode: &AddressingMode) {
-        let (memory_pointer, additional_cycle) = self.get_memory_data(addressing_mode)
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
             .expect("Invalid Addressing mode for AND instruction!");
 
         let result = self.register_a & self.read(memory_pointer);
@@ -658,10 +1399,6 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
         self.register_a = result;
-
-        if additional_cycle {
-            self.clock.borrow_mut().tick(1);
-        }
     }
 
     fn execute_asl(&mut self, addressing_mode: &AddressingMode) {
@@ -679,7 +1416,7 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
 
         if let Some((memory_pointer, _)) = memory_data {
-            self.write(memory_pointer, result);
+            self.write_modified(memory_pointer, value, result);
         } else {
             self.register_a = result;
         }
@@ -693,8 +1430,8 @@ impl Cpu {
             self.clock.borrow_mut().tick(1);
 
             let offset = memory_pointer as i8;
-            let next_pc = self.program_counter.wrapping_add(1);
-            let jump_pc = next_pc.wrapping_add(offset as u16) as u16;
+            let next_pc = self.program_counter;
+            let jump_pc = next_pc.wrapping_add(offset as u16);
 
             if self.is_page_cross(next_pc, jump_pc) {
                 self.clock.borrow_mut().tick(1);
@@ -740,9 +1477,28 @@ impl Cpu {
         self.branch(!self.status.get_flag(CpuStatusRegisterFlags::Negative));
     }
 
-    fn execute_brk(&self, addressing_mode: &AddressingMode) {
+    fn execute_brk(&mut self, addressing_mode: &AddressingMode) {
         self.get_memory_data(addressing_mode);
-        // Do nothing
+
+        // BRK skips a padding byte, so RTI returns past it. Unlike NMI and IRQ
+        // it pushes the Break (B) flag as `1'.
+        let status = self.status.get() | CpuStatusRegisterFlags::Break as u8;
+
+        self.push_stack_u16(self.program_counter.wrapping_add(1));
+        self.push_stack(status);
+
+        // The vector is fetched on the last cycles, an NMI latched during the first four
+        // hijacks it to $FFFA while the pushed status keeps Break set
+        self.clock.borrow_mut().tick(BRK_NMI_HIJACK_CYCLES);
+        self.cycles_ticked = BRK_NMI_HIJACK_CYCLES;
+
+        let vector = match self.bus.borrow_mut().poll_interrupt() {
+            Some(()) => 0xFFFA,
+            None => 0xFFFE,
+        };
+
+        self.status.set_flag(CpuStatusRegisterFlags::InterruptDisable, true);
+        self.program_counter = self.read_u16(vector);
     }
 
     fn execute_bvc(&mut self) {
@@ -765,6 +1521,7 @@ impl Cpu {
 
     fn execute_cli(&mut self, addressing_mode: &AddressingMode) {
         self.get_memory_data(addressing_mode);
+        self.delay_interrupt_disable();
         self.status.set_flag(CpuStatusRegisterFlags::InterruptDisable, false);
     }
 
@@ -774,7 +1531,7 @@ impl Cpu {
     }
 
     fn compare(&mut self, addressing_mode: &AddressingMode, register_value: u8) {
-        let (memory_pointer, additional_cycle) = self.get_memory_data(addressing_mode)
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
             .expect("Invalid Addressing mode for CMP/CPX/CPY instructions!");
 
         let memory_value = self.read(memory_pointer);
@@ -783,10 +1540,6 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Carry, register_value >= memory_value);
         self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
-
-        if additional_cycle {
-            self.clock.borrow_mut().tick(1);
-        }
     }
 
     fn execute_cmp(&mut self, addressing_mode: &AddressingMode) {
@@ -810,7 +1563,7 @@ impl Cpu {
 
         self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
-        self.write(memory_pointer, result);
+        self.write_modified(memory_pointer, memory_value, result);
     }
 
     fn execute_dex(&mut self, addressing_mode: &AddressingMode) {
@@ -834,7 +1587,7 @@ impl Cpu {
     }
 
     fn execute_eor(&mut self, addressing_mode: &AddressingMode) {
-        let (memory_pointer, additional_cycle) = self.get_memory_data(addressing_mode)
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
             .expect("Invalid Addressing mode for EOR instruction!");
 
         let result = self.register_a ^ self.read(memory_pointer);
@@ -842,10 +1595,6 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
         self.register_a = result;
-
-        if additional_cycle {
-            self.clock.borrow_mut().tick(1);
-        }
     }
 
     fn execute_inc(&mut self, addressing_mode: &AddressingMode) {
@@ -857,7 +1606,7 @@ impl Cpu {
 
         self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
-        self.write(memory_pointer, result);
+        self.write_modified(memory_pointer, memory_value, result);
     }
 
     fn execute_inx(&mut self, addressing_mode: &AddressingMode) {
@@ -890,12 +1639,13 @@ impl Cpu {
     fn execute_jsr(&mut self) {
         let (memory_pointer, _) = self.get_memory_data(&AddressingMode::Absolute).unwrap();
 
-        self.push_stack_u16(self.program_counter.wrapping_add(1));
+        // JSR pushes the address of its last byte, RTS adds one back
+        self.push_stack_u16(self.program_counter.wrapping_sub(1));
         self.program_counter = memory_pointer;
     }
 
     fn execute_lda(&mut self, addressing_mode: &AddressingMode) {
-        let (memory_pointer, additional_cycle) = self.get_memory_data(addressing_mode)
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
             .expect("Invalid Addressing mode for LDA instruction!");
 
         let memory_value = self.read(memory_pointer);
@@ -903,14 +1653,10 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Zero, memory_value == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, memory_value & 0x80 == 0x80);
         self.register_a = memory_value;
-
-        if additional_cycle {
-            self.clock.borrow_mut().tick(1);
-        }
     }
 
     fn execute_ldx(&mut self, addressing_mode: &AddressingMode) {
-        let (memory_pointer, additional_cycle) = self.get_memory_data(addressing_mode)
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
             .expect("Invalid Addressing mode for LDA instruction!");
 
         let memory_value = self.read(memory_pointer);
@@ -918,14 +1664,10 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Zero, memory_value == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, memory_value & 0x80 == 0x80);
         self.register_x = memory_value;
-
-        if additional_cycle {
-            self.clock.borrow_mut().tick(1);
-        }
     }
 
     fn execute_ldy(&mut self, addressing_mode: &AddressingMode) {
-        let (memory_pointer, additional_cycle) = self.get_memory_data(addressing_mode)
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
             .expect("Invalid Addressing mode for LDA instruction!");
 
         let memory_value = self.read(memory_pointer);
@@ -933,10 +1675,6 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Zero, memory_value == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, memory_value & 0x80 == 0x80);
         self.register_y = memory_value;
-
-        if additional_cycle {
-            self.clock.borrow_mut().tick(1);
-        }
     }
 
     fn execute_lsr(&mut self, addressing_mode: &AddressingMode) {
@@ -954,22 +1692,18 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
 
         if let Some((memory_pointer, _)) = memory_data {
-            self.write(memory_pointer, result);
+            self.write_modified(memory_pointer, value, result);
         } else {
             self.register_a = result;
         }
     }
 
-    fn execute_nop(&self, addressing_mode: &AddressingMode) {
-        if let Some((_, additional_cycle)) = self.get_memory_data(addressing_mode) {
-            if additional_cycle {
-                self.clock.borrow_mut().tick(1);
-            }
-        }
+    fn execute_nop(&mut self, addressing_mode: &AddressingMode) {
+        self.get_memory_data(addressing_mode);
     }
 
     fn execute_ora(&mut self, addressing_mode: &AddressingMode) {
-        let (memory_pointer, additional_cycle) = self.get_memory_data(addressing_mode)
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
             .expect("Invalid Addressing mode for ORA instruction!");
         
         let result = self.register_a | self.read(memory_pointer);
@@ -977,10 +1711,6 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
         self.register_a = result;
-
-        if additional_cycle {
-            self.clock.borrow_mut().tick(1);
-        }
     }
 
     fn execute_pha(&mut self, addressing_mode: &AddressingMode) {
@@ -1008,16 +1738,10 @@ impl Cpu {
     fn execute_plp(&mut self, addressing_mode: &AddressingMode) {
         self.get_memory_data(addressing_mode);
 
+        // Break (B) and Unused bits of the pulled byte are dropped, the register has no room for them
         let status = self.pop_stack();
-
-        // If PHP always pushes the Break (B) flag as `1', then we should
-        // restore Break (B) flag, when we're pulling out Status register.
-        // Also we should set Unused flag (nestest.log have this flag set
-        // after PLP)!
-
+        self.delay_interrupt_disable();
         self.status.set(status);
-        self.status.set_flag(CpuStatusRegisterFlags::Break, false);
-        self.status.set_flag(CpuStatusRegisterFlags::Unused, true);
     }
 
     fn execute_rol(&mut self, addressing_mode: &AddressingMode) {
@@ -1039,7 +1763,7 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
 
         if let Some((memory_pointer, _)) = memory_data {
-            self.write(memory_pointer, result);
+            self.write_modified(memory_pointer, value, result);
         } else {
             self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
             self.register_a = result;
@@ -1065,7 +1789,7 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
 
         if let Some((memory_pointer, _)) = memory_data {
-            self.write(memory_pointer, result);
+            self.write_modified(memory_pointer, value, result);
         } else {
             self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
             self.register_a = result;
@@ -1078,8 +1802,8 @@ impl Cpu {
         let status = self.pop_stack();
         let program_counter = self.pop_stack_u16();
 
+        // Break (B) flag only exists on the stack, like in PLP
         self.status.set(status);
-        self.status.set_flag(CpuStatusRegisterFlags::Unused, true);
         self.program_counter = program_counter;
     }
 
@@ -1089,7 +1813,7 @@ impl Cpu {
     }
 
     fn execute_sbc(&mut self, addressing_mode: &AddressingMode) {
-        let (memory_pointer, additional_cycle) = self.get_memory_data(addressing_mode)
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
             .expect("Invalid Addressing mode for ADC instruction!");
 
         let a = self.register_a as u16;
@@ -1104,8 +1828,8 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Overflow, overflow);
         self.register_a = result as u8;
 
-        if additional_cycle {
-            self.clock.borrow_mut().tick(1);
+        if self.is_decimal_mode() {
+            self.subtract_decimal(a as u8, m as u8 ^ 0xFF, c as u8);
         }
     }
 
@@ -1121,6 +1845,7 @@ impl Cpu {
 
     fn execute_sei(&mut self, addressing_mode: &AddressingMode) {
         self.get_memory_data(addressing_mode);
+        self.delay_interrupt_disable();
         self.status.set_flag(CpuStatusRegisterFlags::InterruptDisable, true);
     }
 
@@ -1187,7 +1912,7 @@ impl Cpu {
 
     // TODO: add tests
     fn execute_lax(&mut self, addressing_mode: &AddressingMode) {
-        let (memory_pointer, additional_cycle) = self.get_memory_data(addressing_mode)
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
             .expect("Invalid Addressing mode for LAX (LDA + TAX) instruction!");
 
         let memory_value = self.read(memory_pointer);
@@ -1199,10 +1924,6 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Zero, self.register_a == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, self.register_a & 0x80 == 0x80);
         self.register_x = self.register_a;
-
-        if additional_cycle {
-            self.clock.borrow_mut().tick(1);
-        }
     }
 
     // TODO: add tests
@@ -1224,7 +1945,7 @@ impl Cpu {
 
         self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
-        self.write(memory_pointer, result);
+        self.write_modified(memory_pointer, memory_value, result);
         
         let register_value = self.register_a;
         let memory_value = self.read(memory_pointer);
@@ -1245,7 +1966,7 @@ impl Cpu {
 
         self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
-        self.write(memory_pointer, result);
+        self.write_modified(memory_pointer, memory_value, result);
 
         let a = self.register_a as u16;
         let m = self.read(memory_pointer) as u16 ^ 0xFF;
@@ -1271,7 +1992,7 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Carry, value & 0x80 == 0x80);
         self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
-        self.write(memory_pointer, result);
+        self.write_modified(memory_pointer, value, result);
 
         let result = self.register_a | result;
 
@@ -1295,7 +2016,7 @@ impl Cpu {
 
         self.status.set_flag(CpuStatusRegisterFlags::Carry, value & 0x80 == 0x80);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
-        self.write(memory_pointer, result);
+        self.write_modified(memory_pointer, value, result);
 
         let result = self.register_a & result;
 
@@ -1315,7 +2036,7 @@ impl Cpu {
         self.status.set_flag(CpuStatusRegisterFlags::Carry, value & 0x1 == 0x1);
         self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
-        self.write(memory_pointer, result);
+        self.write_modified(memory_pointer, value, result);
 
         let result = self.register_a ^ result;
 
@@ -1339,7 +2060,7 @@ impl Cpu {
 
         self.status.set_flag(CpuStatusRegisterFlags::Carry, value & 0x1 == 0x1);
         self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
-        self.write(memory_pointer, result);
+        self.write_modified(memory_pointer, value, result);
 
         let a = self.register_a as u16;
         let m = self.read(memory_pointer) as u16;
@@ -1354,134 +2075,353 @@ impl Cpu {
         self.register_a = result as u8;
     }
 
-    fn handle_interrupt(&mut self) {
-        self.push_stack_u16(self.program_counter);
-        self.push_stack(self.status.get() & 0b1100_1111);
-        self.status.set_flag(CpuStatusRegisterFlags::InterruptDisable, true);
-        self.program_counter = self.read_u16(0xFFFA);
-        self.clock.borrow_mut().tick(2);
+    fn execute_anc(&mut self, addressing_mode: &AddressingMode) {
+        self.execute_and(addressing_mode);
+
+        // Carry gets bit 7, as if the result was shifted by ASL
+        let negative_flag = self.status.get_flag(CpuStatusRegisterFlags::Negative);
+        self.status.set_flag(CpuStatusRegisterFlags::Carry, negative_flag);
     }
 
-    pub fn fetch(&mut self) {
-        let interrupt = self.bus.borrow_mut().poll_interrupt();
-        if let Some(_) = interrupt {
-            self.handle_interrupt();
-        }
+    fn execute_alr(&mut self, addressing_mode: &AddressingMode) {
+        self.execute_and(addressing_mode);
+        self.execute_lsr(&AddressingMode::Accumulator);
+    }
 
-        let Instruction {
-            opcode,
-            bytes,
-            name,
-            cycles,
-            addressing_mode
-        } = INSTRUCTIONS[self.read(self.program_counter) as usize];
+    fn execute_arr(&mut self, addressing_mode: &AddressingMode) {
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
+            .expect("Invalid Addressing mode for ARR instruction!");
+
+        let carry_flag = self.status.get_flag(CpuStatusRegisterFlags::Carry);
+        let result = ((self.register_a & self.read(memory_pointer)) >> 1) | ((carry_flag as u8) << 7);
+
+        // Carry comes from bit 6 and Overflow from bit 6 XOR bit 5 of the rotated value
+        self.status.set_flag(CpuStatusRegisterFlags::Carry, result & 0x40 == 0x40);
+        self.status.set_flag(CpuStatusRegisterFlags::Overflow, ((result >> 6) ^ (result >> 5)) & 0x1 == 0x1);
+        self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
+        self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
+        self.register_a = result;
+    }
+
+    fn execute_axs(&mut self, addressing_mode: &AddressingMode) {
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
+            .expect("Invalid Addressing mode for AXS instruction!");
+
+        let value = self.register_a & self.register_x;
+        let memory_value = self.read(memory_pointer);
+        let result = value.wrapping_sub(memory_value);
+
+        self.status.set_flag(CpuStatusRegisterFlags::Carry, value >= memory_value);
+        self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
+        self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
+        self.register_x = result;
+    }
+
+    // SHX, SHY, AHX and TAS store the value ANDed with the high byte of the base address plus one.
+    // When indexing crosses a page the stored value also replaces the high byte of the address.
+    fn store_high_byte_and(&mut self, addressing_mode: &AddressingMode, index: u8, value: u8) {
+        let (memory_pointer, is_page_cross) = self.get_memory_data(addressing_mode)
+            .expect("Invalid Addressing mode for SH* instruction!");
+
+        if !self.unstable_opcodes.high_byte_and {
+            self.write(memory_pointer, value);
+            return;
+        }
+
+        let base_high_byte = (memory_pointer.wrapping_sub(index as u16) >> 8) as u8;
+        let result = value & base_high_byte.wrapping_add(1);
+
+        let memory_pointer = if is_page_cross {
+            u16::from_le_bytes([memory_pointer as u8, result])
+        } else {
+            memory_pointer
+        };
+
+        self.write(memory_pointer, result);
+    }
+
+    fn execute_shx(&mut self, addressing_mode: &AddressingMode) {
+        self.store_high_byte_and(addressing_mode, self.register_y, self.register_x);
+    }
+
+    fn execute_shy(&mut self, addressing_mode: &AddressingMode) {
+        self.store_high_byte_and(addressing_mode, self.register_x, self.register_y);
+    }
+
+    fn execute_ahx(&mut self, addressing_mode: &AddressingMode) {
+        self.store_high_byte_and(addressing_mode, self.register_y, self.register_a & self.register_x);
+    }
+
+    fn execute_tas(&mut self, addressing_mode: &AddressingMode) {
+        self.stack_pointer = self.register_a & self.register_x;
+        self.store_high_byte_and(addressing_mode, self.register_y, self.stack_pointer);
+    }
+
+    fn execute_xaa(&mut self, addressing_mode: &AddressingMode) {
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
+            .expect("Invalid Addressing mode for XAA instruction!");
+
+        let result = (self.register_a | self.unstable_opcodes.magic) & self.register_x & self.read(memory_pointer);
+
+        self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
+        self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
+        self.register_a = result;
+    }
+
+    fn execute_las(&mut self, addressing_mode: &AddressingMode) {
+        let (memory_pointer, _) = self.get_memory_data(addressing_mode)
+            .expect("Invalid Addressing mode for LAS instruction!");
+
+        let result = self.read(memory_pointer) & self.stack_pointer;
+
+        self.status.set_flag(CpuStatusRegisterFlags::Zero, result == 0);
+        self.status.set_flag(CpuStatusRegisterFlags::Negative, result & 0x80 == 0x80);
+        self.register_a = result;
+        self.register_x = result;
+        self.stack_pointer = result;
+    }
+
+    fn delay_interrupt_disable(&mut self) {
+        self.delayed_interrupt_disable = Some(self.status.get_flag(CpuStatusRegisterFlags::InterruptDisable));
+    }
+
+    fn execute_kil(&mut self, opcode: u8) {
+        match self.illegal_opcode_policy {
+            IllegalOpcodePolicy::Panic => {
+                let address = self.program_counter.wrapping_sub(1);
+                panic!("Illegal opcode ${:02X} at ${:04X}!", opcode, address);
+            },
+            IllegalOpcodePolicy::TreatAsNop => (),
+            IllegalOpcodePolicy::Halt => self.halted = true,
+        }
+    }
+
+    // NMI and IRQ push the status with Break clear and Unused set, then jump through their vector
+    fn handle_interrupt(&mut self, vector: u16) {
+        self.push_stack_u16(self.program_counter);
+        self.push_stack(self.status.get());
+        self.status.set_flag(CpuStatusRegisterFlags::InterruptDisable, true);
+        self.program_counter = self.read_u16(vector);
+        self.clock.borrow_mut().tick(7);
+    }
+
+    /// Loads `bytes` at `start` and runs up to `max_instructions` from there, or until the CPU jams.
+    pub fn load_and_run(&mut self, start: u16, bytes: &[u8], max_instructions: usize) -> Result<(), LoadError> {
+        if self.has_flat_memory() {
+            bytes
+                .iter()
+                .enumerate()
+                .for_each(|(offset, &byte)| self.write(start.wrapping_add(offset as u16), byte));
+        } else {
+            self.bus.borrow_mut().load(start, bytes)?;
+        }
+
+        self.program_counter = start;
+
+        for _ in 0..max_instructions {
+            if self.halted {
+                break;
+            }
+
+            self.execute_instruction();
+        }
+
+        Ok(())
+    }
+
+    // Executes one instruction, breakpoints are ignored
+    pub fn fetch(&mut self) {
+        self.execute_instruction();
+    }
+
+    /// Executes one instruction, or services a pending interrupt before it, and returns
+    /// the CPU cycles that took, page cross and branch penalties included.
+    /// Stops before an instruction with a breakpoint, stepping again executes it.
+    pub fn step(&mut self) -> StepResult {
+        let resume_from_breakpoint = std::mem::take(&mut self.resume_from_breakpoint);
+
+        if !resume_from_breakpoint && !self.halted && self.breakpoints.contains(&self.program_counter) {
+            self.resume_from_breakpoint = true;
+            return StepResult::BreakpointHit(self.program_counter);
+        }
+
+        let start_cycles = self.clock.borrow().get_cycles();
+        let start_pc = self.program_counter;
+
+        self.watched_access.borrow_mut().take();
+        self.pc_out_of_rom = None;
+        self.execute_instruction();
+
+        let cycles = self.clock.borrow().get_cycles() - start_cycles;
+
+        if let Some(pc) = self.pc_out_of_rom.take() {
+            return StepResult::PcOutOfRom { pc, cycles };
+        }
+
+        match self.watched_access.borrow_mut().take() {
+            Some((address, value, kind)) => StepResult::WatchpointHit {
+                hit: WatchpointHit {
+                    pc: start_pc,
+                    address,
+                    value,
+                    kind,
+                },
+                cycles,
+            },
+            None => StepResult::Executed(cycles),
+        }
+    }
+
+    fn execute_instruction(&mut self) {
+        // A jammed CPU ignores interrupts, the clock keeps running so the PPU still draws
+        if self.halted {
+            self.clock.borrow_mut().tick(1);
+            return;
+        }
+
+        // Interrupts are polled between instructions, which stands for the last cycle of the
+        // previous one. The NMI edge stays latched on the bus, so one raised and cleared
+        // while that instruction ran is still serviced here
+        let interrupt = self.bus.borrow_mut().poll_interrupt();
+        let irq = self.irq_requested || self.bus.borrow().poll_irq();
+        let interrupt_disable = self.delayed_interrupt_disable
+            .take()
+            .unwrap_or_else(|| self.status.get_flag(CpuStatusRegisterFlags::InterruptDisable));
+
+        // NMI wins when both are pending, the IRQ stays asserted for the next instruction
+        if interrupt.is_some() {
+            self.handle_interrupt(0xFFFA);
+        } else if irq && !interrupt_disable {
+            self.irq_requested = false;
+            self.handle_interrupt(0xFFFE);
+        }
+
+        self.instruction_address = self.program_counter;
+        self.check_pc_out_of_rom();
+
+        let opcode = self.read(self.program_counter);
+        let instruction = &INSTRUCTIONS[opcode as usize];
+
+        if self.history.is_some() {
+            self.record_history(opcode);
+        }
+        let Instruction {
+            operation,
+            bytes,
+            cycles,
+            addressing_mode,
+            page_cross_cycle,
+            ..
+        } = *instruction;
 
         self.program_counter = self.program_counter.wrapping_add(1);
         self.internal_state = Some(InternalState {
-            current_instruction: name.to_string(),
-            args_length: bytes - 1
+            current_instruction: operation,
+            args_length: bytes - 1,
+            unofficial: instruction.is_unofficial(),
         });
 
-        let current_program_counter = self.program_counter;
-
-        match name {
-            "ADC" => self.execute_adc(&addressing_mode),
-            "AND" => self.execute_and(&addressing_mode),
-            "ASL" => self.execute_asl(&addressing_mode),
-            "BCC" => self.execute_bcc(),
-            "BCS" => self.execute_bcs(),
-            "BEQ" => self.execute_beq(),
-            "BIT" => self.execute_bit(&addressing_mode),
-            "BMI" => self.execute_bmi(),
-            "BNE" => self.execute_bne(),
-            "BPL" => self.execute_bpl(),
-            "BRK" => self.execute_brk(&addressing_mode),
-            "BVC" => self.execute_bvc(),
-            "BVS" => self.execute_bvs(),
-            "CLC" => self.execute_clc(&addressing_mode),
-            "CLD" => self.execute_cld(&addressing_mode),
-            "CLI" => self.execute_cli(&addressing_mode),
-            "CLV" => self.execute_clv(&addressing_mode),
-            "CMP" => self.execute_cmp(&addressing_mode),
-            "CPX" => self.execute_cpx(&addressing_mode),
-            "CPY" => self.execute_cpy(&addressing_mode),
-            "DEC" => self.execute_dec(&addressing_mode),
-            "DEX" => self.execute_dex(&addressing_mode),
-            "DEY" => self.execute_dey(&addressing_mode),
-            "EOR" => self.execute_eor(&addressing_mode),
-            "INC" => self.execute_inc(&addressing_mode),
-            "INX" => self.execute_inx(&addressing_mode),
-            "INY" => self.execute_iny(&addressing_mode),
-            "JMP" => self.execute_jmp(&addressing_mode),
-            "JSR" => self.execute_jsr(),
-            "LDA" => self.execute_lda(&addressing_mode),
-            "LDX" => self.execute_ldx(&addressing_mode),
-            "LDY" => self.execute_ldy(&addressing_mode),
-            "LSR" => self.execute_lsr(&addressing_mode),
-            "NOP" => self.execute_nop(&addressing_mode),
-            "ORA" => self.execute_ora(&addressing_mode),
-            "PHA" => self.execute_pha(&addressing_mode),
-            "PHP" => self.execute_php(&addressing_mode),
-            "PLA" => self.execute_pla(&addressing_mode),
-            "PLP" => self.execute_plp(&addressing_mode),
-            "ROL" => self.execute_rol(&addressing_mode),
-            "ROR" => self.execute_ror(&addressing_mode),
-            "RTI" => self.execute_rti(&addressing_mode),
-            "RTS" => self.execute_rts(&addressing_mode),
-            "SBC" => self.execute_sbc(&addressing_mode),
-            "SEC" => self.execute_sec(&addressing_mode),
-            "SED" => self.execute_sed(&addressing_mode),
-            "SEI" => self.execute_sei(&addressing_mode),
-            "STA" => self.execute_sta(&addressing_mode),
-            "STX" => self.execute_stx(&addressing_mode),
-            "STY" => self.execute_sty(&addressing_mode),
-            "TAX" => self.execute_tax(&addressing_mode),
-            "TAY" => self.execute_tay(&addressing_mode),
-            "TSX" => self.execute_tsx(&addressing_mode),
-            "TXA" => self.execute_txa(&addressing_mode),
-            "TXS" => self.execute_txs(&addressing_mode),
-            "TYA" => self.execute_tya(&addressing_mode),
-            "LAX" => self.execute_lax(&addressing_mode),
-            "SAX" => self.execute_sax(&addressing_mode),
-            "DCP" => self.execute_dcp(&addressing_mode),
-            "ISC" => self.execute_isc(&addressing_mode),
-            "SLO" => self.execute_slo(&addressing_mode),
-            "RLA" => self.execute_rla(&addressing_mode),
-            "SRE" => self.execute_sre(&addressing_mode),
-            "RRA" => self.execute_rra(&addressing_mode),
-            "KIL" => (),
-            _ => panic!("Illegal opcode {:#02X} occured!", opcode),
-        }
-
-        self.clock.borrow_mut().tick(cycles as usize);
-
-        if current_program_counter == self.program_counter {
-            let args_length = (bytes - 1) as u16;
-
-            self.program_counter = self.program_counter.wrapping_add(args_length);
+        self.page_crossed = false;
+        self.cycles_ticked = 0;
+
+        match operation {
+            Opcode::Adc => self.execute_adc(&addressing_mode),
+            Opcode::And => self.execute_and(&addressing_mode),
+            Opcode::Asl => self.execute_asl(&addressing_mode),
+            Opcode::Bcc => self.execute_bcc(),
+            Opcode::Bcs => self.execute_bcs(),
+            Opcode::Beq => self.execute_beq(),
+            Opcode::Bit => self.execute_bit(&addressing_mode),
+            Opcode::Bmi => self.execute_bmi(),
+            Opcode::Bne => self.execute_bne(),
+            Opcode::Bpl => self.execute_bpl(),
+            Opcode::Brk => self.execute_brk(&addressing_mode),
+            Opcode::Bvc => self.execute_bvc(),
+            Opcode::Bvs => self.execute_bvs(),
+            Opcode::Clc => self.execute_clc(&addressing_mode),
+            Opcode::Cld => self.execute_cld(&addressing_mode),
+            Opcode::Cli => self.execute_cli(&addressing_mode),
+            Opcode::Clv => self.execute_clv(&addressing_mode),
+            Opcode::Cmp => self.execute_cmp(&addressing_mode),
+            Opcode::Cpx => self.execute_cpx(&addressing_mode),
+            Opcode::Cpy => self.execute_cpy(&addressing_mode),
+            Opcode::Dec => self.execute_dec(&addressing_mode),
+            Opcode::Dex => self.execute_dex(&addressing_mode),
+            Opcode::Dey => self.execute_dey(&addressing_mode),
+            Opcode::Eor => self.execute_eor(&addressing_mode),
+            Opcode::Inc => self.execute_inc(&addressing_mode),
+            Opcode::Inx => self.execute_inx(&addressing_mode),
+            Opcode::Iny => self.execute_iny(&addressing_mode),
+            Opcode::Jmp => self.execute_jmp(&addressing_mode),
+            Opcode::Jsr => self.execute_jsr(),
+            Opcode::Lda => self.execute_lda(&addressing_mode),
+            Opcode::Ldx => self.execute_ldx(&addressing_mode),
+            Opcode::Ldy => self.execute_ldy(&addressing_mode),
+            Opcode::Lsr => self.execute_lsr(&addressing_mode),
+            Opcode::Nop => self.execute_nop(&addressing_mode),
+            Opcode::Ora => self.execute_ora(&addressing_mode),
+            Opcode::Pha => self.execute_pha(&addressing_mode),
+            Opcode::Php => self.execute_php(&addressing_mode),
+            Opcode::Pla => self.execute_pla(&addressing_mode),
+            Opcode::Plp => self.execute_plp(&addressing_mode),
+            Opcode::Rol => self.execute_rol(&addressing_mode),
+            Opcode::Ror => self.execute_ror(&addressing_mode),
+            Opcode::Rti => self.execute_rti(&addressing_mode),
+            Opcode::Rts => self.execute_rts(&addressing_mode),
+            Opcode::Sbc => self.execute_sbc(&addressing_mode),
+            Opcode::Sec => self.execute_sec(&addressing_mode),
+            Opcode::Sed => self.execute_sed(&addressing_mode),
+            Opcode::Sei => self.execute_sei(&addressing_mode),
+            Opcode::Sta => self.execute_sta(&addressing_mode),
+            Opcode::Stx => self.execute_stx(&addressing_mode),
+            Opcode::Sty => self.execute_sty(&addressing_mode),
+            Opcode::Tax => self.execute_tax(&addressing_mode),
+            Opcode::Tay => self.execute_tay(&addressing_mode),
+            Opcode::Tsx => self.execute_tsx(&addressing_mode),
+            Opcode::Txa => self.execute_txa(&addressing_mode),
+            Opcode::Txs => self.execute_txs(&addressing_mode),
+            Opcode::Tya => self.execute_tya(&addressing_mode),
+            Opcode::Lax => self.execute_lax(&addressing_mode),
+            Opcode::Sax => self.execute_sax(&addressing_mode),
+            Opcode::Dcp => self.execute_dcp(&addressing_mode),
+            Opcode::Isc => self.execute_isc(&addressing_mode),
+            Opcode::Slo => self.execute_slo(&addressing_mode),
+            Opcode::Rla => self.execute_rla(&addressing_mode),
+            Opcode::Sre => self.execute_sre(&addressing_mode),
+            Opcode::Rra => self.execute_rra(&addressing_mode),
+            Opcode::Kil => self.execute_kil(instruction.opcode),
+            Opcode::Anc => self.execute_anc(&addressing_mode),
+            Opcode::Alr => self.execute_alr(&addressing_mode),
+            Opcode::Arr => self.execute_arr(&addressing_mode),
+            Opcode::Axs => self.execute_axs(&addressing_mode),
+            Opcode::Shx => self.execute_shx(&addressing_mode),
+            Opcode::Shy => self.execute_shy(&addressing_mode),
+            Opcode::Ahx => self.execute_ahx(&addressing_mode),
+            Opcode::Tas => self.execute_tas(&addressing_mode),
+            Opcode::Xaa => self.execute_xaa(&addressing_mode),
+            Opcode::Las => self.execute_las(&addressing_mode),
+        }
+
+        let page_cross_cycles = (page_cross_cycle && self.page_crossed) as usize;
+        self.clock.borrow_mut().tick(cycles as usize + page_cross_cycles - self.cycles_ticked);
+
+        // DMA suspends the CPU once the writing instruction is done, one more cycle aligns it
+        // to a read cycle when it starts on an odd one
+        if std::mem::take(&mut self.oam_dma_pending) {
+            let odd_cycle = self.clock.borrow().get_cycles() % 2;
+            self.clock.borrow_mut().tick(OAM_DMA_CYCLES + odd_cycle);
         }
     }
 }
 
-impl Memory for Cpu {
-    fn read(&self, address: u16) -> u8 {
+impl Cpu {
+    fn read_memory_map(&self, address: u16) -> u8 {
         match address {
-            0x0000..=0x1FFF => {
-                self.bus
-                    .borrow_mut()
-                    .cpu_memory_map()
-                    .read(address & 0x7FF)
-            },
-            0x2000 => panic!("Attempt to read from PPU Controller register!"),
-            0x2001 => panic!("Attempt to read from PPU Mask register!"),
-            0x2002 => self.clock.borrow().ppu().borrow_mut().read_status(),
-            0x2003 => panic!("Attempt to read from PPU OAM Address register!"),
-            0x2004 => self.clock.borrow().ppu().borrow_mut().read_oamdata(),
-            0x2005 => panic!("Attempt to read from PPU Scroll register!"),
-            0x2006 => panic!("Attempt to read from PPU Address register!"),
-            0x2007 => self.clock.borrow().ppu().borrow_mut().read_data(),
-            0x2008..=0x3FFF => self.read(address & 0x2007),
-            0x4000..=0x4017 => {
+            0x0000..=0x1FFF => self.ram[address as usize & 0x7FF].get(),
+            0x2000..=0x3FFF => self.clock.borrow().ppu().borrow_mut().read_register(address),
+            0x4016 => self.bus.borrow_mut().joypad(0).read(),
+            0x4017 => self.bus.borrow_mut().joypad(1).read(),
+            0x4000..=0x4015 => {
                 // TODO: implement read from APU
                 0x00
             },
@@ -1495,48 +2435,31 @@ impl Memory for Cpu {
         }
     }
 
-    fn write(&mut self, address: u16, data: u8) {
+    fn write_memory_map(&mut self, address: u16, data: u8) {
         match address {
-            0x0000..=0x1FFF => {
-                self.bus
-                    .borrow_mut()
-                    .cpu_memory_map()
-                    .write(address & 0x7FF, data);
-            },
-            0x2000 => self.clock.borrow().ppu().borrow_mut().write_controller(data),
-            0x2001 => self.clock.borrow().ppu().borrow_mut().write_mask(data),
-            0x2002 => panic!("Attempt to write to PPU Status register!"),
-            0x2003 => self.clock.borrow().ppu().borrow_mut().write_oamaddress(data),
-            0x2004 => self.clock.borrow().ppu().borrow_mut().write_oamdata(data),
-            0x2005 => self.clock.borrow().ppu().borrow_mut().write_scroll(data),
-            0x2006 => self.clock.borrow().ppu().borrow_mut().write_address(data),
-            0x2007 => self.clock.borrow().ppu().borrow_mut().write_data(data),
-            0x2008..=0x3FFF => self.write(address & 0x2007, data),
+            0x0000..=0x1FFF => self.ram[address as usize & 0x7FF].set(data),
+            0x2000..=0x3FFF => self.clock.borrow().ppu().borrow_mut().write_register(address, data),
             0x4000..=0x4017 => {
-                match address {
-                    0x4014 => {
-                        let start = u16::from_le_bytes([0x00, data]);
-                        let end = start + 0x100;
-
-                        for address in start..end {
-                            let byte = self.bus
-                                .borrow_mut()
-                                .cpu_memory_map()
-                                .read(address);
-
-                            self.bus
-                                .borrow_mut()
-                                .ppu_memory_map()
-                                .set_oam_value((address & 0xFF) as u8, byte);
-                        }
-
-                        // dummy cycle
-                        let odd_cycle = self.clock.borrow().get_cycles() % 2;
-                        //self.clock.borrow_mut().tick(513 + odd_cycle);
-                    },
-                    // TODO: implement write to APU
-                    _ => (),
+                // OAM DMA writes the page through $2004, starting at the current OAM address
+                if address == 0x4014 {
+                    let start = u16::from_le_bytes([0x00, data]);
+
+                    for address in start..start + 0x100 {
+                        let byte = self.read(address);
+                        self.clock.borrow().ppu().borrow_mut().write_oamdata(byte);
+                    }
+
+                    self.oam_dma_pending = true;
+                }
+
+                if address == 0x4016 {
+                    let mut bus = self.bus.borrow_mut();
+
+                    bus.joypad(0).write(data);
+                    bus.joypad(1).write(data);
                 }
+
+                self.bus.borrow_mut().apu().write_register(address, data);
             },
             0x4018..=0x401F => panic!("APU and I/O func. test is normally disabled!"),
             0x4020..=0xFFFF => {
@@ -1547,13 +2470,171 @@ impl Memory for Cpu {
             },
         }
     }
+
+    // Reads for the trace, watchpoints and the code/data logger don't see them
+    fn peek(&self, address: u16) -> u8 {
+        #[cfg(feature = "flat-memory")]
+        if let Some(memory) = &self.flat_memory {
+            return memory.read(address);
+        }
+
+        self.read_memory_map(address)
+    }
+}
+
+impl Memory for Cpu {
+    fn read(&self, address: u16) -> u8 {
+        let value = self.peek(address);
+
+        if !self.watchpoints.is_empty() {
+            self.watch_access(address, value, WatchKind::Read);
+        }
+
+        if let Some(logger) = &self.code_data_logger {
+            self.log_access(logger, address);
+        }
+
+        value
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        if !self.watchpoints.is_empty() {
+            self.watch_access(address, data, WatchKind::Write);
+        }
+
+        #[cfg(feature = "flat-memory")]
+        if let Some(memory) = self.flat_memory.as_mut() {
+            memory.write(address, data);
+            return;
+        }
+
+        self.write_memory_map(address, data);
+    }
+}
+
+// Lets the disassembler decode the instruction at PC without tripping watchpoints or the logger
+struct PeekMemory<'a>(&'a Cpu);
+
+impl Memory for PeekMemory<'_> {
+    fn read(&self, address: u16) -> u8 {
+        self.0.peek(address)
+    }
+
+    fn write(&mut self, _address: u16, _data: u8) {}
+}
+
+impl Cpu {
+    fn current_instruction(&self) -> DisassembledLine {
+        disasm::disassemble_with_symbols(&PeekMemory(self), self.program_counter, 1, &self.symbols).remove(0)
+    }
+
+    // NV-BDIZC, uppercase when set
+    fn format_flags(&self) -> String {
+        let status = self.status.get();
+
+        "NV-BDIZC"
+            .chars()
+            .enumerate()
+            .map(|(index, flag)| match status & (0x80 >> index) {
+                0 => flag.to_ascii_lowercase(),
+                _ => flag,
+            })
+            .collect()
+    }
+}
+
+// The bus and clock are left out, only the registers and the instruction at PC are shown
+impl fmt::Debug for Cpu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cpu")
+            .field("a", &format_args!("${:02X}", self.register_a))
+            .field("x", &format_args!("${:02X}", self.register_x))
+            .field("y", &format_args!("${:02X}", self.register_y))
+            .field("p", &format_args!("${:02X} [{}]", self.status.get(), self.format_flags()))
+            .field("sp", &format_args!("${:02X}", self.stack_pointer))
+            .field("pc", &format_args!("${:04X}", self.program_counter))
+            .field("cycles", &self.clock.borrow().get_cycles())
+            .field("instruction", &format_args!("{}", self.current_instruction()))
+            .finish()
+    }
+}
+
+// One line in the layout of the trace log
+impl fmt::Display for Cpu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<47} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
+            self.current_instruction().to_string(),
+            self.register_a, self.register_x, self.register_y, self.status.get(), self.stack_pointer,
+            self.clock.borrow().get_cycles()
+        )
+    }
+}
+
+// Illegal opcodes and bus panics unwind through the CPU, the history shows how execution got there
+impl Drop for Cpu {
+    fn drop(&mut self) {
+        if std::thread::panicking() && self.history.is_some() {
+            eprintln!("Last executed instructions:\n{}", self.format_history());
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::core::{cartridge::Cartridge, ppu::{Ppu, Mirroring}};
+    use std::cell::Cell;
+
+    use crate::core::{cartridge::Cartridge, mappers::{Mapper, Mapper000}, ppu::{Ppu, Mirroring}};
     use super::*;
 
+    // Counts CPU writes to $6000-$7FFF, runs code from RAM
+    #[derive(Clone)]
+    struct WriteCountingMapper {
+        writes: Rc<Cell<usize>>,
+        chr_rom: Vec<u8>,
+    }
+
+    impl Memory for WriteCountingMapper {
+        fn read(&self, _address: u16) -> u8 {
+            0x00
+        }
+
+        fn write(&mut self, _address: u16, _data: u8) {
+            self.writes.set(self.writes.get() + 1);
+        }
+    }
+
+    impl Mapper for WriteCountingMapper {
+        fn get_chr_rom(&mut self) -> &mut Vec<u8> {
+            &mut self.chr_rom
+        }
+    }
+
+    // Points NMI at $0500 and IRQ/BRK at $0600
+    #[derive(Clone)]
+    struct VectorMapper {
+        chr_rom: Vec<u8>,
+    }
+
+    impl Memory for VectorMapper {
+        fn read(&self, address: u16) -> u8 {
+            match address {
+                0xFFFB => 0x05,
+                0xFFFF => 0x06,
+                _ => 0x00,
+            }
+        }
+
+        fn write(&mut self, _address: u16, _data: u8) {}
+    }
+
+    impl Mapper for VectorMapper {
+        fn get_chr_rom(&mut self) -> &mut Vec<u8> {
+            &mut self.chr_rom
+        }
+    }
+
     #[test]
     fn test_adc_instruction() {
         let cartridge = Cartridge::empty();
@@ -1577,6 +2658,7 @@ mod tests {
         cpu.register_a = 128;
         cpu.write(0x0000, 0x69);
         cpu.write(0x0001, 0x80);
+        cpu.program_counter = 0x0001;
         cpu.status.set_flag(CpuStatusRegisterFlags::Carry, true);
 
         cpu.execute_adc(&AddressingMode::Immediate);
@@ -1638,7 +2720,7 @@ mod tests {
         cpu.program_counter = 0x0001;
         cpu.status.set_flag(CpuStatusRegisterFlags::Carry, true);
         cpu.execute_bcc();
-        assert_eq!(cpu.program_counter, 0x0001, "CPU PC should be 0x0001 after BCC with active Carry flag!");
+        assert_eq!(cpu.program_counter, 0x0002, "CPU PC should be 0x0002 after BCC with active Carry flag!");
     }
 
     #[test]
@@ -1653,7 +2735,7 @@ mod tests {
         cpu.write(0x0001, 0x04);
         cpu.status.set_flag(CpuStatusRegisterFlags::Carry, false);
         cpu.execute_bcs();
-        assert_eq!(cpu.program_counter, 0x0001, "CPU PC should be 0x0001 after BCS with inactive Carry flag!");
+        assert_eq!(cpu.program_counter, 0x0002, "CPU PC should be 0x0002 after BCS with inactive Carry flag!");
 
         cpu.program_counter = 0x0001;
         cpu.status.set_flag(CpuStatusRegisterFlags::Carry, true);
@@ -1673,7 +2755,7 @@ mod tests {
         cpu.write(0x0001, 0x04);
         cpu.status.set_flag(CpuStatusRegisterFlags::Zero, false);
         cpu.execute_beq();
-        assert_eq!(cpu.program_counter, 0x0001, "CPU PC should be 0x0001 after BEQ with inactive Zero flag!");
+        assert_eq!(cpu.program_counter, 0x0002, "CPU PC should be 0x0002 after BEQ with inactive Zero flag!");
 
         cpu.program_counter = 0x0001;
         cpu.status.set_flag(CpuStatusRegisterFlags::Zero, true);
@@ -1728,7 +2810,7 @@ mod tests {
         cpu.write(0x0001, 0x04);
         cpu.status.set_flag(CpuStatusRegisterFlags::Negative, false);
         cpu.execute_bmi();
-        assert_eq!(cpu.program_counter, 0x0001, "CPU PC should be 0x0001 after BMI with inactive Negative flag!");
+        assert_eq!(cpu.program_counter, 0x0002, "CPU PC should be 0x0002 after BMI with inactive Negative flag!");
 
         cpu.program_counter = 0x0001;
         cpu.status.set_flag(CpuStatusRegisterFlags::Negative, true);
@@ -1753,7 +2835,7 @@ mod tests {
         cpu.program_counter = 0x0001;
         cpu.status.set_flag(CpuStatusRegisterFlags::Zero, true);
         cpu.execute_bne();
-        assert_eq!(cpu.program_counter, 0x0001, "CPU PC should be 0x0001 after BNE with active Zero flag!");
+        assert_eq!(cpu.program_counter, 0x0002, "CPU PC should be 0x0002 after BNE with active Zero flag!");
     }
 
     #[test]
@@ -1773,7 +2855,7 @@ mod tests {
         cpu.program_counter = 0x0001;
         cpu.status.set_flag(CpuStatusRegisterFlags::Negative, true);
         cpu.execute_bpl();
-        assert_eq!(cpu.program_counter, 0x0001, "CPU PC should be 0x0001 after BPL with active Negative flag!");
+        assert_eq!(cpu.program_counter, 0x0002, "CPU PC should be 0x0002 after BPL with active Negative flag!");
     }
 
     #[test]
@@ -1793,7 +2875,7 @@ mod tests {
         cpu.program_counter = 0x0001;
         cpu.status.set_flag(CpuStatusRegisterFlags::Overflow, true);
         cpu.execute_bvc();
-        assert_eq!(cpu.program_counter, 0x0001, "CPU PC should be 0x0001 after BVC with active Overflow flag!");
+        assert_eq!(cpu.program_counter, 0x0002, "CPU PC should be 0x0002 after BVC with active Overflow flag!");
     }
 
     #[test]
@@ -1813,7 +2895,7 @@ mod tests {
         cpu.program_counter = 0x0001;
         cpu.status.set_flag(CpuStatusRegisterFlags::Overflow, false);
         cpu.execute_bvs();
-        assert_eq!(cpu.program_counter, 0x0001, "CPU PC should be 0x0001 after BVC with inactive Overflow flag!");
+        assert_eq!(cpu.program_counter, 0x0002, "CPU PC should be 0x0002 after BVC with inactive Overflow flag!");
     }
 
     #[test] 
@@ -2259,11 +3341,7 @@ mod tests {
 
         let status_from_stack = cpu.read(0x0100 + stack_pointer_buf as u16);
 
-        assert_eq!(
-            cpu.status.get() | CpuStatusRegisterFlags::Break as u8,
-            status_from_stack,
-            "Invalid value of register inside Stack!"
-        );
+        assert_eq!(status_from_stack, 0b0011_0100, "PHP should push the status with Break and Unused set!");
     }
 
     #[test]
@@ -2304,6 +3382,13 @@ mod tests {
         cpu.execute_plp(&AddressingMode::Implicit);
 
         assert_eq!(cpu.status.get(), 0b1110_1111, "Status should have 0b1110_1111!");
+        assert!(!cpu.status.get_flag(CpuStatusRegisterFlags::Break), "PLP should drop the pulled Break flag!");
+
+        cpu.write(0x150, 0x00);
+        cpu.stack_pointer = 0x4F;
+        cpu.execute_plp(&AddressingMode::Implicit);
+
+        assert_eq!(cpu.status.get(), 0b0010_0000, "Unused flag should read as set after PLP!");
     }
 
     #[test]
@@ -2523,24 +3608,23 @@ mod tests {
 
     #[test]
     fn test_tax_instruction() {
-        let cartridge = Cartridge::empty();
-		let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
-		let ppu = Rc::new(RefCell::new(Ppu::new(&bus, Mirroring::Horizontal)));
-		let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
-		let mut cpu = Cpu::new(&bus, &clock);
+        let mut cpu = create_cpu();
 
-        cpu.register_a = 0xFF;
-        cpu.execute_tax(&AddressingMode::Implicit);
+        // TAX, TAX
+        cpu.write(0x0400, 0xAA);
+        cpu.write(0x0401, 0xAA);
+        cpu.set_program_counter(0x0400);
 
-        assert_eq!(cpu.register_x, 0xFF, "Register X should have 0xFF!");
-        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Negative), "Negative flag should be set!");
-        assert!(!cpu.status.get_flag(CpuStatusRegisterFlags::Zero), "Negative flag should be unset!");
+        cpu.set_a(0xFF);
+        assert_eq!(cpu.step(), StepResult::Executed(2), "TAX should take 2 cycles!");
+        assert_eq!(cpu.get_x(), 0xFF, "Register X should have 0xFF!");
+        assert_eq!(cpu.get_status_byte() & 0x82, 0x80, "Negative flag should be set and Zero unset!");
 
-        cpu.register_a = 0x00;
-        cpu.execute_tax(&AddressingMode::Implicit);
-        assert_eq!(cpu.register_x, 0x00, "Register X should have 0x00!");
-        assert!(!cpu.status.get_flag(CpuStatusRegisterFlags::Negative), "Negative flag should be unset!");
-        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Zero), "Negative flag should be set!");
+        cpu.set_a(0x00);
+        cpu.step();
+        assert_eq!(cpu.get_x(), 0x00, "Register X should have 0x00!");
+        assert_eq!(cpu.get_status_byte() & 0x82, 0x02, "Zero flag should be set and Negative unset!");
+        assert_eq!(cpu.get_program_counter(), 0x0402, "PC should be past both instructions!");
     }
 
     #[test]
@@ -2611,16 +3695,17 @@ mod tests {
 
     #[test]
     fn test_txs_instruction() {
-        let cartridge = Cartridge::empty();
-		let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
-		let ppu = Rc::new(RefCell::new(Ppu::new(&bus, Mirroring::Horizontal)));
-		let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
-		let mut cpu = Cpu::new(&bus, &clock);
+        let mut cpu = create_cpu();
 
-        cpu.register_x = 0xAB;
-        cpu.execute_txs(&AddressingMode::Implicit);
+        // TXS
+        cpu.write(0x0400, 0x9A);
+        cpu.set_program_counter(0x0400);
+        cpu.set_x(0xAB);
+        cpu.set_status_byte(0x00);
+        cpu.step();
 
-        assert_eq!(cpu.stack_pointer, 0xAB, "Stack pointer should have 0xAB!");
+        assert_eq!(cpu.get_sp(), 0xAB, "Stack pointer should have 0xAB!");
+        assert_eq!(cpu.get_status_byte(), 0x20, "TXS should not change the flags!");
     }
 
     #[test]
@@ -2644,4 +3729,1395 @@ mod tests {
         assert!(!cpu.status.get_flag(CpuStatusRegisterFlags::Negative), "Negative flag should be unset!");
         assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Zero), "Negative flag should be set!");
     }
+
+    #[test]
+    fn test_nmi_is_handled_before_next_opcode() {
+        let cartridge = Cartridge::empty();
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, Mirroring::Horizontal)));
+        let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
+        let mut cpu = Cpu::new(&bus, &clock);
+
+        // Empty cartridge has its NMI vector at $0000, where a NOP waits
+        cpu.write(0x0000, 0xEA);
+        cpu.write(0x0400, 0xE8);
+        cpu.program_counter = 0x0400;
+        cpu.stack_pointer = 0xFD;
+        cpu.status.set(0b1101_1011);
+        bus.borrow_mut().set_interrupt(Some(()));
+
+        let cycles = clock.borrow().get_cycles();
+        cpu.fetch();
+
+        assert_eq!(cpu.program_counter, 0x0001, "CPU should run the NMI handler instead of the next opcode!");
+        assert_eq!(cpu.register_x, 0x00, "Interrupted opcode should not run yet!");
+        assert_eq!(cpu.stack_pointer, 0xFA, "NMI should push PC and status!");
+        assert_eq!(cpu.read_u16(0x01FC), 0x0400, "NMI should push the interrupted PC!");
+
+        assert_eq!(cpu.read(0x01FB), 0b1110_1011, "NMI should push status with Break clear and Unused set!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::InterruptDisable), "NMI should set InterruptDisable!");
+        assert_eq!(clock.borrow().get_cycles() - cycles, 7 + 2, "NMI should take 7 cycles before the NOP!");
+        assert!(bus.borrow().get_interrupt().is_none(), "NMI should be consumed!");
+    }
+
+    #[test]
+    fn test_irq_respects_interrupt_disable() {
+        let cartridge = Cartridge::empty();
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, Mirroring::Horizontal)));
+        let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
+        let mut cpu = Cpu::new(&bus, &clock);
+
+        // Empty cartridge has its IRQ vector at $0000, where a NOP waits
+        cpu.write(0x0000, 0xEA);
+        cpu.write(0x0400, 0x78); // SEI
+        cpu.write(0x0401, 0xE8); // INX
+        cpu.write(0x0402, 0x58); // CLI
+        cpu.write(0x0403, 0xE8); // INX
+        cpu.program_counter = 0x0400;
+        cpu.stack_pointer = 0xFD;
+        cpu.status.set_flag(CpuStatusRegisterFlags::InterruptDisable, false);
+
+        cpu.fetch();
+        cpu.fetch();
+        bus.borrow_mut().set_irq(true);
+        assert_eq!(cpu.register_x, 0x01, "Instruction after SEI should run!");
+
+        cpu.fetch();
+        assert_eq!(cpu.program_counter, 0x0403, "SEI should block the IRQ until CLI has run!");
+
+        cpu.fetch();
+        assert_eq!(cpu.register_x, 0x02, "Instruction after CLI should run before the IRQ!");
+
+        cpu.fetch();
+        assert_eq!(cpu.program_counter, 0x0001, "IRQ should be taken one instruction after CLI!");
+        assert_eq!(cpu.read_u16(0x01FC), 0x0404, "IRQ should push the interrupted PC!");
+        assert_eq!(cpu.read(0x01FB), 0b0010_0000, "IRQ should push status with Break clear and Unused set!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::InterruptDisable), "IRQ should set InterruptDisable!");
+    }
+
+    #[test]
+    fn test_irq_waits_one_instruction_after_cli() {
+        let mut cpu = create_cpu();
+
+        // CLI, LDA #$42, NOP; the IRQ vector of the empty cartridge points at $0000
+        cpu.write(0x0000, 0xEA);
+        cpu.write(0x0400, 0x58);
+        cpu.write(0x0401, 0xA9);
+        cpu.write(0x0402, 0x42);
+        cpu.write(0x0403, 0xEA);
+        cpu.program_counter = 0x0400;
+        cpu.stack_pointer = 0xFD;
+        cpu.bus.borrow_mut().set_irq(true);
+
+        cpu.fetch();
+        cpu.fetch();
+        assert_eq!(cpu.register_a, 0x42, "LDA should run before the IRQ!");
+        assert_eq!(cpu.stack_pointer, 0xFD, "IRQ should not be taken between CLI and LDA!");
+
+        cpu.fetch();
+        assert_eq!(cpu.read_u16(0x01FC), 0x0403, "IRQ should be taken after LDA!");
+        assert_eq!(cpu.program_counter, 0x0001, "IRQ handler should have run instead of the NOP!");
+    }
+
+    #[test]
+    fn test_irq_taken_right_after_sei() {
+        let mut cpu = create_cpu();
+
+        // SEI, INX
+        cpu.write(0x0000, 0xEA);
+        cpu.write(0x0400, 0x78);
+        cpu.write(0x0401, 0xE8);
+        cpu.program_counter = 0x0400;
+        cpu.stack_pointer = 0xFD;
+        cpu.status.set_flag(CpuStatusRegisterFlags::InterruptDisable, false);
+
+        cpu.fetch();
+        cpu.bus.borrow_mut().set_irq(true);
+        cpu.fetch();
+
+        assert_eq!(cpu.register_x, 0x00, "IRQ should still be taken right after SEI!");
+        assert_eq!(cpu.read_u16(0x01FC), 0x0401, "IRQ should interrupt the instruction after SEI!");
+        assert_ne!(cpu.read(0x01FB) & CpuStatusRegisterFlags::InterruptDisable as u8, 0x00, "Pushed status should keep I set by SEI!");
+    }
+
+    #[test]
+    fn test_nmi_method_triggers_single_nmi() {
+        let (mut cpu, _) = create_cpu_with_vectors();
+
+        cpu.write(0x0400, 0xE8);
+        cpu.write(0x0500, 0xEA);
+        cpu.program_counter = 0x0400;
+        cpu.stack_pointer = 0xFD;
+        cpu.status.set(0x00);
+
+        cpu.nmi();
+        cpu.nmi();
+        assert_eq!(cpu.step(), StepResult::Executed(7 + 2), "NMI should take 7 cycles before the handler's NOP!");
+        assert_eq!(cpu.program_counter, 0x0501, "NMI should jump through $FFFA!");
+        assert_eq!(cpu.read_u16(0x01FC), 0x0400, "NMI should push the interrupted PC!");
+        assert_eq!(cpu.read(0x01FB), 0x20, "NMI should push status with Break clear!");
+        assert_eq!(cpu.stack_pointer, 0xFA, "NMI should push PC and status once!");
+
+        cpu.program_counter = 0x0400;
+        cpu.step();
+        assert_eq!(cpu.register_x, 0x01, "Repeated nmi calls should trigger a single NMI!");
+    }
+
+    #[test]
+    fn test_irq_method_waits_for_interrupt_disable() {
+        let (mut cpu, _) = create_cpu_with_vectors();
+
+        // INX, CLI, INX
+        cpu.write(0x0400, 0xE8);
+        cpu.write(0x0401, 0x58);
+        cpu.write(0x0402, 0xE8);
+        cpu.write(0x0600, 0xEA);
+        cpu.write(0x0601, 0xEA);
+        cpu.program_counter = 0x0400;
+        cpu.stack_pointer = 0xFD;
+
+        cpu.irq();
+        cpu.step();
+        cpu.irq();
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.register_x, 0x02, "IRQ should stay pending while InterruptDisable is set!");
+
+        assert_eq!(cpu.step(), StepResult::Executed(7 + 2), "IRQ should take 7 cycles before the handler's NOP!");
+        assert_eq!(cpu.program_counter, 0x0601, "IRQ should jump through $FFFE!");
+        assert_eq!(cpu.read_u16(0x01FC), 0x0403, "IRQ should push the interrupted PC!");
+        assert_eq!(cpu.read(0x01FB) & CpuStatusRegisterFlags::Break as u8, 0x00, "IRQ should push status with Break clear!");
+
+        cpu.status.set_flag(CpuStatusRegisterFlags::InterruptDisable, false);
+        cpu.step();
+        assert_eq!(cpu.stack_pointer, 0xFA, "Serviced IRQ should not be taken again!");
+    }
+
+    #[test]
+    fn test_nmi_has_priority_over_irq() {
+        let cartridge = Cartridge::empty();
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, Mirroring::Horizontal)));
+        let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
+        let mut cpu = Cpu::new(&bus, &clock);
+
+        cpu.write(0x0000, 0xEA);
+        cpu.program_counter = 0x0400;
+        cpu.stack_pointer = 0xFD;
+        cpu.status.set_flag(CpuStatusRegisterFlags::InterruptDisable, false);
+        bus.borrow_mut().set_interrupt(Some(()));
+        bus.borrow_mut().set_irq(true);
+
+        cpu.fetch();
+        assert_eq!(cpu.stack_pointer, 0xFA, "Only one interrupt should be taken at a time!");
+        assert!(bus.borrow().get_interrupt().is_none(), "NMI should be taken first!");
+        assert!(bus.borrow().poll_irq(), "IRQ should stay asserted!");
+    }
+
+    #[test]
+    fn test_brk_instruction() {
+        let cartridge = Cartridge::empty();
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, Mirroring::Horizontal)));
+        let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
+        let mut cpu = Cpu::new(&bus, &clock);
+
+        // Empty cartridge has its BRK vector at $0000, where an RTI waits
+        cpu.write(0x0000, 0x40);
+        cpu.write(0x0400, 0x00);
+        cpu.program_counter = 0x0400;
+        cpu.stack_pointer = 0xFD;
+        cpu.status.set_flag(CpuStatusRegisterFlags::InterruptDisable, false);
+        cpu.status.set_flag(CpuStatusRegisterFlags::Carry, true);
+
+        let cycles = clock.borrow().get_cycles();
+        cpu.fetch();
+
+        assert_eq!(cpu.program_counter, 0x0000, "BRK should jump through $FFFE!");
+        assert_eq!(cpu.stack_pointer, 0xFA, "BRK should push PC and status!");
+        assert_eq!(cpu.read_u16(0x01FC), 0x0402, "BRK should push PC + 2!");
+        assert_eq!(cpu.read(0x01FB), 0x31, "BRK should push status with Break and Unused set!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::InterruptDisable), "BRK should set InterruptDisable!");
+        assert_eq!(clock.borrow().get_cycles() - cycles, 7, "BRK should take 7 cycles!");
+
+        cpu.fetch();
+
+        assert_eq!(cpu.program_counter, 0x0402, "RTI should return past the BRK padding byte!");
+        assert!(!cpu.status.get_flag(CpuStatusRegisterFlags::Break), "RTI should not keep Break set!");
+        assert!(!cpu.status.get_flag(CpuStatusRegisterFlags::InterruptDisable), "RTI should restore InterruptDisable!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Carry), "RTI should restore Carry!");
+    }
+
+    #[test]
+    fn test_jumps_to_next_instruction_do_not_skip_it() {
+        let cartridge = Cartridge::empty();
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, Mirroring::Horizontal)));
+        let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
+        let mut cpu = Cpu::new(&bus, &clock);
+
+        // JMP $0403; BEQ +0; INX
+        [0x4C, 0x03, 0x04, 0xF0, 0x00, 0xE8]
+            .iter()
+            .enumerate()
+            .for_each(|(offset, byte)| cpu.write(0x0400 + offset as u16, *byte));
+        cpu.program_counter = 0x0400;
+        cpu.status.set_flag(CpuStatusRegisterFlags::Zero, true);
+
+        cpu.fetch();
+        assert_eq!(cpu.program_counter, 0x0403, "JMP to PC + 3 should land on the next instruction!");
+
+        cpu.fetch();
+        assert_eq!(cpu.program_counter, 0x0405, "Taken branch with offset 0 should land on the next instruction!");
+
+        cpu.fetch();
+        assert_eq!(cpu.register_x, 0x01, "Instruction after the branch should run!");
+
+        // BEQ -1 lands on its own operand, which used to look like a branch that wasn't taken
+        cpu.write(0x0410, 0xF0);
+        cpu.write(0x0411, 0xFF);
+        cpu.program_counter = 0x0410;
+        cpu.status.set_flag(CpuStatusRegisterFlags::Zero, true);
+
+        cpu.fetch();
+        assert_eq!(cpu.program_counter, 0x0411, "Branch to its own operand should not skip the operand!");
+    }
+
+    #[test]
+    fn test_instruction_lengths_match_addressing_modes() {
+        for instruction in INSTRUCTIONS.iter() {
+            assert_eq!(
+                instruction.bytes as u16 - 1,
+                instruction.addressing_mode.operand_length(),
+                "Opcode {:#04X} length should match its addressing mode!", instruction.opcode
+            );
+        }
+    }
+
+    #[test]
+    fn test_official_opcode_count() {
+        let official = INSTRUCTIONS.iter().filter(|instruction| !instruction.is_unofficial()).count();
+
+        assert_eq!(official, 151, "The 6502 should have 151 official opcodes!");
+    }
+
+    #[test]
+    fn test_read_modify_write_writes_twice() {
+        let writes = Rc::new(Cell::new(0));
+        let cartridge = Cartridge::from_mapper(Box::new(WriteCountingMapper {
+            writes: writes.clone(),
+            chr_rom: vec![0; 0x2000],
+        }));
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, Mirroring::Horizontal)));
+        let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
+        let mut cpu = Cpu::new(&bus, &clock);
+
+        // ASL $6000; STA $6000
+        [0x0E, 0x00, 0x60, 0x8D, 0x00, 0x60]
+            .iter()
+            .enumerate()
+            .for_each(|(offset, byte)| cpu.write(0x0400 + offset as u16, *byte));
+        cpu.program_counter = 0x0400;
+
+        cpu.fetch();
+        assert_eq!(writes.get(), 2, "ASL should write the original value before the result!");
+
+        cpu.fetch();
+        assert_eq!(writes.get(), 3, "STA should write once!");
+    }
+
+    #[test]
+    fn test_page_cross_cycle_only_for_reads() {
+        let cartridge = Cartridge::empty();
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, Mirroring::Horizontal)));
+        let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
+        let mut cpu = Cpu::new(&bus, &clock);
+
+        // STA $02FF,X; LDA $02FF,X; LDA $0200,X
+        [0x9D, 0xFF, 0x02, 0xBD, 0xFF, 0x02, 0xBD, 0x00, 0x02]
+            .iter()
+            .enumerate()
+            .for_each(|(offset, byte)| cpu.write(0x0400 + offset as u16, *byte));
+        cpu.program_counter = 0x0400;
+        cpu.register_x = 0x02;
+
+        let mut fetch_cycles = || {
+            let cycles = clock.borrow().get_cycles();
+            cpu.fetch();
+            clock.borrow().get_cycles() - cycles
+        };
+
+        assert_eq!(fetch_cycles(), 5, "STA absolute,X should not take the page cross cycle!");
+        assert_eq!(fetch_cycles(), 5, "LDA absolute,X should take one more cycle across a page!");
+        assert_eq!(fetch_cycles(), 4, "LDA absolute,X within a page should take 4 cycles!");
+    }
+
+    fn create_cpu() -> Cpu {
+        let cartridge = Cartridge::empty();
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, Mirroring::Horizontal)));
+        let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
+
+        Cpu::new(&bus, &clock)
+    }
+
+    // Operand of an immediate instruction executed directly, at $0001
+    fn create_cpu_with_immediate(value: u8) -> Cpu {
+        let mut cpu = create_cpu();
+
+        cpu.write(0x0001, value);
+        cpu.program_counter = 0x0001;
+        cpu
+    }
+
+    #[test]
+    fn test_anc_instruction() {
+        let mut cpu = create_cpu_with_immediate(0xF0);
+        cpu.register_a = 0x8F;
+        cpu.execute_anc(&AddressingMode::Immediate);
+
+        assert_eq!(cpu.register_a, 0x80, "Register A should be 0x80!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Carry), "CPU Status: Carry should copy bit 7!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Negative), "CPU Status: Negative flag should be set!");
+
+        let mut cpu = create_cpu_with_immediate(0x0F);
+        cpu.register_a = 0xF0;
+        cpu.status.set_flag(CpuStatusRegisterFlags::Carry, true);
+        cpu.execute_anc(&AddressingMode::Immediate);
+
+        assert_eq!(cpu.register_a, 0x00, "Register A should be 0!");
+        assert!(!cpu.status.get_flag(CpuStatusRegisterFlags::Carry), "CPU Status: Carry should be unset!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Zero), "CPU Status: Zero should be set!");
+    }
+
+    #[test]
+    fn test_alr_instruction() {
+        let mut cpu = create_cpu_with_immediate(0x03);
+        cpu.register_a = 0xFF;
+        cpu.execute_alr(&AddressingMode::Immediate);
+
+        assert_eq!(cpu.register_a, 0x01, "Register A should be 1!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Carry), "CPU Status: Carry should get the shifted out bit!");
+        assert!(!cpu.status.get_flag(CpuStatusRegisterFlags::Zero), "CPU Status: Zero should be unset!");
+
+        let mut cpu = create_cpu_with_immediate(0x80);
+        cpu.register_a = 0x81;
+        cpu.execute_alr(&AddressingMode::Immediate);
+
+        assert_eq!(cpu.register_a, 0x40, "Register A should be 0x40!");
+        assert!(!cpu.status.get_flag(CpuStatusRegisterFlags::Carry), "CPU Status: Carry should be unset!");
+        assert!(!cpu.status.get_flag(CpuStatusRegisterFlags::Negative), "CPU Status: Negative flag should be unset!");
+    }
+
+    #[test]
+    fn test_arr_instruction() {
+        // (flags before, A & immediate, result, carry, overflow)
+        let cases = [
+            (false, 0x00, 0x00, false, false),
+            (true, 0x00, 0x80, false, false),
+            (false, 0x40, 0x20, false, true),
+            (false, 0x80, 0x40, true, true),
+            (false, 0xC0, 0x60, true, false),
+        ];
+
+        for (carry, value, result, carry_after, overflow_after) in cases {
+            let mut cpu = create_cpu_with_immediate(0xFF);
+            cpu.register_a = value;
+            cpu.status.set_flag(CpuStatusRegisterFlags::Carry, carry);
+            cpu.execute_arr(&AddressingMode::Immediate);
+
+            assert_eq!(cpu.register_a, result, "ARR of {:#04X} should rotate into {:#04X}!", value, result);
+            assert_eq!(cpu.status.get_flag(CpuStatusRegisterFlags::Carry), carry_after, "ARR of {:#04X} should take Carry from bit 6!", value);
+            assert_eq!(cpu.status.get_flag(CpuStatusRegisterFlags::Overflow), overflow_after, "ARR of {:#04X} should take Overflow from bits 5 and 6!", value);
+            assert_eq!(cpu.status.get_flag(CpuStatusRegisterFlags::Zero), result == 0, "ARR of {:#04X} should set Zero from the result!", value);
+            assert_eq!(cpu.status.get_flag(CpuStatusRegisterFlags::Negative), carry, "ARR should move Carry into bit 7!");
+        }
+    }
+
+    #[test]
+    fn test_axs_instruction() {
+        let mut cpu = create_cpu_with_immediate(0x02);
+        cpu.register_a = 0x0F;
+        cpu.register_x = 0xFC;
+        cpu.execute_axs(&AddressingMode::Immediate);
+
+        assert_eq!(cpu.register_x, 0x0A, "Register X should be (A & X) - 2!");
+        assert_eq!(cpu.register_a, 0x0F, "Register A should not change!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Carry), "CPU Status: Carry should be set without borrow!");
+
+        let mut cpu = create_cpu_with_immediate(0x01);
+        cpu.register_a = 0x00;
+        cpu.register_x = 0xFF;
+        cpu.status.set_flag(CpuStatusRegisterFlags::Carry, true);
+        cpu.execute_axs(&AddressingMode::Immediate);
+
+        assert_eq!(cpu.register_x, 0xFF, "Register X should wrap around!");
+        assert!(!cpu.status.get_flag(CpuStatusRegisterFlags::Carry), "CPU Status: Carry should be unset on borrow!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Negative), "CPU Status: Negative flag should be set!");
+
+        let mut cpu = create_cpu_with_immediate(0x0F);
+        cpu.register_a = 0x0F;
+        cpu.register_x = 0x0F;
+        cpu.execute_axs(&AddressingMode::Immediate);
+
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Zero), "CPU Status: Zero should be set!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Carry), "CPU Status: Carry should be set on equal values!");
+    }
+
+    fn write_absolute_operand(cpu: &mut Cpu, address: u16) {
+        let [lo, hi] = address.to_le_bytes();
+
+        cpu.write(0x0001, lo);
+        cpu.write(0x0002, hi);
+        cpu.program_counter = 0x0001;
+    }
+
+    #[test]
+    fn test_shx_instruction() {
+        let mut cpu = create_cpu();
+        write_absolute_operand(&mut cpu, 0x0500);
+        cpu.register_x = 0xFF;
+        cpu.register_y = 0x10;
+        cpu.execute_shx(&AddressingMode::AbsoluteY);
+
+        assert_eq!(cpu.read(0x0510), 0x06, "SHX should store X & (high byte + 1)!");
+
+        // 0x05F0 + 0x20 crosses into 0x0610, the stored value becomes the high byte
+        let mut cpu = create_cpu();
+        write_absolute_operand(&mut cpu, 0x05F0);
+        cpu.register_x = 0x03;
+        cpu.register_y = 0x20;
+        cpu.execute_shx(&AddressingMode::AbsoluteY);
+
+        assert_eq!(cpu.read(0x0210), 0x02, "SHX should corrupt the address on page cross!");
+        assert_eq!(cpu.read(0x0610), 0x00, "SHX should not write to the indexed address on page cross!");
+    }
+
+    #[test]
+    fn test_shy_instruction() {
+        let mut cpu = create_cpu();
+        write_absolute_operand(&mut cpu, 0x0300);
+        cpu.register_x = 0x04;
+        cpu.register_y = 0xFF;
+        cpu.execute_shy(&AddressingMode::AbsoluteX);
+
+        assert_eq!(cpu.read(0x0304), 0x04, "SHY should store Y & (high byte + 1)!");
+    }
+
+    #[test]
+    fn test_ahx_and_tas_instructions() {
+        let mut cpu = create_cpu();
+        write_absolute_operand(&mut cpu, 0x0700);
+        cpu.register_a = 0xFC;
+        cpu.register_x = 0x3F;
+        cpu.register_y = 0x01;
+        cpu.execute_ahx(&AddressingMode::AbsoluteY);
+
+        assert_eq!(cpu.read(0x0701), 0x08, "AHX should store A & X & (high byte + 1)!");
+
+        let mut cpu = create_cpu();
+        write_absolute_operand(&mut cpu, 0x0700);
+        cpu.register_a = 0xFC;
+        cpu.register_x = 0x3F;
+        cpu.register_y = 0x01;
+        cpu.execute_tas(&AddressingMode::AbsoluteY);
+
+        assert_eq!(cpu.stack_pointer, 0x3C, "TAS should set SP to A & X!");
+        assert_eq!(cpu.read(0x0701), 0x08, "TAS should store SP & (high byte + 1)!");
+    }
+
+    #[test]
+    fn test_sh_instructions_without_high_byte_and() {
+        let unstable_opcodes = UnstableOpcodes { high_byte_and: false, ..UnstableOpcodes::default() };
+
+        let mut cpu = create_cpu();
+        cpu.set_unstable_opcodes(unstable_opcodes);
+        write_absolute_operand(&mut cpu, 0x05F0);
+        cpu.register_x = 0x03;
+        cpu.register_y = 0x20;
+        cpu.execute_shx(&AddressingMode::AbsoluteY);
+
+        assert_eq!(cpu.read(0x0610), 0x03, "SHX should store plain X at the indexed address!");
+        assert_eq!(cpu.read(0x0210), 0x00, "SHX should not corrupt the address!");
+
+        let mut cpu = create_cpu();
+        cpu.set_unstable_opcodes(unstable_opcodes);
+        write_absolute_operand(&mut cpu, 0x0700);
+        cpu.register_a = 0xFC;
+        cpu.register_x = 0x3F;
+        cpu.register_y = 0x01;
+        cpu.execute_tas(&AddressingMode::AbsoluteY);
+
+        assert_eq!(cpu.stack_pointer, 0x3C, "TAS should still set SP to A & X!");
+        assert_eq!(cpu.read(0x0701), 0x3C, "TAS should store plain SP!");
+    }
+
+    #[test]
+    fn test_xaa_instruction() {
+        let mut cpu = create_cpu_with_immediate(0x0F);
+        cpu.register_a = 0x00;
+        cpu.register_x = 0xFF;
+        cpu.execute_xaa(&AddressingMode::Immediate);
+
+        assert_eq!(cpu.register_a, 0x0E, "XAA should mix the default magic into A!");
+
+        let mut cpu = create_cpu_with_immediate(0xF0);
+        cpu.set_unstable_opcodes(UnstableOpcodes { magic: 0xFF, ..UnstableOpcodes::default() });
+        cpu.register_x = 0x9F;
+        cpu.execute_xaa(&AddressingMode::Immediate);
+
+        assert_eq!(cpu.register_a, 0x90, "XAA should use the configured magic!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Negative), "CPU Status: Negative flag should be set!");
+
+        let mut cpu = create_cpu_with_immediate(0xFF);
+        cpu.set_unstable_opcodes(UnstableOpcodes { magic: 0x00, ..UnstableOpcodes::default() });
+        cpu.register_x = 0xFF;
+        cpu.execute_xaa(&AddressingMode::Immediate);
+
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Zero), "CPU Status: Zero flag should be set!");
+    }
+
+    #[test]
+    fn test_las_instruction() {
+        let mut cpu = create_cpu();
+        write_absolute_operand(&mut cpu, 0x0400);
+        cpu.write(0x0402, 0xB3);
+        cpu.register_y = 0x02;
+        cpu.stack_pointer = 0xF0;
+        cpu.execute_las(&AddressingMode::AbsoluteY);
+
+        assert_eq!(cpu.register_a, 0xB0, "LAS should load memory & SP into A!");
+        assert_eq!(cpu.register_x, 0xB0, "LAS should load memory & SP into X!");
+        assert_eq!(cpu.stack_pointer, 0xB0, "LAS should load memory & SP into SP!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Negative), "CPU Status: Negative flag should be set!");
+    }
+
+    #[test]
+    fn test_kil_halts_cpu() {
+        let mut cpu = create_cpu();
+        cpu.write(0x0400, 0x02);
+        cpu.write(0x0401, 0xE8);
+        cpu.program_counter = 0x0400;
+
+        cpu.fetch();
+        assert!(cpu.is_halted(), "KIL should halt the CPU!");
+
+        let program_counter = cpu.program_counter;
+        let cycles = cpu.clock.borrow().get_cycles();
+
+        for _ in 0..10 {
+            cpu.fetch();
+        }
+
+        assert_eq!(cpu.program_counter, program_counter, "Halted CPU should not advance PC!");
+        assert_eq!(cpu.register_x, 0x00, "Halted CPU should not execute the next instruction!");
+        assert_eq!(cpu.clock.borrow().get_cycles(), cycles + 10, "Halted CPU should still tick one cycle per fetch!");
+
+        cpu.reset();
+        assert!(!cpu.is_halted(), "Reset should recover a halted CPU!");
+    }
+
+    #[test]
+    fn test_reset_to_entry_point() {
+        let mut cpu = create_cpu();
+        cpu.register_a = 0x12;
+        cpu.stack_pointer = 0x80;
+        cpu.status.set(0xC3);
+        cpu.clock.borrow_mut().set_cycles(1000);
+
+        cpu.reset_to(0xC000);
+
+        let state = cpu.state();
+        assert_eq!(state.pc, 0xC000, "Reset should start at the entry point!");
+        assert_eq!((state.a, state.x, state.y), (0x00, 0x00, 0x00), "Reset should clear the registers!");
+        assert_eq!(state.sp, 0xFD, "Reset should leave SP at 0xFD!");
+        assert_eq!(state.p, 0x24, "Reset should set InterruptDisable and Unused only!");
+        assert_eq!(state.cycles, 7, "Reset should take 7 cycles!");
+    }
+
+    #[test]
+    fn test_step_returns_cycles() {
+        let mut cpu = create_cpu();
+
+        // LDA #$42
+        cpu.write(0x0400, 0xA9);
+        cpu.write(0x0401, 0x42);
+        cpu.program_counter = 0x0400;
+        assert_eq!(cpu.step(), StepResult::Executed(2), "LDA immediate should take 2 cycles!");
+
+        // LDA $04FF,X with X = 1 crosses into page 0x05
+        cpu.write(0x0402, 0xBD);
+        cpu.write(0x0403, 0xFF);
+        cpu.write(0x0404, 0x04);
+        cpu.register_x = 0x01;
+        assert_eq!(cpu.step(), StepResult::Executed(5), "LDA absolute,X with page cross should take 5 cycles!");
+
+        // BNE +$10 from $04FD lands on $050F
+        cpu.write(0x04FD, 0xD0);
+        cpu.write(0x04FE, 0x10);
+        cpu.program_counter = 0x04FD;
+        cpu.status.set_flag(CpuStatusRegisterFlags::Zero, false);
+        assert_eq!(cpu.step(), StepResult::Executed(4), "Taken branch across a page should take 4 cycles!");
+        assert_eq!(cpu.program_counter, 0x050F, "Branch should jump to the next page!");
+    }
+
+    #[test]
+    fn test_zero_page_indexed_wraps_without_penalty() {
+        let mut cpu = create_cpu();
+
+        // LDA $FF,X with X = 2 wraps to $0001
+        cpu.write(0x0001, 0x42);
+        cpu.write(0x0101, 0x99);
+        cpu.write(0x0400, 0xB5);
+        cpu.write(0x0401, 0xFF);
+        cpu.program_counter = 0x0400;
+        cpu.register_x = 0x02;
+        assert_eq!(cpu.step(), StepResult::Executed(4), "LDA zero page,X should take 4 cycles when it wraps!");
+        assert_eq!(cpu.register_a, 0x42, "LDA $FF,X should read from $0001!");
+
+        // LDX $FE,Y with Y = 3 wraps to $0001
+        cpu.write(0x0402, 0xB6);
+        cpu.write(0x0403, 0xFE);
+        cpu.register_y = 0x03;
+        assert_eq!(cpu.step(), StepResult::Executed(4), "LDX zero page,Y should take 4 cycles when it wraps!");
+        assert_eq!(cpu.register_x, 0x42, "LDX $FE,Y should read from $0001!");
+    }
+
+    #[test]
+    fn test_cpu_state_round_trip() {
+        let mut cpu = create_cpu();
+        let state = CpuState {
+            a: 0x12,
+            x: 0x34,
+            y: 0x56,
+            p: 0xE5,
+            sp: 0xF0,
+            pc: 0xC000,
+            cycles: 1234,
+            halted: false,
+            nmi_pending: false,
+            irq_requested: false,
+            delayed_interrupt_disable: None,
+        };
+
+        cpu.load_state(state.clone());
+        assert_eq!(cpu.state(), state, "Loaded state should read back unchanged!");
+        assert_eq!(cpu.register_a, 0x12, "Loaded state should set register A!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Negative), "Loaded state should set the status flags!");
+
+        // INX
+        cpu.write(0x0400, 0xE8);
+        cpu.load_state(CpuState { pc: 0x0400, ..state });
+        cpu.fetch();
+
+        let after = cpu.state();
+        assert_eq!(after.x, 0x35, "State should reflect executed instructions!");
+        assert_eq!(after.pc, 0x0401, "State should reflect the advanced PC!");
+        assert_eq!(after.cycles, 1236, "State should include the cycles taken!");
+    }
+
+    #[test]
+    fn test_disassembler_writes_to_sink() {
+        let lines = Rc::new(RefCell::new(Vec::<String>::new()));
+        let mut cpu = create_cpu();
+
+        {
+            let lines = lines.clone();
+            cpu.use_disassembler_with(TraceSink::callback(move |line| lines.borrow_mut().push(line.to_string())));
+        }
+
+        // LDA $0300
+        cpu.write(0x0300, 0x55);
+        cpu.write(0x0400, 0xAD);
+        cpu.write(0x0401, 0x00);
+        cpu.write(0x0402, 0x03);
+        cpu.reset_to(0x0400);
+        cpu.fetch();
+
+        assert_eq!(
+            *lines.borrow(),
+            ["0400  AD 00 03  LDA $0300 = 55                  A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7"],
+            "Trace line should follow the nestest format!"
+        );
+    }
+
+    #[test]
+    fn test_trace_write_error_stops_disassembler() {
+        struct ClosedPipe;
+
+        impl io::Write for ClosedPipe {
+            fn write(&mut self, _buffer: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut cpu = create_cpu();
+        cpu.use_disassembler_with(TraceSink::writer(ClosedPipe));
+
+        // INX, INX
+        cpu.write(0x0400, 0xE8);
+        cpu.write(0x0401, 0xE8);
+        cpu.program_counter = 0x0400;
+        cpu.fetch();
+
+        let error = cpu.take_trace_error().expect("Write error should be kept for the caller!");
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe, "Write error should be kept as is!");
+
+        cpu.fetch();
+        assert!(cpu.take_trace_error().is_none(), "Tracing should stop after the first error!");
+        assert_eq!(cpu.register_x, 0x02, "CPU should keep running without the trace!");
+    }
+
+    #[test]
+    fn test_disassembler_formats_addressing_modes() {
+        let lines = Rc::new(RefCell::new(Vec::<String>::new()));
+        let mut cpu = create_cpu();
+
+        {
+            let lines = lines.clone();
+            cpu.use_disassembler_with(TraceSink::callback(move |line| lines.borrow_mut().push(line.to_string())));
+        }
+
+        // LDX #$02, LDA $10,X, STA ($20),Y, ASL A, JMP $0400
+        let program = [0xA2, 0x02, 0xB5, 0x10, 0x91, 0x20, 0x0A, 0x4C, 0x00, 0x04];
+        cpu.bus.borrow_mut().load(0x0400, &program).unwrap();
+
+        cpu.write(0x0012, 0x81);
+        cpu.write(0x0020, 0x00);
+        cpu.write(0x0021, 0x03);
+        cpu.reset_to(0x0400);
+
+        for _ in 0..5 {
+            cpu.fetch();
+        }
+
+        assert_eq!(
+            *lines.borrow(),
+            [
+                "0400  A2 02     LDX #$02                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7",
+                "0402  B5 10     LDA $10,X @ 12 = 81             A:00 X:02 Y:00 P:24 SP:FD PPU:  0, 27 CYC:9",
+                "0404  91 20     STA ($20),Y = 0300 @ 0300 = 00  A:81 X:02 Y:00 P:A4 SP:FD PPU:  0, 39 CYC:13",
+                "0406  0A        ASL A                           A:81 X:02 Y:00 P:A4 SP:FD PPU:  0, 57 CYC:19",
+                "0407  4C 00 04  JMP $0400                       A:02 X:02 Y:00 P:25 SP:FD PPU:  0, 63 CYC:21",
+            ],
+            "Trace lines should follow the nestest format!"
+        );
+    }
+
+    #[test]
+    fn test_disassembler_substitutes_labels() {
+        let lines = Rc::new(RefCell::new(Vec::<String>::new()));
+        let mut cpu = create_cpu();
+
+        {
+            let lines = lines.clone();
+            cpu.use_disassembler_with(TraceSink::callback(move |line| lines.borrow_mut().push(line.to_string())));
+        }
+
+        let symbols = SymbolTable::parse_nl("$0400#Main#Entry point\n$0500#UpdateSprites#\n$0300#Buffer#\n").unwrap();
+        cpu.set_symbols(symbols);
+
+        // JSR $0500, LDA $0300, LDA $0301
+        let program = [0x20, 0x00, 0x05];
+        cpu.bus.borrow_mut().load(0x0400, &program).unwrap();
+
+        cpu.write(0x0500, 0xAD);
+        cpu.write(0x0501, 0x00);
+        cpu.write(0x0502, 0x03);
+        cpu.write(0x0503, 0xAD);
+        cpu.write(0x0504, 0x01);
+        cpu.write(0x0505, 0x03);
+        cpu.reset_to(0x0400);
+
+        for _ in 0..3 {
+            cpu.fetch();
+        }
+
+        assert_eq!(
+            *lines.borrow(),
+            [
+                "0400  20 00 05  JSR UpdateSprites               A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7 ; Entry point",
+                "0500  AD 00 03  LDA Buffer = 00                 A:00 X:00 Y:00 P:24 SP:FB PPU:  0, 39 CYC:13",
+                "0503  AD 01 03  LDA $0301 = 00                  A:00 X:00 Y:00 P:26 SP:FB PPU:  0, 51 CYC:17",
+            ],
+            "Known addresses should be shown as labels!"
+        );
+    }
+
+    #[test]
+    fn test_breakpoint_stops_before_instruction() {
+        let mut cpu = create_cpu();
+
+        // LDA #$01, INX, INX, LDY #$05
+        cpu.bus.borrow_mut().load(0x0400, &[0xA9, 0x01, 0xE8, 0xE8, 0xA0, 0x05]).unwrap();
+
+        cpu.program_counter = 0x0400;
+        cpu.add_breakpoint(0x0403);
+        cpu.add_breakpoint(0x0404);
+
+        let mut steps = 0;
+        let address = loop {
+            match cpu.step() {
+                StepResult::BreakpointHit(address) => break address,
+                _ => steps += 1,
+            }
+        };
+        let state = cpu.state();
+
+        assert_eq!(address, 0x0403, "Execution should stop at the breakpoint!");
+        assert_eq!(steps, 2, "Instructions before the breakpoint should run!");
+        assert_eq!((state.a, state.x, state.y), (0x01, 0x01, 0x00), "Instruction at the breakpoint should not run!");
+        assert_eq!(state.pc, 0x0403, "PC should stay at the breakpoint!");
+
+        assert_eq!(cpu.step(), StepResult::Executed(2), "Stepping again should run the instruction!");
+        assert_eq!(cpu.register_x, 0x02, "Resumed instruction should execute once!");
+
+        cpu.remove_breakpoint(0x0404);
+        assert_eq!(cpu.step(), StepResult::Executed(2), "Removed breakpoint should not stop execution!");
+        assert_eq!(cpu.register_y, 0x05, "LDY should run after the breakpoint was removed!");
+    }
+
+    #[test]
+    fn test_watchpoint_catches_indexed_write() {
+        let mut cpu = create_cpu();
+
+        // LDX #$05, LDY $0300, LDA #$42, STA $0300,X
+        cpu.bus.borrow_mut().load(0x0400, &[0xA2, 0x05, 0xAC, 0x00, 0x03, 0xA9, 0x42, 0x9D, 0x00, 0x03]).unwrap();
+
+        cpu.program_counter = 0x0400;
+        cpu.add_watchpoint(0x0300..=0x03FF, WatchKind::Write);
+
+        let (hit, cycles) = loop {
+            match cpu.step() {
+                StepResult::WatchpointHit { hit, cycles } => break (hit, cycles),
+                StepResult::Executed(_) => (),
+                StepResult::BreakpointHit(_) => panic!("No breakpoints were set!"),
+                StepResult::PcOutOfRom { .. } => panic!("Code should run from RAM!"),
+            }
+        };
+
+        assert_eq!(hit, WatchpointHit {
+            pc: 0x0407,
+            address: 0x0305,
+            value: 0x42,
+            kind: WatchKind::Write,
+        }, "STA $0300,X should hit the write watchpoint, reading $0300 should not!");
+        assert_eq!(cycles, 5, "Watchpoint should report the cycles the instruction took!");
+        assert_eq!(cpu.program_counter, 0x040A, "Watchpoint should stop after the instruction completes!");
+        assert_eq!(cpu.read(0x0305), 0x42, "Watched write should still happen!");
+    }
+
+    #[test]
+    fn test_watchpoint_distinguishes_reads() {
+        let mut cpu = create_cpu();
+
+        // LDY $0300
+        cpu.write(0x0300, 0x99);
+        cpu.write(0x0400, 0xAC);
+        cpu.write(0x0401, 0x00);
+        cpu.write(0x0402, 0x03);
+        cpu.program_counter = 0x0400;
+        cpu.add_watchpoint(0x0300..=0x0300, WatchKind::Read);
+
+        let expected = WatchpointHit { pc: 0x0400, address: 0x0300, value: 0x99, kind: WatchKind::Read };
+        assert_eq!(cpu.step(), StepResult::WatchpointHit { hit: expected, cycles: 4 }, "LDY should hit the read watchpoint!");
+
+        cpu.remove_watchpoint(0x0300..=0x0300, WatchKind::Read);
+        cpu.program_counter = 0x0400;
+        assert_eq!(cpu.step(), StepResult::Executed(4), "Removed watchpoint should not be hit!");
+    }
+
+    #[test]
+    fn test_read_watchpoint_ignores_instruction_fetches() {
+        let mut cpu = create_cpu();
+
+        // LDA #$01, LDA $0400
+        cpu.bus.borrow_mut().load(0x0400, &[0xA9, 0x01, 0xAD, 0x00, 0x04]).unwrap();
+        cpu.program_counter = 0x0400;
+        cpu.add_watchpoint(0x0400..=0x0404, WatchKind::Read);
+
+        assert_eq!(cpu.step(), StepResult::Executed(2), "Opcode and operand fetches should not hit the watchpoint!");
+
+        let expected = WatchpointHit { pc: 0x0402, address: 0x0400, value: 0xA9, kind: WatchKind::Read };
+        assert_eq!(cpu.step(), StepResult::WatchpointHit { hit: expected, cycles: 4 }, "Data read should hit the watchpoint!");
+    }
+
+    #[test]
+    fn test_pc_out_of_rom_detection() {
+        let mut cpu = create_cpu();
+
+        // JMP $5000
+        cpu.write(0x0400, 0x4C);
+        cpu.write(0x0401, 0x00);
+        cpu.write(0x0402, 0x50);
+        cpu.program_counter = 0x0400;
+        cpu.enable_pc_out_of_rom_detection();
+
+        assert_eq!(cpu.step(), StepResult::Executed(3), "JMP from RAM should not be reported!");
+        // Nothing mapped at $5000 reads as BRK
+        assert_eq!(cpu.step(), StepResult::PcOutOfRom { pc: 0x5000, cycles: 7 }, "Fetch from $5000 should be reported!");
+
+        cpu.program_counter = 0x5000;
+        assert_eq!(cpu.step(), StepResult::Executed(7), "PC out of ROM should only be reported once!");
+    }
+
+    #[test]
+    fn test_load_and_run() {
+        let mut cpu = create_cpu();
+
+        // LDA #$42, STA $0200, INX, KIL
+        let result = cpu.load_and_run(0x0400, &[0xA9, 0x42, 0x8D, 0x00, 0x02, 0xE8, 0x02], 10);
+
+        assert_eq!(result, Ok(()), "Program in RAM should load!");
+        assert_eq!(cpu.read(0x0200), 0x42, "Program should store A!");
+        assert_eq!(cpu.register_x, 0x01, "Program should run until the CPU jams!");
+        assert!(cpu.is_halted(), "KIL should stop the run!");
+    }
+
+    #[test]
+    fn test_load_and_run_refuses_prg_rom() {
+        let mut cpu = create_cpu();
+        let program_counter = cpu.program_counter;
+
+        assert_eq!(cpu.load_and_run(0x8000, &[0xE8], 1), Err(LoadError { address: 0x8000 }), "PRG-ROM should be refused!");
+        assert_eq!(cpu.program_counter, program_counter, "Refused program should not run!");
+    }
+
+    #[test]
+    fn test_history_evicts_oldest_entries() {
+        let mut cpu = create_cpu();
+
+        // INX, repeated past the capacity of the history
+        cpu.bus.borrow_mut().load(0x0400, &[0xE8; 0x0300]).unwrap();
+        cpu.program_counter = 0x0400;
+        cpu.enable_history();
+
+        for _ in 0..HISTORY_CAPACITY + 10 {
+            cpu.fetch();
+        }
+
+        let history = cpu.history().collect::<Vec<_>>();
+        assert_eq!(history.len(), HISTORY_CAPACITY, "History should be capped at its capacity!");
+        assert_eq!(history[0].pc, 0x040A, "Oldest entries should be evicted first!");
+        assert!(history.windows(2).all(|pair| pair[1].pc == pair[0].pc + 1), "History should stay in execution order!");
+
+        let last = history[HISTORY_CAPACITY - 1];
+        assert_eq!((last.opcode, last.x), (0xE8, (HISTORY_CAPACITY + 9) as u8), "Entries should hold registers before the instruction!");
+        assert_eq!(
+            cpu.format_history().lines().last(),
+            Some("0509  E8   INX  A:00 X:09 Y:00 P:24 SP:FD"),
+            "History should be formatted like the trace log!"
+        );
+    }
+
+    #[test]
+    fn test_cpu_debug_and_display() {
+        let mut cpu = create_cpu();
+
+        // LDA $0300,X
+        cpu.write(0x0400, 0xBD);
+        cpu.write(0x0401, 0x00);
+        cpu.write(0x0402, 0x03);
+        cpu.load_state(CpuState {
+            a: 0x12,
+            x: 0x34,
+            y: 0x56,
+            p: 0xC3,
+            sp: 0xF0,
+            pc: 0x0400,
+            cycles: 1234,
+            halted: false,
+            nmi_pending: false,
+            irq_requested: false,
+            delayed_interrupt_disable: None,
+        });
+
+        assert_eq!(
+            format!("{:?}", cpu),
+            "Cpu { a: $12, x: $34, y: $56, p: $E3 [NV-bdiZC], sp: $F0, pc: $0400, cycles: 1234, instruction: 0400  BD 00 03  LDA $0300,X }",
+            "Debug should show the registers, flags and the instruction at PC!"
+        );
+        assert_eq!(
+            cpu.to_string(),
+            "0400  BD 00 03  LDA $0300,X                     A:12 X:34 Y:56 P:E3 SP:F0 CYC:1234",
+            "Display should follow the trace layout!"
+        );
+    }
+
+    fn create_cpu_with_vectors() -> (Cpu, Rc<RefCell<Ppu>>) {
+        let cartridge = Cartridge::from_mapper(Box::new(VectorMapper { chr_rom: vec![0; 0x2000] }));
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, Mirroring::Horizontal)));
+        let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
+        let cpu = Cpu::new(&bus, &clock);
+
+        ppu.borrow_mut().write_controller(0x80);
+        (cpu, ppu)
+    }
+
+    // Runs BRK with the NMI raised this many PPU dots after it started
+    fn brk_with_nmi_after(dots: usize) -> Cpu {
+        let (_, ppu) = create_cpu_with_vectors();
+        let mut dots_to_nmi = 0;
+
+        while !ppu.borrow().has_interrupt() {
+            ppu.borrow_mut().tick(1);
+            dots_to_nmi += 1;
+        }
+
+        let (mut cpu, ppu) = create_cpu_with_vectors();
+        for _ in 0..dots_to_nmi - dots {
+            ppu.borrow_mut().tick(1);
+        }
+
+        cpu.write(0x0400, 0x00);
+        cpu.program_counter = 0x0400;
+        cpu.stack_pointer = 0xFD;
+        cpu.fetch();
+        cpu
+    }
+
+    // Runs INC $0300,X (7 cycles) with the PPU raising NMI 2 CPU cycles into it
+    fn create_cpu_with_nmi_during_inc() -> (Cpu, Rc<RefCell<Ppu>>) {
+        let (_, ppu) = create_cpu_with_vectors();
+        let mut dots_to_nmi = 0;
+
+        while !ppu.borrow().has_interrupt() {
+            ppu.borrow_mut().tick(1);
+            dots_to_nmi += 1;
+        }
+
+        let (mut cpu, ppu) = create_cpu_with_vectors();
+        for _ in 0..dots_to_nmi - 6 {
+            ppu.borrow_mut().tick(1);
+        }
+
+        cpu.bus.borrow_mut().load(0x0400, &[0xFE, 0x00, 0x03, 0xE8]).unwrap();
+        cpu.write(0x0500, 0xEA);
+        cpu.program_counter = 0x0400;
+        cpu.stack_pointer = 0xFD;
+
+        assert_eq!(cpu.step(), StepResult::Executed(7), "INC absolute,X should take 7 cycles!");
+        assert!(ppu.borrow().has_interrupt(), "PPU should raise NMI during INC!");
+        (cpu, ppu)
+    }
+
+    #[test]
+    fn test_nmi_during_instruction_is_serviced_after_it() {
+        let (mut cpu, _) = create_cpu_with_nmi_during_inc();
+
+        assert_eq!(cpu.step(), StepResult::Executed(7 + 2), "NMI should be serviced right after INC!");
+        assert_eq!(cpu.read_u16(0x01FC), 0x0403, "NMI should interrupt the instruction after INC!");
+        assert_eq!(cpu.register_x, 0x00, "Interrupted instruction should not run yet!");
+    }
+
+    #[test]
+    fn test_nmi_cleared_before_poll_is_not_lost() {
+        let (mut cpu, ppu) = create_cpu_with_nmi_during_inc();
+
+        // Reading PPUSTATUS clears VBlank and drops the NMI line again
+        ppu.borrow_mut().read_status();
+        assert_eq!(cpu.step(), StepResult::Executed(7 + 2), "Latched NMI should still be serviced!");
+        assert_eq!(cpu.program_counter, 0x0501, "NMI should jump through $FFFA!");
+    }
+
+    #[test]
+    fn test_nmi_hijacks_brk() {
+        let cpu = brk_with_nmi_after(6);
+
+        assert_eq!(cpu.program_counter, 0x0500, "NMI during BRK should hijack the vector to $FFFA!");
+        assert_eq!(cpu.read_u16(0x01FC), 0x0402, "Hijacked BRK should still push the PC past its padding byte!");
+        assert_ne!(cpu.read(0x01FB) & CpuStatusRegisterFlags::Break as u8, 0x00, "Hijacked BRK should push Break set!");
+        assert!(cpu.bus.borrow().get_interrupt().is_none(), "Hijacking should consume the NMI!");
+
+        let cpu = brk_with_nmi_after(7 * 3 + 3);
+        assert_eq!(cpu.program_counter, 0x0600, "NMI after BRK should not hijack its vector!");
+        assert!(cpu.bus.borrow().get_interrupt().is_none(), "NMI should not be raised during BRK!");
+    }
+
+    #[test]
+    fn test_decimal_mode_adc_and_sbc() {
+        // (A, operand, carry in, result, carry out)
+        let additions = [
+            (0x09, 0x01, false, 0x10, false),
+            (0x12, 0x34, false, 0x46, false),
+            (0x58, 0x46, true, 0x05, true),
+            (0x99, 0x01, false, 0x00, true),
+            (0x99, 0x99, true, 0x99, true),
+        ];
+        let subtractions = [
+            (0x10, 0x01, true, 0x09, true),
+            (0x46, 0x12, true, 0x34, true),
+            (0x40, 0x13, true, 0x27, true),
+            (0x32, 0x02, false, 0x29, true),
+            (0x00, 0x01, true, 0x99, false),
+        ];
+
+        for (is_addition, cases) in [(true, additions), (false, subtractions)] {
+            for (a, operand, carry, result, carry_after) in cases {
+                let mut cpu = create_cpu_with_immediate(operand);
+                cpu.set_bcd_enabled(true);
+                cpu.register_a = a;
+                cpu.status.set_flag(CpuStatusRegisterFlags::DecimalMode, true);
+                cpu.status.set_flag(CpuStatusRegisterFlags::Carry, carry);
+
+                if is_addition {
+                    cpu.execute_adc(&AddressingMode::Immediate);
+                } else {
+                    cpu.execute_sbc(&AddressingMode::Immediate);
+                }
+
+                let operation = if is_addition { "+" } else { "-" };
+                assert_eq!(cpu.register_a, result, "${:02X} {} ${:02X} should be ${:02X} in decimal mode!", a, operation, operand, result);
+                assert_eq!(cpu.status.get_flag(CpuStatusRegisterFlags::Carry), carry_after, "${:02X} {} ${:02X} should set Carry to {}!", a, operation, operand, carry_after);
+            }
+        }
+
+        // $99 + $01 is $9A in binary, so Zero stays clear and Negative comes from the unadjusted $A0
+        let mut cpu = create_cpu_with_immediate(0x01);
+        cpu.set_bcd_enabled(true);
+        cpu.register_a = 0x99;
+        cpu.status.set_flag(CpuStatusRegisterFlags::DecimalMode, true);
+        cpu.execute_adc(&AddressingMode::Immediate);
+
+        assert!(!cpu.status.get_flag(CpuStatusRegisterFlags::Zero), "Decimal ADC should take Zero from the binary sum!");
+        assert!(cpu.status.get_flag(CpuStatusRegisterFlags::Negative), "Decimal ADC should take Negative before the high nibble adjust!");
+    }
+
+    #[test]
+    fn test_decimal_flag_ignored_by_default() {
+        let mut cpu = create_cpu_with_immediate(0x01);
+        cpu.register_a = 0x09;
+        cpu.status.set_flag(CpuStatusRegisterFlags::DecimalMode, true);
+        cpu.execute_adc(&AddressingMode::Immediate);
+
+        assert_eq!(cpu.register_a, 0x0A, "2A03 should add in binary even with the D flag set!");
+    }
+
+    #[test]
+    fn test_oam_dma_stalls_cpu() {
+        for start_cycles in [100, 101] {
+            let mut cpu = create_cpu();
+
+            for offset in 0..0x100 {
+                cpu.write(0x0200 + offset, offset as u8 ^ 0x5A);
+            }
+
+            // STA $4014 with A = 2 takes 4 cycles before the DMA starts
+            cpu.write(0x0400, 0x8D);
+            cpu.write(0x0401, 0x14);
+            cpu.write(0x0402, 0x40);
+            cpu.register_a = 0x02;
+            cpu.program_counter = 0x0400;
+            cpu.clock.borrow_mut().set_cycles(start_cycles);
+
+            let expected = 4 + 513 + (start_cycles + 4) % 2;
+            assert_eq!(cpu.step(), StepResult::Executed(expected), "OAM DMA should stall the CPU for 513 or 514 cycles!");
+
+            let oam = cpu.bus.borrow_mut().ppu_memory_map().get_oam().to_vec();
+            let page = (0..0x100).map(|offset| offset as u8 ^ 0x5A).collect::<Vec<_>>();
+            assert_eq!(oam, page, "OAM should hold the copied page!");
+        }
+    }
+
+    fn create_cpu_with_kil(policy: IllegalOpcodePolicy) -> Cpu {
+        let mut cpu = create_cpu();
+
+        // KIL, INX
+        cpu.write(0x0400, 0x12);
+        cpu.write(0x0401, 0xE8);
+        cpu.program_counter = 0x0400;
+        cpu.set_illegal_opcode_policy(policy);
+        cpu
+    }
+
+    #[test]
+    fn test_illegal_opcode_policy_halt() {
+        let mut cpu = create_cpu_with_kil(IllegalOpcodePolicy::Halt);
+
+        cpu.fetch();
+        cpu.fetch();
+        assert!(cpu.is_halted(), "Halt policy should jam the CPU!");
+        assert_eq!(cpu.register_x, 0x00, "Jammed CPU should not execute the next instruction!");
+    }
+
+    #[test]
+    fn test_illegal_opcode_policy_treat_as_nop() {
+        let mut cpu = create_cpu_with_kil(IllegalOpcodePolicy::TreatAsNop);
+
+        assert_eq!(cpu.step(), StepResult::Executed(1), "KIL as NOP should take the cycles of the table!");
+        assert!(!cpu.is_halted(), "NOP policy should not jam the CPU!");
+        assert_eq!(cpu.program_counter, 0x0401, "KIL as NOP should skip its single byte!");
+
+        cpu.fetch();
+        assert_eq!(cpu.register_x, 0x01, "Next instruction should run after the skipped opcode!");
+    }
+
+    #[test]
+    #[should_panic(expected = "Illegal opcode $12 at $0400!")]
+    fn test_illegal_opcode_policy_panic() {
+        let mut cpu = create_cpu_with_kil(IllegalOpcodePolicy::Panic);
+        cpu.fetch();
+    }
+
+    // Loops through register-only instructions, so the CPU state alone decides what runs next
+    fn create_cpu_with_register_loop() -> Cpu {
+        let mut cpu = create_cpu();
+
+        // INX, ADC #$03, ROL A, TAY, DEY, BNE -3, JMP $0400
+        let program = [0xE8, 0x69, 0x03, 0x2A, 0xA8, 0x88, 0xD0, 0xFD, 0x4C, 0x00, 0x04];
+        cpu.bus.borrow_mut().load(0x0400, &program).unwrap();
+
+        cpu.set_program_counter(0x0400);
+        cpu
+    }
+
+    #[test]
+    fn test_save_state_round_trip() {
+        let mut cpu = create_cpu_with_register_loop();
+
+        for _ in 0..1000 {
+            cpu.step();
+        }
+
+        let snapshot = cpu.save_state();
+
+        for _ in 0..1000 {
+            cpu.step();
+        }
+
+        let expected = cpu.save_state();
+        cpu.restore_state(&snapshot);
+        assert_eq!(cpu.save_state(), snapshot, "Restored state should match the snapshot!");
+
+        for _ in 0..1000 {
+            cpu.step();
+        }
+
+        assert_eq!(cpu.save_state(), expected, "Rerun from the snapshot should end in the same state!");
+
+        // CLI, LDA #$42 with an IRQ asserted; the IRQ vector of the empty cartridge points at $0000
+        cpu.write(0x0000, 0xEA);
+        cpu.bus.borrow_mut().load(0x0600, &[0x58, 0xA9, 0x42]).unwrap();
+        cpu.set_program_counter(0x0600);
+        cpu.stack_pointer = 0xFD;
+        cpu.status.set_flag(CpuStatusRegisterFlags::InterruptDisable, true);
+        cpu.bus.borrow_mut().set_irq(true);
+        cpu.step();
+
+        let snapshot = cpu.save_state();
+        cpu.step();
+        cpu.step();
+        cpu.restore_state(&snapshot);
+        cpu.step();
+        assert_eq!(cpu.program_counter, 0x0603, "Restored CLI delay should let LDA run before the IRQ!");
+    }
+
+    #[test]
+    fn test_save_state_keeps_pending_nmi() {
+        let (mut cpu, _) = create_cpu_with_vectors();
+
+        // INX, the NMI handler at $0500 starts with a NOP
+        cpu.write(0x0400, 0xE8);
+        cpu.write(0x0500, 0xEA);
+        cpu.program_counter = 0x0400;
+        cpu.stack_pointer = 0xFD;
+        cpu.nmi();
+
+        let snapshot = cpu.save_state();
+        assert!(snapshot.nmi_pending, "Snapshot should record the latched NMI!");
+
+        cpu.step();
+        cpu.restore_state(&snapshot);
+        cpu.step();
+        assert_eq!(cpu.program_counter, 0x0501, "NMI latched before the snapshot should still be serviced!");
+    }
+
+    #[test]
+    fn test_save_state_keeps_requested_irq() {
+        let mut cpu = create_cpu();
+
+        // INX; the IRQ vector of the empty cartridge points at a NOP at $0000
+        cpu.write(0x0000, 0xEA);
+        cpu.write(0x0400, 0xE8);
+        cpu.program_counter = 0x0400;
+        cpu.stack_pointer = 0xFD;
+        cpu.status.set(0x00);
+        cpu.irq();
+
+        let snapshot = cpu.save_state();
+        cpu.step();
+        cpu.restore_state(&snapshot);
+        cpu.step();
+        assert_eq!(cpu.program_counter, 0x0001, "IRQ requested before the snapshot should still be serviced!");
+    }
+
+    #[cfg(feature = "savestate")]
+    #[test]
+    fn test_save_state_serializes_deterministically() {
+        let mut cpu = create_cpu_with_register_loop();
+
+        for _ in 0..1000 {
+            cpu.step();
+        }
+
+        let bytes = serde_json::to_vec(&cpu.save_state()).unwrap();
+
+        for _ in 0..1000 {
+            cpu.step();
+        }
+
+        let expected = serde_json::to_vec(&cpu.save_state()).unwrap();
+        cpu.restore_state(&serde_json::from_slice(&bytes).unwrap());
+
+        for _ in 0..1000 {
+            cpu.step();
+        }
+
+        assert_eq!(serde_json::to_vec(&cpu.save_state()).unwrap(), expected, "Serialized states should match byte for byte!");
+    }
+
+    #[test]
+    fn test_code_data_logger_classifies_prg_rom() {
+        let mut prg_rom = vec![0x00; 0x4000];
+
+        // LDA $8010, LDX #$05, JMP ($8012) to $8020, NOP
+        prg_rom[..8].copy_from_slice(&[0xAD, 0x10, 0x80, 0xA2, 0x05, 0x6C, 0x12, 0x80]);
+        prg_rom[0x12..0x14].copy_from_slice(&[0x20, 0x80]);
+        prg_rom[0x20] = 0xEA;
+
+        let cartridge = Cartridge::from_mapper(Box::new(Mapper000::new(prg_rom, vec![0; 0x2000])));
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, Mirroring::Horizontal)));
+        let clock = Rc::new(RefCell::new(Clock::new(&ppu, |_| {})));
+        let mut cpu = Cpu::new(&bus, &clock);
+
+        cpu.enable_code_data_logger();
+        cpu.program_counter = 0xC000;
+
+        for _ in 0..4 {
+            cpu.step();
+        }
+
+        let logger = cpu.code_data_logger().unwrap();
+        let opcodes = (0..0x30).filter(|offset| logger.is_opcode(*offset)).collect::<Vec<_>>();
+        let operands = (0..0x30).filter(|offset| logger.is_operand(*offset)).collect::<Vec<_>>();
+        let data = (0..0x30).filter(|offset| logger.is_data(*offset)).collect::<Vec<_>>();
+
+        assert_eq!(opcodes, [0x00, 0x03, 0x05, 0x20], "Fetched opcodes should be logged as code!");
+        assert_eq!(operands, [0x01, 0x02, 0x04, 0x06, 0x07], "Operand bytes should be logged as operands!");
+        assert_eq!(data, [0x10, 0x12, 0x13], "LDA and JMP indirect targets should be logged as data!");
+
+        let cdl = logger.export();
+        assert_eq!(&cdl[..3], [0x09, 0x09, 0x09], "Code fetched through $C000 should have bank bits 0b10!");
+        assert_eq!(cdl[0x10], 0x02, "LDA $8010 should log data through bank 0b00!");
+        assert_eq!(cdl[0x21], 0x00, "Untouched byte should stay clear!");
+    }
 }
@@ -0,0 +1,180 @@
+use std::fmt;
+
+use super::cpu::{AddressingMode, INSTRUCTIONS};
+use super::memory::Memory;
+use super::symbols::SymbolTable;
+
+/// One decoded instruction of a listing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisassembledLine {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub mnemonic: &'static str,
+    pub operand: String,
+    pub unofficial: bool,
+}
+
+// Same columns as the trace, without the registers
+impl fmt::Display for DisassembledLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hexdump = self.bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let marker = if self.unofficial { '*' } else { ' ' };
+
+        let line = format!("{:04X}  {:<8} {}{} {}", self.address, hexdump, marker, self.mnemonic, self.operand);
+        f.write_str(line.trim_end())
+    }
+}
+
+/// Decodes `count` instructions from `start` without executing them. Operands are
+/// shown as written, indexed and indirect addresses aren't resolved since that
+/// would need the registers.
+pub fn disassemble(memory: &dyn Memory, start: u16, count: usize) -> Vec<DisassembledLine> {
+    disassemble_with_symbols(memory, start, count, &SymbolTable::default())
+}
+
+// Like `disassemble`, with labels in place of known absolute addresses
+pub fn disassemble_with_symbols(memory: &dyn Memory, start: u16, count: usize, symbols: &SymbolTable) -> Vec<DisassembledLine> {
+    let mut address = start;
+
+    (0..count)
+        .map(|_| {
+            let line = disassemble_instruction(memory, address, symbols);
+            address = address.wrapping_add(line.bytes.len() as u16);
+            line
+        })
+        .collect()
+}
+
+fn disassemble_instruction(memory: &dyn Memory, address: u16, symbols: &SymbolTable) -> DisassembledLine {
+    let instruction = &INSTRUCTIONS[memory.read(address) as usize];
+    let bytes = (0..instruction.bytes as u16)
+        .map(|offset| memory.read(address.wrapping_add(offset)))
+        .collect::<Vec<_>>();
+
+    let byte = bytes.get(1).copied().unwrap_or(0);
+    let word = u16::from_le_bytes([byte, bytes.get(2).copied().unwrap_or(0)]);
+
+    let operand = match instruction.addressing_mode {
+        AddressingMode::Implicit => String::new(),
+        AddressingMode::Accumulator => "A".into(),
+        AddressingMode::Immediate => format!("#${:02X}", byte),
+        AddressingMode::ZeroPage => format!("${:02X}", byte),
+        AddressingMode::ZeroPageX => format!("${:02X},X", byte),
+        AddressingMode::ZeroPageY => format!("${:02X},Y", byte),
+        AddressingMode::Relative => {
+            symbols.format_address(address.wrapping_add(2).wrapping_add(byte as i8 as u16))
+        },
+        AddressingMode::Absolute => symbols.format_address(word),
+        AddressingMode::AbsoluteX => format!("{},X", symbols.format_address(word)),
+        AddressingMode::AbsoluteY => format!("{},Y", symbols.format_address(word)),
+        AddressingMode::Indirect => format!("({})", symbols.format_address(word)),
+        AddressingMode::IndexedIndirect => format!("(${:02X},X)", byte),
+        AddressingMode::IndirectIndexed => format!("(${:02X}),Y", byte),
+    };
+
+    DisassembledLine {
+        address,
+        bytes,
+        mnemonic: instruction.operation.mnemonic(),
+        operand,
+        unofficial: instruction.is_unofficial(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ProgramMemory {
+        start: u16,
+        bytes: Vec<u8>,
+    }
+
+    impl Memory for ProgramMemory {
+        fn read(&self, address: u16) -> u8 {
+            self.bytes
+                .get(address.wrapping_sub(self.start) as usize)
+                .copied()
+                .unwrap_or(0x00)
+        }
+
+        fn write(&mut self, _address: u16, _data: u8) {}
+    }
+
+    #[test]
+    fn test_disassemble_addressing_modes() {
+        let memory = ProgramMemory {
+            start: 0xC000,
+            bytes: vec![
+                0xEA, // NOP
+                0x0A, // ASL A
+                0xA9, 0x42, // LDA #$42
+                0xA5, 0x10, // LDA $10
+                0xB5, 0x10, // LDA $10,X
+                0xB6, 0x10, // LDX $10,Y
+                0xD0, 0xFE, // BNE $C00A
+                0xAD, 0x34, 0x12, // LDA $1234
+                0xBD, 0x34, 0x12, // LDA $1234,X
+                0xB9, 0x34, 0x12, // LDA $1234,Y
+                0x6C, 0xFF, 0x02, // JMP ($02FF)
+                0xA1, 0x20, // LDA ($20,X)
+                0xB1, 0x20, // LDA ($20),Y
+                0xA7, 0x30, // LAX $30
+            ],
+        };
+
+        let lines = disassemble(&memory, 0xC000, 14)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(lines, [
+            "C000  EA        NOP",
+            "C001  0A        ASL A",
+            "C002  A9 42     LDA #$42",
+            "C004  A5 10     LDA $10",
+            "C006  B5 10     LDA $10,X",
+            "C008  B6 10     LDX $10,Y",
+            "C00A  D0 FE     BNE $C00A",
+            "C00C  AD 34 12  LDA $1234",
+            "C00F  BD 34 12  LDA $1234,X",
+            "C012  B9 34 12  LDA $1234,Y",
+            "C015  6C FF 02  JMP ($02FF)",
+            "C018  A1 20     LDA ($20,X)",
+            "C01A  B1 20     LDA ($20),Y",
+            "C01C  A7 30    *LAX $30",
+        ], "Listing should decode every addressing mode!");
+    }
+
+    #[test]
+    fn test_disassemble_with_symbols() {
+        let memory = ProgramMemory { start: 0x8000, bytes: vec![0x20, 0x19, 0x8E, 0xD0, 0xFB, 0xBD, 0x00, 0x07] };
+        let symbols = SymbolTable::parse_nl("$8E19#UpdateSprites#\n$8000#Loop#\n").unwrap();
+
+        let lines = disassemble_with_symbols(&memory, 0x8000, 3, &symbols)
+            .iter()
+            .map(|line| line.operand.clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(lines, ["UpdateSprites", "Loop", "$0700,X"], "Known addresses should be shown as labels!");
+    }
+
+    #[test]
+    fn test_disassemble_line_fields() {
+        let memory = ProgramMemory { start: 0x8000, bytes: vec![0x4C, 0x00, 0x80] };
+        let lines = disassemble(&memory, 0x8000, 2);
+
+        assert_eq!(lines[0], DisassembledLine {
+            address: 0x8000,
+            bytes: vec![0x4C, 0x00, 0x80],
+            mnemonic: "JMP",
+            operand: "$8000".into(),
+            unofficial: false,
+        }, "JMP should decode into its fields!");
+        assert_eq!(lines[1].address, 0x8003, "Next line should start past the operands!");
+    }
+}
@@ -0,0 +1,759 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use super::bus::{Bus, RamInitPattern};
+use super::cartridge::Cartridge;
+use super::clock::{Clock, ClockState};
+use super::config::Config;
+use super::cpu::{Cpu, CpuState, StepResult};
+use super::frontend::{AudioSink, Frame, Hotkey, InputSource, InputState, VideoSink};
+use super::mappers::Mapper;
+use super::ppu::Ppu;
+
+// Holding frame advance repeats it every 6 polls (~10 FPS) after a 30 poll delay
+const FRAME_ADVANCE_REPEAT_DELAY: usize = 30;
+const FRAME_ADVANCE_REPEAT_INTERVAL: usize = 6;
+
+pub const MAX_RUN_AHEAD_FRAMES: usize = 2;
+
+/// Copy of the whole console state, restoring it rewinds the emulator.
+pub struct Snapshot {
+    cpu: CpuState,
+    clock: ClockState,
+    bus: Bus,
+    ppu: Ppu,
+    ram: [u8; 0x800],
+    mapper: Box<dyn Mapper>,
+}
+
+/// Owns the whole console and drives the frontend sinks once per frame.
+pub struct Emulator {
+    bus: Rc<RefCell<Bus>>,
+    ppu: Rc<RefCell<Ppu>>,
+    clock: Rc<RefCell<Clock>>,
+    cpu: Cpu,
+    frame_complete: Rc<Cell<bool>>,
+    audio_samples: Vec<f32>,
+    input: InputState,
+    paused: bool,
+    pending_frames: usize,
+    frame_advance_polls: usize,
+    run_ahead_frames: usize,
+    run_ahead_frame: Option<Frame>,
+}
+
+impl Emulator {
+    pub fn new(cartridge: &Cartridge) -> Self {
+        Self::with_ram_init(cartridge, RamInitPattern::AllZeros)
+    }
+
+    pub fn with_ram_init(cartridge: &Cartridge, ram_init: RamInitPattern) -> Self {
+        let bus = Rc::new(RefCell::new(Bus::with_ram_init(cartridge, ram_init)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(&bus, cartridge.get_mirroring())));
+        ppu.borrow_mut().set_region(cartridge.get_region());
+
+        let frame_complete = Rc::new(Cell::new(false));
+        let clock = {
+            let frame_complete = frame_complete.clone();
+
+            Rc::new(RefCell::new(Clock::new(&ppu, move |_| {
+                frame_complete.set(true);
+            })))
+        };
+
+        let mut cpu = Cpu::new(&bus, &clock);
+        cpu.reset();
+
+        Self {
+            bus,
+            ppu,
+            clock,
+            cpu,
+            frame_complete,
+            audio_samples: Vec::new(),
+            input: InputState::default(),
+            paused: false,
+            pending_frames: 0,
+            frame_advance_polls: 0,
+            run_ahead_frames: 0,
+            run_ahead_frame: None,
+        }
+    }
+
+    /// Builds an emulator whose power-up RAM is filled from `seed`, so two
+    /// emulators with the same seed and inputs render identical frames.
+    /// The core never reads the wall clock, so the seed is the only source
+    /// of variation between runs.
+    pub fn new_deterministic(cartridge: &Cartridge, seed: u64) -> Self {
+        Self::with_ram_init(cartridge, RamInitPattern::Random(seed))
+    }
+
+    pub fn reset(&mut self) {
+        self.cpu.reset();
+    }
+
+    pub fn save_snapshot(&self) -> Snapshot {
+        let bus = self.bus.borrow();
+
+        Snapshot {
+            cpu: self.cpu.save_state(),
+            clock: self.clock.borrow().save_state(),
+            bus: bus.clone(),
+            ppu: self.ppu.borrow().clone(),
+            ram: bus.save_ram(),
+            mapper: bus.save_mapper(),
+        }
+    }
+
+    pub fn load_snapshot(&mut self, snapshot: &Snapshot) {
+        self.cpu.restore_state(&snapshot.cpu);
+        self.clock.borrow_mut().restore_state(&snapshot.clock);
+        *self.bus.borrow_mut() = snapshot.bus.clone();
+        *self.ppu.borrow_mut() = snapshot.ppu.clone();
+
+        self.bus.borrow().load_ram(&snapshot.ram);
+        self.bus.borrow().load_mapper(snapshot.mapper.as_ref());
+    }
+
+    pub fn load_battery_ram(&mut self, data: &[u8]) {
+        self.bus.borrow_mut().load_battery_ram(data);
+    }
+
+    pub fn take_dirty_battery_ram(&mut self) -> Option<Vec<u8>> {
+        self.bus.borrow_mut().take_dirty_battery_ram()
+    }
+
+    pub fn cpu(&mut self) -> &mut Cpu {
+        &mut self.cpu
+    }
+
+    pub fn bus(&self) -> &Rc<RefCell<Bus>> {
+        &self.bus
+    }
+
+    pub fn ppu(&self) -> &Rc<RefCell<Ppu>> {
+        &self.ppu
+    }
+
+    pub fn clock(&self) -> &Rc<RefCell<Clock>> {
+        &self.clock
+    }
+
+    // Settings are applied before reset, so the game starts with them
+    pub fn apply_config(&mut self, config: &Config) {
+        self.set_extra_vblank_scanlines(config.extra_vblank_scanlines);
+        self.set_run_ahead(config.run_ahead_frames);
+
+        if let Some(ram_init) = config.ram_init {
+            self.bus.borrow_mut().cpu_memory_map().init_ram(ram_init);
+        }
+
+        self.reset();
+    }
+
+    // See `Ppu::set_extra_vblank_scanlines`
+    pub fn set_extra_vblank_scanlines(&mut self, scanlines: usize) {
+        self.ppu.borrow_mut().set_extra_vblank_scanlines(scanlines);
+    }
+
+    pub fn set_input(&mut self, input: InputState) {
+        let mut bus = self.bus.borrow_mut();
+
+        for (index, buttons) in input.joypads.iter().enumerate() {
+            bus.joypad(index).set_buttons(*buttons);
+        }
+
+        // Hotkeys act once when pressed, not for every frame they are held
+        let pressed_hotkeys = input.hotkeys & !self.input.hotkeys;
+        let is_pressed = |hotkey: Hotkey| pressed_hotkeys & hotkey as u8 != 0;
+        self.input = input;
+
+        if is_pressed(Hotkey::SwitchDiskSide) {
+            bus.switch_disk_side();
+        }
+
+        if is_pressed(Hotkey::SpriteZeroDebug) {
+            let mut ppu = self.ppu.borrow_mut();
+            let enabled = ppu.get_debug_mask().is_some();
+            ppu.set_debug_mask(!enabled);
+        }
+
+        if is_pressed(Hotkey::WaveformView) {
+            let enabled = bus.apu().waveforms().is_some();
+            bus.apu().set_waveform_taps(!enabled);
+        }
+
+        if is_pressed(Hotkey::Pause) {
+            self.paused = !self.paused;
+            self.pending_frames = 0;
+        }
+
+        if !input.is_hotkey_pressed(Hotkey::FrameAdvance) {
+            self.frame_advance_polls = 0;
+        } else if self.paused {
+            let polls = self.frame_advance_polls;
+            let is_repeat = polls >= FRAME_ADVANCE_REPEAT_DELAY
+                && (polls - FRAME_ADVANCE_REPEAT_DELAY).is_multiple_of(FRAME_ADVANCE_REPEAT_INTERVAL);
+
+            if polls == 0 || is_repeat {
+                self.pending_frames += 1;
+            }
+
+            self.frame_advance_polls += 1;
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Runs a frame unless paused, a frame advance while paused runs exactly one.
+    /// Returns whether a whole frame was run, poll input first so it lands on that frame.
+    pub fn advance(&mut self) -> bool {
+        if self.paused {
+            if self.pending_frames == 0 {
+                return false;
+            }
+
+            self.pending_frames -= 1;
+        }
+
+        // Running ahead from the middle of a frame would show frames the game never reaches
+        if !self.run_frame() {
+            self.run_ahead_frame = None;
+            return false;
+        }
+
+        self.run_ahead_frame = if self.run_ahead_frames > 0 {
+            Some(self.run_ahead())
+        } else {
+            None
+        };
+
+        true
+    }
+
+    /// Shows frames rendered this many frames ahead with the current input, hiding
+    /// the game's own input latency. Every frame then costs `frames + 1` frames of CPU time.
+    pub fn set_run_ahead(&mut self, frames: usize) {
+        if frames > MAX_RUN_AHEAD_FRAMES {
+            panic!("Run-ahead should be between 0 and {} frames!", MAX_RUN_AHEAD_FRAMES);
+        }
+
+        self.run_ahead_frames = frames;
+        self.run_ahead_frame = None;
+    }
+
+    pub fn get_run_ahead(&self) -> usize {
+        self.run_ahead_frames
+    }
+
+    // Runs ahead from the real frame and rewinds, keeping only the real frame's audio
+    fn run_ahead(&mut self) -> Frame {
+        let snapshot = self.save_snapshot();
+        let audio_samples = self.audio_samples.len();
+
+        for _ in 0..self.run_ahead_frames {
+            self.run_frame_ignoring_breakpoints();
+        }
+
+        let frame = self.frame();
+        self.load_snapshot(&snapshot);
+        self.audio_samples.truncate(audio_samples);
+
+        frame
+    }
+
+    /// Frame the frontend shows, ahead of `frame` when run-ahead is on.
+    pub fn displayed_frame(&self) -> Frame {
+        self.run_ahead_frame
+            .clone()
+            .unwrap_or_else(|| self.frame())
+    }
+
+    /// Runs the CPU until the PPU reports that a frame was rendered, returns whether it was.
+    /// A CPU breakpoint, watchpoint or PC out of ROM pauses the emulator in the middle of the frame.
+    pub fn run_frame(&mut self) -> bool {
+        self.frame_complete.set(false);
+
+        while !self.frame_complete.get() {
+            if let StepResult::BreakpointHit(_) | StepResult::WatchpointHit { .. } | StepResult::PcOutOfRom { .. } =
+                self.cpu.step()
+            {
+                self.paused = true;
+                self.pending_frames = 0;
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // Frames run ahead are thrown away, so they don't stop at breakpoints or watchpoints
+    fn run_frame_ignoring_breakpoints(&mut self) {
+        self.frame_complete.set(false);
+
+        while !self.frame_complete.get() {
+            self.cpu.fetch();
+        }
+    }
+
+    pub fn frame(&self) -> Frame {
+        let ppu = self.ppu.borrow();
+        let frame = Frame::from(ppu.get_screen_buffer());
+
+        match ppu.get_debug_mask() {
+            Some(debug_mask) => frame.with_debug_mask(debug_mask),
+            None => frame,
+        }
+    }
+
+    pub fn poll_input(&mut self, input: &mut dyn InputSource) {
+        let state = input.poll();
+        self.set_input(state);
+    }
+
+    pub fn present(&mut self, video: &mut dyn VideoSink, audio: &mut dyn AudioSink) {
+        audio.push(&self.audio_samples);
+        self.audio_samples.clear();
+
+        video.show_input(&self.input);
+        video.show_waveforms(self.bus.borrow_mut().apu().waveforms());
+        video.present(&self.displayed_frame());
+    }
+
+    pub fn drive_frame(
+        &mut self,
+        video: &mut dyn VideoSink,
+        audio: &mut dyn AudioSink,
+        input: &mut dyn InputSource,
+    ) {
+        self.poll_input(input);
+        self.advance();
+        self.present(video, audio);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::frontend::{NullAudioSink, NullInputSource, NullVideoSink};
+    use crate::core::joypad::JoypadButton;
+    use crate::core::memory::Memory;
+    use crate::core::ppu::Region;
+    use super::*;
+
+    struct RecordingVideoSink {
+        frames: Vec<Frame>,
+    }
+
+    impl VideoSink for RecordingVideoSink {
+        fn present(&mut self, frame: &Frame) {
+            self.frames.push(frame.clone());
+        }
+    }
+
+    struct RecordingAudioSink {
+        pushes: usize,
+    }
+
+    impl AudioSink for RecordingAudioSink {
+        fn push(&mut self, _samples: &[f32]) {
+            self.pushes += 1;
+        }
+    }
+
+    struct ScriptedInputSource {
+        polls: usize,
+    }
+
+    impl InputSource for ScriptedInputSource {
+        fn poll(&mut self) -> InputState {
+            self.polls += 1;
+
+            let mut state = InputState::default();
+            state.set_button(0, JoypadButton::Start, true);
+            state
+        }
+    }
+
+    fn load_program(emulator: &Emulator, program: &[u8]) {
+        for (address, byte) in program.iter().enumerate() {
+            emulator.bus()
+                .borrow_mut()
+                .cpu_memory_map()
+                .write(address as u16, *byte);
+        }
+    }
+
+    fn emulator_with_nmi_loop() -> Emulator {
+        let cartridge = Cartridge::empty();
+        let emulator = Emulator::new(&cartridge);
+
+        // LDA #$80; STA $2000; JMP $0005
+        load_program(&emulator, &[0xA9, 0x80, 0x8D, 0x00, 0x20, 0x4C, 0x05, 0x00]);
+
+        emulator
+    }
+
+    fn deterministic_frame_hashes(seed: u64, frames: usize) -> Vec<u64> {
+        let cartridge = Cartridge::empty();
+        let mut emulator = Emulator::new_deterministic(&cartridge, seed);
+
+        // Copies the power-up value of $0200 into the backdrop color every frame
+        load_program(&emulator, &[
+            0xA9, 0x80,       // LDA #$80
+            0x8D, 0x00, 0x20, // STA $2000
+            0xA9, 0x3F,       // LDA #$3F
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x06, 0x20, // STA $2006
+            0xAD, 0x00, 0x02, // LDA $0200
+            0x29, 0x3F,       // AND #$3F
+            0x8D, 0x07, 0x20, // STA $2007
+            0x4C, 0x05, 0x00, // JMP $0005
+        ]);
+
+        (0..frames)
+            .map(|_| {
+                emulator.run_frame();
+                emulator.frame().hash()
+            })
+            .collect()
+    }
+
+    fn header_region(flag7: u8, flag9: u8, flag12: u8) -> Region {
+        let mut rom = b"NES\x1A\x02\x01\x00".to_vec();
+        rom.extend([flag7, 0x00, flag9, 0x00, 0x00, flag12, 0x00, 0x00, 0x00]);
+        rom.resize(16 + 2 * 0x4000 + 0x2000, 0x00);
+
+        let emulator = Emulator::new(&Cartridge::new(rom));
+        let region = emulator.ppu().borrow().get_region();
+        region
+    }
+
+    #[test]
+    fn test_region_comes_from_header() {
+        assert_eq!(header_region(0x00, 0x00, 0x00), Region::Ntsc, "iNES carts should default to NTSC!");
+        assert_eq!(header_region(0x00, 0x01, 0x00), Region::Pal, "iNES byte 9 should select PAL!");
+        assert_eq!(header_region(0x08, 0x00, 0x01), Region::Pal, "NES 2.0 byte 12 should select PAL!");
+        assert_eq!(header_region(0x08, 0x01, 0x02), Region::Ntsc, "Multi-region NES 2.0 carts should run as NTSC!");
+    }
+
+    #[test]
+    fn test_drive_frame_flows_through_sinks() {
+        let mut emulator = emulator_with_nmi_loop();
+        let mut video = RecordingVideoSink { frames: Vec::new() };
+        let mut audio = RecordingAudioSink { pushes: 0 };
+        let mut input = ScriptedInputSource { polls: 0 };
+
+        for _ in 0..3 {
+            emulator.drive_frame(&mut video, &mut audio, &mut input);
+        }
+
+        assert_eq!(video.frames.len(), 3, "Video sink should receive one frame per driven frame!");
+        assert_eq!(video.frames[0].width(), 256, "Frame width should be 256!");
+        assert_eq!(video.frames[0].height(), 240, "Frame height should be 240!");
+        assert_eq!(audio.pushes, 3, "Audio sink should be pushed once per driven frame!");
+        assert_eq!(input.polls, 3, "Input source should be polled once per driven frame!");
+
+        let buttons = emulator.bus().borrow_mut().joypad(0).get_buttons();
+        assert_eq!(buttons, JoypadButton::Start as u8, "Polled input should reach joypad 1!");
+    }
+
+    #[test]
+    fn test_null_frontend_runs_headless() {
+        let mut emulator = emulator_with_nmi_loop();
+
+        emulator.drive_frame(&mut NullVideoSink, &mut NullAudioSink, &mut NullInputSource);
+
+        let buttons = emulator.bus().borrow_mut().joypad(0).get_buttons();
+        assert_eq!(buttons, 0x00, "Null input source should release every button!");
+    }
+
+    fn frame_cycles_and_hash(extra_vblank_scanlines: usize) -> (usize, u64) {
+        let cartridge = Cartridge::empty();
+        let mut emulator = Emulator::new(&cartridge);
+        emulator.set_extra_vblank_scanlines(extra_vblank_scanlines);
+
+        // Static scene with a colored backdrop
+        load_program(&emulator, &[
+            0xA9, 0x3F,       // LDA #$3F
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA9, 0x21,       // LDA #$21
+            0x8D, 0x07, 0x20, // STA $2007
+            0xA9, 0x80,       // LDA #$80
+            0x8D, 0x00, 0x20, // STA $2000
+            0x4C, 0x14, 0x00, // JMP $0014
+        ]);
+
+        emulator.run_frame();
+        emulator.run_frame();
+        let start = emulator.clock().borrow().get_cycles();
+        emulator.run_frame();
+        let end = emulator.clock().borrow().get_cycles();
+
+        (end - start, emulator.frame().hash())
+    }
+
+    #[test]
+    fn test_extra_vblank_scanlines_add_cpu_cycles() {
+        let (cycles, hash) = frame_cycles_and_hash(0);
+        let (overclocked_cycles, overclocked_hash) = frame_cycles_and_hash(20);
+        let extra_cycles = overclocked_cycles - cycles;
+
+        // Frames end mid-instruction, so each boundary can land up to one NMI entry late
+        assert!(extra_cycles.abs_diff(20 * 341 / 3) <= 7, "Frame should run 20 scanlines worth of extra CPU cycles!");
+        assert_eq!(overclocked_hash, hash, "Extra scanlines should not change the visible frame!");
+    }
+
+    fn hotkey_input(hotkey: Option<Hotkey>) -> InputState {
+        let mut input = InputState::default();
+
+        if let Some(hotkey) = hotkey {
+            input.set_hotkey(hotkey, true);
+        }
+
+        input
+    }
+
+    fn ppu_frame(emulator: &Emulator) -> u64 {
+        emulator.ppu().borrow().get_frame()
+    }
+
+    #[test]
+    fn test_pause_stops_frames() {
+        let mut emulator = emulator_with_nmi_loop();
+        emulator.advance();
+
+        emulator.set_input(hotkey_input(Some(Hotkey::Pause)));
+        emulator.set_input(hotkey_input(None));
+        let frame = ppu_frame(&emulator);
+
+        for _ in 0..5 {
+            assert!(!emulator.advance(), "Paused emulator should not run frames!");
+        }
+        assert_eq!(ppu_frame(&emulator), frame, "Paused emulator should keep the same frame!");
+
+        emulator.set_input(hotkey_input(Some(Hotkey::Pause)));
+        assert!(emulator.advance(), "Unpaused emulator should run frames!");
+    }
+
+    #[test]
+    fn test_breakpoint_pauses_emulator() {
+        let mut emulator = emulator_with_nmi_loop();
+        emulator.cpu().add_breakpoint(0x0005);
+
+        assert!(!emulator.advance(), "Frame stopped at a breakpoint should not count as run!");
+        assert!(emulator.is_paused(), "Breakpoint should pause the emulator!");
+        assert_eq!(emulator.cpu().state().pc, 0x0005, "CPU should stop at the breakpoint!");
+        assert!(!emulator.advance(), "Emulator paused by a breakpoint should not run frames!");
+
+        emulator.cpu().remove_breakpoint(0x0005);
+        emulator.set_input(hotkey_input(Some(Hotkey::Pause)));
+        emulator.advance();
+        assert!(!emulator.is_paused(), "Unpausing should resume past the breakpoint!");
+    }
+
+    #[test]
+    fn test_breakpoint_skips_run_ahead() {
+        let mut emulator = emulator_with_nmi_loop();
+        emulator.set_run_ahead(1);
+        assert!(emulator.advance(), "Whole frame should count as run!");
+
+        emulator.cpu().add_breakpoint(0x0005);
+        assert!(!emulator.advance(), "Frame stopped at a breakpoint should not count as run!");
+        assert_eq!(emulator.displayed_frame(), emulator.frame(), "Stopped frame should be shown without running ahead!");
+    }
+
+    #[test]
+    fn test_sprite_zero_debug_hotkey_toggles_mask() {
+        let mut emulator = emulator_with_nmi_loop();
+        emulator.advance();
+        assert!(emulator.frame().debug_mask().is_none(), "Debug mask should start disabled!");
+
+        emulator.set_input(hotkey_input(Some(Hotkey::SpriteZeroDebug)));
+        emulator.advance();
+        assert!(emulator.frame().debug_mask().is_some(), "Hotkey should attach the debug mask to frames!");
+
+        emulator.set_input(hotkey_input(None));
+        emulator.set_input(hotkey_input(Some(Hotkey::SpriteZeroDebug)));
+        assert!(emulator.frame().debug_mask().is_none(), "Pressing the hotkey again should disable the debug mask!");
+    }
+
+    #[test]
+    fn test_waveform_view_hotkey_toggles_taps() {
+        let mut emulator = emulator_with_nmi_loop();
+        let has_waveforms = |emulator: &Emulator| emulator.bus().borrow_mut().apu().waveforms().is_some();
+        assert!(!has_waveforms(&emulator), "Waveform taps should start disabled!");
+
+        emulator.set_input(hotkey_input(Some(Hotkey::WaveformView)));
+        assert!(has_waveforms(&emulator), "Hotkey should enable the waveform taps!");
+
+        emulator.set_input(hotkey_input(None));
+        emulator.set_input(hotkey_input(Some(Hotkey::WaveformView)));
+        assert!(!has_waveforms(&emulator), "Pressing the hotkey again should disable the waveform taps!");
+    }
+
+    #[test]
+    fn test_frame_advance_runs_single_frames() {
+        let mut emulator = emulator_with_nmi_loop();
+        emulator.advance();
+        emulator.set_input(hotkey_input(Some(Hotkey::Pause)));
+        let frame = ppu_frame(&emulator);
+
+        for _ in 0..3 {
+            emulator.set_input(hotkey_input(Some(Hotkey::FrameAdvance)));
+            emulator.advance();
+            emulator.set_input(hotkey_input(None));
+            emulator.advance();
+        }
+
+        assert_eq!(ppu_frame(&emulator) - frame, 3, "3 frame advances should run exactly 3 frames!");
+        assert!(emulator.is_paused(), "Emulator should stay paused after frame advances!");
+    }
+
+    #[test]
+    fn test_held_frame_advance_repeats() {
+        let mut emulator = emulator_with_nmi_loop();
+        emulator.advance();
+        emulator.set_input(hotkey_input(Some(Hotkey::Pause)));
+        let frame = ppu_frame(&emulator);
+
+        let polls = FRAME_ADVANCE_REPEAT_DELAY + 2 * FRAME_ADVANCE_REPEAT_INTERVAL;
+        let advanced = (0..polls)
+            .filter(|_| {
+                emulator.set_input(hotkey_input(Some(Hotkey::FrameAdvance)));
+                emulator.advance()
+            })
+            .count();
+
+        assert_eq!(advanced, 3, "Held frame advance should repeat after the delay!");
+        assert_eq!(ppu_frame(&emulator) - frame, 3, "Every repeat should run one frame!");
+    }
+
+    // Displayed frame hashes with A pressed from `press_frame` on, the NMI handler shows
+    // the backdrop color it computed from joypad 1 on the previous frame
+    fn lagging_input_displayed_hashes(run_ahead: usize, press_frame: usize, frames: usize) -> Vec<u64> {
+        let cartridge = Cartridge::empty();
+        let mut emulator = Emulator::new(&cartridge);
+        emulator.set_run_ahead(run_ahead);
+
+        load_program(&emulator, &[
+            0xA5, 0xF0,       // LDA $F0
+            0xA2, 0x3F,       // LDX #$3F
+            0x8E, 0x06, 0x20, // STX $2006
+            0xA2, 0x00,       // LDX #$00
+            0x8E, 0x06, 0x20, // STX $2006
+            0x8D, 0x07, 0x20, // STA $2007
+            0x8E, 0x06, 0x20, // STX $2006
+            0x8E, 0x06, 0x20, // STX $2006
+            0xA9, 0x01,       // LDA #$01
+            0x8D, 0x16, 0x40, // STA $4016
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x16, 0x40, // STA $4016
+            0xAD, 0x16, 0x40, // LDA $4016
+            0x29, 0x01,       // AND #$01
+            0x0A, 0x0A, 0x0A, // ASL; ASL; ASL
+            0x0A, 0x0A,       // ASL; ASL
+            0x09, 0x01,       // ORA #$01
+            0x85, 0xF0,       // STA $F0
+            0xA9, 0x80,       // LDA #$80
+            0x8D, 0x00, 0x20, // STA $2000
+            0x4C, 0x32, 0x00, // JMP $0032
+        ]);
+
+        (0..frames)
+            .map(|frame| {
+                let mut input = InputState::default();
+                input.set_button(0, JoypadButton::A, frame >= press_frame);
+                emulator.set_input(input);
+                emulator.advance();
+
+                emulator.displayed_frame().hash()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_snapshot_restores_state() {
+        let cartridge = Cartridge::new(include_bytes!("../../tests/roms/start_game.nes").to_vec());
+        let mut emulator = Emulator::new(&cartridge);
+        let mut input = InputState::default();
+        input.set_button(0, JoypadButton::Start, true);
+
+        (0..5).for_each(|_| { emulator.run_frame(); });
+        let snapshot = emulator.save_snapshot();
+        let title_hash = emulator.frame().hash();
+
+        emulator.set_input(input);
+        (0..5).for_each(|_| { emulator.run_frame(); });
+        let game_hash = emulator.frame().hash();
+        assert_ne!(game_hash, title_hash, "Pressing Start should leave the title screen!");
+
+        emulator.load_snapshot(&snapshot);
+        assert_eq!(emulator.frame().hash(), title_hash, "Snapshot should restore the title screen!");
+
+        // Joypad state is part of the snapshot, so the input has to be applied again
+        emulator.set_input(input);
+        (0..5).for_each(|_| { emulator.run_frame(); });
+        assert_eq!(emulator.frame().hash(), game_hash, "Replaying from a snapshot should render the same frames!");
+    }
+
+    #[test]
+    fn test_ram_init_pattern_reaches_the_cpu() {
+        let cartridge = Cartridge::empty();
+        let mut emulator = Emulator::with_ram_init(&cartridge, RamInitPattern::AllOnes);
+        assert_eq!(emulator.cpu().read(0x0000), 0xFF, "Emulator should power up with the given RAM!");
+
+        let config = Config {
+            ram_init: Some(RamInitPattern::Pattern00FF),
+            ..Default::default()
+        };
+        emulator.apply_config(&config);
+        assert_eq!((emulator.cpu().read(0x0003), emulator.cpu().read(0x0004)), (0x00, 0xFF), "Config should reinitialize the RAM!");
+    }
+
+    #[test]
+    fn test_snapshot_restores_ram_seen_by_cpu() {
+        let cartridge = Cartridge::empty();
+        let mut emulator = Emulator::new(&cartridge);
+
+        emulator.bus().borrow_mut().write_ram(0x0010, 0x42);
+        let snapshot = emulator.save_snapshot();
+
+        emulator.cpu().write(0x0810, 0x99);
+        assert_eq!(emulator.bus().borrow().read_ram(0x0010), 0x99, "CPU and bus should share the RAM!");
+
+        emulator.load_snapshot(&snapshot);
+        assert_eq!(emulator.cpu().read(0x0010), 0x42, "Snapshot should restore the RAM the CPU reads!");
+    }
+
+    #[test]
+    fn test_run_ahead_shows_input_one_frame_earlier() {
+        let first_changed = |hashes: &[u64]| 5 + hashes[5..].iter().position(|hash| *hash != hashes[5]).unwrap();
+
+        let normal = lagging_input_displayed_hashes(0, 10, 20);
+        let run_ahead = lagging_input_displayed_hashes(1, 10, 20);
+
+        assert_eq!(first_changed(&run_ahead) + 1, first_changed(&normal), "Run-ahead should show the press one frame earlier!");
+        assert_eq!(run_ahead[..19], normal[1..], "Run-ahead should show the same frames, one frame earlier!");
+    }
+
+    #[test]
+    fn test_same_seed_renders_identical_frames() {
+        let first = deterministic_frame_hashes(0xC0FFEE, 300);
+        let second = deterministic_frame_hashes(0xC0FFEE, 300);
+
+        assert_eq!(first, second, "Emulators with the same seed should render identical frames!");
+    }
+
+    #[test]
+    fn test_different_seeds_diverge() {
+        let first = deterministic_frame_hashes(1, 300);
+        let second = deterministic_frame_hashes(2, 300);
+
+        assert!(first.iter().zip(second.iter()).any(|(a, b)| a != b), "Emulators with different seeds should diverge!");
+    }
+}
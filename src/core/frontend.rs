@@ -0,0 +1,159 @@
+use super::apu::Waveforms;
+use super::hash::fnv1a;
+use super::joypad::JoypadButton;
+use super::ppu::ScreenBuffer;
+
+/// Palette indices of one rendered frame, detached from the PPU.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    emphasis: Vec<u8>,
+    debug_mask: Option<Vec<u8>>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height],
+            emphasis: vec![0; width * height],
+            debug_mask: None,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    // PPUMASK emphasis bits of the pixel, red in bit 0, green in bit 1 and blue in bit 2
+    pub fn get_emphasis(&self, x: usize, y: usize) -> u8 {
+        self.emphasis[y * self.width + x]
+    }
+
+    // Attaches the PPU's per-pixel `DebugMaskFlags`, it isn't part of the hash
+    pub fn with_debug_mask(mut self, debug_mask: &[u8]) -> Self {
+        self.debug_mask = Some(debug_mask.to_vec());
+        self
+    }
+
+    pub fn debug_mask(&self) -> Option<&[u8]> {
+        self.debug_mask.as_deref()
+    }
+
+    // Frames without emphasis keep the hash of their palette indices
+    pub fn hash(&self) -> u64 {
+        if self.emphasis.iter().all(|emphasis| *emphasis == 0) {
+            fnv1a(&self.pixels)
+        } else {
+            fnv1a(&[self.pixels.as_slice(), self.emphasis.as_slice()].concat())
+        }
+    }
+}
+
+impl From<&ScreenBuffer> for Frame {
+    fn from(screen_buffer: &ScreenBuffer) -> Self {
+        Self {
+            width: screen_buffer.width(),
+            height: screen_buffer.height(),
+            pixels: screen_buffer.get().to_vec(),
+            emphasis: screen_buffer.get_emphasis().to_vec(),
+            debug_mask: None,
+        }
+    }
+}
+
+/// Emulator commands that aren't sent to the console through a joypad.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Hotkey {
+    SwitchDiskSide = 1 << 0,
+    Pause = 1 << 1,
+    FrameAdvance = 1 << 2,
+    SpriteZeroDebug = 1 << 3,
+    WaveformView = 1 << 4,
+}
+
+/// State of both joypads, one `JoypadButton` bitmask per port, and held `Hotkey`s.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct InputState {
+    pub joypads: [u8; 2],
+    pub hotkeys: u8,
+}
+
+impl InputState {
+    pub fn set_button(&mut self, joypad: usize, button: JoypadButton, pressed: bool) {
+        if pressed {
+            self.joypads[joypad] |= button as u8;
+        } else {
+            self.joypads[joypad] &= !(button as u8);
+        }
+    }
+
+    pub fn is_pressed(&self, joypad: usize, button: JoypadButton) -> bool {
+        self.joypads[joypad] & button as u8 != 0
+    }
+
+    pub fn set_hotkey(&mut self, hotkey: Hotkey, pressed: bool) {
+        if pressed {
+            self.hotkeys |= hotkey as u8;
+        } else {
+            self.hotkeys &= !(hotkey as u8);
+        }
+    }
+
+    pub fn is_hotkey_pressed(&self, hotkey: Hotkey) -> bool {
+        self.hotkeys & hotkey as u8 != 0
+    }
+}
+
+pub trait VideoSink {
+    fn present(&mut self, frame: &Frame);
+
+    // Called before `present` with the input that was applied to the frame
+    fn show_input(&mut self, _input: &InputState) {}
+
+    // Called before `present` with the APU traces, `None` while the waveform view is closed
+    fn show_waveforms(&mut self, _waveforms: Option<&Waveforms>) {}
+}
+
+pub trait AudioSink {
+    fn push(&mut self, samples: &[f32]);
+}
+
+pub trait InputSource {
+    fn poll(&mut self) -> InputState;
+}
+
+/// Sinks and sources that do nothing, for headless runs.
+pub struct NullVideoSink;
+pub struct NullAudioSink;
+pub struct NullInputSource;
+
+impl VideoSink for NullVideoSink {
+    fn present(&mut self, _frame: &Frame) {}
+}
+
+impl AudioSink for NullAudioSink {
+    fn push(&mut self, _samples: &[f32]) {}
+}
+
+impl InputSource for NullInputSource {
+    fn poll(&mut self) -> InputState {
+        InputState::default()
+    }
+}
@@ -0,0 +1,93 @@
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub enum JoypadButton {
+    A = 1 << 0,
+    B = 1 << 1,
+    Select = 1 << 2,
+    Start = 1 << 3,
+    Up = 1 << 4,
+    Down = 1 << 5,
+    Left = 1 << 6,
+    Right = 1 << 7,
+}
+
+#[derive(Clone)]
+pub struct Joypad {
+    strobe: bool,
+    index: u8,
+    buttons: u8,
+}
+
+impl Joypad {
+    pub fn new() -> Self {
+        Self {
+            strobe: false,
+            index: 0,
+            buttons: 0x00,
+        }
+    }
+
+    pub fn set_buttons(&mut self, buttons: u8) {
+        self.buttons = buttons;
+    }
+
+    pub fn get_buttons(&self) -> u8 {
+        self.buttons
+    }
+
+    pub fn write(&mut self, data: u8) {
+        self.strobe = data & 0x1 == 0x1;
+
+        if self.strobe {
+            self.index = 0;
+        }
+    }
+
+    pub fn read(&mut self) -> u8 {
+        // After all 8 buttons were shifted out, official joypads return 1
+        if self.index > 7 {
+            return 0x01;
+        }
+
+        let result = (self.buttons >> self.index) & 0x1;
+
+        if !self.strobe {
+            self.index += 1;
+        }
+
+        result
+    }
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_joypad_shifts_buttons_in_order() {
+        let mut joypad = Joypad::new();
+        joypad.set_buttons(JoypadButton::A as u8 | JoypadButton::Start as u8);
+        joypad.write(0x01);
+        joypad.write(0x00);
+
+        let bits = (0..8).map(|_| joypad.read()).collect::<Vec<_>>();
+        assert_eq!(bits, vec![1, 0, 0, 1, 0, 0, 0, 0], "Buttons should be reported as A, B, Select, Start, Up, Down, Left, Right!");
+        assert_eq!(joypad.read(), 0x01, "Reads after the 8th button should return 1!");
+    }
+
+    #[test]
+    fn test_joypad_strobe_keeps_reporting_a() {
+        let mut joypad = Joypad::new();
+        joypad.set_buttons(JoypadButton::A as u8);
+        joypad.write(0x01);
+
+        assert_eq!(joypad.read(), 0x01, "Strobe high should report button A!");
+        assert_eq!(joypad.read(), 0x01, "Strobe high should keep reporting button A!");
+    }
+}
@@ -0,0 +1,532 @@
+use std::cell::Cell;
+
+use crate::core::memory::Memory;
+use crate::core::ppu::Mirroring;
+
+use super::Mapper;
+
+const HEADER_SIZE: usize = 16;
+const SIDE_SIZE: usize = 65500;
+const BIOS_SIZE: usize = 0x2000;
+
+// Gaps are stored in the disk stream as zero bytes, 28300 bits before the first block
+// and 976 bits between blocks
+const LEADING_GAP_SIZE: usize = 28300 / 8;
+const BLOCK_GAP_SIZE: usize = 976 / 8;
+const BLOCK_START_MARK: u8 = 0x80;
+
+// Drive reads one byte every ~150 CPU cycles, spinning up the motor takes much longer
+const BYTE_CYCLES: usize = 150;
+const MOTOR_DELAY_CYCLES: usize = 50000;
+const DISK_SWAP_CYCLES: usize = 1_000_000;
+
+#[repr(u8)]
+enum DiskControlFlags {
+    MotorOn = 1 << 0,
+    TransferReset = 1 << 1,
+    ReadMode = 1 << 2,
+    HorizontalMirroring = 1 << 3,
+    CrcControl = 1 << 4,
+    TransferStart = 1 << 6,
+    TransferIrq = 1 << 7,
+}
+
+#[repr(u8)]
+enum DiskStatusFlags {
+    TimerIrq = 1 << 0,
+    ByteTransferred = 1 << 1,
+    EndOfHead = 1 << 6,
+}
+
+// Converts an .fds image (with or without fwNES header) into disk sides the drive can stream,
+// adding the gaps, block start marks and CRC bytes that the format strips away
+pub fn parse_disk_sides(image: &[u8]) -> Vec<Vec<u8>> {
+    let data = if image.starts_with(b"FDS\x1A") {
+        image.get(HEADER_SIZE..).expect("Unable to parse FDS header!")
+    } else {
+        image
+    };
+
+    let sides = data
+        .chunks(SIDE_SIZE)
+        .filter(|side| side.first() == Some(&0x01))
+        .map(stream_disk_side)
+        .collect::<Vec<_>>();
+
+    if sides.is_empty() {
+        panic!("This image has no FDS disk sides!");
+    }
+
+    sides
+}
+
+fn stream_disk_side(side: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x00; LEADING_GAP_SIZE];
+    let mut position = 0;
+    let mut file_size = 0;
+
+    while let Some(&block_type) = side.get(position) {
+        let block_size = match block_type {
+            0x01 => 56,
+            0x02 => 2,
+            0x03 => 16,
+            0x04 => 1 + file_size,
+            _ => break,
+        };
+
+        let block = side
+            .get(position..position + block_size)
+            .expect("Unable to read FDS block, image is truncated!");
+
+        if block_type == 0x03 {
+            file_size = u16::from_le_bytes([block[13], block[14]]) as usize;
+        }
+
+        stream.push(BLOCK_START_MARK);
+        stream.extend_from_slice(block);
+        // BIOS never gets a CRC error, so a placeholder is enough
+        stream.extend_from_slice(&[0x4D, 0x62]);
+        stream.extend(std::iter::repeat_n(0x00, BLOCK_GAP_SIZE));
+
+        position += block_size;
+    }
+
+    stream.resize(stream.len().max(SIDE_SIZE), 0x00);
+    stream
+}
+
+// Famicom Disk System RAM adapter and disk drive
+#[derive(Clone)]
+pub struct MapperFds {
+    bios: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr_ram: Vec<u8>,
+    disk_sides: Vec<Vec<u8>>,
+    disk_side: Option<usize>,
+    next_disk_side: usize,
+    swap_delay: usize,
+    disk_registers_enabled: bool,
+    timer_reload: u16,
+    timer_counter: u16,
+    timer_enabled: bool,
+    timer_repeat: bool,
+    timer_irq: Cell<bool>,
+    control: u8,
+    disk_irq: Cell<bool>,
+    byte_transferred: Cell<bool>,
+    read_data: u8,
+    write_data: u8,
+    position: usize,
+    delay: usize,
+    scanning: bool,
+    end_of_head: bool,
+    gap_ended: bool,
+}
+
+impl MapperFds {
+    pub fn new(bios: Vec<u8>, image: &[u8]) -> Self {
+        if bios.len() != BIOS_SIZE {
+            panic!("FDS BIOS should be exactly 8KB!");
+        }
+
+        Self {
+            bios,
+            prg_ram: vec![0; 0x8000],
+            chr_ram: vec![0; 0x2000],
+            disk_sides: parse_disk_sides(image),
+            disk_side: Some(0),
+            next_disk_side: 0,
+            swap_delay: 0,
+            disk_registers_enabled: false,
+            timer_reload: 0,
+            timer_counter: 0,
+            timer_enabled: false,
+            timer_repeat: false,
+            timer_irq: Cell::new(false),
+            control: 0x00,
+            disk_irq: Cell::new(false),
+            byte_transferred: Cell::new(false),
+            read_data: 0x00,
+            write_data: 0x00,
+            position: 0,
+            delay: 0,
+            scanning: false,
+            end_of_head: true,
+            gap_ended: false,
+        }
+    }
+
+    fn is_control_set(&self, flag: DiskControlFlags) -> bool {
+        self.control & flag as u8 != 0
+    }
+
+    fn read_status(&self) -> u8 {
+        let status = [
+            (self.timer_irq.get(), DiskStatusFlags::TimerIrq),
+            (self.byte_transferred.get(), DiskStatusFlags::ByteTransferred),
+            (self.end_of_head, DiskStatusFlags::EndOfHead),
+        ]
+            .into_iter()
+            .filter(|(is_set, _)| *is_set)
+            .fold(0x00, |status, (_, flag)| status | flag as u8);
+
+        self.timer_irq.set(false);
+        self.byte_transferred.set(false);
+        self.disk_irq.set(false);
+
+        status
+    }
+
+    fn read_data(&self) -> u8 {
+        self.byte_transferred.set(false);
+        self.disk_irq.set(false);
+
+        self.read_data
+    }
+
+    fn read_drive_status(&self) -> u8 {
+        let ejected = self.disk_side.is_none();
+        let not_ready = ejected || !self.scanning;
+
+        // Bit 2 reports the disk as write protected when nothing is inserted
+        0x40 | ejected as u8 | (not_ready as u8) << 1 | (ejected as u8) << 2
+    }
+
+    fn tick_timer(&mut self) {
+        if !self.timer_enabled {
+            return;
+        }
+
+        if self.timer_counter == 0 {
+            self.timer_irq.set(true);
+            self.timer_counter = self.timer_reload;
+            self.timer_enabled = self.timer_repeat;
+        } else {
+            self.timer_counter -= 1;
+        }
+    }
+
+    fn tick_swap(&mut self) {
+        if self.swap_delay == 0 {
+            return;
+        }
+
+        self.swap_delay -= 1;
+
+        if self.swap_delay == 0 {
+            self.disk_side = Some(self.next_disk_side);
+        }
+    }
+
+    fn tick_drive(&mut self) {
+        let Some(side) = self.disk_side else {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        };
+
+        if !self.is_control_set(DiskControlFlags::MotorOn) {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        }
+
+        if self.is_control_set(DiskControlFlags::TransferReset) && !self.scanning {
+            return;
+        }
+
+        if self.end_of_head {
+            self.delay = MOTOR_DELAY_CYCLES;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        self.scanning = true;
+        let irq_enabled = self.is_control_set(DiskControlFlags::TransferIrq);
+        let transfer_started = self.is_control_set(DiskControlFlags::TransferStart);
+
+        if self.is_control_set(DiskControlFlags::ReadMode) {
+            let data = self.disk_sides[side][self.position];
+            let mut needs_irq = irq_enabled;
+
+            if !transfer_started {
+                self.gap_ended = false;
+            } else if data != 0x00 && !self.gap_ended {
+                // Start mark ends the gap but does not raise an IRQ on its own
+                self.gap_ended = true;
+                needs_irq = false;
+            }
+
+            if self.gap_ended {
+                self.byte_transferred.set(true);
+                self.read_data = data;
+
+                if needs_irq {
+                    self.disk_irq.set(true);
+                }
+            }
+        } else {
+            let mut data = 0x00;
+
+            if !self.is_control_set(DiskControlFlags::CrcControl) {
+                self.byte_transferred.set(true);
+                data = self.write_data;
+
+                if irq_enabled {
+                    self.disk_irq.set(true);
+                }
+            }
+
+            if !transfer_started {
+                data = 0x00;
+            }
+
+            self.disk_sides[side][self.position] = data;
+            self.gap_ended = false;
+        }
+
+        self.position += 1;
+
+        if self.position >= self.disk_sides[side].len() {
+            self.control &= !(DiskControlFlags::MotorOn as u8);
+        } else {
+            self.delay = BYTE_CYCLES;
+        }
+    }
+}
+
+impl Memory for MapperFds {
+    fn read(&self, address: u16) -> u8 {
+        match address {
+            0x4030 if self.disk_registers_enabled => self.read_status(),
+            0x4031 if self.disk_registers_enabled => self.read_data(),
+            0x4032 if self.disk_registers_enabled => self.read_drive_status(),
+            // Battery is always good
+            0x4033 if self.disk_registers_enabled => 0x80,
+            0x4020..=0x5FFF => 0x00,
+            0x6000..=0xDFFF => self.prg_ram[address as usize - 0x6000],
+            0xE000..=0xFFFF => self.bios[address as usize - 0xE000],
+            _ => panic!("Invalid address for reading FDS RAM adapter!"),
+        }
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        match address {
+            0x4020 => self.timer_reload = (self.timer_reload & 0xFF00) | data as u16,
+            0x4021 => self.timer_reload = (self.timer_reload & 0x00FF) | (data as u16) << 8,
+            0x4022 if self.disk_registers_enabled => {
+                self.timer_repeat = data & 0x1 == 0x1;
+                self.timer_enabled = data & 0x2 == 0x2;
+
+                if self.timer_enabled {
+                    self.timer_counter = self.timer_reload;
+                } else {
+                    self.timer_irq.set(false);
+                }
+            },
+            0x4023 => {
+                self.disk_registers_enabled = data & 0x1 == 0x1;
+
+                if !self.disk_registers_enabled {
+                    self.timer_enabled = false;
+                    self.timer_irq.set(false);
+                    self.disk_irq.set(false);
+                }
+            },
+            0x4024 if self.disk_registers_enabled => {
+                self.write_data = data;
+                self.byte_transferred.set(false);
+                self.disk_irq.set(false);
+            },
+            0x4025 if self.disk_registers_enabled => {
+                self.control = data;
+                self.disk_irq.set(false);
+            },
+            0x6000..=0xDFFF => {
+                self.prg_ram[address as usize - 0x6000] = data;
+            },
+            _ => (),
+        }
+    }
+}
+
+impl Mapper for MapperFds {
+    fn get_chr_rom(&mut self) -> &mut Vec<u8> {
+        &mut self.chr_ram
+    }
+
+    fn tick(&mut self, cycles: usize) {
+        for _ in 0..cycles {
+            self.tick_timer();
+            self.tick_swap();
+            self.tick_drive();
+        }
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.timer_irq.get() || self.disk_irq.get()
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        if self.is_control_set(DiskControlFlags::HorizontalMirroring) {
+            Some(Mirroring::Horizontal)
+        } else {
+            Some(Mirroring::Vertical)
+        }
+    }
+
+    // Ejects the disk and inserts the next side once the BIOS had time to notice
+    fn switch_disk_side(&mut self) {
+        let current = self.disk_side.unwrap_or(self.next_disk_side);
+
+        self.next_disk_side = (current + 1) % self.disk_sides.len();
+        self.disk_side = None;
+        self.swap_delay = DISK_SWAP_CYCLES;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_side(file: &[u8]) -> Vec<u8> {
+        let mut side = vec![0x01];
+        side.extend_from_slice(b"*NINTENDO-HVC*");
+        side.resize(56, 0x00);
+        side.extend_from_slice(&[0x02, 0x01]);
+
+        let mut file_header = vec![0x03, 0x00, 0x00];
+        file_header.extend_from_slice(b"FILE0000");
+        file_header.extend_from_slice(&[0x00, 0x60]);
+        file_header.extend_from_slice(&(file.len() as u16).to_le_bytes());
+        file_header.push(0x00);
+        side.extend_from_slice(&file_header);
+
+        side.push(0x04);
+        side.extend_from_slice(file);
+        side.resize(SIDE_SIZE, 0x00);
+        side
+    }
+
+    fn create_image(sides: usize) -> Vec<u8> {
+        let mut image = b"FDS\x1A".to_vec();
+        image.push(sides as u8);
+        image.resize(HEADER_SIZE, 0x00);
+
+        for side in 0..sides {
+            image.extend(create_side(&[side as u8; 4]));
+        }
+
+        image
+    }
+
+    fn create_mapper(sides: usize) -> MapperFds {
+        let mut bios = vec![0x00; BIOS_SIZE];
+        bios[0x1FFC] = 0x24;
+        bios[0x1FFD] = 0xEE;
+
+        MapperFds::new(bios, &create_image(sides))
+    }
+
+    #[test]
+    fn test_fds_parses_sides_with_and_without_header() {
+        let image = create_image(2);
+
+        assert_eq!(parse_disk_sides(&image).len(), 2, "Header image should have 2 sides!");
+        assert_eq!(parse_disk_sides(&image[HEADER_SIZE..]).len(), 2, "Headerless image should have 2 sides!");
+    }
+
+    #[test]
+    fn test_fds_stream_has_gaps_and_start_marks() {
+        let side = &parse_disk_sides(&create_image(1))[0];
+
+        assert!(side[..LEADING_GAP_SIZE].iter().all(|byte| *byte == 0x00), "Stream should start with a gap!");
+        assert_eq!(side[LEADING_GAP_SIZE], BLOCK_START_MARK, "First block should have a start mark!");
+        assert_eq!(side[LEADING_GAP_SIZE + 1], 0x01, "First block should be the disk info block!");
+
+        let file_amount_block = LEADING_GAP_SIZE + 1 + 56 + 2 + BLOCK_GAP_SIZE;
+        assert_eq!(side[file_amount_block], BLOCK_START_MARK, "Second block should follow the gap!");
+        assert_eq!(side[file_amount_block + 1], 0x02, "Second block should be the file amount block!");
+    }
+
+    #[test]
+    fn test_fds_ram_adapter_memory() {
+        let mut mapper = create_mapper(1);
+        mapper.write(0x6000, 0x12);
+        mapper.write(0xDFFF, 0x34);
+        mapper.write(0xFFFC, 0x56);
+
+        assert_eq!(mapper.read(0x6000), 0x12, "PRG-RAM should start at $6000!");
+        assert_eq!(mapper.read(0xDFFF), 0x34, "PRG-RAM should end at $DFFF!");
+        assert_eq!(mapper.read(0xFFFC), 0x24, "BIOS should not be writable!");
+        assert_eq!(mapper.get_chr_rom().len(), 0x2000, "CHR-RAM should be 8KB!");
+    }
+
+    #[test]
+    fn test_fds_timer_irq() {
+        let mut mapper = create_mapper(1);
+        mapper.write(0x4023, 0x01);
+        mapper.write(0x4020, 10);
+        mapper.write(0x4021, 0);
+        mapper.write(0x4022, 0x02);
+
+        mapper.tick(10);
+        assert!(!mapper.irq_pending(), "Timer IRQ should not fire before the counter expires!");
+
+        mapper.tick(1);
+        assert!(mapper.irq_pending(), "Timer IRQ should fire when the counter expires!");
+        assert_eq!(mapper.read(0x4030) & 0x1, 0x1, "Disk status should report the timer IRQ!");
+        assert!(!mapper.irq_pending(), "Reading disk status should acknowledge the IRQ!");
+
+        mapper.tick(20);
+        assert!(!mapper.irq_pending(), "Timer without repeat should fire only once!");
+    }
+
+    #[test]
+    fn test_fds_reads_disk_blocks() {
+        let mut mapper = create_mapper(1);
+        mapper.write(0x4023, 0x01);
+        // Motor on, read mode, transfer start, byte IRQ enabled
+        mapper.write(0x4025, 0xE5);
+
+        let mut transferred = vec![];
+        let mut irqs = 0;
+
+        for _ in 0..(MOTOR_DELAY_CYCLES + (LEADING_GAP_SIZE + 4) * (BYTE_CYCLES + 1)) {
+            mapper.tick(1);
+            irqs += mapper.irq_pending() as usize;
+
+            if mapper.read(0x4030) & 0x2 == 0x2 {
+                transferred.push(mapper.read(0x4031));
+            }
+        }
+
+        assert_eq!(transferred[..3], [0x80, 0x01, b'*'], "Drive should stream the disk info block!");
+        assert_eq!(irqs, transferred.len() - 1, "Every byte but the start mark should raise an IRQ!");
+        assert_eq!(mapper.read(0x4032) & 0x3, 0x0, "Disk should be inserted and ready!");
+    }
+
+    #[test]
+    fn test_fds_switch_disk_side() {
+        let mut mapper = create_mapper(2);
+        mapper.write(0x4023, 0x01);
+        mapper.switch_disk_side();
+
+        assert_eq!(mapper.disk_side, None, "Disk should be ejected while switching sides!");
+        assert_eq!(mapper.read(0x4032) & 0x1, 0x1, "Drive should report no disk!");
+
+        mapper.tick(DISK_SWAP_CYCLES);
+        assert_eq!(mapper.disk_side, Some(1), "Second side should be inserted!");
+
+        mapper.switch_disk_side();
+        mapper.tick(DISK_SWAP_CYCLES);
+        assert_eq!(mapper.disk_side, Some(0), "Switching from the last side should wrap around!");
+    }
+}
@@ -2,9 +2,11 @@ use crate::core::memory::Memory;
 
 use super::Mapper;
 
+#[derive(Clone)]
 pub struct Mapper000 {
     prg_rom: Vec<u8>,
     chr_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
     is_32kb_size: bool,
 }
 
@@ -15,29 +17,39 @@ impl Mapper000 {
         Self {
             prg_rom,
             chr_rom,
+            prg_ram: vec![0; 0x2000],
             is_32kb_size,
         }
     }
+
+    fn prg_address(&self, address: u16) -> usize {
+        if !self.is_32kb_size {
+            (address as usize - 0x8000) & 0x3FFF
+        } else {
+            address as usize - 0x8000
+        }
+    }
 }
 
 impl Memory for Mapper000 {
     fn read(&self, address: u16) -> u8 {
         match address {
-            // Mapper 000 doesn't have RAM
-            0x4020..=0x7FFF => 0x00,
-            0x8000..=0xFFFF => {
-                if !self.is_32kb_size {
-                    self.prg_rom[(address as usize - 0x8000) & 0x3FFF]
-                } else {
-                    self.prg_rom[address as usize - 0x8000]
-                }
-            },
+            0x4020..=0x5FFF => 0x00,
+            // Family Basic boards have PRG-RAM here, test ROMs also report their results into it
+            0x6000..=0x7FFF => self.prg_ram[address as usize - 0x6000],
+            0x8000..=0xFFFF => self.prg_rom[self.prg_address(address)],
             _ => panic!("Invalid address for reading PRG-ROM!"),
         }
     }
 
-    fn write(&mut self, _address: u16, _data: u8) {
-        panic!("Attempt to write into PRG-ROM!");
+    fn write(&mut self, address: u16, data: u8) {
+        match address {
+            0x6000..=0x7FFF => {
+                self.prg_ram[address as usize - 0x6000] = data;
+            },
+            0x8000..=0xFFFF => panic!("Attempt to write into PRG-ROM!"),
+            _ => (),
+        }
     }
 }
 
@@ -45,4 +57,12 @@ impl Mapper for Mapper000 {
     fn get_chr_rom(&mut self) -> &mut Vec<u8> {
         &mut self.chr_rom
     }
+
+    fn prg_rom_address(&self, address: u16) -> Option<usize> {
+        (address >= 0x8000).then(|| self.prg_address(address))
+    }
+
+    fn prg_rom_len(&self) -> usize {
+        self.prg_rom.len()
+    }
 }
@@ -0,0 +1,334 @@
+use crate::core::memory::Memory;
+use crate::core::ppu::Mirroring;
+
+use super::Mapper;
+
+const PRG_BANK_SIZE: usize = 16 * 1024;
+const CHR_BANK_SIZE: usize = 4 * 1024;
+const PRG_RAM_BANK_SIZE: usize = 8 * 1024;
+
+// MMC1 can only bank 256KB of PRG-ROM, larger boards use the CHR bank lines instead
+const PRG_OUTER_BANK_SIZE: usize = 256 * 1024;
+
+// MMC1, including SUROM/SOROM/SXROM boards that use CHR bank bits
+// to select the 256KB PRG-ROM half and the 8KB PRG-RAM bank
+#[derive(Clone)]
+pub struct Mapper001 {
+    prg_rom: Vec<u8>,
+    chr_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    prg_ram_dirty: bool,
+    shift_register: u8,
+    shift_count: u8,
+    control: u8,
+    chr_bank0: u8,
+    chr_bank1: u8,
+    prg_bank: u8,
+}
+
+impl Mapper001 {
+    pub fn new(prg_rom: Vec<u8>, chr_rom: Vec<u8>, prg_ram_size: usize) -> Self {
+        Self {
+            prg_rom,
+            chr_rom,
+            prg_ram: vec![0; prg_ram_size.max(PRG_RAM_BANK_SIZE)],
+            prg_ram_dirty: false,
+            shift_register: 0x00,
+            shift_count: 0,
+            // Power-up state fixes the last PRG bank at $C000
+            control: 0x0C,
+            chr_bank0: 0,
+            chr_bank1: 0,
+            prg_bank: 0,
+        }
+    }
+
+    fn prg_bank_count(&self) -> usize {
+        (self.prg_rom.len() / PRG_BANK_SIZE).max(1)
+    }
+
+    // SUROM and SXROM select the 256KB half with bit 4 of the first CHR bank
+    fn prg_outer_bank(&self) -> usize {
+        if self.prg_rom.len() > PRG_OUTER_BANK_SIZE {
+            ((self.chr_bank0 >> 4) & 0x1) as usize
+        } else {
+            0
+        }
+    }
+
+    // SOROM selects 8KB of its 16KB with CHR bit 3, SXROM 8KB of 32KB with CHR bits 2-3
+    fn prg_ram_address(&self, address: u16) -> usize {
+        let bank = match self.prg_ram.len() / PRG_RAM_BANK_SIZE {
+            0 | 1 => 0,
+            2 => (self.chr_bank0 >> 3) & 0x1,
+            _ => (self.chr_bank0 >> 2) & 0x3,
+        } as usize;
+
+        (bank * PRG_RAM_BANK_SIZE + (address as usize - 0x6000)) % self.prg_ram.len()
+    }
+
+    fn prg_address(&self, address: u16) -> usize {
+        let offset = address as usize & 0x3FFF;
+        let prg_bank = (self.prg_bank & 0x0F) as usize;
+        let banks_per_half = PRG_OUTER_BANK_SIZE / PRG_BANK_SIZE;
+        let last_bank = self.prg_bank_count().min(banks_per_half) - 1;
+
+        let bank = match ((self.control >> 2) & 0b11, address) {
+            // 32KB mode ignores the lowest bit of bank number
+            (0 | 1, 0x8000..=0xBFFF) => prg_bank & !0x1,
+            (0 | 1, _) => prg_bank | 0x1,
+            (2, 0x8000..=0xBFFF) => 0,
+            (2, _) => prg_bank,
+            (_, 0x8000..=0xBFFF) => prg_bank,
+            (_, _) => last_bank,
+        };
+
+        let bank = self.prg_outer_bank() * banks_per_half + bank;
+
+        ((bank % self.prg_bank_count()) * PRG_BANK_SIZE + offset) % self.prg_rom.len()
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x8000..=0x9FFF => {
+                self.control = data;
+            },
+            0xA000..=0xBFFF => self.chr_bank0 = data,
+            0xC000..=0xDFFF => self.chr_bank1 = data,
+            _ => self.prg_bank = data & 0x0F,
+        }
+    }
+}
+
+impl Memory for Mapper001 {
+    fn read(&self, address: u16) -> u8 {
+        match address {
+            0x4020..=0x5FFF => 0x00,
+            0x6000..=0x7FFF => self.prg_ram[self.prg_ram_address(address)],
+            0x8000..=0xFFFF => self.prg_rom[self.prg_address(address)],
+            _ => panic!("Invalid address for reading PRG-ROM!"),
+        }
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        match address {
+            0x6000..=0x7FFF => {
+                let address = self.prg_ram_address(address);
+                self.prg_ram[address] = data;
+                self.prg_ram_dirty = true;
+            },
+            0x8000..=0xFFFF => {
+                if data & 0x80 == 0x80 {
+                    self.shift_register = 0x00;
+                    self.shift_count = 0;
+                    self.control |= 0x0C;
+                    return;
+                }
+
+                self.shift_register = (self.shift_register >> 1) | ((data & 0x1) << 4);
+                self.shift_count += 1;
+
+                if self.shift_count == 5 {
+                    self.write_register(address, self.shift_register);
+                    self.shift_register = 0x00;
+                    self.shift_count = 0;
+                }
+            },
+            _ => (),
+        }
+    }
+}
+
+impl Mapper for Mapper001 {
+    fn get_chr_rom(&mut self) -> &mut Vec<u8> {
+        &mut self.chr_rom
+    }
+
+    fn prg_rom_address(&self, address: u16) -> Option<usize> {
+        (address >= 0x8000).then(|| self.prg_address(address))
+    }
+
+    fn prg_rom_len(&self) -> usize {
+        self.prg_rom.len()
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(match self.control & 0b11 {
+            0 => Mirroring::SingleScreenLow,
+            1 => Mirroring::SingleScreenHigh,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        })
+    }
+
+    fn battery_ram(&self) -> Option<&[u8]> {
+        Some(&self.prg_ram)
+    }
+
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        let length = data.len().min(self.prg_ram.len());
+        self.prg_ram[..length].copy_from_slice(&data[..length]);
+    }
+
+    fn is_battery_ram_dirty(&self) -> bool {
+        self.prg_ram_dirty
+    }
+
+    fn clear_battery_ram_dirty(&mut self) {
+        self.prg_ram_dirty = false;
+    }
+
+    fn chr_address(&self, address: u16) -> usize {
+        let address = address as usize;
+        let is_4kb_mode = (self.control >> 4) & 0x1 == 0x1;
+
+        let (bank, offset) = match (is_4kb_mode, address) {
+            (false, _) => ((self.chr_bank0 & !0x1) as usize, address),
+            (true, 0x0000..=0x0FFF) => (self.chr_bank0 as usize, address),
+            (true, _) => (self.chr_bank1 as usize, address - CHR_BANK_SIZE),
+        };
+
+        (bank * CHR_BANK_SIZE + offset) % self.chr_rom.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_serial(mapper: &mut Mapper001, address: u16, value: u8) {
+        for bit in 0..5 {
+            mapper.write(address, (value >> bit) & 0x1);
+        }
+    }
+
+    fn create_mapper() -> Mapper001 {
+        let prg_rom = (0..8).flat_map(|bank| vec![bank as u8; PRG_BANK_SIZE]).collect();
+        let chr_rom = (0..4).flat_map(|bank| vec![bank as u8; CHR_BANK_SIZE]).collect();
+
+        Mapper001::new(prg_rom, chr_rom, 0x2000)
+    }
+
+    fn create_surom_mapper(prg_ram_size: usize) -> Mapper001 {
+        let prg_rom = (0..32).flat_map(|bank| vec![bank as u8; PRG_BANK_SIZE]).collect();
+
+        Mapper001::new(prg_rom, vec![0; 0x2000], prg_ram_size)
+    }
+
+    #[test]
+    fn test_mmc1_power_up_fixes_last_bank() {
+        let mut mapper = create_mapper();
+        write_serial(&mut mapper, 0xE000, 0x03);
+
+        assert_eq!(mapper.read(0x8000), 0x03, "$8000 should be switched to bank 3!");
+        assert_eq!(mapper.read(0xC000), 0x07, "$C000 should be fixed to last bank!");
+    }
+
+    #[test]
+    fn test_mmc1_32kb_prg_mode() {
+        let mut mapper = create_mapper();
+        write_serial(&mut mapper, 0x8000, 0x00);
+        write_serial(&mut mapper, 0xE000, 0x05);
+
+        assert_eq!(mapper.read(0x8000), 0x04, "32KB mode should ignore the lowest bank bit!");
+        assert_eq!(mapper.read(0xC000), 0x05, "32KB mode should map the next bank at $C000!");
+    }
+
+    #[test]
+    fn test_mmc1_reset_bit_restores_prg_mode() {
+        let mut mapper = create_mapper();
+        write_serial(&mut mapper, 0x8000, 0x00);
+        mapper.write(0x8000, 0x01);
+        mapper.write(0x8000, 0x80);
+        write_serial(&mut mapper, 0xE000, 0x02);
+
+        assert_eq!(mapper.read(0x8000), 0x02, "Reset should discard partial writes!");
+        assert_eq!(mapper.read(0xC000), 0x07, "Reset should fix last bank at $C000!");
+    }
+
+    #[test]
+    fn test_mmc1_chr_banks() {
+        let mut mapper = create_mapper();
+        write_serial(&mut mapper, 0x8000, 0x1C);
+        write_serial(&mut mapper, 0xA000, 0x02);
+        write_serial(&mut mapper, 0xC000, 0x01);
+
+        assert_eq!(mapper.chr_address(0x0000), 2 * CHR_BANK_SIZE, "4KB mode should map $0000 to CHR bank 0 register!");
+        assert_eq!(mapper.chr_address(0x1000), CHR_BANK_SIZE, "4KB mode should map $1000 to CHR bank 1 register!");
+
+        write_serial(&mut mapper, 0x8000, 0x0C);
+        write_serial(&mut mapper, 0xA000, 0x03);
+
+        assert_eq!(mapper.chr_address(0x1000), 3 * CHR_BANK_SIZE, "8KB mode should ignore the lowest bank bit!");
+    }
+
+    #[test]
+    fn test_mmc1_prg_ram() {
+        let mut mapper = create_mapper();
+        mapper.write(0x6000, 0xDE);
+        mapper.write(0x7FFF, 0xAD);
+
+        assert_eq!(mapper.read(0x6000), 0xDE, "PRG-RAM should keep written value!");
+        assert_eq!(mapper.read(0x7FFF), 0xAD, "PRG-RAM should keep written value!");
+    }
+
+    #[test]
+    fn test_mmc1_surom_selects_prg_half() {
+        let mut mapper = create_surom_mapper(0x2000);
+        write_serial(&mut mapper, 0xE000, 0x03);
+
+        assert_eq!(mapper.read(0x8000), 0x03, "Switchable bank should come from the first half!");
+        assert_eq!(mapper.read(0xC000), 0x0F, "Fixed bank should be the last bank of the first half!");
+
+        write_serial(&mut mapper, 0xA000, 0x10);
+
+        assert_eq!(mapper.read(0x8000), 0x13, "Switchable bank should come from the second half!");
+        assert_eq!(mapper.read(0xC000), 0x1F, "Fixed bank should be the last bank of the second half!");
+
+        write_serial(&mut mapper, 0x8000, 0x08);
+
+        assert_eq!(mapper.read(0x8000), 0x10, "Fixed first bank should be the first bank of the second half!");
+    }
+
+    #[test]
+    fn test_mmc1_sxrom_banks_prg_ram() {
+        let mut mapper = create_surom_mapper(0x8000);
+        mapper.write(0x6000, 0xAA);
+        write_serial(&mut mapper, 0xA000, 0x0C);
+        mapper.write(0x6000, 0xBB);
+
+        assert_eq!(mapper.read(0x6000), 0xBB, "PRG-RAM bank 3 should keep its own value!");
+        write_serial(&mut mapper, 0xA000, 0x00);
+        assert_eq!(mapper.read(0x6000), 0xAA, "PRG-RAM bank 0 should keep its own value!");
+
+        let battery_ram = mapper.battery_ram().unwrap();
+        assert_eq!(battery_ram.len(), 0x8000, "Battery save should cover every PRG-RAM bank!");
+        assert_eq!(battery_ram[3 * PRG_RAM_BANK_SIZE], 0xBB, "Battery save should include switched out banks!");
+    }
+
+    #[test]
+    fn test_mmc1_sorom_banks_prg_ram() {
+        let mut mapper = create_mapper();
+        mapper.prg_ram = vec![0; 0x4000];
+        write_serial(&mut mapper, 0xA000, 0x08);
+        mapper.write(0x6000, 0xCC);
+
+        assert_eq!(mapper.prg_ram[PRG_RAM_BANK_SIZE], 0xCC, "CHR bit 3 should select the second PRG-RAM bank!");
+        assert_eq!(mapper.read(0xC000), 0x07, "Boards up to 256KB should ignore the PRG half bit!");
+    }
+
+    #[test]
+    fn test_mmc1_control_selects_mirroring() {
+        let mut mapper = create_mapper();
+
+        for (control, mirroring) in [
+            (0x00, Mirroring::SingleScreenLow),
+            (0x01, Mirroring::SingleScreenHigh),
+            (0x02, Mirroring::Vertical),
+            (0x03, Mirroring::Horizontal),
+        ] {
+            write_serial(&mut mapper, 0x8000, control);
+            assert_eq!(mapper.mirroring(), Some(mirroring), "Control bits 0-1 should select the mirroring!");
+        }
+    }
+}
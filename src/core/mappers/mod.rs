@@ -1,8 +1,76 @@
 pub mod mapper000;
 pub use mapper000::*;
 
+pub mod mapper001;
+pub use mapper001::*;
+
+pub mod fds;
+pub use fds::*;
+
 use super::memory::Memory;
+use super::ppu::Mirroring;
+
+// Lets snapshots copy whole mappers without knowing which one is inserted
+pub trait MapperClone {
+    fn clone_mapper(&self) -> Box<dyn Mapper>;
+}
+
+impl<T: Mapper + Clone + 'static> MapperClone for T {
+    fn clone_mapper(&self) -> Box<dyn Mapper> {
+        Box::new(self.clone())
+    }
+}
 
-pub trait Mapper: Memory {
+pub trait Mapper: Memory + MapperClone {
     fn get_chr_rom(&mut self) -> &mut Vec<u8>;
+
+    // Translates PPU address into CHR-ROM index, mappers with CHR banking override it
+    fn chr_address(&self, address: u16) -> usize {
+        address as usize
+    }
+
+    // Translates CPU address into PRG-ROM index, None where no PRG-ROM is mapped
+    fn prg_rom_address(&self, _address: u16) -> Option<usize> {
+        None
+    }
+
+    fn prg_rom_len(&self) -> usize {
+        0
+    }
+
+    // Whether the CPU can fetch code from the address, the expansion area is open bus on most boards
+    fn is_prg_mapped(&self, address: u16) -> bool {
+        address >= 0x6000
+    }
+
+    // Called with the amount of CPU cycles that passed, for mappers with timers or drives
+    fn tick(&mut self, _cycles: usize) {}
+
+    // Mirroring picked through mapper registers, None keeps the one from the cartridge header
+    fn mirroring(&self) -> Option<Mirroring> {
+        None
+    }
+
+    // Called on rising edges of PPU address line A12, already filtered the way MMC3 does
+    fn a12_rising_edge(&mut self) {}
+
+    fn irq_pending(&self) -> bool {
+        false
+    }
+
+    fn switch_disk_side(&mut self) {}
+
+    // Whole PRG-RAM to keep in the save file, including banks that are switched out
+    fn battery_ram(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn load_battery_ram(&mut self, _data: &[u8]) {}
+
+    // Set by CPU writes to PRG-RAM, so autosave only writes saves that changed
+    fn is_battery_ram_dirty(&self) -> bool {
+        false
+    }
+
+    fn clear_battery_ram_dirty(&mut self) {}
 }
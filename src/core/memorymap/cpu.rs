@@ -1,29 +1,103 @@
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 use crate::core::mappers::Mapper;
 use crate::core::memory::Memory;
+use crate::core::random::Xorshift64;
 
-use super::MemoryMap;
+/// Contents of the internal RAM at power-up, which real consoles leave undefined.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RamInitPattern {
+    AllZeros,
+    AllOnes,
+    // Four $00 bytes then four $FF bytes, the pattern FCEUX and some test ROMs assume
+    Pattern00FF,
+    // Same seed gives the same RAM, so seeded runs stay reproducible
+    Random(u64),
+}
+
+impl RamInitPattern {
+    fn fill(self, ram: &mut [u8]) {
+        match self {
+            Self::AllZeros => ram.fill(0x00),
+            Self::AllOnes => ram.fill(0xFF),
+            Self::Pattern00FF => ram
+                .iter_mut()
+                .enumerate()
+                .for_each(|(index, byte)| *byte = if index & 0x04 == 0 { 0x00 } else { 0xFF }),
+            Self::Random(seed) => {
+                let mut random = Xorshift64::new(seed);
+                ram.iter_mut().for_each(|byte| *byte = random.next_u8());
+            },
+        }
+    }
+}
+
+/// The 2 KiB of internal RAM, shared with the CPU so its accesses skip the bus borrow.
+pub type InternalRam = Rc<[Cell<u8>; 0x800]>;
 
+#[derive(Clone)]
 pub struct CpuMemoryMap {
-    internal_ram: [u8; 0x800],
+    internal_ram: InternalRam,
     mapper: Rc<RefCell<Box<dyn Mapper>>>,
 }
 
 impl CpuMemoryMap {
     pub fn new(mapper: &Rc<RefCell<Box<dyn Mapper>>>) -> Self {
-        Self {
-            internal_ram: [0; 0x800],
+        Self::with_ram_init(mapper, RamInitPattern::AllZeros)
+    }
+
+    pub fn with_ram_init(mapper: &Rc<RefCell<Box<dyn Mapper>>>, pattern: RamInitPattern) -> Self {
+        let memory_map = Self {
+            internal_ram: Rc::new(std::array::from_fn(|_| Cell::new(0))),
             mapper: mapper.clone(),
-        }
+        };
+
+        memory_map.load_ram(&Self::pattern_bytes(pattern));
+        memory_map
+    }
+
+    fn pattern_bytes(pattern: RamInitPattern) -> [u8; 0x800] {
+        let mut bytes = [0; 0x800];
+        pattern.fill(&mut bytes);
+        bytes
+    }
+
+    pub fn init_ram(&mut self, pattern: RamInitPattern) {
+        self.load_ram(&Self::pattern_bytes(pattern));
+    }
+
+    pub fn internal_ram(&self) -> &InternalRam {
+        &self.internal_ram
+    }
+
+    pub fn save_ram(&self) -> [u8; 0x800] {
+        std::array::from_fn(|index| self.internal_ram[index].get())
+    }
+
+    // Cloned memory maps share the RAM, so it is restored in place
+    pub fn load_ram(&self, bytes: &[u8; 0x800]) {
+        self.internal_ram.iter().zip(bytes).for_each(|(byte, &data)| byte.set(data));
+    }
+
+    // $0000-$1FFF without the memory map dispatch, the CPU reads RAM far more than anything else
+    pub fn read_ram(&self, address: u16) -> u8 {
+        self.internal_ram[address as usize & 0x7FF].get()
+    }
+
+    pub fn write_ram(&mut self, address: u16, data: u8) {
+        self.internal_ram[address as usize & 0x7FF].set(data);
+    }
+
+    pub fn mapper(&self) -> &Rc<RefCell<Box<dyn Mapper>>> {
+        &self.mapper
     }
 }
 
 impl Memory for CpuMemoryMap {
     fn read(&self, address: u16) -> u8 {
         match address {
-            0x0000..=0x1FFF => self.internal_ram[address as usize & 0x7FF],
+            0x0000..=0x1FFF => self.read_ram(address),
             0x4020..=0xFFFF => self.mapper.borrow_mut().read(address),
             _ => panic!("Unable to read from address {:#04X} in CPU Memory Map!", address),
         }
@@ -31,13 +105,59 @@ impl Memory for CpuMemoryMap {
 
     fn write(&mut self, address: u16, data: u8) {
         match address {
-            0x0000..=0x1FFF => {
-                self.internal_ram[address as usize & 0x7FF] = data;
-            },
-            0x8000..=0xFFFF => panic!("Attempt to write into PRG-ROM in CPU Memory Map!"),
+            0x0000..=0x1FFF => self.write_ram(address, data),
+            0x4020..=0xFFFF => self.mapper.borrow_mut().write(address, data),
             _ => (),
         }
     }
 }
 
-impl MemoryMap for CpuMemoryMap {}
+#[cfg(test)]
+mod tests {
+    use crate::core::cartridge::Cartridge;
+    use super::*;
+
+    fn first_ram_bytes(pattern: RamInitPattern) -> Vec<u8> {
+        let cartridge = Cartridge::empty();
+        let memory_map = CpuMemoryMap::with_ram_init(cartridge.get_mapper(), pattern);
+
+        (0..10).map(|address| memory_map.read_ram(address)).collect()
+    }
+
+    #[test]
+    fn test_ram_init_constant_patterns() {
+        assert_eq!(first_ram_bytes(RamInitPattern::AllZeros), [0x00; 10], "RAM should be cleared!");
+        assert_eq!(first_ram_bytes(RamInitPattern::AllOnes), [0xFF; 10], "RAM should be filled with $FF!");
+        assert_eq!(
+            first_ram_bytes(RamInitPattern::Pattern00FF),
+            [0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00],
+            "RAM should alternate four $00 and four $FF bytes!"
+        );
+    }
+
+    #[test]
+    fn test_ram_init_random_is_reproducible() {
+        let mut random = Xorshift64::new(0x1234);
+        let expected = (0..10).map(|_| random.next_u8()).collect::<Vec<_>>();
+
+        assert_eq!(first_ram_bytes(RamInitPattern::Random(0x1234)), expected, "RAM should be filled from the seed!");
+        assert_eq!(
+            first_ram_bytes(RamInitPattern::Random(0x1234)),
+            first_ram_bytes(RamInitPattern::Random(0x1234)),
+            "Same seed should give the same RAM!"
+        );
+        assert_ne!(
+            first_ram_bytes(RamInitPattern::Random(0x1234)),
+            first_ram_bytes(RamInitPattern::Random(0x4321)),
+            "Different seeds should give different RAM!"
+        );
+    }
+
+    #[test]
+    fn test_default_ram_is_zeroed() {
+        let cartridge = Cartridge::empty();
+        let memory_map = CpuMemoryMap::new(cartridge.get_mapper());
+
+        assert!((0..0x800).all(|address| memory_map.read_ram(address) == 0x00), "Default RAM should stay zeroed!");
+    }
+}
@@ -5,9 +5,18 @@ use std::cell::RefCell;
 use crate::core::mappers::Mapper;
 use crate::core::memory::Memory;
 
-use super::MemoryMap;
+/// Nametable, palette and OAM contents, for savestates. Pattern tables belong to the mapper.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "savestate", derive(serde::Serialize, serde::Deserialize))]
+pub struct PpuMemoryState {
+    pub nametable: Vec<u8>,
+    pub palette: Vec<u8>,
+    pub oam: Vec<u8>,
+}
 
+#[derive(Clone)]
 pub struct PpuMemoryMap {
+    // Large enough for four-screen carts, other modes mirror onto the first 2 KiB
     nametable: [u8; 0x1000],
     palette: [u8; 0x20],
     oam: [u8; 0x100],
@@ -32,26 +41,51 @@ impl PpuMemoryMap {
         self.oam[address as usize] = value;
     }
 
-    pub fn set_oam(&mut self, buf: &Vec<u8>) {
+    pub fn set_oam(&mut self, buf: &[u8]) {
         self.oam.copy_from_slice(buf);
     }
+
+    pub fn save_state(&self) -> PpuMemoryState {
+        PpuMemoryState {
+            nametable: self.nametable.to_vec(),
+            palette: self.palette.to_vec(),
+            oam: self.oam.to_vec(),
+        }
+    }
+
+    pub fn restore_state(&mut self, state: &PpuMemoryState) {
+        self.nametable.copy_from_slice(&state.nametable);
+        self.palette.copy_from_slice(&state.palette);
+        self.oam.copy_from_slice(&state.oam);
+    }
+}
+
+// Palette RAM repeats every 32 bytes, and the sprite backdrop entries alias the background ones
+fn palette_index(address: u16) -> usize {
+    let index = (address as usize - 0x3F00) % 0x20;
+
+    match index {
+        0x10 | 0x14 | 0x18 | 0x1C => index - 0x10,
+        _ => index,
+    }
 }
 
 impl Memory for PpuMemoryMap {
     fn read(&self, address: u16) -> u8 {
         match address {
             0x0000..=0x1FFF => {
-                self.mapper
-                    .borrow_mut()
+                let mut mapper = self.mapper.borrow_mut();
+                let chr_address = mapper.chr_address(address);
+
+                mapper
                     .get_chr_rom()
-                    .get(address as usize)
+                    .get(chr_address)
                     .cloned()
                     .expect("Unable to get value from Pattern table!")
             },
             0x2000..=0x2FFF => self.nametable[address as usize - 0x2000],
             0x3000..=0x3EFF => self.nametable[address as usize - 0x3000],
-            0x3F10 | 0x3F14 | 0x3F18 | 0x3F1C => self.palette[address as usize - 0x3F10],
-            0x3F00..=0x3FFF => self.palette[address as usize & 0x3F1F - 0x3F00],
+            0x3F00..=0x3FFF => self.palette[palette_index(address)],
             _ => panic!("Unable to read from address {:#04X} in CPU Memory Map!", address),
         }
     }
@@ -60,8 +94,9 @@ impl Memory for PpuMemoryMap {
         match address {
             0x0000..=0x1FFF => {
                 let mut mapper = self.mapper.borrow_mut();
+                let chr_address = mapper.chr_address(address);
                 let chr_rom = mapper.get_chr_rom();
-                let pattern_table_cell = chr_rom.index_mut(address as usize);
+                let pattern_table_cell = chr_rom.index_mut(chr_address);
 
                 *pattern_table_cell = data;
             },
@@ -71,15 +106,47 @@ impl Memory for PpuMemoryMap {
             0x3000..=0x3EFF => {
                 self.nametable[address as usize - 0x3000] = data;
             },
-            0x3F10 | 0x3F14 | 0x3F18 | 0x3F1C => {
-                self.palette[address as usize - 0x3F10] = data;
-            },
             0x3F00..=0x3FFF => {
-                self.palette[address as usize & 0x3F1F - 0x3F00] = data;
+                self.palette[palette_index(address)] = data;
             },
             _ => panic!("Unable to read from address {:#04X} in CPU Memory Map!", address),
         }
     }
 }
 
-impl MemoryMap for PpuMemoryMap {}
+#[cfg(test)]
+mod tests {
+    use crate::core::cartridge::Cartridge;
+    use super::*;
+
+    fn create_memory_map() -> PpuMemoryMap {
+        let cartridge = Cartridge::empty();
+
+        PpuMemoryMap::new(cartridge.get_mapper())
+    }
+
+    #[test]
+    fn test_sprite_backdrop_entries_alias_background() {
+        let mut memory_map = create_memory_map();
+
+        for address in [0x3F10, 0x3F14, 0x3F18, 0x3F1C] {
+            memory_map.write(address, address as u8);
+            assert_eq!(memory_map.read(address - 0x10), address as u8, "Sprite backdrop should alias the background entry!");
+        }
+
+        memory_map.write(0x3F00, 0x0F);
+        assert_eq!(memory_map.read(0x3F10), 0x0F, "Background backdrop should alias the sprite entry!");
+    }
+
+    #[test]
+    fn test_palette_mirrors_every_32_bytes() {
+        let mut memory_map = create_memory_map();
+
+        memory_map.write(0x3F35, 0x2A);
+        assert_eq!(memory_map.read(0x3F15), 0x2A, "$3F35 should mirror $3F15!");
+        assert_eq!(memory_map.read(0x3FF5), 0x2A, "$3FF5 should mirror $3F15!");
+
+        memory_map.write(0x3F30, 0x21);
+        assert_eq!(memory_map.read(0x3F00), 0x21, "$3F30 should mirror the backdrop!");
+    }
+}
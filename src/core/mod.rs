@@ -1,9 +1,22 @@
-mod memory;
+pub mod memory;
 mod registers;
 mod memorymap;
 pub mod cpu;
+pub mod disasm;
+pub mod cdl;
+pub mod symbols;
+pub mod trace;
 pub mod bus;
 pub mod clock;
 pub mod cartridge;
 mod mappers;
 pub mod ppu;
+pub mod apu;
+pub mod joypad;
+pub mod frontend;
+pub mod emulator;
+mod random;
+pub mod hash;
+pub mod netplay;
+pub mod config;
+pub mod battery;
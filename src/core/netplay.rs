@@ -0,0 +1,516 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use super::frontend::{InputSource, InputState};
+
+const PROTOCOL_VERSION: u8 = 1;
+// Frames between frame hash exchanges
+const HASH_INTERVAL: u32 = 30;
+// Remote peer is considered gone if it stalls us for this long
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+const MESSAGE_HELLO: u8 = 0x01;
+const MESSAGE_INPUT: u8 = 0x02;
+const MESSAGE_FRAME_HASH: u8 = 0x03;
+
+#[derive(Debug)]
+pub enum NetplayError {
+    Io(io::Error),
+    VersionMismatch,
+    RomMismatch,
+    UnexpectedMessage(u8),
+    Desync { frame: u32 },
+}
+
+impl fmt::Display for NetplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetplayError::Io(error) => write!(f, "connection error: {}", error),
+            NetplayError::VersionMismatch => write!(f, "peer uses a different netplay protocol"),
+            NetplayError::RomMismatch => write!(f, "peer runs a different ROM"),
+            NetplayError::UnexpectedMessage(tag) => write!(f, "unexpected message {:#04X}", tag),
+            NetplayError::Desync { frame } => write!(f, "desync detected at frame {}", frame),
+        }
+    }
+}
+
+impl std::error::Error for NetplayError {}
+
+impl From<io::Error> for NetplayError {
+    fn from(error: io::Error) -> Self {
+        NetplayError::Io(error)
+    }
+}
+
+enum Message {
+    Hello { version: u8, rom_hash: u64, seed: u64, input_delay: u8 },
+    Input { frame: u32, buttons: u8 },
+    FrameHash { frame: u32, hash: u64 },
+}
+
+fn write_message(stream: &mut TcpStream, message: &Message) -> io::Result<()> {
+    let mut buffer = Vec::with_capacity(19);
+
+    match message {
+        Message::Hello { version, rom_hash, seed, input_delay } => {
+            buffer.extend([MESSAGE_HELLO, *version]);
+            buffer.extend(rom_hash.to_le_bytes());
+            buffer.extend(seed.to_le_bytes());
+            buffer.push(*input_delay);
+        },
+        Message::Input { frame, buttons } => {
+            buffer.push(MESSAGE_INPUT);
+            buffer.extend(frame.to_le_bytes());
+            buffer.push(*buttons);
+        },
+        Message::FrameHash { frame, hash } => {
+            buffer.push(MESSAGE_FRAME_HASH);
+            buffer.extend(frame.to_le_bytes());
+            buffer.extend(hash.to_le_bytes());
+        },
+    }
+
+    stream.write_all(&buffer)
+}
+
+fn read_bytes<const N: usize>(stream: &mut TcpStream) -> io::Result<[u8; N]> {
+    let mut buffer = [0; N];
+    stream.read_exact(&mut buffer)?;
+
+    Ok(buffer)
+}
+
+fn read_message(stream: &mut TcpStream) -> Result<Message, NetplayError> {
+    let [tag] = read_bytes::<1>(stream)?;
+
+    let message = match tag {
+        MESSAGE_HELLO => Message::Hello {
+            version: read_bytes::<1>(stream)?[0],
+            rom_hash: u64::from_le_bytes(read_bytes(stream)?),
+            seed: u64::from_le_bytes(read_bytes(stream)?),
+            input_delay: read_bytes::<1>(stream)?[0],
+        },
+        MESSAGE_INPUT => Message::Input {
+            frame: u32::from_le_bytes(read_bytes(stream)?),
+            buttons: read_bytes::<1>(stream)?[0],
+        },
+        MESSAGE_FRAME_HASH => Message::FrameHash {
+            frame: u32::from_le_bytes(read_bytes(stream)?),
+            hash: u64::from_le_bytes(read_bytes(stream)?),
+        },
+        _ => return Err(NetplayError::UnexpectedMessage(tag)),
+    };
+
+    Ok(message)
+}
+
+/// Lockstep session between two emulators running the deterministic core.
+/// Host plays on joypad 1 and the joining side on joypad 2; local input is
+/// applied `input_delay` frames late on both sides so it can reach the peer
+/// in time, and a late packet stalls the frame until it arrives.
+pub struct Netplay {
+    stream: TcpStream,
+    player: usize,
+    seed: u64,
+    frame: u32,
+    remote_frame: u32,
+    checked_frames: u32,
+    local_inputs: VecDeque<u8>,
+    remote_inputs: VecDeque<u8>,
+    local_hashes: BTreeMap<u32, u64>,
+    remote_hashes: BTreeMap<u32, u64>,
+    // Error `NetplayInput` ran into, the session is over once it's set
+    error: Option<NetplayError>,
+}
+
+impl Netplay {
+    // The delay travels in one byte of the handshake, so both sides always agree on it
+    pub const DEFAULT_INPUT_DELAY: u8 = 2;
+
+    fn new(stream: TcpStream, player: usize, seed: u64, input_delay: u8) -> Result<Self, NetplayError> {
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        Ok(Self {
+            stream,
+            player,
+            seed,
+            frame: 0,
+            remote_frame: input_delay as u32,
+            checked_frames: 0,
+            local_inputs: VecDeque::from(vec![0x00; input_delay as usize]),
+            remote_inputs: VecDeque::from(vec![0x00; input_delay as usize]),
+            local_hashes: BTreeMap::new(),
+            remote_hashes: BTreeMap::new(),
+            error: None,
+        })
+    }
+
+    /// Waits for a peer on `listener`, the host decides the seed and input delay.
+    pub fn host(listener: &TcpListener, rom_hash: u64, seed: u64, input_delay: u8) -> Result<Self, NetplayError> {
+        let (stream, _) = listener.accept()?;
+        let mut netplay = Self::new(stream, 0, seed, input_delay)?;
+
+        write_message(&mut netplay.stream, &Message::Hello {
+            version: PROTOCOL_VERSION,
+            rom_hash,
+            seed,
+            input_delay,
+        })?;
+
+        match read_message(&mut netplay.stream)? {
+            Message::Hello { version, .. } if version != PROTOCOL_VERSION => Err(NetplayError::VersionMismatch),
+            Message::Hello { rom_hash: remote_rom_hash, .. } if remote_rom_hash != rom_hash => Err(NetplayError::RomMismatch),
+            Message::Hello { .. } => Ok(netplay),
+            _ => Err(NetplayError::UnexpectedMessage(MESSAGE_HELLO)),
+        }
+    }
+
+    pub fn join<A: ToSocketAddrs>(address: A, rom_hash: u64) -> Result<Self, NetplayError> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        let (version, remote_rom_hash, seed, input_delay) = match read_message(&mut stream)? {
+            Message::Hello { version, rom_hash, seed, input_delay } => (version, rom_hash, seed, input_delay),
+            _ => return Err(NetplayError::UnexpectedMessage(MESSAGE_HELLO)),
+        };
+
+        // Reply even on mismatch, so the host can report it too
+        write_message(&mut stream, &Message::Hello {
+            version: PROTOCOL_VERSION,
+            rom_hash,
+            seed,
+            input_delay,
+        })?;
+
+        if version != PROTOCOL_VERSION {
+            return Err(NetplayError::VersionMismatch);
+        }
+
+        if remote_rom_hash != rom_hash {
+            return Err(NetplayError::RomMismatch);
+        }
+
+        Self::new(stream, 1, seed, input_delay)
+    }
+
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn handle_message(&mut self) -> Result<(), NetplayError> {
+        match read_message(&mut self.stream)? {
+            Message::Input { frame, buttons } if frame == self.remote_frame => {
+                self.remote_inputs.push_back(buttons);
+                self.remote_frame += 1;
+            },
+            Message::FrameHash { frame, hash } => {
+                self.remote_hashes.insert(frame, hash);
+            },
+            Message::Input { .. } => return Err(NetplayError::UnexpectedMessage(MESSAGE_INPUT)),
+            Message::Hello { .. } => return Err(NetplayError::UnexpectedMessage(MESSAGE_HELLO)),
+        }
+
+        Ok(())
+    }
+
+    /// Sends local joypad 1 and returns the input both sides apply this frame.
+    pub fn exchange(&mut self, local: InputState) -> Result<InputState, NetplayError> {
+        let buttons = local.joypads[0];
+
+        write_message(&mut self.stream, &Message::Input {
+            frame: self.frame + self.local_inputs.len() as u32,
+            buttons,
+        })?;
+        self.local_inputs.push_back(buttons);
+
+        while self.remote_inputs.is_empty() {
+            self.handle_message()?;
+        }
+
+        let local_buttons = self.local_inputs.pop_front().unwrap_or(0x00);
+        let remote_buttons = self.remote_inputs.pop_front().unwrap_or(0x00);
+        self.frame += 1;
+
+        // Hotkeys are dropped, acting on them on one side only would desync the session
+        let mut input = InputState::default();
+        input.joypads[self.player] = local_buttons;
+        input.joypads[1 - self.player] = remote_buttons;
+
+        Ok(input)
+    }
+
+    /// Reports the hash of the frame that was just rendered, every few frames
+    /// it is sent to the peer and compared with the peer's hash of that frame.
+    pub fn check_frame(&mut self, frame_hash: u64) -> Result<(), NetplayError> {
+        self.checked_frames += 1;
+
+        if self.checked_frames.is_multiple_of(HASH_INTERVAL) {
+            let frame = self.checked_frames;
+
+            write_message(&mut self.stream, &Message::FrameHash { frame, hash: frame_hash })?;
+            self.local_hashes.insert(frame, frame_hash);
+        }
+
+        let compared = self.remote_hashes
+            .iter()
+            .filter(|(frame, _)| self.local_hashes.contains_key(frame))
+            .map(|(frame, hash)| (*frame, *hash))
+            .collect::<Vec<_>>();
+
+        for (frame, remote_hash) in compared {
+            if self.local_hashes.remove(&frame) != Some(remote_hash) {
+                return Err(NetplayError::Desync { frame });
+            }
+
+            self.remote_hashes.remove(&frame);
+        }
+
+        Ok(())
+    }
+
+    /// Ends the session without resetting the connection under a peer that
+    /// is still sending its last frames.
+    pub fn finish(mut self) -> Result<(), NetplayError> {
+        self.stream.shutdown(Shutdown::Write)?;
+        io::copy(&mut self.stream, &mut io::sink())?;
+
+        Ok(())
+    }
+
+    /// Error that ended the session while polling `input`, e.g. the peer disconnected.
+    pub fn take_error(&mut self) -> Option<NetplayError> {
+        self.error.take()
+    }
+
+    pub fn input<'a>(&'a mut self, local: &'a mut dyn InputSource) -> NetplayInput<'a> {
+        NetplayInput {
+            netplay: self,
+            local,
+        }
+    }
+}
+
+/// Merges a local input source with the peer's input for one frame. A failed exchange
+/// polls no buttons and leaves the error in `Netplay::take_error`.
+pub struct NetplayInput<'a> {
+    netplay: &'a mut Netplay,
+    local: &'a mut dyn InputSource,
+}
+
+impl InputSource for NetplayInput<'_> {
+    fn poll(&mut self) -> InputState {
+        let local = self.local.poll();
+
+        self.netplay
+            .exchange(local)
+            .unwrap_or_else(|error| {
+                self.netplay.error = Some(error);
+                InputState::default()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::core::cartridge::Cartridge;
+    use crate::core::emulator::Emulator;
+    use crate::core::frontend::Hotkey;
+    use crate::core::hash::fnv1a;
+    use crate::core::joypad::JoypadButton;
+    use super::*;
+
+    const ROM: &[u8] = include_bytes!("../../tests/roms/start_game.nes");
+
+    struct ScriptedInputSource {
+        frame: usize,
+        button: JoypadButton,
+        pause_frame: Option<usize>,
+    }
+
+    impl InputSource for ScriptedInputSource {
+        fn poll(&mut self) -> InputState {
+            self.frame += 1;
+
+            let mut input = InputState::default();
+            input.set_button(0, JoypadButton::Start, self.frame % 100 == 10);
+            input.set_button(0, self.button, !self.frame.is_multiple_of(3));
+            input.set_hotkey(Hotkey::Pause, self.pause_frame == Some(self.frame));
+            input.set_hotkey(Hotkey::FrameAdvance, self.pause_frame.is_some_and(|frame| self.frame > frame));
+            input
+        }
+    }
+
+    // Runs the session like the frontend's main loop
+    fn play(mut netplay: Netplay, mut local: ScriptedInputSource, frames: usize) -> Result<Vec<u64>, NetplayError> {
+        let cartridge = Cartridge::new(ROM.to_vec());
+        let mut emulator = Emulator::new_deterministic(&cartridge, netplay.seed());
+        let mut hashes = Vec::new();
+
+        for _ in 0..frames {
+            emulator.poll_input(&mut netplay.input(&mut local));
+
+            if let Some(error) = netplay.take_error() {
+                return Err(error);
+            }
+
+            if !emulator.advance() {
+                continue;
+            }
+
+            let hash = emulator.frame().hash();
+            netplay.check_frame(hash)?;
+            hashes.push(hash);
+        }
+
+        netplay.finish()?;
+        Ok(hashes)
+    }
+
+    fn listen() -> (TcpListener, u16) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Unable to bind test listener!");
+        let port = listener.local_addr().expect("Unable to get test listener address!").port();
+
+        (listener, port)
+    }
+
+    #[test]
+    fn test_lockstep_session_stays_in_sync() {
+        let (listener, port) = listen();
+        let rom_hash = fnv1a(ROM);
+
+        let host = thread::spawn(move || {
+            let netplay = Netplay::host(&listener, rom_hash, 0xBEEF, Netplay::DEFAULT_INPUT_DELAY)?;
+            play(netplay, ScriptedInputSource { frame: 0, button: JoypadButton::Right, pause_frame: None }, 300)
+        });
+
+        let client = Netplay::join(("127.0.0.1", port), rom_hash).expect("Client should join the session!");
+        assert_eq!(client.seed(), 0xBEEF, "Client should adopt the host seed!");
+        assert_eq!(client.player(), 1, "Client should play on joypad 2!");
+
+        let client_local = ScriptedInputSource { frame: 0, button: JoypadButton::Left, pause_frame: None };
+        let client_hashes = play(client, client_local, 300).expect("Client should not desync!");
+        let host_hashes = host.join().unwrap().expect("Host should not desync!");
+
+        assert_eq!(host_hashes, client_hashes, "Both sides should render identical frames!");
+    }
+
+    #[test]
+    fn test_pause_on_one_side_is_ignored() {
+        let (listener, port) = listen();
+        let rom_hash = fnv1a(ROM);
+
+        let host = thread::spawn(move || {
+            let netplay = Netplay::host(&listener, rom_hash, 0xBEEF, Netplay::DEFAULT_INPUT_DELAY)?;
+            play(netplay, ScriptedInputSource { frame: 0, button: JoypadButton::Right, pause_frame: Some(40) }, 120)
+        });
+
+        let client = Netplay::join(("127.0.0.1", port), rom_hash).expect("Client should join the session!");
+        let client_local = ScriptedInputSource { frame: 0, button: JoypadButton::Left, pause_frame: None };
+        let client_hashes = play(client, client_local, 120).expect("Client should not desync!");
+        let host_hashes = host.join().unwrap().expect("Host should not desync!");
+
+        assert_eq!(host_hashes.len(), 120, "Pause should not stop a netplay session!");
+        assert_eq!(host_hashes, client_hashes, "Both sides should keep rendering identical frames!");
+    }
+
+    #[test]
+    fn test_exchange_applies_input_delay() {
+        let (listener, port) = listen();
+
+        let host = thread::spawn(move || {
+            let mut netplay = Netplay::host(&listener, 0, 0, 2).unwrap();
+            let inputs = (0..4u8)
+                .map(|frame| netplay.exchange(InputState { joypads: [frame + 1, 0], ..Default::default() }).unwrap())
+                .collect::<Vec<_>>();
+
+            netplay.finish().unwrap();
+            inputs
+        });
+
+        let mut client = Netplay::join(("127.0.0.1", port), 0).unwrap();
+        let client_inputs = (0..4u8)
+            .map(|frame| client.exchange(InputState { joypads: [(frame + 1) << 4, 0], ..Default::default() }).unwrap())
+            .collect::<Vec<_>>();
+
+        client.finish().unwrap();
+        let host_inputs = host.join().unwrap();
+
+        assert_eq!(host_inputs, client_inputs, "Both sides should apply the same inputs!");
+        assert_eq!(host_inputs[0].joypads, [0x00, 0x00], "First frames should be covered by the input delay!");
+        assert_eq!(host_inputs[2].joypads, [0x01, 0x10], "Input should be applied 2 frames later!");
+        assert_eq!(host_inputs[3].joypads, [0x02, 0x20], "Input should be applied 2 frames later!");
+    }
+
+    #[test]
+    fn test_lost_peer_is_reported_by_input() {
+        let (listener, port) = listen();
+
+        let host = thread::spawn(move || {
+            let mut netplay = Netplay::host(&listener, 0, 0, 0).unwrap();
+            netplay.exchange(InputState::default()).unwrap();
+        });
+
+        let mut client = Netplay::join(("127.0.0.1", port), 0).unwrap();
+        let mut local = ScriptedInputSource { frame: 0, button: JoypadButton::A, pause_frame: None };
+        client.input(&mut local).poll();
+        assert!(client.take_error().is_none(), "Exchange with a connected peer should succeed!");
+
+        host.join().unwrap();
+        let input = client.input(&mut local).poll();
+
+        assert_eq!(input, InputState::default(), "Input should be empty once the peer is gone!");
+        assert!(matches!(client.take_error(), Some(NetplayError::Io(_))), "Lost peer should be reported as an error!");
+    }
+
+    #[test]
+    fn test_handshake_rejects_different_rom() {
+        let (listener, port) = listen();
+        let host = thread::spawn(move || Netplay::host(&listener, 1, 0, 2).err());
+
+        let client = Netplay::join(("127.0.0.1", port), 2);
+
+        assert!(matches!(client, Err(NetplayError::RomMismatch)), "Client should reject a different ROM!");
+        assert!(matches!(host.join().unwrap(), Some(NetplayError::RomMismatch)), "Host should reject a different ROM!");
+    }
+
+    #[test]
+    fn test_frame_hash_mismatch_reports_desync() {
+        let (listener, port) = listen();
+
+        let host = thread::spawn(move || {
+            let mut netplay = Netplay::host(&listener, 0, 0, 2).unwrap();
+
+            (0..HASH_INTERVAL * 2)
+                .map(|_| {
+                    netplay.exchange(InputState::default())?;
+                    netplay.check_frame(0x1111)
+                })
+                .collect::<Result<Vec<_>, _>>()
+        });
+
+        let mut client = Netplay::join(("127.0.0.1", port), 0).unwrap();
+        let client_result = (0..HASH_INTERVAL * 2)
+            .map(|_| {
+                client.exchange(InputState::default())?;
+                client.check_frame(0x2222)
+            })
+            .collect::<Result<Vec<_>, _>>();
+
+        let host_result = host.join().unwrap();
+        let desynced = [client_result, host_result]
+            .into_iter()
+            .any(|result| matches!(result, Err(NetplayError::Desync { frame: HASH_INTERVAL })));
+
+        assert!(desynced, "Different frame hashes should be reported as desync!");
+    }
+}
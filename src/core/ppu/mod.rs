@@ -3,12 +3,14 @@ mod screenstate;
 
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::time::Duration;
 
-use self::screenbuffer::ScreenBuffer;
+pub use self::screenbuffer::ScreenBuffer;
 use self::screenstate::ScreenState;
 
 use super::bus::Bus;
 use super::memory::Memory;
+use super::memorymap::PpuMemoryState;
 use super::registers::Register;
 use super::registers::ppu::data::PpuDataRegister;
 use super::registers::ppu::oamdata::PpuOamDataRegister;
@@ -18,16 +20,116 @@ use super::registers::ppu::mask::{PpuMaskRegister, PpuMaskRegisterFlags};
 use super::registers::ppu::controller::{PpuControllerRegister, PpuControllerRegisterFlags};
 use super::registers::ppu::vram::PpuVRamRegister;
 
+pub const MAX_EXTRA_VBLANK_SCANLINES: usize = 100;
+
+// Bits of the I/O latch fade to 0 about 600ms after they were last driven
+const IO_LATCH_DECAY_FRAMES: u64 = 36;
+
+// MMC3 ignores A12 rises after short low periods, like the ones between neighbouring pattern fetches
+const A12_FILTER_DOTS: usize = 16;
+
+// What happened at each pixel of the frame, recorded only while the debug mask is on
+#[repr(u8)]
+pub enum DebugMaskFlags {
+    SpriteZeroHit = 1 << 0,
+    SpriteZeroArea = 1 << 1,
+    SpritePixel = 1 << 2,
+}
+
+/// TV system the console was built for, which sets the PPU frame timing.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "savestate", derive(serde::Serialize, serde::Deserialize))]
+pub enum Region {
+    #[default]
+    Ntsc,
+    Pal,
+}
+
+impl Region {
+    /// Scanlines per frame, including the pre-render scanline.
+    pub fn scanlines(self) -> isize {
+        match self {
+            Region::Ntsc => 262,
+            Region::Pal => 312,
+        }
+    }
+
+    /// Time the console takes for one frame, about 60.1 Hz on NTSC and 50.0 Hz on PAL.
+    pub fn frame_duration(self) -> Duration {
+        // Dots per frame over the dot clock, NTSC averages out the skipped dot of odd frames
+        match self {
+            Region::Ntsc => Duration::from_nanos(16_639_267),
+            Region::Pal => Duration::from_nanos(19_997_194),
+        }
+    }
+}
+
+/// OAM entry decoded for debuggers.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct SpriteInfo {
+    pub x: u8,
+    pub y: u8,
+    pub tile: u8,
+    pub palette: u8,
+    pub behind_background: bool,
+    pub flip_h: bool,
+    pub flip_v: bool,
+    // Selected by the most recent sprite evaluation
+    pub in_range: bool,
+}
+
+/// Half of palette RAM a debug palette is read from.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PaletteKind {
+    Background,
+    Sprite,
+}
+
 #[repr(u8)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "savestate", derive(serde::Serialize, serde::Deserialize))]
 pub enum Mirroring {
     Horizontal,
     Vertical,
     FourScreen,
+    SingleScreenLow,
+    SingleScreenHigh,
+}
+
+/// Registers, latches, rendering progress and memory of the PPU, for emulator snapshots.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "savestate", derive(serde::Serialize, serde::Deserialize))]
+pub struct PpuState {
+    pub controller: u8,
+    pub mask: u8,
+    pub status: u8,
+    pub oamaddress: u8,
+    pub vram: u16,
+    pub vram_temp: u16,
+    pub fine_x: u16,
+    pub address_latch: bool,
+    pub internal_buf: Option<u8>,
+    pub io_latch: u8,
+    pub io_latch_refreshed: [u64; 8],
+    pub vblank_suppressed: bool,
+    pub a12_low_dots: usize,
+    pub screen_state: ScreenState,
+    pub screen_buffer: ScreenBuffer,
+    pub internal_oam: [u8; 0x20],
+    pub cycles: usize,
+    pub scanline: isize,
+    pub frame: u64,
+    pub odd_frame: bool,
+    pub frame_complete: bool,
+    pub mirroring: Mirroring,
+    pub memory: PpuMemoryState,
 }
 
+#[derive(Clone)]
 pub struct Ppu {
+    // From the cartridge header, used while the mapper doesn't pick one
     mirroring: Mirroring,
+    region: Region,
     controller: PpuControllerRegister,
     mask: PpuMaskRegister,
     status: PpuStatusRegister,
@@ -42,15 +144,31 @@ pub struct Ppu {
     cycles: usize,
     scanline: isize,
     internal_buf: Option<u8>,
+    // Last value on the PPU data bus, read back from write-only registers
+    io_latch: u8,
+    io_latch_refreshed: [u64; 8],
+    // PPUSTATUS was read right before VBlank, so this frame neither sets it nor raises NMI
+    vblank_suppressed: bool,
+    // Dots since a pattern fetch last drove A12 high
+    a12_low_dots: usize,
     screen_state: ScreenState,
     screen_buffer: ScreenBuffer,
     internal_oam: [u8; 0x20],
+    extra_vblank_scanlines: usize,
+    frame: u64,
+    odd_frame: bool,
+    frame_complete: bool,
+    debug_mask: Option<Vec<u8>>,
+    // Debug overrides for the layers that get drawn, the game still sees its own mask
+    background_override: Option<bool>,
+    sprites_override: Option<bool>,
 }
 
 impl Ppu {
     pub fn new(bus: &Rc<RefCell<Bus>>, mirroring: Mirroring) -> Self {
         Self {
             mirroring,
+            region: Region::Ntsc,
             controller: PpuControllerRegister::new(),
             mask: PpuMaskRegister::new(),
             status: PpuStatusRegister::new(),
@@ -65,9 +183,20 @@ impl Ppu {
             cycles: 0, 
             scanline: 0,
             internal_buf: None,
+            io_latch: 0,
+            io_latch_refreshed: [0; 8],
+            vblank_suppressed: false,
+            a12_low_dots: A12_FILTER_DOTS,
             screen_state: ScreenState::new(),
             screen_buffer: ScreenBuffer::new(256, 240),
             internal_oam: [0xFF; 0x20],
+            extra_vblank_scanlines: 0,
+            frame: 0,
+            odd_frame: false,
+            frame_complete: false,
+            debug_mask: None,
+            background_override: None,
+            sprites_override: None,
         }
     }
 
@@ -146,18 +275,18 @@ impl Ppu {
 
     fn read_tile_id(&mut self) {
         let vram_address = self.vram.get();
-        let tile_id = self.read(0x2000 | (vram_address & 0x0FFF));
+        let tile_id = self.read(self.mirror_address(0x2000 | (vram_address & 0x0FFF)));
         self.screen_state.bg_next_tile_id = tile_id;
     }
 
     fn read_attribute(&mut self) {
-        let mut attribute = self.read(
+        let mut attribute = self.read(self.mirror_address(
             0x23C0 |
             (self.vram.get_nametable_y() << 11) |
             (self.vram.get_nametable_x() << 10) |
             ((self.vram.get_coarse_y() >> 2) << 3) |
             (self.vram.get_coarse_x() >> 2)
-        );
+        ));
 
         if self.vram.get_coarse_y() & 0b10 != 0 {
             attribute >>= 4;
@@ -177,7 +306,7 @@ impl Ppu {
             0u16
         };
 
-        let tile_lsb = self.read(
+        let tile_lsb = self.read_pattern(
             (bg_pattern_table << 12) +
             ((self.screen_state.bg_next_tile_id as u16) << 4) +
             self.vram.get_fine_y()
@@ -193,7 +322,7 @@ impl Ppu {
             0u16
         };
 
-        let tile_msb = self.read(
+        let tile_msb = self.read_pattern(
             (bg_pattern_table << 12) +
             ((self.screen_state.bg_next_tile_id as u16) << 4) +
             self.vram.get_fine_y() +
@@ -203,6 +332,27 @@ impl Ppu {
         self.screen_state.bg_next_tile_msb = tile_msb;
     }
 
+    fn read_pattern(&mut self, address: u16) -> u8 {
+        self.watch_a12(address);
+        self.read(address)
+    }
+
+    // Clocks the mapper when a rendering fetch raises A12 after it stayed low long enough
+    fn watch_a12(&mut self, address: u16) {
+        let is_rendering = self.mask.get_flag(PpuMaskRegisterFlags::ShowBackground)
+            || self.mask.get_flag(PpuMaskRegisterFlags::ShowSprites);
+
+        if !is_rendering || address & 0x1000 == 0 {
+            return;
+        }
+
+        if self.a12_low_dots >= A12_FILTER_DOTS {
+            self.bus.borrow_mut().clock_mapper_a12();
+        }
+
+        self.a12_low_dots = 0;
+    }
+
     fn load_background_shift(&mut self) {
         let tile_lsb = self.screen_state.bg_next_tile_lsb;
         let tile_msb = self.screen_state.bg_next_tile_msb;
@@ -232,10 +382,23 @@ impl Ppu {
             };
     }
 
-    fn update_shift(&mut self) {
+    // Layers that are drawn, which are the ones enabled in PPUMASK unless overridden for debugging
+    fn drawn_layers(&self) -> (bool, bool) {
         let show_background = self.mask.get_flag(PpuMaskRegisterFlags::ShowBackground);
         let show_sprites = self.mask.get_flag(PpuMaskRegisterFlags::ShowSprites);
 
+        (
+            self.background_override.unwrap_or(show_background),
+            self.sprites_override.unwrap_or(show_sprites),
+        )
+    }
+
+    // Shifters also run for hidden layers so sprite zero hits stay where the game expects them
+    fn update_shift(&mut self) {
+        let (draw_background, draw_sprites) = self.drawn_layers();
+        let show_background = draw_background || self.mask.get_flag(PpuMaskRegisterFlags::ShowBackground);
+        let show_sprites = draw_sprites || self.mask.get_flag(PpuMaskRegisterFlags::ShowSprites);
+
         if show_background {
             self.screen_state.bg_shift_pattern_lo <<= 1;
             self.screen_state.bg_shift_pattern_hi <<= 1;
@@ -280,215 +443,310 @@ impl Ppu {
         }
     }
 
-    pub fn skip_odd_frame(&mut self) {
-        if self.scanline == 0 && self.cycles == 0 {
-            self.cycles = 1;
-        }
-    }
-
     pub fn reset_vblank(&mut self) {
         if self.scanline == -1 && self.cycles == 1 {
             self.status.set_flag(PpuStatusRegisterFlags::VBlank, false);
             self.status.set_flag(PpuStatusRegisterFlags::SpriteZeroHit, false);
             self.status.set_flag(PpuStatusRegisterFlags::SpriteOverflow, false);
+            self.update_nmi_line();
 
             self.screen_state.sprite_shift_pattern_lo.fill(0);
             self.screen_state.sprite_shift_pattern_hi.fill(0);
+
+            if let Some(debug_mask) = self.debug_mask.as_mut() {
+                debug_mask.fill(0);
+            }
+        }
+    }
+
+    fn mark_debug_pixel(&mut self, x: usize, y: isize, flag: DebugMaskFlags) {
+        if let Some(debug_mask) = self.debug_mask.as_mut() {
+            if x < 256 && (0..240).contains(&y) {
+                debug_mask[y as usize * 256 + x] |= flag as u8;
+            }
         }
     }
 
     pub fn update_vblank(&mut self) {
         if self.scanline == 241 && self.cycles == 1 {
+            if std::mem::take(&mut self.vblank_suppressed) {
+                return;
+            }
+
             self.status.set_flag(PpuStatusRegisterFlags::VBlank, true);
+            self.update_nmi_line();
+        }
+    }
 
-            if self.controller.get_flag(PpuControllerRegisterFlags::GenerateVBlankNMI) {
-                self.bus.borrow_mut().set_interrupt(Some(()));
-            }
+    // NMI is asserted while both VBlank and NMI generation are set
+    fn update_nmi_line(&mut self) {
+        let level = self.status.get_flag(PpuStatusRegisterFlags::VBlank)
+            && self.controller.get_flag(PpuControllerRegisterFlags::GenerateVBlankNMI);
+
+        self.bus.borrow_mut().set_nmi_line(level);
+    }
+
+    fn background_pipeline(&mut self, dot: usize) {
+        self.fetch_data();
+
+        match dot {
+            256 => {
+                self.increment_scroll_y();
+            },
+            257 => {
+                self.load_background_shift();
+                self.transfer_address_x();
+            },
+            280..=304 if self.scanline == -1 => {
+                self.transfer_address_y();
+            },
+            338 | 340 => {
+                self.read_tile_id();
+            },
+            _ => (),
         }
     }
 
-    pub fn tick(&mut self, amount: usize) {
-        self.cycles += amount;
+    fn sprite_height(&self) -> isize {
+        if self.controller.get_flag(PpuControllerRegisterFlags::SpriteSize) {
+            16
+        } else {
+            8
+        }
+    }
 
-        if self.scanline >= -1 && self.scanline < 240 {
-            self.skip_odd_frame();
-            self.reset_vblank();
-            self.fetch_data();
+    // Secondary OAM is cleared on dots 1-64, filled on 65-256 and handed to the fetches on 257.
+    // The pre-render scanline finds no sprites, as none can start above scanline 1.
+    fn sprite_evaluation(&mut self, dot: usize) {
+        match dot {
+            1..=64 if dot.is_multiple_of(2) => self.screen_state.secondary_oam[dot / 2 - 1] = 0xFF,
+            65 => {
+                self.screen_state.secondary_sprite_count = 0;
+                self.screen_state.secondary_sprite_zero = false;
+                self.screen_state.eval_sprite_index = 0;
+                self.screen_state.eval_sprite_byte = 0;
+            },
+            66..=256 if dot.is_multiple_of(2) => self.sprite_evaluation_step(),
+            257 => self.latch_secondary_oam(),
+            _ => (),
+        }
+    }
 
-            match self.cycles {
-                256 => {
-                    self.increment_scroll_y();
-                },
-                257 => {
-                    self.load_background_shift();
-                    self.transfer_address_x();
-
-                    if self.scanline >= 0 {
-                        self.internal_oam.fill(0xFF);
-                        self.screen_state.sprite_count = 0;
-                        self.screen_state.sprite_zero_occured = false;
-                        self.screen_state.sprite_shift_pattern_lo.fill(0);
-                        self.screen_state.sprite_shift_pattern_hi.fill(0);
-
-                        self.bus
-                            .borrow_mut()
-                            .ppu_memory_map()
-                            .get_oam()
-                            .chunks(4)
-                            .enumerate()
-                            .for_each(|(index, sprite)| {
-                                let sprite_count = self.screen_state.sprite_count;
-
-                                if sprite_count < 9 {
-                                    let diff = self.scanline - sprite[0] as isize;
-
-                                    // TODO: 8x16 sprites
-                                    if (0..8).contains(&diff) && sprite_count < 8 {
-                                        if index == 0 {
-                                            self.screen_state.sprite_zero_occured = true;
-                                        }
-
-                                        let internal_index = sprite_count as usize * 4;
-                                        self.internal_oam[internal_index..internal_index + 4].copy_from_slice(sprite);
-                                        self.screen_state.sprite_count += 1;
-                                    }
-                                }
-                            });
-
-                        self.status.set_flag(
-                            PpuStatusRegisterFlags::SpriteOverflow,
-                            self.screen_state.sprite_count > 8
-                        );
-                    }
-                },
-                280..=304 if self.scanline == -1 => {
-                    self.transfer_address_y();
-                },
-                cycles @ (338 | 340) => {
-                    self.read_tile_id();
-
-                    if cycles == 340 && self.scanline >= 0 {
-                        let sprite_count = self.screen_state.sprite_count as usize;
-                        let sprite_pattern_table = if self.controller.get_flag(PpuControllerRegisterFlags::SpritesPatternTable) {
-                            1u16
-                        } else {
-                            0u16
-                        };
+    // One OAM read and secondary OAM write, which takes two dots on hardware
+    fn sprite_evaluation_step(&mut self) {
+        let index = self.screen_state.eval_sprite_index as usize;
+        let byte = self.screen_state.eval_sprite_byte as usize;
 
-                        for (index, sprite) in self.internal_oam.chunks(4).take(sprite_count).enumerate() {
-                            let pattern_address_lo = if sprite[2] & 0x80 != 0x80 {
-                                (sprite_pattern_table << 12) |
-                                ((sprite[1] as u16) << 4) |
-                                (self.scanline - sprite[0] as isize) as u16
-                            } else {
-                                (sprite_pattern_table << 12) |
-                                ((sprite[1] as u16) << 4) |
-                                (7 - (self.scanline - sprite[0] as isize) as u16)
-                            };
-
-                            let pattern_address_hi = pattern_address_lo + 8;
-                            let mut pattern_bits_lo = self.read(pattern_address_lo);
-                            let mut pattern_bits_hi = self.read(pattern_address_hi);
-
-                            if sprite[2] & 0x40 == 0x40 {
-                                let flip_byte = |mut b| {
-                                    b = (b & 0xF0) >> 4 | (b & 0x0F) << 4;
-                                    b = (b & 0xCC) >> 2 | (b & 0x33) << 2;
-                                    b = (b & 0xAA) >> 1 | (b & 0x55) << 1;
-                                    b
-                                };
-
-                                pattern_bits_lo = flip_byte(pattern_bits_lo);
-                                pattern_bits_hi = flip_byte(pattern_bits_hi);
-                            }
-
-                            self.screen_state.sprite_shift_pattern_lo[index] = pattern_bits_lo;
-                            self.screen_state.sprite_shift_pattern_hi[index] = pattern_bits_hi;
-                        }
-                    }
-                },
-                _ => (),
+        if index >= 64 {
+            return;
+        }
+
+        let value = self.bus.borrow_mut().ppu_memory_map().get_oam()[index * 4 + byte];
+        let is_in_range = (0..self.sprite_height()).contains(&(self.scanline - value as isize));
+        let sprite_count = self.screen_state.secondary_sprite_count as usize;
+
+        if sprite_count < 8 {
+            if byte == 0 && !is_in_range {
+                self.screen_state.eval_sprite_index += 1;
+                return;
+            }
+
+            self.screen_state.secondary_oam[sprite_count * 4 + byte] = value;
+            self.screen_state.secondary_sprite_zero |= index == 0;
+            self.screen_state.secondary_sprite_indices[sprite_count] = index as u8;
+
+            if byte == 3 {
+                self.screen_state.eval_sprite_index += 1;
+                self.screen_state.eval_sprite_byte = 0;
+                self.screen_state.secondary_sprite_count += 1;
+            } else {
+                self.screen_state.eval_sprite_byte += 1;
+            }
+        } else if is_in_range {
+            // Overflow can be set falsely or missed, once set it stays until the pre-render scanline
+            self.status.set_flag(PpuStatusRegisterFlags::SpriteOverflow, true);
+            self.screen_state.eval_sprite_index = 64;
+        } else {
+            // With secondary OAM full the PPU keeps looking for a ninth sprite, but a missing
+            // carry makes it step the byte within each entry along with the entry, so tile,
+            // attribute and X bytes get compared as Y
+            self.screen_state.eval_sprite_index += 1;
+            self.screen_state.eval_sprite_byte = (self.screen_state.eval_sprite_byte + 1) % 4;
+        }
+    }
+
+    fn latch_secondary_oam(&mut self) {
+        self.internal_oam = self.screen_state.secondary_oam;
+        self.screen_state.sprite_count = self.screen_state.secondary_sprite_count;
+        self.screen_state.sprite_zero_occured = self.screen_state.secondary_sprite_zero;
+        self.screen_state.sprite_indices = self.screen_state.secondary_sprite_indices;
+        self.screen_state.sprite_shift_pattern_lo.fill(0);
+        self.screen_state.sprite_shift_pattern_hi.fill(0);
+
+        // Sprites found here are drawn on the next scanline
+        if self.screen_state.sprite_zero_occured {
+            let x = self.internal_oam[3] as usize;
+
+            for x in x..x + 8 {
+                self.mark_debug_pixel(x, self.scanline + 1, DebugMaskFlags::SpriteZeroArea);
             }
         }
+    }
 
-        self.update_vblank();
+    // Each of the eight sprite slots gets its patterns over 8 dots in 257-320
+    fn sprite_fetch(&mut self, dot: usize) {
+        if !(257..=320).contains(&dot) || (dot - 257) % 8 != 7 {
+            return;
+        }
 
-        let mut bg_pixel = 0u8;
-        let mut bg_palette = 0u8;
+        let index = (dot - 257) / 8;
 
+        let sprite_pattern_table = if self.controller.get_flag(PpuControllerRegisterFlags::SpritesPatternTable) {
+            1u16
+        } else {
+            0u16
+        };
+
+        let sprite_height = self.sprite_height() as u16;
+
+        // Empty slots still fetch tile $FF, which mappers watching A12 rely on
+        if index >= self.screen_state.sprite_count as usize {
+            let pattern_table = if sprite_height == 16 { 1 } else { sprite_pattern_table };
+            self.watch_a12((pattern_table << 12) | 0x0FF0);
+            return;
+        }
+
+        let sprite: [u8; 4] = self.internal_oam[index * 4..index * 4 + 4].try_into().unwrap();
+        let row = (self.scanline - sprite[0] as isize) as u16;
+
+        // Flipping a 8x16 sprite vertically also swaps its two tiles
+        let row = if sprite[2] & 0x80 != 0x80 {
+            row
+        } else {
+            sprite_height - 1 - row
+        };
+
+        // 8x16 sprites take the pattern table from bit 0 of the tile index
+        // and use the next tile for their bottom half
+        let (pattern_table, tile) = if sprite_height == 16 {
+            ((sprite[1] & 0x01) as u16, (sprite[1] & 0xFE) as u16 + (row >> 3))
+        } else {
+            (sprite_pattern_table, sprite[1] as u16)
+        };
+
+        let pattern_address_lo = (pattern_table << 12) | (tile << 4) | (row & 0x07);
+
+        let pattern_address_hi = pattern_address_lo + 8;
+        let mut pattern_bits_lo = self.read_pattern(pattern_address_lo);
+        let mut pattern_bits_hi = self.read_pattern(pattern_address_hi);
+
+        if sprite[2] & 0x40 == 0x40 {
+            let flip_byte = |mut b| {
+                b = (b & 0xF0) >> 4 | (b & 0x0F) << 4;
+                b = (b & 0xCC) >> 2 | (b & 0x33) << 2;
+                b = (b & 0xAA) >> 1 | (b & 0x55) << 1;
+                b
+            };
+
+            pattern_bits_lo = flip_byte(pattern_bits_lo);
+            pattern_bits_hi = flip_byte(pattern_bits_hi);
+        }
+
+        self.screen_state.sprite_shift_pattern_lo[index] = pattern_bits_lo;
+        self.screen_state.sprite_shift_pattern_hi[index] = pattern_bits_hi;
+    }
+
+    fn compose_pixel(&mut self) -> (u8, u8) {
         let show_background = self.mask.get_flag(PpuMaskRegisterFlags::ShowBackground);
         let show_sprites = self.mask.get_flag(PpuMaskRegisterFlags::ShowSprites);
+        let (draw_background, draw_sprites) = self.drawn_layers();
 
-        if show_background {
-            let bit_mux = 0x8000 >> self.fine_x;
+        let bit_mux = 0x8000 >> self.fine_x;
 
-            let p0_pixel = (self.screen_state.bg_shift_pattern_lo & bit_mux) > 0;
-            let p1_pixel = (self.screen_state.bg_shift_pattern_hi & bit_mux) > 0;
+        let p0_pixel = (self.screen_state.bg_shift_pattern_lo & bit_mux) > 0;
+        let p1_pixel = (self.screen_state.bg_shift_pattern_hi & bit_mux) > 0;
 
-            bg_pixel = (u8::from(p1_pixel) << 1) | u8::from(p0_pixel);
+        let mut bg_pixel = (u8::from(p1_pixel) << 1) | u8::from(p0_pixel);
 
-            let bg_palette0 = (self.screen_state.bg_shift_attribute_lo & bit_mux) > 0;
-            let bg_palette1 = (self.screen_state.bg_shift_attribute_hi & bit_mux) > 0;
+        let bg_palette0 = (self.screen_state.bg_shift_attribute_lo & bit_mux) > 0;
+        let bg_palette1 = (self.screen_state.bg_shift_attribute_hi & bit_mux) > 0;
 
-            bg_palette = (u8::from(bg_palette1) << 1) | u8::from(bg_palette0);
-        }
+        let bg_palette = (u8::from(bg_palette1) << 1) | u8::from(bg_palette0);
 
         let mut fg_pixel = 0u8;
         let mut fg_palette = 0u8;
         let mut fg_priority = false;
 
-        if show_sprites {
-            self.screen_state.sprite_zero_rendering = false;
-
-            let sprite_count = self.screen_state.sprite_count as usize;
+        self.screen_state.sprite_zero_rendering = false;
 
-            for (index, sprite) in self.internal_oam.chunks(4).take(sprite_count).enumerate() {
-                if sprite[3] == 0 {
-                    let pattern_lo = self.screen_state.sprite_shift_pattern_lo[index];
-                    let pattern_hi = self.screen_state.sprite_shift_pattern_hi[index];
+        let sprite_count = self.screen_state.sprite_count as usize;
 
-                    let fg_pixel_lo = u8::from((pattern_lo & 0x80) > 0);
-                    let fg_pixel_hi = u8::from((pattern_hi & 0x80) > 0);
+        for (index, sprite) in self.internal_oam.chunks(4).take(sprite_count).enumerate() {
+            if sprite[3] == 0 {
+                let pattern_lo = self.screen_state.sprite_shift_pattern_lo[index];
+                let pattern_hi = self.screen_state.sprite_shift_pattern_hi[index];
 
-                    fg_pixel = (u8::from(fg_pixel_hi) << 1) | u8::from(fg_pixel_lo);
+                let fg_pixel_lo = u8::from((pattern_lo & 0x80) > 0);
+                let fg_pixel_hi = u8::from((pattern_hi & 0x80) > 0);
 
-                    fg_palette = (sprite[2] & 0x03) + 0x04;
-                    fg_priority = (sprite[2] & 0x20) == 0;
+                fg_pixel = (fg_pixel_hi << 1) | fg_pixel_lo;
 
-                    if fg_pixel != 0 {
-                        if index == 0 {
-                            self.screen_state.sprite_zero_rendering = true;
-                        }
+                fg_palette = (sprite[2] & 0x03) + 0x04;
+                fg_priority = (sprite[2] & 0x20) == 0;
 
-                        break;
+                if fg_pixel != 0 {
+                    if index == 0 {
+                        self.screen_state.sprite_zero_rendering = true;
                     }
+
+                    break;
                 }
             }
         }
 
-        let is_sprite_zero_hit = 
-            self.screen_state.sprite_zero_occured &&
-            self.screen_state.sprite_zero_rendering;
+        // Leftmost 8 pixels are transparent for the layers whose leftmost flag is clear
+        let x = self.cycles as isize - 1;
+        let is_leftmost = (0..8).contains(&x);
 
-        let is_showing_leftmost = !(
-            self.mask.get_flag(PpuMaskRegisterFlags::ShowBackgroundLeftmost) |
-            self.mask.get_flag(PpuMaskRegisterFlags::ShowSpritesLeftmost)
-        );
+        if is_leftmost && !self.mask.get_flag(PpuMaskRegisterFlags::ShowBackgroundLeftmost) {
+            bg_pixel = 0;
+        }
+
+        if is_leftmost && !self.mask.get_flag(PpuMaskRegisterFlags::ShowSpritesLeftmost) {
+            fg_pixel = 0;
+            self.screen_state.sprite_zero_rendering = false;
+        }
+
+        let had_sprite_zero_hit = self.status.get_flag(PpuStatusRegisterFlags::SpriteZeroHit);
+
+        // Only the first opaque overlap of the frame counts, hits follow the layers the game
+        // enabled whatever the debug overrides draw. Clipping keeps hits out of the leftmost
+        // pixels and the last column never hits
+        let is_sprite_zero_hit = !had_sprite_zero_hit
+            && self.screen_state.sprite_zero_occured
+            && self.screen_state.sprite_zero_rendering
+            && bg_pixel != 0
+            && show_background
+            && show_sprites
+            && (0..255).contains(&x);
+
+        if is_sprite_zero_hit {
+            self.status.set_flag(PpuStatusRegisterFlags::SpriteZeroHit, true);
+        }
+
+        if !draw_background {
+            bg_pixel = 0;
+        }
+
+        if !draw_sprites {
+            fg_pixel = 0;
+        }
 
         let (pixel, palette) = match (bg_pixel, fg_pixel) {
             (0, 0) => (0x00, 0x00),
             (0, 1..=3) => (fg_pixel, fg_palette),
             (1..=3, 0) => (bg_pixel, bg_palette),
             (1..=3, 1..=3) => {
-                if is_sprite_zero_hit && show_background && show_sprites {
-                    if is_showing_leftmost {
-                        if self.cycles >= 9 && self.cycles < 258 {
-                            self.status.set_flag(PpuStatusRegisterFlags::SpriteZeroHit, true);
-                        }
-                    } else if self.cycles >= 1 && self.cycles < 258 {
-                        self.status.set_flag(PpuStatusRegisterFlags::SpriteZeroHit, true);
-                    }
-                }
                 if fg_priority {
                     (fg_pixel, fg_palette)
                 } else {
@@ -498,44 +756,405 @@ impl Ppu {
             _ => panic!("Invalid pixel data!"),
         };
 
-        let pixel_color = self.read(0x3F00 + ((palette << 2) + pixel) as u16);
+        if self.debug_mask.is_some() && self.cycles >= 1 {
+            let (x, y) = (self.cycles - 1, self.scanline);
+
+            if fg_pixel != 0 && (bg_pixel == 0 || fg_priority) {
+                self.mark_debug_pixel(x, y, DebugMaskFlags::SpritePixel);
+            }
+
+            if is_sprite_zero_hit {
+                self.mark_debug_pixel(x, y, DebugMaskFlags::SpriteZeroHit);
+            }
+        }
+
+        (pixel, palette)
+    }
 
-        self.screen_buffer.set_pixel(self.cycles - 1, self.scanline as usize, pixel_color);
+    fn advance_counters(&mut self) {
+        // NTSC odd frames drop the last dot of the pre-render scanline while the background is shown
+        let skips_dot = self.scanline == -1
+            && self.region == Region::Ntsc
+            && self.odd_frame
+            && self.mask.get_flag(PpuMaskRegisterFlags::ShowBackground);
+        let scanline_length = if skips_dot { 340 } else { 341 };
 
-        if self.cycles >= 341 {
+        if self.cycles >= scanline_length {
             self.cycles = 0;
             self.scanline += 1;
 
-            if self.scanline >= 261 {
+            if self.scanline >= self.region.scanlines() - 1 + self.extra_vblank_scanlines as isize {
                 self.scanline = -1;
+                self.frame += 1;
+                self.odd_frame = !self.odd_frame;
+                self.frame_complete = true;
+            }
+        }
+    }
+
+    // OAMADDR is held at 0 while sprite patterns are fetched on rendered scanlines
+    fn reset_oam_address(&mut self, dot: usize) {
+        let is_rendering = self.mask.get_flag(PpuMaskRegisterFlags::ShowBackground)
+            || self.mask.get_flag(PpuMaskRegisterFlags::ShowSprites);
+
+        if is_rendering && (257..=320).contains(&dot) {
+            self.oamaddress.set(0);
+        }
+    }
+
+    pub fn tick(&mut self, amount: usize) {
+        for _ in 0..amount {
+            self.step_dot();
+        }
+    }
+
+    fn step_dot(&mut self) {
+        self.cycles += 1;
+        self.a12_low_dots = self.a12_low_dots.saturating_add(1);
+
+        if self.scanline >= -1 && self.scanline < 240 {
+            self.reset_vblank();
+
+            let dot = self.cycles;
+            self.background_pipeline(dot);
+            self.sprite_evaluation(dot);
+            self.sprite_fetch(dot);
+            self.reset_oam_address(dot);
+        }
+
+        self.update_vblank();
+
+        if (0..240).contains(&self.scanline) && (1..=256).contains(&self.cycles) {
+            let is_rendering = self.mask.get_flag(PpuMaskRegisterFlags::ShowBackground)
+                || self.mask.get_flag(PpuMaskRegisterFlags::ShowSprites);
+
+            // With rendering off the backdrop is shown, or the palette entry v points at
+            let palette_address = match self.vram.get() {
+                _ if is_rendering => {
+                    let (pixel, palette) = self.compose_pixel();
+                    0x3F00 + ((palette << 2) + pixel) as u16
+                },
+                address @ 0x3F00..=0x3FFF => address,
+                _ => 0x3F00,
+            };
+
+            let pixel_color = self.read(palette_address) & self.palette_mask();
+
+            let emphasis = self.mask.get() >> 5;
+
+            self.screen_buffer.set_pixel(self.cycles - 1, self.scanline as usize, pixel_color, emphasis);
+        }
+
+        self.advance_counters();
+    }
+
+    // Greyscale keeps only the brightness column of the palette
+    fn palette_mask(&self) -> u8 {
+        if self.mask.get_flag(PpuMaskRegisterFlags::Greyscale) {
+            0x30
+        } else {
+            0x3F
+        }
+    }
+
+    pub fn get_cycles(&self) -> usize {
+        self.cycles
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    pub fn get_region(&self) -> Region {
+        self.region
+    }
+
+    /// Overclocks the console by adding idle scanlines at the end of VBlank, giving
+    /// lagging NMI handlers more time. NMI still fires on scanline 241, but games that
+    /// time raster effects by counting CPU cycles will break.
+    pub fn set_extra_vblank_scanlines(&mut self, scanlines: usize) {
+        if scanlines > MAX_EXTRA_VBLANK_SCANLINES {
+            panic!("Extra VBlank scanlines should be between 0 and {}!", MAX_EXTRA_VBLANK_SCANLINES);
+        }
+
+        self.extra_vblank_scanlines = scanlines;
+    }
+
+    pub fn get_extra_vblank_scanlines(&self) -> usize {
+        self.extra_vblank_scanlines
+    }
+
+    pub fn get_scanline(&self) -> isize {
+        self.scanline
+    }
+
+    pub fn get_frame(&self) -> u64 {
+        self.frame
+    }
+
+    // Debug mask has one `DebugMaskFlags` byte per pixel of the screen buffer
+    pub fn set_debug_mask(&mut self, enabled: bool) {
+        self.debug_mask = enabled.then(|| vec![0; 256 * 240]);
+    }
+
+    /// Forces the background or sprite layer off with `Some(false)` or on with `Some(true)` when
+    /// drawing, `None` follows PPUMASK. The game keeps reading its own mask and sprite zero hits.
+    pub fn set_layer_override(&mut self, background: Option<bool>, sprites: Option<bool>) {
+        self.background_override = background;
+        self.sprites_override = sprites;
+    }
+
+    pub fn get_debug_mask(&self) -> Option<&[u8]> {
+        self.debug_mask.as_deref()
+    }
+
+    /// Decodes the four nametables at $2000-$2FFF to palette indices with the selected background
+    /// pattern table, through the current mirroring. Live PPU state is left untouched.
+    pub fn debug_render_nametables(&self) -> [[u8; 256 * 240]; 4] {
+        let bg_pattern_table = if self.controller.get_flag(PpuControllerRegisterFlags::BackgroundPatternTable) {
+            0x1000u16
+        } else {
+            0x0000u16
+        };
+
+        let mut nametables = [[0; 256 * 240]; 4];
+
+        for (index, pixels) in nametables.iter_mut().enumerate() {
+            let base = 0x2000 + index as u16 * 0x400;
+
+            for (tile_y, tile_x) in (0..30u16).flat_map(|y| (0..32u16).map(move |x| (y, x))) {
+                let tile_id = self.read(self.mirror_address(base + tile_y * 32 + tile_x));
+                let attribute = self.read(self.mirror_address(base + 0x3C0 + (tile_y >> 2) * 8 + (tile_x >> 2)));
+                let palette = (attribute >> (((tile_y & 0b10) << 1) | (tile_x & 0b10))) & 0b11;
+
+                for row in 0..8u16 {
+                    let pattern_address = bg_pattern_table + ((tile_id as u16) << 4) + row;
+                    let pattern_lo = self.read(pattern_address);
+                    let pattern_hi = self.read(pattern_address + 8);
+
+                    for column in 0..8u16 {
+                        let pixel = (((pattern_hi << column) & 0x80) >> 6) | (((pattern_lo << column) & 0x80) >> 7);
+                        let palette_address = match pixel {
+                            0 => 0x3F00,
+                            _ => 0x3F00 + ((palette << 2) | pixel) as u16,
+                        };
+
+                        let (x, y) = ((tile_x * 8 + column) as usize, (tile_y * 8 + row) as usize);
+                        pixels[y * 256 + x] = self.read(palette_address) & self.palette_mask();
+                    }
+                }
             }
         }
+
+        nametables
+    }
+
+    /// Decodes all 64 OAM entries, marking the ones picked by the last sprite evaluation.
+    pub fn debug_sprites(&self) -> [SpriteInfo; 64] {
+        let oam = *self.bus.borrow_mut().ppu_memory_map().get_oam();
+        let selected = &self.screen_state.sprite_indices[..self.screen_state.sprite_count as usize];
+        let mut sprites = [SpriteInfo::default(); 64];
+
+        for (index, (sprite, entry)) in sprites.iter_mut().zip(oam.chunks(4)).enumerate() {
+            *sprite = SpriteInfo {
+                x: entry[3],
+                y: entry[0],
+                tile: entry[1],
+                palette: entry[2] & 0x03,
+                behind_background: entry[2] & 0x20 != 0,
+                flip_h: entry[2] & 0x40 != 0,
+                flip_v: entry[2] & 0x80 != 0,
+                in_range: selected.contains(&(index as u8)),
+            };
+        }
+
+        sprites
+    }
+
+    /// Colors of one of the four palettes as [backdrop, c1, c2, c3]. Entry 0 of every palette
+    /// shows the backdrop at $3F00, the sprite ones alias it through $3F10.
+    pub fn debug_palette(&self, kind: PaletteKind, index: u8) -> [u8; 4] {
+        let base = match kind {
+            PaletteKind::Background => 0x3F00,
+            PaletteKind::Sprite => 0x3F10,
+        } + (index as u16 & 0b11) * 4;
+
+        [
+            self.read(0x3F00),
+            self.read(base + 1),
+            self.read(base + 2),
+            self.read(base + 3),
+        ]
+    }
+
+    /// Base nametable selected through PPUCTRL, which lives in the nametable bits of t.
+    pub fn get_nametable_index(&self) -> usize {
+        ((self.vram_temp.get_nametable_y() << 1) | self.vram_temp.get_nametable_x()) as usize
+    }
+
+    /// Tile ids and attributes of nametable `index`, read through the current mirroring.
+    pub fn get_nametable(&self, index: usize) -> [u8; 0x400] {
+        let base = 0x2000 + (index as u16 & 0b11) * 0x400;
+        let mut nametable = [0; 0x400];
+
+        for (offset, byte) in nametable.iter_mut().enumerate() {
+            *byte = self.read(self.mirror_address(base + offset as u16));
+        }
+
+        nametable
+    }
+
+    /// Raw contents of pattern table `table`, 0 for $0000 and 1 for $1000.
+    pub fn get_pattern_table(&self, table: usize) -> [u8; 0x1000] {
+        let base = (table as u16 & 0b1) << 12;
+        let mut pattern_table = [0; 0x1000];
+
+        for (offset, byte) in pattern_table.iter_mut().enumerate() {
+            *byte = self.read(base + offset as u16);
+        }
+
+        pattern_table
+    }
+
+    /// Snapshot of everything the PPU needs to resume mid-frame, including VRAM, palette
+    /// RAM and OAM. Region, overclocking and debug settings are configuration and stay put.
+    pub fn save_state(&self) -> PpuState {
+        PpuState {
+            controller: self.controller.get(),
+            mask: self.mask.get(),
+            status: self.status.get(),
+            oamaddress: self.oamaddress.get(),
+            vram: self.vram.get_internal(),
+            vram_temp: self.vram_temp.get_internal(),
+            fine_x: self.fine_x,
+            address_latch: self.address_latch,
+            internal_buf: self.internal_buf,
+            io_latch: self.io_latch,
+            io_latch_refreshed: self.io_latch_refreshed,
+            vblank_suppressed: self.vblank_suppressed,
+            a12_low_dots: self.a12_low_dots,
+            screen_state: self.screen_state.clone(),
+            screen_buffer: self.screen_buffer.clone(),
+            internal_oam: self.internal_oam,
+            cycles: self.cycles,
+            scanline: self.scanline,
+            frame: self.frame,
+            odd_frame: self.odd_frame,
+            frame_complete: self.frame_complete,
+            mirroring: self.mirroring,
+            memory: self.bus.borrow_mut().ppu_memory_map().save_state(),
+        }
+    }
+
+    /// Restores a `save_state` snapshot and drives the NMI line to match it.
+    pub fn restore_state(&mut self, state: &PpuState) {
+        self.controller.set(state.controller);
+        self.mask.set(state.mask);
+        self.status.set(state.status);
+        self.oamaddress.set(state.oamaddress);
+        self.vram.set_internal(state.vram);
+        self.vram_temp.set_internal(state.vram_temp);
+        self.fine_x = state.fine_x;
+        self.address_latch = state.address_latch;
+        self.internal_buf = state.internal_buf;
+        self.io_latch = state.io_latch;
+        self.io_latch_refreshed = state.io_latch_refreshed;
+        self.vblank_suppressed = state.vblank_suppressed;
+        self.a12_low_dots = state.a12_low_dots;
+        self.screen_state = state.screen_state.clone();
+        self.screen_buffer = state.screen_buffer.clone();
+        self.internal_oam = state.internal_oam;
+        self.cycles = state.cycles;
+        self.scanline = state.scanline;
+        self.frame = state.frame;
+        self.odd_frame = state.odd_frame;
+        self.frame_complete = state.frame_complete;
+        self.mirroring = state.mirroring;
+        self.bus.borrow_mut().ppu_memory_map().restore_state(&state.memory);
+        self.update_nmi_line();
     }
 
     pub fn get_screen_buffer(&self) -> &ScreenBuffer {
         &self.screen_buffer
     }
 
+    pub fn bus(&self) -> &Rc<RefCell<Bus>> {
+        &self.bus
+    }
+
+    /// Returns whether a frame finished since the last call, clearing the signal.
+    pub fn take_frame_complete(&mut self) -> bool {
+        std::mem::take(&mut self.frame_complete)
+    }
+
     pub fn has_interrupt(&self) -> bool {
         self.bus.borrow().get_interrupt().is_some()
     }
 
+    /// Folds a nametable address onto the tables backed by VRAM, four-screen uses all 4 KiB unmirrored.
     pub fn mirror_address(&self, address: u16) -> u16 {
+        let mirroring = self.bus.borrow().current_mirroring().unwrap_or(self.mirroring);
         let nametable_index = (address - 0x2000) / 0x400;
-        match (self.mirroring, nametable_index) {
-            (Mirroring::Horizontal, 1) | (Mirroring::Horizontal, 3) => address - 0x400,
+        match (mirroring, nametable_index) {
+            // Both CIRAM pages sit at $2000 and $2400 in every mode, so switching keeps their contents
+            (Mirroring::Horizontal, 1) | (Mirroring::Horizontal, 2) => address - 0x400,
+            (Mirroring::Horizontal, 3) => address - 0x800,
             (Mirroring::Vertical, 2) | (Mirroring::Vertical, 3) => address - 0x800,
-            (Mirroring::FourScreen, _) => todo!("Add Four screen mirroring!"),
+            (Mirroring::SingleScreenLow, _) => 0x2000 | (address & 0x03FF),
+            (Mirroring::SingleScreenHigh, _) => 0x2400 | (address & 0x03FF),
             _ => address,
         }
     }
 
+    /// Reads the register at `address` in $2000-$2007, write-only registers return the I/O latch.
+    pub fn read_register(&mut self, address: u16) -> u8 {
+        match address & 0x0007 {
+            0x0002 => self.read_status(),
+            0x0004 => self.read_oamdata(),
+            0x0007 => self.read_data(),
+            _ => self.io_latch(),
+        }
+    }
+
+    /// Writes the register at `address` in $2000-$2007, every write also fills the I/O latch.
+    pub fn write_register(&mut self, address: u16, data: u8) {
+        self.refresh_io_latch(data, 0xFF);
+
+        match address & 0x0007 {
+            0x0000 => self.write_controller(data),
+            0x0001 => self.write_mask(data),
+            0x0003 => self.write_oamaddress(data),
+            0x0004 => self.write_oamdata(data),
+            0x0005 => self.write_scroll(data),
+            0x0006 => self.write_address(data),
+            0x0007 => self.write_data(data),
+            _ => (),
+        }
+    }
+
+    fn io_latch(&self) -> u8 {
+        (0..8)
+            .filter(|bit| self.frame < self.io_latch_refreshed[*bit] + IO_LATCH_DECAY_FRAMES)
+            .fold(0, |latch, bit| latch | (self.io_latch & (1 << bit)))
+    }
+
+    // Only the bits in `mask` are driven, the others keep decaying
+    fn refresh_io_latch(&mut self, value: u8, mask: u8) {
+        for bit in (0..8).filter(|bit| mask & (1 << bit) != 0) {
+            self.io_latch_refreshed[bit] = self.frame;
+        }
+
+        self.io_latch = (self.io_latch & !mask) | (value & mask);
+    }
+
+    // Enabling NMI generation during VBlank raises another NMI
     pub fn write_controller(&mut self, data: u8) {
         self.controller.set(data);
+        self.update_nmi_line();
+
+        let nametable_x = data & 0b1;
+        let nametable_y = (data >> 1) & 0b1;
 
-        let nametable_x = data;
-        let nametable_y = data >> 1;
-        
         self.vram_temp.set_nametable_x(nametable_x as u16);
         self.vram_temp.set_nametable_y(nametable_y as u16);
     }
@@ -583,9 +1202,10 @@ impl Ppu {
 
     pub fn write_address(&mut self, data: u8) {
         match self.address_latch {
+            // First write sets bits 8-13 of t and clears bit 14
             false => {
                 let [lo, _] = self.vram_temp.get().to_le_bytes();
-                let vram = u16::from_le_bytes([lo, data]);
+                let vram = u16::from_le_bytes([lo, data & 0x3F]);
 
                 self.vram_temp.set(vram);
                 self.address_latch = true;
@@ -620,22 +1240,51 @@ impl Ppu {
         });
     }
 
+    // Only bits 7-5 are driven, the rest comes from the I/O latch
     pub fn read_status(&mut self) -> u8 {
-        let result = (self.status.get() & 0xE0) | (self.internal_buf.unwrap_or(0) & 0x1F);
+        // Reading as VBlank is about to be set reads it clear and skips it for the frame,
+        // reading in the two dots after it was set still cancels the NMI
+        if self.scanline == 241 {
+            match self.cycles {
+                0 => self.vblank_suppressed = true,
+                1 | 2 => self.bus.borrow_mut().set_interrupt(None),
+                _ => (),
+            }
+        }
+
+        let result = (self.status.get() & 0xE0) | (self.io_latch() & 0x1F);
+        self.refresh_io_latch(result, 0xE0);
 
         self.status.set_flag(PpuStatusRegisterFlags::VBlank, false);
+        self.update_nmi_line();
         self.address_latch = false;
 
         result
     }
 
+    // Attribute bytes have no bits 2-4. While secondary OAM is being cleared on
+    // visible scanlines, reads see the $FF written there
     pub fn read_oamdata(&mut self) -> u8 {
-        *self.bus
-            .borrow_mut()
-            .ppu_memory_map()
-            .get_oam()
-            .get(self.oamaddress.get() as usize)
-            .expect("Unable to read from OAM!")
+        let is_rendering = self.mask.get_flag(PpuMaskRegisterFlags::ShowBackground)
+            || self.mask.get_flag(PpuMaskRegisterFlags::ShowSprites);
+
+        let value = if is_rendering && (0..240).contains(&self.scanline) && (1..=64).contains(&self.cycles) {
+            0xFF
+        } else {
+            let address = self.oamaddress.get();
+            let value = self.bus
+                .borrow_mut()
+                .ppu_memory_map()
+                .get_oam()[address as usize];
+
+            match address % 4 {
+                2 => value & 0xE3,
+                _ => value,
+            }
+        };
+
+        self.refresh_io_latch(value, 0xFF);
+        value
     }
 
     pub fn read_data(&mut self) -> u8 {
@@ -657,10 +1306,20 @@ impl Ppu {
         match address {
             0x0000..=0x3EFF => {
                 self.internal_buf = Some(self.read(read_address));
+                self.refresh_io_latch(internal_buf, 0xFF);
 
                 internal_buf
             }
-            _ => self.read(read_address),
+            // Palette entries have 6 bits, the top two come from the I/O latch. The buffer
+            // still picks up the nametable byte that sits under the palette at $2Fxx
+            _ => {
+                self.internal_buf = Some(self.read(self.mirror_address(address - 0x1000)));
+
+                let color = self.read(read_address) & self.palette_mask();
+                self.refresh_io_latch(color, 0x3F);
+
+                self.io_latch()
+            },
         }
     }
 }
@@ -680,3 +1339,1341 @@ impl Memory for Ppu {
             .write(address, data);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use crate::core::cartridge::Cartridge;
+    use crate::core::frontend::Frame;
+    use crate::core::mappers::Mapper;
+    use super::*;
+
+    const DOTS_PER_FRAME: usize = 341 * 262;
+
+    fn create_ppu() -> Ppu {
+        let cartridge = Cartridge::empty();
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+
+        Ppu::new(&bus, cartridge.get_mirroring())
+    }
+
+    fn write_vram(ppu: &mut Ppu, address: u16, data: &[u8]) {
+        let [lo, hi] = address.to_le_bytes();
+
+        ppu.write_address(hi);
+        ppu.write_address(lo);
+        data.iter().for_each(|byte| ppu.write_data(*byte));
+    }
+
+    fn load_background_scene(ppu: &mut Ppu) {
+        write_vram(ppu, 0x0010, &[0xF0, 0x0F, 0xF0, 0x0F, 0xFF, 0x00, 0x81, 0x42]);
+        write_vram(ppu, 0x0018, &[0x3C, 0x3C, 0xC3, 0xC3, 0x00, 0xFF, 0x18, 0x24]);
+        write_vram(ppu, 0x0020, &[0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55]);
+        write_vram(ppu, 0x0028, &[0x0F, 0x0F, 0x0F, 0x0F, 0xF0, 0xF0, 0xF0, 0xF0]);
+
+        let tiles = (0..0x3C0).map(|index| ((index % 32 + index / 32) % 3) as u8).collect::<Vec<_>>();
+        let attributes = (0..0x40).map(|index| (index as u8).wrapping_mul(0x1B)).collect::<Vec<_>>();
+        let palette = (0..0x20).map(|index| (index * 3 % 0x40) as u8).collect::<Vec<_>>();
+
+        write_vram(ppu, 0x2000, &tiles);
+        write_vram(ppu, 0x23C0, &attributes);
+        write_vram(ppu, 0x3F00, &palette);
+
+        ppu.write_scroll(3);
+        ppu.write_scroll(5);
+        ppu.write_mask(0x0A);
+    }
+
+    fn load_sprite_scene(ppu: &mut Ppu) {
+        load_background_scene(ppu);
+
+        let mut oam = [0xFF; 0x100];
+        for (index, sprite) in oam.chunks_mut(4).take(16).enumerate() {
+            sprite[0] = (index * 13 + 4) as u8;
+            sprite[1] = (index % 2 + 1) as u8;
+            sprite[2] = ((index as u8) & 0x03) | ((index as u8) << 4 & 0xE0);
+            sprite[3] = (index * 29 + 2) as u8;
+        }
+
+        ppu.bus.borrow_mut().ppu_memory_map().set_oam(&oam);
+        ppu.write_mask(0x1E);
+    }
+
+    fn render_frames(ppu: &mut Ppu, frames: usize) -> u64 {
+        for _ in 0..frames * DOTS_PER_FRAME {
+            ppu.tick(1);
+        }
+
+        Frame::from(ppu.get_screen_buffer()).hash()
+    }
+
+    #[test]
+    fn test_background_scene_frame_hash() {
+        let mut ppu = create_ppu();
+        load_background_scene(&mut ppu);
+
+        assert_eq!(render_frames(&mut ppu, 2), 0xD7E38713D35C22AD, "Background scene should render the recorded frame!");
+    }
+
+    #[test]
+    fn test_sprite_scene_frame_hash() {
+        let mut ppu = create_ppu();
+        load_sprite_scene(&mut ppu);
+
+        assert_eq!(render_frames(&mut ppu, 2), 0x2AEBB310CE3BC16D, "Sprite scene should render the recorded frame!");
+    }
+
+    #[test]
+    fn test_background_pipeline_loads_shifters() {
+        let mut ppu = create_ppu();
+        ppu.write_mask(0x08);
+        ppu.screen_state.bg_next_tile_lsb = 0xAB;
+        ppu.screen_state.bg_next_tile_msb = 0xCD;
+        ppu.screen_state.bg_next_tile_attribute = 0b10;
+        ppu.cycles = 257;
+
+        ppu.background_pipeline(257);
+
+        assert_eq!(ppu.screen_state.bg_shift_pattern_lo & 0xFF, 0xAB, "Low pattern shifter should be loaded!");
+        assert_eq!(ppu.screen_state.bg_shift_pattern_hi & 0xFF, 0xCD, "High pattern shifter should be loaded!");
+        assert_eq!(ppu.screen_state.bg_shift_attribute_lo & 0xFF, 0x00, "Low attribute shifter should be cleared!");
+        assert_eq!(ppu.screen_state.bg_shift_attribute_hi & 0xFF, 0xFF, "High attribute shifter should be set!");
+    }
+
+    fn evaluate_sprites(ppu: &mut Ppu) {
+        (1..=257).for_each(|dot| ppu.sprite_evaluation(dot));
+    }
+
+    fn fetch_sprites(ppu: &mut Ppu) {
+        (257..=320).for_each(|dot| ppu.sprite_fetch(dot));
+    }
+
+    #[test]
+    fn test_sprite_evaluation_selects_sprites_on_scanline() {
+        let mut ppu = create_ppu();
+        let mut oam = [0xFF; 0x100];
+        oam[0..4].copy_from_slice(&[9, 0x01, 0x00, 0x10]);
+        oam[4..8].copy_from_slice(&[40, 0x02, 0x00, 0x20]);
+        oam[8..12].copy_from_slice(&[5, 0x03, 0x00, 0x30]);
+        ppu.bus.borrow_mut().ppu_memory_map().set_oam(&oam);
+        ppu.scanline = 10;
+
+        evaluate_sprites(&mut ppu);
+
+        assert_eq!(ppu.screen_state.sprite_count, 2, "Two sprites should be on scanline 10!");
+        assert!(ppu.screen_state.sprite_zero_occured, "Sprite zero should be selected!");
+        assert_eq!(&ppu.internal_oam[0..8], &[9, 0x01, 0x00, 0x10, 5, 0x03, 0x00, 0x30], "Selected sprites should be copied in OAM order!");
+    }
+
+    // Runs sprite evaluation for scanline 10 with eight sprites on it followed by `rest`
+    fn sprite_overflow_with(rest: &[[u8; 4]]) -> bool {
+        let mut ppu = create_ppu();
+        let mut oam = [0xFF; 0x100];
+        for sprite in oam.chunks_mut(4).take(8) {
+            sprite.copy_from_slice(&[8, 0x00, 0x00, 0x00]);
+        }
+        for (sprite, bytes) in oam.chunks_mut(4).skip(8).zip(rest) {
+            sprite.copy_from_slice(bytes);
+        }
+
+        ppu.bus.borrow_mut().ppu_memory_map().set_oam(&oam);
+        ppu.scanline = 10;
+        evaluate_sprites(&mut ppu);
+
+        assert_eq!(ppu.screen_state.sprite_count, 8, "Only eight sprites should be selected!");
+        ppu.status.get_flag(PpuStatusRegisterFlags::SpriteOverflow)
+    }
+
+    #[test]
+    fn test_sprite_evaluation_runs_during_the_scanline() {
+        let mut ppu = create_ppu();
+        let mut oam = [0xFF; 0x100];
+        for sprite in oam.chunks_mut(4).take(9) {
+            sprite.copy_from_slice(&[8, 0x00, 0x00, 0x00]);
+        }
+        oam[0..4].copy_from_slice(&[8, 0x01, 0x02, 0x03]);
+
+        ppu.bus.borrow_mut().ppu_memory_map().set_oam(&oam);
+        ppu.scanline = 10;
+
+        ppu.tick(64);
+        assert_eq!(ppu.screen_state.secondary_oam, [0xFF; 0x20], "Secondary OAM should be cleared by dot 64!");
+        assert!(!ppu.status.get_flag(PpuStatusRegisterFlags::SpriteOverflow), "Overflow should not be set before evaluation!");
+
+        ppu.tick(192);
+        assert!(ppu.status.get_flag(PpuStatusRegisterFlags::SpriteOverflow), "Overflow should be set during evaluation!");
+        assert_eq!(ppu.screen_state.sprite_count, 0, "Sprites should not be handed over before dot 257!");
+
+        ppu.tick(1);
+        assert_eq!(ppu.screen_state.sprite_count, 8, "Eight sprites should be selected at dot 257!");
+        assert_eq!(&ppu.internal_oam[0..8], &[8, 0x01, 0x02, 0x03, 8, 0x00, 0x00, 0x00], "Selected sprites should be copied in OAM order!");
+    }
+
+    #[test]
+    fn test_sprite_overflow_with_nine_sprites() {
+        assert!(!sprite_overflow_with(&[]), "Eight sprites should not overflow!");
+        assert!(sprite_overflow_with(&[[8, 0x00, 0x00, 0x00]]), "Ninth sprite right after the eighth should overflow!");
+    }
+
+    #[test]
+    fn test_sprite_overflow_hardware_bug() {
+        // Ninth entry is off the line, so the tenth is checked through its tile byte
+        let false_positive = [[0xF0, 0xF0, 0xF0, 0xF0], [0xF0, 0x09, 0xF0, 0xF0]];
+        assert!(sprite_overflow_with(&false_positive), "Tile byte in range should set overflow falsely!");
+
+        // Real eleventh sprite on the line is checked through its attribute byte
+        let false_negative = [[0xF0, 0xF0, 0xF0, 0xF0], [0xF0, 0xF0, 0xF0, 0xF0], [8, 0x00, 0xF0, 0x00]];
+        assert!(!sprite_overflow_with(&false_negative), "Sprite on the line should be missed after the misaligned scan!");
+    }
+
+    #[test]
+    fn test_sprite_fetch_flips_patterns() {
+        let mut ppu = create_ppu();
+        write_vram(&mut ppu, 0x0010, &[0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        write_vram(&mut ppu, 0x0018, &[0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        ppu.internal_oam[0..8].copy_from_slice(&[8, 0x01, 0x00, 0x00, 8, 0x01, 0x40, 0x00]);
+        ppu.screen_state.sprite_count = 2;
+        ppu.scanline = 10;
+
+        fetch_sprites(&mut ppu);
+
+        assert_eq!(ppu.screen_state.sprite_shift_pattern_lo[0], 0xC0, "Sprite pattern should be fetched for its row!");
+        assert_eq!(ppu.screen_state.sprite_shift_pattern_hi[0], 0x01, "Sprite pattern should be fetched for its row!");
+        assert_eq!(ppu.screen_state.sprite_shift_pattern_lo[1], 0x03, "Horizontally flipped pattern should be reversed!");
+        assert_eq!(ppu.screen_state.sprite_shift_pattern_hi[1], 0x80, "Horizontally flipped pattern should be reversed!");
+    }
+
+    // Tile $04 in $1000 and tile $05 below it, one row set in each
+    fn fetch_tall_sprite_rows(attributes: u8, scanlines: [isize; 2]) -> [(u8, u8); 2] {
+        let mut ppu = create_ppu();
+        write_vram(&mut ppu, 0x1040, &[0x00, 0x00, 0x00, 0xF0, 0x00, 0x00, 0x00, 0x00]);
+        write_vram(&mut ppu, 0x1048, &[0x00, 0x00, 0x00, 0x0F, 0x00, 0x00, 0x00, 0x00]);
+        write_vram(&mut ppu, 0x1050, &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x81, 0x00]);
+        write_vram(&mut ppu, 0x1058, &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x00]);
+        ppu.write_controller(0x20);
+
+        let mut oam = [0xFF; 0x100];
+        oam[0..4].copy_from_slice(&[20, 0x05, attributes, 0x00]);
+        ppu.bus.borrow_mut().ppu_memory_map().set_oam(&oam);
+
+        scanlines.map(|scanline| {
+            ppu.scanline = scanline;
+            evaluate_sprites(&mut ppu);
+            assert_eq!(ppu.screen_state.sprite_count, 1, "8x16 sprite should cover 16 scanlines!");
+
+            fetch_sprites(&mut ppu);
+            (ppu.screen_state.sprite_shift_pattern_lo[0], ppu.screen_state.sprite_shift_pattern_hi[0])
+        })
+    }
+
+    #[test]
+    fn test_8x16_sprites_fetch_both_tiles() {
+        let rows = fetch_tall_sprite_rows(0x00, [23, 34]);
+
+        assert_eq!(rows[0], (0xF0, 0x0F), "Top half should come from the even tile of the odd index's table!");
+        assert_eq!(rows[1], (0x81, 0x18), "Bottom half should come from the next tile!");
+    }
+
+    #[test]
+    fn test_8x16_sprites_flip_vertically() {
+        let rows = fetch_tall_sprite_rows(0x80, [21, 32]);
+
+        assert_eq!(rows[0], (0x81, 0x18), "Flipped top half should show the bottom tile!");
+        assert_eq!(rows[1], (0xF0, 0x0F), "Flipped bottom half should show the top tile!");
+    }
+
+    #[test]
+    fn test_8x8_sprites_skip_rows_below_tile() {
+        let mut ppu = create_ppu();
+        let mut oam = [0xFF; 0x100];
+        oam[0..4].copy_from_slice(&[20, 0x05, 0x00, 0x00]);
+        ppu.bus.borrow_mut().ppu_memory_map().set_oam(&oam);
+        ppu.scanline = 28;
+
+        evaluate_sprites(&mut ppu);
+        assert_eq!(ppu.screen_state.sprite_count, 0, "8x8 sprite should not cover its ninth row!");
+    }
+
+    #[test]
+    fn test_compose_pixel_applies_sprite_priority() {
+        let mut ppu = create_ppu();
+        ppu.write_mask(0x1E);
+        ppu.screen_state.bg_shift_pattern_lo = 0x8000;
+        ppu.screen_state.bg_shift_attribute_hi = 0x8000;
+        ppu.screen_state.sprite_shift_pattern_hi[0] = 0x80;
+        ppu.screen_state.sprite_count = 1;
+        ppu.internal_oam[0..4].copy_from_slice(&[0, 0x00, 0x01, 0x00]);
+        ppu.cycles = 100;
+
+        assert_eq!(ppu.compose_pixel(), (0x02, 0x05), "Sprite in front of background should win!");
+
+        ppu.internal_oam[2] = 0x21;
+        assert_eq!(ppu.compose_pixel(), (0x01, 0x02), "Sprite behind background should lose!");
+
+        ppu.screen_state.bg_shift_pattern_lo = 0x0000;
+        assert_eq!(ppu.compose_pixel(), (0x02, 0x05), "Sprite behind transparent background should show!");
+    }
+
+    #[test]
+    fn test_compose_pixel_sets_sprite_zero_hit() {
+        let mut ppu = create_ppu();
+        ppu.write_mask(0x1E);
+        ppu.screen_state.bg_shift_pattern_lo = 0x8000;
+        ppu.screen_state.sprite_shift_pattern_lo[0] = 0x80;
+        ppu.screen_state.sprite_count = 1;
+        ppu.screen_state.sprite_zero_occured = true;
+        ppu.internal_oam[0..4].copy_from_slice(&[0, 0x00, 0x00, 0x00]);
+        ppu.cycles = 100;
+
+        ppu.compose_pixel();
+
+        assert!(ppu.status.get_flag(PpuStatusRegisterFlags::SpriteZeroHit), "Opaque sprite zero over background should set sprite zero hit!");
+    }
+
+    // Opaque sprite zero over opaque background at screen column `x`
+    fn sprite_zero_hit_at(x: usize, mask: u8) -> bool {
+        let mut ppu = create_ppu();
+        ppu.write_mask(mask);
+        ppu.screen_state.bg_shift_pattern_lo = 0x8000;
+        ppu.screen_state.sprite_shift_pattern_lo[0] = 0x80;
+        ppu.screen_state.sprite_count = 1;
+        ppu.screen_state.sprite_zero_occured = true;
+        ppu.internal_oam[0..4].copy_from_slice(&[0, 0x00, 0x00, 0x00]);
+        ppu.cycles = x + 1;
+
+        ppu.compose_pixel();
+        ppu.status.get_flag(PpuStatusRegisterFlags::SpriteZeroHit)
+    }
+
+    #[test]
+    fn test_sprite_zero_hit_respects_left_clipping() {
+        assert!(sprite_zero_hit_at(3, 0x1E), "Hit should happen in the leftmost pixels when both layers show them!");
+        assert!(!sprite_zero_hit_at(3, 0x1A), "Clipped sprites should not hit in the leftmost pixels!");
+        assert!(!sprite_zero_hit_at(3, 0x1C), "Clipped background should not hit in the leftmost pixels!");
+        assert!(!sprite_zero_hit_at(3, 0x18), "Clipped layers should not hit in the leftmost pixels!");
+        assert!(sprite_zero_hit_at(12, 0x18), "Clipping should not affect pixels past the leftmost 8!");
+        assert!(!sprite_zero_hit_at(255, 0x1E), "Hit should never happen at x=255!");
+    }
+
+    #[test]
+    fn test_sprite_zero_hit_needs_both_layers() {
+        assert!(!sprite_zero_hit_at(12, 0x0A), "Hit should not happen with sprites disabled!");
+        assert!(!sprite_zero_hit_at(12, 0x14), "Hit should not happen with the background disabled!");
+    }
+
+    #[test]
+    fn test_sprite_zero_hit_is_marked_once_per_frame() {
+        let mut ppu = create_ppu();
+        ppu.write_mask(0x1E);
+        ppu.set_debug_mask(true);
+        ppu.screen_state.bg_shift_pattern_lo = 0xC000;
+        ppu.screen_state.sprite_shift_pattern_lo[0] = 0xC0;
+        ppu.screen_state.sprite_count = 1;
+        ppu.screen_state.sprite_zero_occured = true;
+        ppu.internal_oam[0..4].copy_from_slice(&[0, 0x00, 0x00, 0x00]);
+
+        for x in [12, 13] {
+            ppu.cycles = x + 1;
+            ppu.compose_pixel();
+            ppu.update_shift();
+        }
+
+        let hits = ppu.get_debug_mask().unwrap()[0..256]
+            .iter()
+            .enumerate()
+            .filter(|(_, flags)| *flags & DebugMaskFlags::SpriteZeroHit as u8 != 0)
+            .map(|(x, _)| x)
+            .collect::<Vec<_>>();
+
+        assert!(ppu.status.get_flag(PpuStatusRegisterFlags::SpriteZeroHit), "Overlap should set the flag!");
+        assert_eq!(hits, [12], "Only the first overlap should count as the hit!");
+    }
+
+    #[test]
+    fn test_left_clipping_hides_layers() {
+        let mut ppu = create_ppu();
+        ppu.write_mask(0x18);
+        ppu.screen_state.bg_shift_pattern_lo = 0x8000;
+        ppu.cycles = 4;
+        assert_eq!(ppu.compose_pixel(), (0x00, 0x00), "Clipped background should be transparent!");
+
+        ppu.screen_state.bg_shift_pattern_lo = 0x0000;
+        ppu.screen_state.sprite_shift_pattern_lo[0] = 0x80;
+        ppu.screen_state.sprite_count = 1;
+        ppu.internal_oam[0..4].copy_from_slice(&[0, 0x00, 0x00, 0x00]);
+        assert_eq!(ppu.compose_pixel(), (0x00, 0x00), "Clipped sprite should be transparent!");
+
+        ppu.write_mask(0x1C);
+        assert_eq!(ppu.compose_pixel(), (0x01, 0x04), "Sprite should show when its leftmost flag is set!");
+    }
+
+    #[test]
+    fn test_write_only_registers_read_io_latch() {
+        let mut ppu = create_ppu();
+        ppu.write_register(0x2003, 0x5A);
+
+        for address in [0x2000, 0x2001, 0x2003, 0x2005, 0x2006] {
+            assert_eq!(ppu.read_register(address), 0x5A, "Write-only register should read back the last write!");
+        }
+
+        ppu.write_register(0x2001, 0x00);
+        assert_eq!(ppu.read_register(0x2005), 0x00, "Every register write should refresh the latch!");
+    }
+
+    #[test]
+    fn test_status_low_bits_come_from_io_latch() {
+        let mut ppu = create_ppu();
+        ppu.status.set_flag(PpuStatusRegisterFlags::VBlank, true);
+        ppu.write_register(0x2003, 0x1F);
+
+        assert_eq!(ppu.read_register(0x2002), 0x9F, "Status should mix its flags with the latch!");
+        assert_eq!(ppu.read_register(0x2000), 0x9F, "Status read should drive bits 7-5 of the latch!");
+    }
+
+    #[test]
+    fn test_io_latch_decays() {
+        let mut ppu = create_ppu();
+        ppu.write_register(0x2000, 0xFF);
+
+        ppu.frame += IO_LATCH_DECAY_FRAMES - 1;
+        ppu.status.set_flag(PpuStatusRegisterFlags::SpriteZeroHit, true);
+        ppu.read_register(0x2002);
+        assert_eq!(ppu.read_register(0x2000), 0x5F, "Latch should hold its bits until they decay!");
+
+        ppu.frame += 1;
+        assert_eq!(ppu.read_register(0x2000), 0x40, "Bits not refreshed by the status read should decay!");
+    }
+
+    #[test]
+    fn test_oamdata_reads_mask_attribute_bytes() {
+        let mut ppu = create_ppu();
+        ppu.scanline = 241;
+        ppu.write_oamaddress(0x04);
+        [0x10, 0x20, 0xFF, 0x30].into_iter().for_each(|data| ppu.write_oamdata(data));
+
+        let read_at = |ppu: &mut Ppu, address: u8| {
+            ppu.write_oamaddress(address);
+            ppu.read_oamdata()
+        };
+
+        assert_eq!(read_at(&mut ppu, 0x04), 0x10, "Y byte should read back unchanged during VBlank!");
+        assert_eq!(read_at(&mut ppu, 0x05), 0x20, "Tile byte should read back unchanged during VBlank!");
+        assert_eq!(read_at(&mut ppu, 0x06), 0xE3, "Attribute byte should read back without bits 2-4!");
+        assert_eq!(read_at(&mut ppu, 0x07), 0x30, "X byte should read back unchanged during VBlank!");
+    }
+
+    #[test]
+    fn test_oamdata_reads_ff_while_clearing_secondary_oam() {
+        let mut ppu = create_ppu();
+        ppu.write_oamdata(0x42);
+        ppu.write_oamaddress(0x00);
+        ppu.write_mask(0x18);
+        ppu.scanline = 100;
+
+        ppu.cycles = 30;
+        assert_eq!(ppu.read_oamdata(), 0xFF, "Reads should see secondary OAM being cleared!");
+        assert_eq!(ppu.io_latch(), 0xFF, "Reads during the clear should drive the latch!");
+
+        ppu.cycles = 0;
+        assert_eq!(ppu.read_oamdata(), 0x42, "Reads outside the clear should see OAM!");
+
+        ppu.cycles = 30;
+        ppu.write_mask(0x00);
+        assert_eq!(ppu.read_oamdata(), 0x42, "Reads with rendering off should see OAM!");
+    }
+
+    #[test]
+    fn test_advance_counters_wraps_frame() {
+        let mut ppu = create_ppu();
+        ppu.cycles = 340;
+        ppu.scanline = 100;
+
+        ppu.advance_counters();
+        assert_eq!((ppu.cycles, ppu.scanline), (340, 100), "Counters should not advance before dot 341!");
+
+        ppu.cycles = 341;
+        ppu.advance_counters();
+        assert_eq!((ppu.cycles, ppu.scanline), (0, 101), "Dot 341 should start the next scanline!");
+
+        ppu.cycles = 341;
+        ppu.scanline = 260;
+        ppu.advance_counters();
+        assert_eq!((ppu.cycles, ppu.scanline), (0, -1), "Scanline 260 should wrap to the pre-render scanline!");
+    }
+
+    // Reads PPUSTATUS with NMI enabled once the PPU reached `dot` of scanline 241,
+    // then finishes VBlank's first scanline
+    fn read_status_at_vblank_dot(dot: usize) -> (u8, bool) {
+        let mut ppu = create_ppu();
+        ppu.write_controller(0x80);
+
+        while (ppu.scanline, ppu.cycles) != (241, dot) {
+            ppu.tick(1);
+        }
+
+        let status = ppu.read_status();
+        while ppu.scanline == 241 {
+            ppu.tick(1);
+        }
+
+        (status & 0x80, ppu.has_interrupt())
+    }
+
+    #[test]
+    fn test_status_read_races_vblank() {
+        assert_eq!(read_status_at_vblank_dot(0), (0x00, false), "Read as VBlank is set should read it clear and suppress NMI!");
+        assert_eq!(read_status_at_vblank_dot(1), (0x80, false), "Read right after VBlank is set should suppress NMI!");
+        assert_eq!(read_status_at_vblank_dot(2), (0x80, false), "Read two dots after VBlank is set should suppress NMI!");
+        assert_eq!(read_status_at_vblank_dot(3), (0x80, true), "Later read should not suppress NMI!");
+    }
+
+    #[test]
+    fn test_suppressed_vblank_is_not_set_later() {
+        let mut ppu = create_ppu();
+
+        while (ppu.scanline, ppu.cycles) != (241, 0) {
+            ppu.tick(1);
+        }
+
+        ppu.read_status();
+        for _ in 0..10 {
+            ppu.tick(1);
+        }
+
+        assert!(!ppu.status.get_flag(PpuStatusRegisterFlags::VBlank), "Suppressed VBlank should stay clear for the frame!");
+    }
+
+    fn read_vram(ppu: &mut Ppu, address: u16, length: usize) -> Vec<u8> {
+        let [lo, hi] = address.to_le_bytes();
+
+        ppu.write_address(hi);
+        ppu.write_address(lo);
+        ppu.read_data();
+        (0..length).map(|_| ppu.read_data()).collect()
+    }
+
+    #[test]
+    fn test_four_screen_nametables_are_unmirrored() {
+        let cartridge = Cartridge::empty();
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let mut ppu = Ppu::new(&bus, Mirroring::FourScreen);
+        let nametables = [0x2000, 0x2400, 0x2800, 0x2C00];
+
+        for (index, address) in nametables.iter().enumerate() {
+            write_vram(&mut ppu, *address, &[0x10 + index as u8, 0x20 + index as u8]);
+        }
+
+        for (index, address) in nametables.iter().enumerate() {
+            assert_eq!(read_vram(&mut ppu, *address, 2), [0x10 + index as u8, 0x20 + index as u8], "Each nametable should keep its own bytes!");
+            assert_eq!(read_vram(&mut ppu, *address + 0x1000, 2), [0x10 + index as u8, 0x20 + index as u8], "$3000-$3EFF should mirror $2000-$2EFF!");
+        }
+    }
+
+    #[test]
+    fn test_single_screen_mirroring() {
+        for (mirroring, base) in [(Mirroring::SingleScreenLow, 0x2000), (Mirroring::SingleScreenHigh, 0x2400)] {
+            let cartridge = Cartridge::empty();
+            let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+            let mut ppu = Ppu::new(&bus, mirroring);
+
+            for (index, address) in [0x2000, 0x2400, 0x2800, 0x2C00].into_iter().enumerate() {
+                assert_eq!(ppu.mirror_address(address + 0x123), base + 0x123, "All nametables should map to the same 1 KiB!");
+
+                write_vram(&mut ppu, address + 0x10, &[index as u8 + 1]);
+                assert_eq!(read_vram(&mut ppu, 0x2010, 1), [index as u8 + 1], "Writes should land in the shared nametable!");
+                assert_eq!(read_vram(&mut ppu, 0x2C10, 1), [index as u8 + 1], "Writes should land in the shared nametable!");
+            }
+        }
+    }
+
+    #[test]
+    fn test_tick_amount_matches_single_dots() {
+        let mut stepped = create_ppu();
+        load_sprite_scene(&mut stepped);
+        let mut batched = stepped.clone();
+
+        for _ in 0..DOTS_PER_FRAME {
+            stepped.tick(1);
+            stepped.tick(1);
+            stepped.tick(1);
+            batched.tick(3);
+
+            assert_eq!(batched.status.get(), stepped.status.get(), "Status should not depend on the tick size!");
+        }
+
+        assert_eq!(batched.cycles, stepped.cycles, "Dot should not depend on the tick size!");
+        assert_eq!(batched.scanline, stepped.scanline, "Scanline should not depend on the tick size!");
+        assert_eq!(
+            Frame::from(batched.get_screen_buffer()).hash(),
+            Frame::from(stepped.get_screen_buffer()).hash(),
+            "Screen buffer should not depend on the tick size!"
+        );
+    }
+
+    #[test]
+    fn test_palette_read_buffers_nametable_underneath() {
+        let mut ppu = create_ppu();
+        write_vram(&mut ppu, 0x2F05, &[0x2B]);
+        write_vram(&mut ppu, 0x3F05, &[0x12]);
+
+        ppu.write_address(0x3F);
+        ppu.write_address(0x05);
+        assert_eq!(ppu.read_data(), 0x12, "Palette should be read without the buffer delay!");
+
+        ppu.write_address(0x20);
+        ppu.write_address(0x00);
+        assert_eq!(ppu.read_data(), 0x2B, "Palette read should buffer the nametable byte underneath!");
+    }
+
+    // Renders the sprite scene with the given layer overrides, returning the frame hash and
+    // whether sprite zero was hit on the visible scanlines that follow
+    fn render_with_layer_override(background: Option<bool>, sprites: Option<bool>) -> (u64, bool) {
+        let mut ppu = create_ppu();
+        load_sprite_scene(&mut ppu);
+        // Palette writes left $2C00 selected in t, which nothing was written to
+        ppu.write_controller(0x00);
+        ppu.set_layer_override(background, sprites);
+
+        let hash = render_frames(&mut ppu, 2);
+        assert_eq!(ppu.mask.get(), 0x1E, "Overrides should not touch the mask register!");
+
+        while ppu.scanline != 240 {
+            ppu.tick(1);
+        }
+
+        (hash, ppu.status.get_flag(PpuStatusRegisterFlags::SpriteZeroHit))
+    }
+
+    #[test]
+    fn test_layer_overrides_hide_layers() {
+        let (hash, sprite_zero_hit) = render_with_layer_override(None, None);
+        let (no_background_hash, no_background_hit) = render_with_layer_override(Some(false), None);
+        let (no_sprites_hash, no_sprites_hit) = render_with_layer_override(None, Some(false));
+
+        assert!(sprite_zero_hit, "Sprite scene should hit sprite zero!");
+        assert_ne!(no_background_hash, hash, "Hiding the background should change the frame!");
+        assert_ne!(no_sprites_hash, hash, "Hiding sprites should change the frame!");
+        assert_ne!(no_background_hash, no_sprites_hash, "Each override should hide a different layer!");
+        assert!(no_background_hit && no_sprites_hit, "Sprite zero hit should ignore the overrides!");
+        assert_eq!(render_with_layer_override(Some(true), Some(true)).0, hash, "Forcing enabled layers on should not change the frame!");
+    }
+
+    #[test]
+    fn test_debug_render_nametables() {
+        let mut ppu = create_ppu();
+        write_vram(&mut ppu, 0x0010, &[0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        write_vram(&mut ppu, 0x0018, &[0x0F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        write_vram(&mut ppu, 0x2000, &[0x01; 0x3C0]);
+        write_vram(&mut ppu, 0x23C0, &[0x02; 0x40]);
+        write_vram(&mut ppu, 0x3F00, &[0x0F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x22, 0x23]);
+        let vram = ppu.vram.get();
+
+        let nametables = ppu.debug_render_nametables();
+
+        assert_eq!(ppu.vram.get(), vram, "Rendering nametables should not move the VRAM address!");
+        assert_eq!(&nametables[0][0..8], &[0x21, 0x21, 0x21, 0x21, 0x22, 0x22, 0x22, 0x22], "Tile row should use the attribute's palette!");
+        assert_eq!(&nametables[0][256..264], &[0x0F; 8], "Transparent pixels should use the backdrop!");
+        assert_eq!(nametables[1], nametables[0], "Horizontal mirroring should show $2000 at $2400!");
+        assert_eq!(nametables[2][0], 0x0F, "$2800 should be empty!");
+    }
+
+    #[test]
+    fn test_debug_sprites_decodes_oam() {
+        let mut ppu = create_ppu();
+        ppu.write_oamaddress(0x08);
+        [0x10, 0x2A, 0xE2, 0x30].into_iter().for_each(|data| ppu.write_oamdata(data));
+        ppu.scanline = 0x12;
+
+        evaluate_sprites(&mut ppu);
+        let sprites = ppu.debug_sprites();
+
+        assert_eq!(sprites[2], SpriteInfo {
+            x: 0x30,
+            y: 0x10,
+            tile: 0x2A,
+            palette: 0x02,
+            behind_background: true,
+            flip_h: true,
+            flip_v: true,
+            in_range: true,
+        }, "Third OAM entry should be decoded!");
+        assert_eq!(sprites.iter().filter(|sprite| sprite.in_range).count(), 1, "Only the written sprite should be in range!");
+    }
+
+    fn timing(ppu: &Ppu) -> (isize, usize, u64) {
+        (ppu.get_scanline(), ppu.get_cycles(), ppu.get_frame())
+    }
+
+    #[test]
+    fn test_timing_counters_wrap() {
+        let mut ppu = create_ppu();
+
+        ppu.tick(340);
+        assert_eq!(timing(&ppu), (0, 340, 0), "Last dot of the scanline should be 340!");
+
+        ppu.tick(1);
+        assert_eq!(timing(&ppu), (1, 0, 0), "Dot 341 should start the next scanline!");
+
+        ppu.tick(259 * 341 + 340);
+        assert_eq!(timing(&ppu), (260, 340, 0), "Scanline 260 should be the last of the frame!");
+
+        ppu.tick(1);
+        assert_eq!(timing(&ppu), (-1, 0, 1), "Frame should wrap to the pre-render scanline!");
+
+        ppu.tick(341);
+        assert_eq!(timing(&ppu), (0, 0, 1), "Pre-render scanline should be 341 dots with rendering off!");
+    }
+
+    #[test]
+    fn test_pal_frames_are_longer() {
+        let mut ppu = create_ppu();
+        ppu.write_mask(0x08);
+        ppu.set_region(Region::Pal);
+
+        assert_eq!(next_frame_lengths(&mut ppu), [341 * 312; 2], "PAL frames should have 312 scanlines and no skipped dot!");
+    }
+
+    #[test]
+    fn test_pal_frames_run_at_50_hz() {
+        let frames_per_second = |region: Region| 1.0 / region.frame_duration().as_secs_f64();
+
+        assert!((frames_per_second(Region::Ntsc) - 60.0988).abs() < 0.001, "NTSC should run at 60.1 frames per second!");
+        assert!((frames_per_second(Region::Pal) - 50.0070).abs() < 0.001, "PAL should run at 50.0 frames per second!");
+    }
+
+    #[test]
+    fn test_pal_nmi_fires_at_scanline_241() {
+        let mut ppu = create_ppu();
+        ppu.set_region(Region::Pal);
+        ppu.write_controller(0x80);
+
+        for _ in 0..2 {
+            while !ppu.has_interrupt() {
+                ppu.tick(1);
+            }
+
+            assert_eq!((ppu.get_scanline(), ppu.get_cycles()), (241, 1), "NMI should fire as VBlank starts!");
+            ppu.bus.borrow_mut().set_interrupt(None);
+            ppu.tick(1);
+        }
+
+        assert_eq!(ppu.get_frame(), 1, "Second NMI should come one PAL frame later!");
+    }
+
+    // Draws one frame with rendering off and the VRAM address left at `address`
+    fn render_disabled_frame(address: u16) -> Vec<u8> {
+        let mut ppu = create_ppu();
+        load_sprite_scene(&mut ppu);
+        write_vram(&mut ppu, 0x3F00, &[0x21]);
+        ppu.write_mask(0x00);
+
+        let [lo, hi] = address.to_le_bytes();
+        ppu.write_address(hi);
+        ppu.write_address(lo);
+        render_frames(&mut ppu, 1);
+
+        ppu.get_screen_buffer().get().to_vec()
+    }
+
+    #[test]
+    fn test_disabled_rendering_shows_backdrop() {
+        let frame = render_disabled_frame(0x2000);
+
+        assert!(frame.iter().all(|pixel| *pixel == 0x21), "Whole frame should be the backdrop color!");
+    }
+
+    #[test]
+    fn test_disabled_rendering_shows_palette_entry_under_v() {
+        let frame = render_disabled_frame(0x3F05);
+
+        assert!(frame.iter().all(|pixel| *pixel == 0x0F), "Whole frame should show palette entry $3F05!");
+    }
+
+    #[test]
+    fn test_mid_frame_scroll_split() {
+        let cartridge = Cartridge::empty();
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let mut ppu = Ppu::new(&bus, Mirroring::Horizontal);
+
+        // Solid tile 1 above the split in $2000, solid tile 2 below it in $2800
+        write_vram(&mut ppu, 0x0010, &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        write_vram(&mut ppu, 0x0028, &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        write_vram(&mut ppu, 0x2000, &[0x01; 0x3C0]);
+        write_vram(&mut ppu, 0x2800, &[0x02; 0x3C0]);
+        write_vram(&mut ppu, 0x3F00, &[0x0F, 0x11, 0x22]);
+
+        ppu.write_controller(0x00);
+        ppu.write_scroll(0);
+        ppu.write_scroll(0);
+        ppu.write_mask(0x0A);
+
+        while (ppu.frame, ppu.scanline, ppu.cycles) != (1, 100, 258) {
+            ppu.tick(1);
+        }
+
+        // Nametable $2800, X = 8 and Y = 16 in the $2006/$2005/$2005/$2006 order
+        ppu.write_address(0x08);
+        ppu.write_scroll(16);
+        ppu.write_scroll(8);
+        ppu.write_address(0x41);
+
+        assert_eq!(ppu.vram_temp.get(), 0x0841, "t should hold the new scroll!");
+        assert_eq!(ppu.vram.get(), 0x0841, "Second $2006 write should copy t to v!");
+        assert_eq!(ppu.fine_x, 0, "Fine X should come from $2005!");
+
+        while ppu.scanline != 240 {
+            ppu.tick(1);
+        }
+
+        let screen_buffer = ppu.get_screen_buffer();
+        assert_eq!(screen_buffer.get_pixel(100, 100), 0x11, "Scanlines above the split should use the old scroll!");
+        assert_eq!(screen_buffer.get_pixel(100, 101), 0x22, "Scanlines below the split should use the new scroll!");
+        assert_eq!(screen_buffer.get_pixel(100, 239), 0x22, "Scanlines below the split should use the new scroll!");
+    }
+
+    #[test]
+    fn test_first_pixel_comes_from_dot_1() {
+        let mut ppu = create_ppu();
+        write_vram(&mut ppu, 0x3F00, &[0x0F, 0x11, 0x22]);
+
+        // With rendering off each dot draws the palette entry v points at
+        let point_at = |ppu: &mut Ppu, address: u16| {
+            let [lo, hi] = address.to_le_bytes();
+            ppu.write_address(hi);
+            ppu.write_address(lo);
+        };
+
+        point_at(&mut ppu, 0x3F01);
+        ppu.tick(1);
+        point_at(&mut ppu, 0x3F02);
+        ppu.tick(DOTS_PER_FRAME - 1);
+
+        let screen_buffer = ppu.get_screen_buffer();
+        assert_eq!(screen_buffer.get_pixel(0, 0), 0x11, "Pixel (0, 0) should be drawn once, on dot 1!");
+        assert!(screen_buffer.get().iter().skip(1).all(|pixel| *pixel == 0x22), "Other pixels should be drawn after dot 1!");
+    }
+
+    #[test]
+    fn test_sprite_top_row_is_drawn_below_oam_y() {
+        let mut ppu = create_ppu();
+        write_vram(&mut ppu, 0x0010, &[0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        write_vram(&mut ppu, 0x3F00, &[0x0F]);
+        write_vram(&mut ppu, 0x3F11, &[0x21]);
+
+        let mut oam = [0xFF; 0x100];
+        oam[0..4].copy_from_slice(&[0x10, 0x01, 0x00, 0x20]);
+        ppu.bus.borrow_mut().ppu_memory_map().set_oam(&oam);
+        ppu.write_mask(0x1E);
+
+        render_frames(&mut ppu, 2);
+
+        let screen_buffer = ppu.get_screen_buffer();
+        assert_eq!(screen_buffer.get_pixel(0x20, 0x10), 0x0F, "Sprite should not show on its OAM Y!");
+        assert_eq!(screen_buffer.get_pixel(0x20, 0x11), 0x21, "Sprite top row should show one scanline below its OAM Y!");
+        assert_eq!(screen_buffer.get_pixel(0x20, 0x12), 0x0F, "Only the top row of the sprite is opaque!");
+    }
+
+    #[test]
+    fn test_pre_render_scanline_evaluates_sprites_for_scanline_0() {
+        let mut ppu = create_ppu();
+        write_vram(&mut ppu, 0x0010, &[0xFF; 8]);
+        write_vram(&mut ppu, 0x3F00, &[0x0F]);
+        write_vram(&mut ppu, 0x3F11, &[0x21]);
+
+        // Sprite 1 is still in range on scanline 239, sprite 0 sits at the top of the screen
+        let mut oam = [0xFF; 0x100];
+        oam[0..8].copy_from_slice(&[0x00, 0x01, 0x00, 0x20, 0xEC, 0x01, 0x00, 0x40]);
+        ppu.bus.borrow_mut().ppu_memory_map().set_oam(&oam);
+        ppu.write_mask(0x1E);
+
+        render_frames(&mut ppu, 1);
+        while (ppu.scanline, ppu.cycles) != (0, 0) {
+            ppu.tick(1);
+        }
+
+        assert_eq!(ppu.screen_state.sprite_count, 0, "Sprites from scanline 239 should not carry over to scanline 0!");
+        assert!(ppu.debug_sprites().iter().all(|sprite| !sprite.in_range), "No sprite should be in range on scanline 0!");
+
+        while ppu.scanline != 2 {
+            ppu.tick(1);
+        }
+
+        let screen_buffer = ppu.get_screen_buffer();
+        assert_eq!(screen_buffer.get_pixel(0x40, 0x00), 0x0F, "Leftover sprite should not show on scanline 0!");
+        assert_eq!(screen_buffer.get_pixel(0x20, 0x00), 0x0F, "Sprite at Y 0 should not show on scanline 0!");
+        assert_eq!(screen_buffer.get_pixel(0x20, 0x01), 0x21, "Sprite at Y 0 should show from scanline 1!");
+        assert_eq!(screen_buffer.get_pixel(0x27, 0x01), 0x21, "Whole top row of the sprite should show!");
+    }
+
+    // PPU dots of the next two frames, counted from the start of the pre-render scanline
+    fn next_frame_lengths(ppu: &mut Ppu) -> [usize; 2] {
+        while ppu.scanline != -1 {
+            ppu.tick(1);
+        }
+
+        [0; 2].map(|_| {
+            let frame = ppu.frame;
+            let mut dots = 0;
+
+            while ppu.frame == frame {
+                ppu.tick(1);
+                dots += 1;
+            }
+
+            dots
+        })
+    }
+
+    #[test]
+    fn test_odd_frames_skip_a_dot_with_rendering() {
+        let mut ppu = create_ppu();
+        ppu.write_mask(0x08);
+
+        let mut lengths = next_frame_lengths(&mut ppu);
+        lengths.sort();
+        assert_eq!(lengths, [DOTS_PER_FRAME - 1, DOTS_PER_FRAME], "Frame lengths should alternate with rendering on!");
+
+        ppu.write_mask(0x00);
+        assert_eq!(next_frame_lengths(&mut ppu), [DOTS_PER_FRAME; 2], "Frames should not skip a dot with rendering off!");
+    }
+
+    #[test]
+    fn test_enabling_nmi_during_vblank_raises_nmi() {
+        let mut ppu = create_ppu();
+        ppu.scanline = 241;
+        ppu.cycles = 1;
+        ppu.update_vblank();
+        assert!(!ppu.has_interrupt(), "VBlank without NMI generation should not raise NMI!");
+
+        ppu.write_controller(0x80);
+        assert!(ppu.has_interrupt(), "Enabling NMI generation during VBlank should raise NMI!");
+
+        ppu.bus.borrow_mut().poll_interrupt();
+        ppu.write_controller(0x80);
+        assert!(!ppu.has_interrupt(), "Rewriting the enabled bit should not raise another NMI!");
+    }
+
+    #[test]
+    fn test_greyscale_masks_palette_reads() {
+        let mut ppu = create_ppu();
+        write_vram(&mut ppu, 0x3F00, &[0x21, 0x15, 0x3C, 0x0F, 0x00, 0x10, 0x20, 0x30]);
+        ppu.write_mask(0x01);
+
+        ppu.write_address(0x3F);
+        ppu.write_address(0x00);
+        let colors = (0..8).map(|_| ppu.read_data()).collect::<Vec<_>>();
+
+        assert_eq!(colors, [0x20, 0x10, 0x30, 0x00, 0x00, 0x10, 0x20, 0x30], "Greyscale should keep only the brightness of palette reads!");
+    }
+
+    #[test]
+    fn test_greyscale_masks_pixel_output() {
+        let mut ppu = create_ppu();
+        write_vram(&mut ppu, 0x3F00, &[0x21]);
+        // Leaving v in palette RAM would show that entry instead of the backdrop
+        write_vram(&mut ppu, 0x2000, &[]);
+
+        render_frames(&mut ppu, 1);
+        assert_eq!(ppu.get_screen_buffer().get_pixel(100, 100), 0x21, "Backdrop should keep its color!");
+
+        ppu.write_mask(0x01);
+        render_frames(&mut ppu, 1);
+        assert_eq!(ppu.get_screen_buffer().get_pixel(100, 100), 0x20, "Greyscale backdrop should lose its hue!");
+    }
+
+    #[test]
+    fn test_emphasis_is_stored_per_pixel() {
+        let mut ppu = create_ppu();
+        ppu.write_mask(0x20);
+        render_frames(&mut ppu, 1);
+
+        let frame = Frame::from(ppu.get_screen_buffer());
+        assert_eq!(frame.get_emphasis(100, 100), 0b001, "Red emphasis should be stored with the pixel!");
+
+        ppu.write_mask(0xC0);
+        render_frames(&mut ppu, 1);
+
+        let frame = Frame::from(ppu.get_screen_buffer());
+        assert_eq!(frame.get_emphasis(100, 100), 0b110, "Green and blue emphasis should be stored with the pixel!");
+        assert_eq!(frame.get_pixel(100, 100), 0x00, "Emphasis should not change the palette index!");
+    }
+
+    #[test]
+    fn test_extra_vblank_scanlines_delay_pre_render() {
+        let mut ppu = create_ppu();
+        ppu.set_extra_vblank_scanlines(10);
+
+        ppu.cycles = 341;
+        ppu.scanline = 260;
+        ppu.advance_counters();
+        assert_eq!(ppu.scanline, 261, "Extra scanlines should follow scanline 260!");
+
+        ppu.cycles = 341;
+        ppu.scanline = 270;
+        ppu.advance_counters();
+        assert_eq!(ppu.scanline, -1, "Last extra scanline should wrap to the pre-render scanline!");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_extra_vblank_scanlines_are_limited() {
+        create_ppu().set_extra_vblank_scanlines(MAX_EXTRA_VBLANK_SCANLINES + 1);
+    }
+
+    #[test]
+    fn test_debug_mask_marks_sprite_zero_hit() {
+        let mut ppu = create_ppu();
+
+        // Tile 1 has only its top left pixel set, drawn by the background at (16, 16)
+        write_vram(&mut ppu, 0x0010, &[0x80]);
+        write_vram(&mut ppu, 0x2042, &[0x01]);
+        ppu.write_scroll(0);
+        ppu.write_scroll(0);
+
+        let mut oam = [0xFF; 0x100];
+        oam[..4].copy_from_slice(&[15, 0x01, 0x00, 16]);
+        ppu.bus.borrow_mut().ppu_memory_map().set_oam(&oam);
+        ppu.write_mask(0x1E);
+        ppu.set_debug_mask(true);
+
+        // Mask is cleared on the pre-render scanline, so stop once the picture is done
+        render_frames(&mut ppu, 1);
+        while ppu.scanline != 240 {
+            ppu.tick(1);
+        }
+
+        let mask = ppu.get_debug_mask().unwrap();
+        let pixels_with = |flag: DebugMaskFlags| {
+            let flag = flag as u8;
+            (0..mask.len()).filter(|index| mask[*index] & flag != 0).collect::<Vec<_>>()
+        };
+
+        assert_eq!(pixels_with(DebugMaskFlags::SpriteZeroHit), [16 * 256 + 16], "Only the overlapping pixel should be a hit!");
+        assert_eq!(pixels_with(DebugMaskFlags::SpritePixel), [16 * 256 + 16], "Sprite should win its only opaque pixel!");
+        assert_eq!(pixels_with(DebugMaskFlags::SpriteZeroArea).len(), 64, "Sprite zero area should cover 8x8 pixels!");
+        assert!(ppu.status.get_flag(PpuStatusRegisterFlags::SpriteZeroHit), "Hit should also set the status flag!");
+    }
+
+    fn finish_frame(ppu: &mut Ppu) -> Vec<u8> {
+        while !ppu.take_frame_complete() {
+            ppu.tick(1);
+        }
+
+        ppu.get_screen_buffer().get().to_vec()
+    }
+
+    fn mid_frame_sprite_scene() -> Ppu {
+        let mut ppu = create_ppu();
+        load_sprite_scene(&mut ppu);
+        render_frames(&mut ppu, 1);
+
+        while (ppu.scanline, ppu.cycles) != (120, 100) {
+            ppu.tick(1);
+        }
+
+        ppu.take_frame_complete();
+        ppu
+    }
+
+    #[test]
+    fn test_restored_state_finishes_the_same_frame() {
+        let mut ppu = mid_frame_sprite_scene();
+        let snapshot = ppu.save_state();
+        let expected = finish_frame(&mut ppu);
+
+        // Pattern tables live in the mapper, so the fresh PPU loads the scene for them
+        let mut restored = create_ppu();
+        load_sprite_scene(&mut restored);
+        restored.restore_state(&snapshot);
+        assert_eq!(restored.save_state(), snapshot, "Restored state should match the snapshot!");
+
+        assert!(finish_frame(&mut restored) == expected, "Restored PPU should draw the same frame!");
+        assert_eq!(restored.save_state(), ppu.save_state(), "Both PPUs should end the frame in the same state!");
+    }
+
+    #[cfg(feature = "savestate")]
+    #[test]
+    fn test_save_state_serializes_round_trip() {
+        let ppu = mid_frame_sprite_scene();
+        let snapshot = ppu.save_state();
+
+        let bytes = serde_json::to_vec(&snapshot).unwrap();
+        let decoded: PpuState = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, snapshot, "Deserialized state should match the snapshot!");
+    }
+
+    #[test]
+    fn test_nametable_and_pattern_getters() {
+        let mut ppu = create_ppu();
+        ppu.mirroring = Mirroring::Horizontal;
+
+        write_vram(&mut ppu, 0x1010, &[0x81, 0x42]);
+        write_vram(&mut ppu, 0x2005, &[0x12]);
+        write_vram(&mut ppu, 0x2BC0, &[0x34]);
+        ppu.write_controller(0x03);
+
+        assert_eq!(ppu.get_nametable_index(), 3, "Nametable index should follow PPUCTRL!");
+        assert_eq!(ppu.get_nametable(1)[0x005], 0x12, "Nametable 1 should mirror nametable 0!");
+        assert_eq!(ppu.get_nametable(3)[0x3C0], 0x34, "Nametable 3 should hold its attribute!");
+        assert_eq!(ppu.get_nametable(2), ppu.get_nametable(3), "Nametable 2 should mirror nametable 3!");
+        assert_eq!(ppu.get_pattern_table(1)[0x10..0x12], [0x81, 0x42], "Pattern table 1 should start at $1000!");
+        assert!(ppu.get_pattern_table(0).iter().all(|byte| *byte == 0), "Pattern table 0 should be untouched!");
+    }
+
+    fn oam_address_after_fetches(mask: u8) -> u8 {
+        let mut ppu = create_ppu();
+        load_sprite_scene(&mut ppu);
+        ppu.write_mask(mask);
+
+        while (ppu.scanline, ppu.cycles) != (10, 200) {
+            ppu.tick(1);
+        }
+
+        ppu.write_oamaddress(0x40);
+        ppu.tick(121);
+        assert_eq!(ppu.cycles, 321, "Should stop right after the sprite fetches!");
+
+        ppu.oamaddress.get()
+    }
+
+    #[test]
+    fn test_sprite_fetches_reset_oam_address() {
+        assert_eq!(oam_address_after_fetches(0x1E), 0x00, "OAMADDR should be reset while rendering!");
+        assert_eq!(oam_address_after_fetches(0x08), 0x00, "Background alone should also reset OAMADDR!");
+        assert_eq!(oam_address_after_fetches(0x00), 0x40, "OAMADDR should be kept with rendering off!");
+    }
+
+    #[derive(Clone)]
+    struct A12CountingMapper {
+        edges: Rc<Cell<usize>>,
+        chr_rom: Vec<u8>,
+    }
+
+    impl Memory for A12CountingMapper {
+        fn read(&self, _address: u16) -> u8 {
+            0x00
+        }
+
+        fn write(&mut self, _address: u16, _data: u8) {}
+    }
+
+    impl Mapper for A12CountingMapper {
+        fn get_chr_rom(&mut self) -> &mut Vec<u8> {
+            &mut self.chr_rom
+        }
+
+        fn a12_rising_edge(&mut self) {
+            self.edges.set(self.edges.get() + 1);
+        }
+    }
+
+    fn create_a12_counting_ppu() -> (Ppu, Rc<Cell<usize>>) {
+        let edges = Rc::new(Cell::new(0));
+        let cartridge = Cartridge::from_mapper(Box::new(A12CountingMapper {
+            edges: edges.clone(),
+            chr_rom: vec![0; 0x2000],
+        }));
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+
+        (Ppu::new(&bus, cartridge.get_mirroring()), edges)
+    }
+
+    #[test]
+    fn test_a12_rises_are_filtered() {
+        let (mut ppu, edges) = create_a12_counting_ppu();
+        ppu.write_mask(0x08);
+
+        // Background fetches from $0000 keep A12 low between the synthetic accesses
+        ppu.watch_a12(0x1000);
+        assert_eq!(edges.get(), 1, "First rise should clock the mapper!");
+
+        ppu.tick(2);
+        ppu.watch_a12(0x1008);
+        assert_eq!(edges.get(), 1, "Staying high should not clock the mapper again!");
+
+        ppu.tick(A12_FILTER_DOTS - 1);
+        ppu.watch_a12(0x0FF0);
+        ppu.watch_a12(0x1000);
+        assert_eq!(edges.get(), 1, "Short low periods should be filtered out!");
+
+        ppu.tick(A12_FILTER_DOTS);
+        ppu.watch_a12(0x1000);
+        assert_eq!(edges.get(), 2, "Rise after a long low period should clock the mapper!");
+
+        ppu.write_mask(0x00);
+        ppu.tick(A12_FILTER_DOTS);
+        ppu.watch_a12(0x1000);
+        assert_eq!(edges.get(), 2, "Accesses outside of rendering should not clock the mapper!");
+    }
+
+    fn a12_edges_in_second_frame(controller: u8, mask: u8) -> usize {
+        let (mut ppu, edges) = create_a12_counting_ppu();
+        ppu.write_controller(controller);
+        ppu.write_mask(mask);
+
+        render_frames(&mut ppu, 1);
+        edges.set(0);
+        render_frames(&mut ppu, 1);
+
+        edges.get()
+    }
+
+    #[test]
+    fn test_a12_clocks_once_per_scanline() {
+        assert_eq!(a12_edges_in_second_frame(0x08, 0x18), 241, "Sprites at $1000 should clock once per rendered scanline!");
+        // The pre-render scanline also clocks on its first fetch, after A12 stayed low through VBlank
+        assert_eq!(a12_edges_in_second_frame(0x10, 0x18), 242, "Background at $1000 should clock twice on the pre-render scanline!");
+        assert_eq!(a12_edges_in_second_frame(0x08, 0x00), 0, "Nothing should be clocked with rendering off!");
+    }
+
+    // MMC3-style scanline counter: a clocked zero counter reloads from the latch,
+    // otherwise it counts down and raises the IRQ when it reaches zero
+    #[derive(Clone)]
+    struct Mmc3CounterMapper {
+        latch: u8,
+        counter: Rc<Cell<u8>>,
+        irq: Rc<Cell<bool>>,
+        chr_rom: Vec<u8>,
+    }
+
+    impl Memory for Mmc3CounterMapper {
+        fn read(&self, _address: u16) -> u8 {
+            0x00
+        }
+
+        fn write(&mut self, _address: u16, _data: u8) {}
+    }
+
+    impl Mapper for Mmc3CounterMapper {
+        fn get_chr_rom(&mut self) -> &mut Vec<u8> {
+            &mut self.chr_rom
+        }
+
+        fn a12_rising_edge(&mut self) {
+            let counter = match self.counter.get() {
+                0 => self.latch,
+                counter => counter - 1,
+            };
+
+            self.counter.set(counter);
+
+            if counter == 0 {
+                self.irq.set(true);
+            }
+        }
+
+        fn irq_pending(&self) -> bool {
+            self.irq.get()
+        }
+    }
+
+    #[test]
+    fn test_a12_pattern_drives_mmc3_counter() {
+        let counter = Rc::new(Cell::new(0));
+        let irq = Rc::new(Cell::new(false));
+        let cartridge = Cartridge::from_mapper(Box::new(Mmc3CounterMapper {
+            latch: 2,
+            counter: counter.clone(),
+            irq: irq.clone(),
+            chr_rom: vec![0; 0x2000],
+        }));
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let mut ppu = Ppu::new(&bus, cartridge.get_mirroring());
+        ppu.write_mask(0x08);
+
+        // One character per dot: A12 driven high by a fetch from $1000 or left low,
+        // then the counter and the IRQ line seen after that dot
+        let script = [
+            ("H.H.H", "22222", "....."),
+            ("................", "2222222222222222", "................"),
+            ("H", "1", "."),
+            ("..............", "11111111111111", ".............."),
+            ("H", "1", "."),
+            ("...............", "111111111111111", "..............."),
+            ("H", "0", "I"),
+            ("...............", "000000000000000", "IIIIIIIIIIIIIII"),
+            ("H", "2", "I"),
+        ];
+
+        let mut dot = 0;
+
+        for (a12, counters, irqs) in script {
+            for ((a12, expected_counter), expected_irq) in a12.chars().zip(counters.chars()).zip(irqs.chars()) {
+                if a12 == 'H' {
+                    ppu.watch_a12(0x1000);
+                }
+
+                ppu.tick(1);
+
+                let expected_counter = expected_counter.to_digit(10).unwrap() as u8;
+                assert_eq!(counter.get(), expected_counter, "Counter differs after dot {}!", dot);
+                assert_eq!(bus.borrow().poll_irq(), expected_irq == 'I', "IRQ line differs after dot {}!", dot);
+                dot += 1;
+            }
+        }
+
+        assert!(irq.get(), "Reloading the counter should not acknowledge the IRQ!");
+    }
+
+    #[test]
+    fn test_debug_palette_starts_with_backdrop() {
+        let mut ppu = create_ppu();
+        let palette = (0..0x20).map(|index| index as u8 + 0x20).collect::<Vec<_>>();
+        write_vram(&mut ppu, 0x3F00, &palette);
+
+        assert_eq!(ppu.debug_palette(PaletteKind::Background, 0), [0x30, 0x21, 0x22, 0x23], "$3F10 should alias the backdrop!");
+        assert_eq!(ppu.debug_palette(PaletteKind::Background, 2), [0x30, 0x29, 0x2A, 0x2B], "Background palette 2 should start at $3F09!");
+        assert_eq!(ppu.debug_palette(PaletteKind::Sprite, 0), [0x30, 0x31, 0x32, 0x33], "Sprite palette 0 should start at $3F11!");
+        assert_eq!(ppu.debug_palette(PaletteKind::Sprite, 3), [0x30, 0x3D, 0x3E, 0x3F], "Sprite palette 3 should start at $3F1D!");
+    }
+
+    #[derive(Clone)]
+    struct SwitchableMirroringMapper {
+        mirroring: Rc<Cell<Mirroring>>,
+        chr_rom: Vec<u8>,
+    }
+
+    impl Memory for SwitchableMirroringMapper {
+        fn read(&self, _address: u16) -> u8 {
+            0x00
+        }
+
+        fn write(&mut self, _address: u16, _data: u8) {}
+    }
+
+    impl Mapper for SwitchableMirroringMapper {
+        fn get_chr_rom(&mut self) -> &mut Vec<u8> {
+            &mut self.chr_rom
+        }
+
+        fn mirroring(&self) -> Option<Mirroring> {
+            Some(self.mirroring.get())
+        }
+    }
+
+    #[test]
+    fn test_mapper_switches_mirroring_mid_frame() {
+        let mirroring = Rc::new(Cell::new(Mirroring::Vertical));
+        let cartridge = Cartridge::from_mapper(Box::new(SwitchableMirroringMapper {
+            mirroring: mirroring.clone(),
+            chr_rom: vec![0; 0x2000],
+        }));
+        let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
+        let mut ppu = Ppu::new(&bus, Mirroring::Horizontal);
+
+        write_vram(&mut ppu, 0x2000, &[0x11]);
+        write_vram(&mut ppu, 0x2400, &[0x22]);
+        ppu.write_mask(0x08);
+        assert_eq!(ppu.get_nametable(2)[0], 0x11, "Mapper mirroring should win over the header!");
+
+        while ppu.scanline != 100 {
+            ppu.tick(1);
+        }
+
+        mirroring.set(Mirroring::Horizontal);
+        assert_eq!(ppu.mirror_address(0x2400), 0x2000, "$2400 should now mirror $2000!");
+        assert_eq!(ppu.get_nametable(1)[0], 0x11, "Nametable 1 should read from $2000 after the switch!");
+        assert_eq!(ppu.get_nametable(2)[0], 0x22, "Nametable 2 should read from $2400 after the switch!");
+    }
+}
@@ -1,21 +1,46 @@
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "savestate", derive(serde::Serialize, serde::Deserialize))]
 pub struct ScreenBuffer {
     width: usize,
+    height: usize,
     buffer: Vec<u8>,
+    // PPUMASK emphasis bits of each pixel, red in bit 0, green in bit 1 and blue in bit 2
+    emphasis: Vec<u8>,
 }
 
 impl ScreenBuffer {
     pub fn new(width: usize, height: usize) -> Self {
         Self {
             width,
+            height,
             buffer: vec![0; width * height],
+            emphasis: vec![0; width * height],
         }
     }
 
-    pub fn set_pixel(&mut self, x: usize, y: usize, color: u8) {
-        let index = y * self.width + x;
-        
-        if index < self.buffer.len() {
-            self.buffer[index] = color;
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self) -> &Vec<u8> {
+        &self.buffer
+    }
+
+    pub fn get_emphasis(&self) -> &Vec<u8> {
+        &self.emphasis
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: u8, emphasis: u8) {
+        // The PPU only emits visible dots, anything else is a timing bug
+        debug_assert!(x < self.width && y < self.height, "Pixel ({}, {}) is off screen!", x, y);
+
+        if x < self.width && y < self.height {
+            self.buffer[y * self.width + x] = color;
+            self.emphasis[y * self.width + x] = emphasis;
         }
     }
 
@@ -1,3 +1,5 @@
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "savestate", derive(serde::Serialize, serde::Deserialize))]
 pub struct ScreenState {
     pub bg_next_tile_id: u8,
     pub bg_next_tile_attribute: u8,
@@ -10,8 +12,17 @@ pub struct ScreenState {
     pub sprite_shift_pattern_lo: [u8; 8],
     pub sprite_shift_pattern_hi: [u8; 8],
     pub sprite_count: u8,
+    // OAM entries the selected sprites were copied from
+    pub sprite_indices: [u8; 8],
     pub sprite_zero_occured: bool,
     pub sprite_zero_rendering: bool,
+    // Sprites for the next scanline, filled in while the current one is drawn
+    pub secondary_oam: [u8; 0x20],
+    pub secondary_sprite_count: u8,
+    pub secondary_sprite_zero: bool,
+    pub secondary_sprite_indices: [u8; 8],
+    pub eval_sprite_index: u8,
+    pub eval_sprite_byte: u8,
 }
 
 impl ScreenState {
@@ -28,8 +39,15 @@ impl ScreenState {
             sprite_shift_pattern_lo: [0; 8],
             sprite_shift_pattern_hi: [0; 8],
             sprite_count: 0,
+            sprite_indices: [0; 8],
             sprite_zero_occured: false,
             sprite_zero_rendering: false,
+            secondary_oam: [0xFF; 0x20],
+            secondary_sprite_count: 0,
+            secondary_sprite_zero: false,
+            secondary_sprite_indices: [0; 8],
+            eval_sprite_index: 0,
+            eval_sprite_byte: 0,
         }
     }
 }
@@ -0,0 +1,46 @@
+/// Small xorshift generator, so seeded runs don't depend on the host.
+pub struct Xorshift64 {
+    state: u64,
+}
+
+impl Xorshift64 {
+    pub fn new(seed: u64) -> Self {
+        // Xorshift gets stuck on a zero state
+        let state = if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed };
+
+        Self { state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xorshift_is_reproducible() {
+        let mut first = Xorshift64::new(0x1234);
+        let mut second = Xorshift64::new(0x1234);
+
+        for _ in 0..100 {
+            assert_eq!(first.next_u64(), second.next_u64(), "Same seed should produce the same sequence!");
+        }
+    }
+
+    #[test]
+    fn test_xorshift_zero_seed_is_not_stuck() {
+        let mut random = Xorshift64::new(0);
+
+        assert_ne!(random.next_u64(), 0, "Zero seed should not produce a zero sequence!");
+    }
+}
@@ -12,35 +12,40 @@ pub enum CpuStatusRegisterFlags {
     Negative = 1 << 7,
 }
 
+// Break and Unused have no latch in the chip, Break only exists in the copies
+// pushed to the stack and Unused always reads as 1
+const LATCHED_FLAGS: u8 = !(CpuStatusRegisterFlags::Break as u8 | CpuStatusRegisterFlags::Unused as u8);
+
+#[derive(Clone)]
 pub struct CpuStatusRegister {
     value: u8
 }
 
 impl CpuStatusRegister {
     pub fn new() -> Self {
-        // Break and InterruptDisable always true when initialized
+        // InterruptDisable always true when initialized
         Self {
-            value: 0b0010_0100, 
+            value: 0b0000_0100,
         }
     }
 }
 
 impl Register<CpuStatusRegisterFlags, u8> for CpuStatusRegister {
     fn get(&self) -> u8 {
-        self.value
+        self.value | CpuStatusRegisterFlags::Unused as u8
     }
 
     fn set(&mut self, value: u8) {
-        self.value = value
+        self.value = value & LATCHED_FLAGS
     }
 
     fn get_flag(&self, flag: CpuStatusRegisterFlags) -> bool {
-        self.value & flag as u8 != 0
+        self.get() & flag as u8 != 0
     }
 
     fn set_flag(&mut self, flag: CpuStatusRegisterFlags, active: bool) {
         if active {
-            self.value |= flag as u8;
+            self.value |= flag as u8 & LATCHED_FLAGS;
         } else {
             self.value &= !(flag as u8);
         }
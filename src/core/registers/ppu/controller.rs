@@ -7,11 +7,12 @@ pub enum PpuControllerRegisterFlags {
     AddressIncrement = 1 << 2,
     SpritesPatternTable = 1 << 3,
     BackgroundPatternTable = 1 << 4,
-    _SpriteSize = 1 << 5,
+    SpriteSize = 1 << 5,
     _MasterSlaveSelect = 1 << 6,
     GenerateVBlankNMI = 1 << 7,
 }
 
+#[derive(Clone)]
 pub struct PpuControllerRegister {
     value: u8
 }
@@ -12,6 +12,7 @@ pub enum PpuDataRegisterFlags {
     _Bit7 = 1 << 7,
 }
 
+#[derive(Clone)]
 pub struct PpuDataRegister {
     value: u8
 }
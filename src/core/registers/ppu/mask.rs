@@ -2,7 +2,7 @@ use crate::core::registers::Register;
 
 #[repr(u8)]
 pub enum PpuMaskRegisterFlags {
-    _Greyscale = 1 << 0,
+    Greyscale = 1 << 0,
     ShowBackgroundLeftmost = 1 << 1,
     ShowSpritesLeftmost = 1 << 2,
     ShowBackground = 1 << 3,
@@ -12,6 +12,7 @@ pub enum PpuMaskRegisterFlags {
     _EmphasizeBlue = 1 << 7,
 }
 
+#[derive(Clone)]
 pub struct PpuMaskRegister {
     value: u8
 }
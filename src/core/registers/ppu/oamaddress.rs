@@ -12,6 +12,7 @@ pub enum PpuOamAddressRegisterFlags {
     _Bit7 = 1 << 7,
 }
 
+#[derive(Clone)]
 pub struct PpuOamAddressRegister {
     value: u8
 }
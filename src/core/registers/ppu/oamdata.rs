@@ -12,6 +12,7 @@ pub enum PpuOamDataRegisterFlags {
     _Bit7 = 1 << 7,
 }
 
+#[derive(Clone)]
 pub struct PpuOamDataRegister {
     value: u8
 }
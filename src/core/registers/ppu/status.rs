@@ -7,6 +7,7 @@ pub enum PpuStatusRegisterFlags {
     VBlank = 1 << 7,
 }
 
+#[derive(Clone)]
 pub struct PpuStatusRegister {
     value: u8
 }
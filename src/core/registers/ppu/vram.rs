@@ -1,3 +1,4 @@
+#[derive(Clone)]
 pub struct PpuVRamRegister {
     coarse_x: u16,
     coarse_y: u16,
@@ -39,6 +40,15 @@ impl PpuVRamRegister {
         ) & 0x3FFF
     }
 
+    // All 15 bits of the register, `get` drops the top bit of fine Y as the address bus does
+    pub fn get_internal(&self) -> u16 {
+        (self.fine_y << 12) | (self.nametable_y << 11) | (self.nametable_x << 10) | (self.coarse_y << 5) | self.coarse_x
+    }
+
+    pub fn set_internal(&mut self, value: u16) {
+        self.update_bits(value);
+    }
+
     pub fn set_coarse_x(&mut self, coarse_x: u16) {
         self.coarse_x = coarse_x & 0b11111;
     }
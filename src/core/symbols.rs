@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub struct SymbolError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SymbolError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub label: String,
+    pub comment: Option<String>,
+}
+
+/// Labels for CPU addresses, used by the trace and the disassembler in place of hex.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SymbolTable {
+    symbols: HashMap<u16, Symbol>,
+}
+
+impl SymbolTable {
+    // FCEUX `.nl` files have one `$ADDR#Label#Comment` per line, the comment is optional
+    pub fn parse_nl(source: &str) -> Result<Self, SymbolError> {
+        let mut table = Self::default();
+
+        for (index, line) in source.lines().enumerate() {
+            let error = |message: &str| SymbolError { line: index + 1, message: message.to_string() };
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut fields = line.splitn(3, '#');
+            let address = fields
+                .next()
+                .and_then(|address| address.strip_prefix('$'))
+                .ok_or_else(|| error("address should start with `$`"))?;
+            let address = u16::from_str_radix(address, 16)
+                .map_err(|_| error("address should be at most four hex digits"))?;
+            let label = fields
+                .next()
+                .map(str::trim)
+                .filter(|label| !label.is_empty())
+                .ok_or_else(|| error("label is missing"))?;
+            let comment = fields
+                .next()
+                .map(|comment| comment.trim_end_matches('#').trim())
+                .filter(|comment| !comment.is_empty());
+
+            table.insert(address, label, comment);
+        }
+
+        Ok(table)
+    }
+
+    pub fn insert(&mut self, address: u16, label: &str, comment: Option<&str>) {
+        self.symbols.insert(address, Symbol {
+            label: label.to_string(),
+            comment: comment.map(str::to_string),
+        });
+    }
+
+    pub fn get(&self, address: u16) -> Option<&Symbol> {
+        self.symbols.get(&address)
+    }
+
+    // Label of the address, or `$XXXX` when there is none
+    pub fn format_address(&self, address: u16) -> String {
+        match self.get(address) {
+            Some(symbol) => symbol.label.clone(),
+            None => format!("${:04X}", address),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nl_symbols() {
+        let source = "$8E19#UpdateSprites#Copies sprites to OAM\n\n$0700#FrameCounter#\n$C000#Reset\n";
+        let table = SymbolTable::parse_nl(source).unwrap();
+
+        assert_eq!(table.get(0x8E19), Some(&Symbol {
+            label: "UpdateSprites".into(),
+            comment: Some("Copies sprites to OAM".into()),
+        }), "Label and comment should be parsed!");
+        assert_eq!(table.get(0x0700).unwrap().comment, None, "Empty comment should be dropped!");
+        assert_eq!(table.format_address(0xC000), "Reset", "Known address should format as its label!");
+        assert_eq!(table.format_address(0xC001), "$C001", "Unknown address should fall back to hex!");
+    }
+
+    #[test]
+    fn test_parse_nl_rejects_malformed_lines() {
+        let cases = [
+            ("8E19#Label#", 1, "address should start with `$`"),
+            ("$C000#Reset\n$8E1G#Label#", 2, "address should be at most four hex digits"),
+            ("$18E19#Label#", 1, "address should be at most four hex digits"),
+            ("$8E19##comment", 1, "label is missing"),
+            ("$8E19", 1, "label is missing"),
+        ];
+
+        for (source, line, message) in cases {
+            assert_eq!(
+                SymbolTable::parse_nl(source),
+                Err(SymbolError { line, message: message.into() }),
+                "`{}` should be rejected!", source
+            );
+        }
+    }
+}
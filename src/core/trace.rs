@@ -0,0 +1,64 @@
+use std::io::{self, BufWriter, Write};
+
+/// Where the disassembler sends its trace lines.
+pub enum TraceSink {
+    Writer(Box<dyn Write>),
+    Callback(Box<dyn FnMut(&str)>),
+}
+
+impl TraceSink {
+    pub fn writer(writer: impl Write + 'static) -> Self {
+        Self::Writer(Box::new(writer))
+    }
+
+    pub fn callback(callback: impl FnMut(&str) + 'static) -> Self {
+        Self::Callback(Box::new(callback))
+    }
+
+    // Stdout is line buffered, a busy trace would be slowed down by a syscall per line
+    pub fn stdout() -> Self {
+        Self::writer(BufWriter::new(io::stdout()))
+    }
+
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        match self {
+            Self::Writer(writer) => writeln!(writer, "{}", line),
+            Self::Callback(callback) => {
+                callback(line);
+                Ok(())
+            },
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Writer(writer) => writer.flush(),
+            Self::Callback(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _buffer: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_error_is_returned() {
+        let mut sink = TraceSink::writer(ClosedPipe);
+        let error = sink.write_line("0400  EA        NOP").unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe, "Write error should reach the caller!");
+    }
+}
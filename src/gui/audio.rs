@@ -0,0 +1,50 @@
+use sdl2::{
+    Sdl,
+    audio::{AudioQueue, AudioSpecDesired},
+};
+
+use crate::core::frontend::AudioSink;
+
+pub struct Audio {
+    queue: AudioQueue<f32>,
+}
+
+impl Audio {
+    pub fn new(context: &Sdl) -> Self {
+        let audio_subsystem = context
+            .audio()
+            .expect("Unable to create audio subsystem!");
+
+        let spec = AudioSpecDesired {
+            freq: Some(44100),
+            channels: Some(1),
+            samples: None,
+        };
+
+        let queue = audio_subsystem
+            .open_queue::<f32, _>(None, &spec)
+            .expect("Unable to open audio queue!");
+
+        queue.resume();
+
+        Self {
+            queue,
+        }
+    }
+
+    // Drops queued samples so nothing keeps playing during shutdown
+    pub fn stop(&mut self) {
+        self.queue.pause();
+        self.queue.clear();
+    }
+}
+
+impl AudioSink for Audio {
+    fn push(&mut self, samples: &[f32]) {
+        if !samples.is_empty() {
+            self.queue
+                .queue_audio(samples)
+                .expect("Unable to queue audio samples!");
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod window;
 pub mod videobuffer;
 pub mod palette;
+pub mod audio;
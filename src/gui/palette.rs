@@ -66,3 +66,23 @@ pub static PALETTE: [Color; 64] = [
     Color::RGB(0x00, 0x00, 0x00),
     Color::RGB(0x00, 0x00, 0x00),
 ];
+
+// Each emphasis bit dims the two other channels
+const EMPHASIS_ATTENUATION: f32 = 0.816;
+
+// RGB of a palette index with the PPUMASK emphasis bits (red, green, blue from bit 0)
+// applied, the blacks in columns $E and $F aren't affected
+pub fn emphasized_color(index: u8, emphasis: u8) -> Color {
+    let color = PALETTE[index as usize & 0x3F];
+
+    if emphasis == 0 || index & 0x0E == 0x0E {
+        return color;
+    }
+
+    let attenuate = |channel: u8, bit: u8| {
+        let dimming_bits = (emphasis & !bit & 0b111).count_ones() as i32;
+        (channel as f32 * EMPHASIS_ATTENUATION.powi(dimming_bits)) as u8
+    };
+
+    Color::RGB(attenuate(color.r, 0b001), attenuate(color.g, 0b010), attenuate(color.b, 0b100))
+}
@@ -1,5 +1,35 @@
 use sdl2::pixels::Color;
 
+use crate::core::frontend::{Frame, InputState};
+use crate::core::joypad::JoypadButton;
+use crate::core::ppu::DebugMaskFlags;
+
+use super::palette::emphasized_color;
+
+const OVERLAY_PAD_WIDTH: usize = 42;
+const OVERLAY_PAD_HEIGHT: usize = 18;
+const OVERLAY_BUTTON_RADIUS: isize = 2;
+const OVERLAY_BACKGROUND: Color = Color::RGB(0x20, 0x20, 0x20);
+const OVERLAY_FOREGROUND: Color = Color::RGB(0xF0, 0xF0, 0xF0);
+
+const DEBUG_SPRITE_ZERO_HIT: Color = Color::RGB(0xFF, 0x00, 0x00);
+const DEBUG_SPRITE_ZERO_OUTLINE: Color = Color::RGB(0xFF, 0xFF, 0x00);
+const DEBUG_SPRITE_TINT: Color = Color::RGB(0x00, 0xFF, 0x00);
+
+const WAVEFORM_BACKGROUND: Color = Color::RGB(0x10, 0x10, 0x10);
+
+// Button centers relative to the top left corner of a pad
+const OVERLAY_BUTTONS: [(JoypadButton, usize, usize); 8] = [
+    (JoypadButton::Up, 8, 4),
+    (JoypadButton::Down, 8, 13),
+    (JoypadButton::Left, 4, 9),
+    (JoypadButton::Right, 12, 9),
+    (JoypadButton::Select, 19, 11),
+    (JoypadButton::Start, 25, 11),
+    (JoypadButton::B, 32, 9),
+    (JoypadButton::A, 38, 9),
+];
+
 pub struct VideoBuffer {
     width: usize,
     buffer: Vec<u8>,
@@ -25,4 +55,232 @@ impl VideoBuffer {
                 .copy_from_slice(&[color.r, color.g, color.b]);
         }
     }
+
+    fn get_pixel(&self, x: usize, y: usize) -> Color {
+        let index = y * self.width * 3 + x * 3;
+        let pixel = &self.buffer[index..index + 3];
+
+        Color::RGB(pixel[0], pixel[1], pixel[2])
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        let right = (x + width).min(self.width);
+
+        for y in y..y + height {
+            for x in x..right {
+                self.set_pixel(x, y, color);
+            }
+        }
+    }
+
+    pub fn draw_circle(&mut self, x: usize, y: usize, radius: isize, color: Color, filled: bool) {
+        let outer = radius * radius + radius;
+        let inner = if filled { 0 } else { radius * radius - radius };
+
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let distance = dx * dx + dy * dy;
+                let (x, y) = (x as isize + dx, y as isize + dy);
+
+                if distance >= inner && distance <= outer && x >= 0 && y >= 0 && (x as usize) < self.width {
+                    self.set_pixel(x as usize, y as usize, color);
+                }
+            }
+        }
+    }
+
+    // Both joypads in the bottom left corner, filled circles for pressed buttons
+    pub fn draw_input_overlay(&mut self, input: &InputState) {
+        let height = self.buffer.len() / (self.width * 3);
+
+        for (joypad, top) in [(0, height - 2 * OVERLAY_PAD_HEIGHT - 4), (1, height - OVERLAY_PAD_HEIGHT - 2)] {
+            let left = 2;
+            self.fill_rect(left, top, OVERLAY_PAD_WIDTH, OVERLAY_PAD_HEIGHT, OVERLAY_BACKGROUND);
+
+            for (button, x, y) in OVERLAY_BUTTONS {
+                let pressed = input.is_pressed(joypad, button);
+                self.draw_circle(left + x, top + y, OVERLAY_BUTTON_RADIUS, OVERLAY_FOREGROUND, pressed);
+            }
+        }
+    }
+
+    // Paints sprite zero hits red over a yellow outline of sprite zero, and optionally
+    // tints every pixel where a sprite won priority over the background
+    pub fn draw_debug_mask(&mut self, debug_mask: &[u8], show_sprite_pixels: bool) {
+        let width = self.width;
+        let height = debug_mask.len() / width;
+        let has_flag = |x: usize, y: usize, flag: DebugMaskFlags| debug_mask[y * width + x] & flag as u8 != 0;
+
+        for y in 0..height {
+            for x in 0..width {
+                if show_sprite_pixels && has_flag(x, y, DebugMaskFlags::SpritePixel) {
+                    let color = self.get_pixel(x, y);
+                    let blend = |channel: u8, tint: u8| ((channel as u16 + tint as u16) / 2) as u8;
+
+                    self.set_pixel(x, y, Color::RGB(
+                        blend(color.r, DEBUG_SPRITE_TINT.r),
+                        blend(color.g, DEBUG_SPRITE_TINT.g),
+                        blend(color.b, DEBUG_SPRITE_TINT.b),
+                    ));
+                }
+
+                if has_flag(x, y, DebugMaskFlags::SpriteZeroArea) {
+                    let is_edge = x == 0 || y == 0 || x + 1 == width || y + 1 == height
+                        || !has_flag(x - 1, y, DebugMaskFlags::SpriteZeroArea)
+                        || !has_flag(x + 1, y, DebugMaskFlags::SpriteZeroArea)
+                        || !has_flag(x, y - 1, DebugMaskFlags::SpriteZeroArea)
+                        || !has_flag(x, y + 1, DebugMaskFlags::SpriteZeroArea);
+
+                    if is_edge {
+                        self.set_pixel(x, y, DEBUG_SPRITE_ZERO_OUTLINE);
+                    }
+                }
+
+                if has_flag(x, y, DebugMaskFlags::SpriteZeroHit) {
+                    self.set_pixel(x, y, DEBUG_SPRITE_ZERO_HIT);
+                }
+            }
+        }
+    }
+
+    // One trace of levels between 0.0 and 1.0 stretched over the whole width, 0.0 on the
+    // bottom row. Consecutive samples are joined with vertical lines so edges stay visible.
+    pub fn draw_waveform(&mut self, samples: &[f32], top: usize, height: usize, color: Color) {
+        self.fill_rect(0, top, self.width, height, WAVEFORM_BACKGROUND);
+
+        if samples.is_empty() {
+            return;
+        }
+
+        let row = |level: f32| top + height - 1 - (level.clamp(0.0, 1.0) * (height - 1) as f32).round() as usize;
+        let mut previous = None;
+
+        for x in 0..self.width {
+            let y = row(samples[x * samples.len() / self.width]);
+            let (from, to) = previous.map_or((y, y), |previous: usize| (previous.min(y), previous.max(y)));
+
+            for y in from..=to {
+                self.set_pixel(x, y, color);
+            }
+
+            previous = Some(y);
+        }
+    }
+
+    pub fn draw_frame(&mut self, frame: &Frame) {
+        for x in 0..frame.width() {
+            for y in 0..frame.height() {
+                let color = frame.get_pixel(x, y);
+                let emphasis = frame.get_emphasis(x, y);
+
+                self.set_pixel(
+                    x,
+                    y,
+                    emphasized_color(color, emphasis)
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::ppu::ScreenBuffer;
+    use super::*;
+
+    // Center of a button on the first joypad of a 256x240 buffer
+    fn button_center(button: JoypadButton) -> (usize, usize) {
+        let (_, x, y) = OVERLAY_BUTTONS
+            .into_iter()
+            .find(|(overlay_button, _, _)| *overlay_button as u8 == button as u8)
+            .unwrap();
+
+        (2 + x, 240 - 2 * OVERLAY_PAD_HEIGHT - 4 + y)
+    }
+
+    #[test]
+    fn test_input_overlay_fills_pressed_buttons() {
+        let mut videobuffer = VideoBuffer::new(256, 240);
+        let mut input = InputState::default();
+        input.set_button(0, JoypadButton::A, true);
+        videobuffer.draw_input_overlay(&input);
+
+        let (x, y) = button_center(JoypadButton::A);
+        assert_eq!(videobuffer.get_pixel(x, y), OVERLAY_FOREGROUND, "Pressed button should be filled!");
+
+        let (x, y) = button_center(JoypadButton::B);
+        assert_eq!(videobuffer.get_pixel(x, y), OVERLAY_BACKGROUND, "Released button should be hollow!");
+        assert_eq!(videobuffer.get_pixel(x + 2, y), OVERLAY_FOREGROUND, "Released button should have an outline!");
+    }
+
+    #[test]
+    fn test_input_overlay_draws_second_joypad() {
+        let mut videobuffer = VideoBuffer::new(256, 240);
+        let mut input = InputState::default();
+        input.set_button(1, JoypadButton::Start, true);
+        videobuffer.draw_input_overlay(&input);
+
+        let (x, y) = button_center(JoypadButton::Start);
+        assert_eq!(videobuffer.get_pixel(x, y), OVERLAY_BACKGROUND, "First joypad should not show the second joypad's buttons!");
+        assert_eq!(videobuffer.get_pixel(x, y + OVERLAY_PAD_HEIGHT + 2), OVERLAY_FOREGROUND, "Second joypad should show Start pressed!");
+        assert_eq!(videobuffer.get_pixel(0, 0), Color::RGB(0, 0, 0), "Overlay should stay in the corner!");
+    }
+
+    #[test]
+    fn test_debug_mask_outlines_sprite_zero() {
+        let mut videobuffer = VideoBuffer::new(256, 240);
+        let mut debug_mask = vec![0; 256 * 240];
+
+        for y in 10..18 {
+            for x in 20..28 {
+                debug_mask[y * 256 + x] |= DebugMaskFlags::SpriteZeroArea as u8 | DebugMaskFlags::SpritePixel as u8;
+            }
+        }
+        debug_mask[12 * 256 + 20] |= DebugMaskFlags::SpriteZeroHit as u8;
+
+        videobuffer.draw_debug_mask(&debug_mask, false);
+        assert_eq!(videobuffer.get_pixel(20, 12), DEBUG_SPRITE_ZERO_HIT, "Hit pixel should be red!");
+        assert_eq!(videobuffer.get_pixel(27, 17), DEBUG_SPRITE_ZERO_OUTLINE, "Sprite zero corner should be outlined!");
+        assert_eq!(videobuffer.get_pixel(23, 13), Color::RGB(0, 0, 0), "Inside of sprite zero should not be tinted!");
+
+        videobuffer.draw_debug_mask(&debug_mask, true);
+        assert_eq!(videobuffer.get_pixel(23, 13), Color::RGB(0x00, 0x7F, 0x00), "Sprite pixels should be tinted green!");
+    }
+
+    #[test]
+    fn test_waveform_plots_levels_bottom_up() {
+        let mut videobuffer = VideoBuffer::new(4, 20);
+        let trace = Color::RGB(0xFF, 0xFF, 0x00);
+        videobuffer.draw_waveform(&[0.0, 0.0, 1.0, 1.0, 0.5, 0.5, 0.0, 0.0], 10, 10, trace);
+
+        assert_eq!(videobuffer.get_pixel(0, 19), trace, "Level 0.0 should be on the bottom row!");
+        assert_eq!(videobuffer.get_pixel(1, 10), trace, "Level 1.0 should be on the top row!");
+        assert_eq!(videobuffer.get_pixel(2, 14), trace, "Level 0.5 should be in the middle!");
+        assert_eq!(videobuffer.get_pixel(1, 15), trace, "Rising edge should be joined with a vertical line!");
+        assert_eq!(videobuffer.get_pixel(0, 10), WAVEFORM_BACKGROUND, "Rows away from the trace should be background!");
+        assert_eq!(videobuffer.get_pixel(0, 9), Color::RGB(0, 0, 0), "Rows above the trace area should stay untouched!");
+    }
+
+    #[test]
+    fn test_red_emphasis_dims_green_and_blue() {
+        let solid_frame = |emphasis: u8| {
+            let mut screen_buffer = ScreenBuffer::new(256, 240);
+            for y in 0..240 {
+                for x in 0..256 {
+                    screen_buffer.set_pixel(x, y, 0x30, emphasis);
+                }
+            }
+
+            let mut videobuffer = VideoBuffer::new(256, 240);
+            videobuffer.draw_frame(&Frame::from(&screen_buffer));
+            videobuffer.get_pixel(100, 100)
+        };
+
+        let plain = solid_frame(0b000);
+        let red = solid_frame(0b001);
+
+        assert_eq!(red.r, plain.r, "Red emphasis should keep the red channel!");
+        assert!(red.g < plain.g, "Red emphasis should dim the green channel!");
+        assert!(red.b < plain.b, "Red emphasis should dim the blue channel!");
+    }
 }
@@ -2,19 +2,46 @@ use std::time::{Instant, Duration};
 
 use sdl2::{
     *,
+    event::Event,
+    keyboard::Scancode,
     render::{TextureCreator, Texture},
     video::WindowContext,
     pixels::PixelFormatEnum,
 };
 
-use crate::core::ppu::Ppu;
+use crate::core::apu::{WaveformChannel, Waveforms};
+use crate::core::frontend::{Frame, Hotkey, InputSource, InputState, VideoSink};
+use crate::core::joypad::JoypadButton;
 
-use super::{videobuffer::VideoBuffer, palette::PALETTE};
+use super::videobuffer::VideoBuffer;
+
+// Traces are stacked top to bottom in `WaveformChannel::ALL` order, one row apart
+const WAVEFORM_TRACE_HEIGHT: usize = 48;
+const WAVEFORM_COLORS: [pixels::Color; 4] = [
+    pixels::Color::RGB(0xF8, 0x38, 0x00),
+    pixels::Color::RGB(0xF8, 0xB8, 0x00),
+    pixels::Color::RGB(0x00, 0xB8, 0xF8),
+    pixels::Color::RGB(0xF0, 0xF0, 0xF0),
+];
 
 pub struct Window {
     context: Sdl,
     canvas: render::Canvas<video::Window>,
     videobuffer: VideoBuffer,
+    show_input_overlay: bool,
+    show_sprite_pixels: bool,
+    input: InputState,
+    key_bindings: Vec<(Scancode, JoypadButton)>,
+    quit_requested: bool,
+    // Open while the APU records waveforms, toggled with F5
+    waveform_canvas: Option<render::Canvas<video::Window>>,
+    waveform_buffer: VideoBuffer,
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Window {
@@ -46,28 +73,114 @@ impl Window {
             context,
             canvas,
             videobuffer: VideoBuffer::new(256, 240),
+            show_input_overlay: false,
+            show_sprite_pixels: false,
+            input: InputState::default(),
+            key_bindings: vec![
+                (Scancode::Z, JoypadButton::A),
+                (Scancode::X, JoypadButton::B),
+                (Scancode::RShift, JoypadButton::Select),
+                (Scancode::Return, JoypadButton::Start),
+                (Scancode::Up, JoypadButton::Up),
+                (Scancode::Down, JoypadButton::Down),
+                (Scancode::Left, JoypadButton::Left),
+                (Scancode::Right, JoypadButton::Right),
+            ],
+            quit_requested: false,
+            waveform_canvas: None,
+            waveform_buffer: VideoBuffer::new(256, WAVEFORM_TRACE_HEIGHT * WaveformChannel::ALL.len()),
         }
     }
 
-    pub fn render(&mut self, ppu: &Ppu) {
+    // Config parsing checks key names with this, so typos are reported before the window uses them
+    pub fn is_key_name(name: &str) -> bool {
+        Scancode::from_name(name).is_some()
+    }
+
+    // Rebinds joypad 1 buttons to SDL key names like "Z" or "Left Shift", unknown names are skipped
+    pub fn set_key_bindings(&mut self, bindings: &[(JoypadButton, String)]) {
+        for (button, key_name) in bindings {
+            let Some(scancode) = Scancode::from_name(key_name) else {
+                continue;
+            };
+
+            self.key_bindings.retain(|(_, bound)| *bound as u8 != *button as u8);
+            self.key_bindings.push((scancode, *button));
+        }
+    }
+
+    // Set once the window is closed, the main loop then shuts down and flushes saves
+    pub fn is_quit_requested(&self) -> bool {
+        self.quit_requested
+    }
+
+    pub fn clear(&mut self) {
+        self.canvas.clear();
+    }
+
+    pub fn present_canvas(&mut self) {
+        self.canvas.present();
+    }
+
+    pub fn texture_creator(&self) -> TextureCreator<WindowContext> {
+        self.canvas.texture_creator()
+    }
+
+    pub fn update_canvas(&mut self, texture: &Texture) {
+        self.canvas
+            .copy(texture, None, None)
+            .expect("Unable to copy texture into canvas!");
+    }
+
+    fn create_waveform_canvas(&self) -> render::Canvas<video::Window> {
+        let height = (WAVEFORM_TRACE_HEIGHT * WaveformChannel::ALL.len()) as u32;
+
+        let window = self.context
+            .video()
+            .expect("Unable to create video subsystem!")
+            .window("Nestify - Waveforms", 256 * 2, height * 2)
+            .build()
+            .expect("Unable to create waveform window!");
+
+        let mut canvas = window
+            .into_canvas()
+            .build()
+            .expect("Unable to create waveform canvas!");
+
+        canvas
+            .set_scale(2.0, 2.0)
+            .expect("Unable to set scale for waveform canvas!");
+
+        canvas
+    }
+
+    pub fn context(&self) -> &Sdl {
+        &self.context
+    }
+
+    pub fn event_pump(&mut self) -> EventPump {
+        self.context
+            .event_pump()
+            .expect("Unable to get event pump!")
+    }
+}
+
+impl VideoSink for Window {
+    fn present(&mut self, frame: &Frame) {
         let target_fps: u32 = 144;
         let frame_duration = Duration::from_secs(1) / target_fps;
         let last_frame_time = Instant::now();
 
         self.clear();
 
-        let screen_buffer = ppu.get_screen_buffer();
+        self.videobuffer.draw_frame(frame);
 
-        for x in 0..256 {
-            for y in 0..240 {
-                let color = screen_buffer.get_pixel(x, y);
+        if let Some(debug_mask) = frame.debug_mask() {
+            self.videobuffer.draw_debug_mask(debug_mask, self.show_sprite_pixels);
+        }
 
-                self.videobuffer.set_pixel(
-                    x,
-                    y,
-                    PALETTE[color as usize]
-                );
-            }
+        if self.show_input_overlay {
+            self.videobuffer.draw_input_overlay(&self.input);
         }
 
         let texture_creator = self.texture_creator();
@@ -80,7 +193,7 @@ impl Window {
             .expect("Unable to update texture!");
 
         self.update_canvas(&texture);
-        self.present();
+        self.present_canvas();
         
         let elapsed_time = last_frame_time.elapsed();
         if elapsed_time < frame_duration {
@@ -89,27 +202,76 @@ impl Window {
         }
     }
 
-    pub fn clear(&mut self) {
-        self.canvas.clear();
+    fn show_input(&mut self, input: &InputState) {
+        self.input = *input;
     }
 
-    pub fn present(&mut self) {
-        self.canvas.present();
-    }
+    fn show_waveforms(&mut self, waveforms: Option<&Waveforms>) {
+        let Some(waveforms) = waveforms else {
+            self.waveform_canvas = None;
+            return;
+        };
 
-    pub fn texture_creator(&self) -> TextureCreator<WindowContext> {
-        self.canvas.texture_creator()
-    }
+        if self.waveform_canvas.is_none() {
+            self.waveform_canvas = Some(self.create_waveform_canvas());
+        }
 
-    pub fn update_canvas(&mut self, texture: &Texture) {
-        self.canvas
-            .copy(texture, None, None)
-            .expect("Unable to copy texture into canvas!");
+        for (index, channel) in WaveformChannel::ALL.into_iter().enumerate() {
+            let top = index * WAVEFORM_TRACE_HEIGHT;
+            self.waveform_buffer.draw_waveform(&waveforms.samples(channel), top, WAVEFORM_TRACE_HEIGHT - 1, WAVEFORM_COLORS[index]);
+        }
+
+        let height = WAVEFORM_TRACE_HEIGHT * WaveformChannel::ALL.len();
+        let canvas = self.waveform_canvas.as_mut().unwrap();
+        let texture_creator = canvas.texture_creator();
+        let mut texture = texture_creator
+            .create_texture_target(PixelFormatEnum::RGB24, 256, height as u32)
+            .expect("Unable to create waveform texture!");
+
+        texture
+            .update(None, self.waveform_buffer.get(), 256 * 3)
+            .expect("Unable to update waveform texture!");
+
+        canvas
+            .copy(&texture, None, None)
+            .expect("Unable to copy waveform texture into canvas!");
+        canvas.present();
     }
+}
 
-    pub fn event_pump(&mut self) -> EventPump {
-        self.context
-            .event_pump()
-            .expect("Unable to get event pump!")
+impl InputSource for Window {
+    fn poll(&mut self) -> InputState {
+        let mut event_pump = self.event_pump();
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => self.quit_requested = true,
+                Event::KeyDown { scancode: Some(Scancode::F6), repeat: false, .. } => {
+                    self.show_input_overlay = !self.show_input_overlay;
+                },
+                Event::KeyDown { scancode: Some(Scancode::F8), repeat: false, .. } => {
+                    self.show_sprite_pixels = !self.show_sprite_pixels;
+                },
+                _ => (),
+            }
+        }
+
+        let keyboard_state = event_pump.keyboard_state();
+        let mut input = InputState::default();
+
+        for (scancode, button) in &self.key_bindings {
+            input.set_button(0, *button, keyboard_state.is_scancode_pressed(*scancode));
+        }
+
+        input.set_hotkey(Hotkey::SwitchDiskSide, keyboard_state.is_scancode_pressed(Scancode::F2));
+        input.set_hotkey(Hotkey::WaveformView, keyboard_state.is_scancode_pressed(Scancode::F5));
+        input.set_hotkey(Hotkey::SpriteZeroDebug, keyboard_state.is_scancode_pressed(Scancode::F7));
+        input.set_hotkey(Hotkey::Pause, keyboard_state.is_scancode_pressed(Scancode::P));
+        input.set_hotkey(
+            Hotkey::FrameAdvance,
+            keyboard_state.is_scancode_pressed(Scancode::Backslash) || keyboard_state.is_scancode_pressed(Scancode::F),
+        );
+
+        input
     }
 }
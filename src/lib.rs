@@ -1,2 +1,3 @@
 pub mod core;
+#[cfg(feature = "sdl")]
 pub mod gui;
@@ -1,40 +1,357 @@
-use std::rc::Rc;
-use std::cell::RefCell;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-use nestify::core::cpu::Cpu;
-use nestify::core::clock::Clock;
-use nestify::core::bus::Bus;
+use nestify::core::battery::{Autosave, SaveFile, AUTOSAVE_INTERVAL};
 use nestify::core::cartridge::Cartridge;
-use nestify::core::ppu::Ppu;
+use nestify::core::config::{Config, ConfigOverrides};
+use nestify::core::emulator::Emulator;
+use nestify::core::hash::fnv1a;
+use nestify::core::netplay::Netplay;
+use nestify::core::symbols::SymbolTable;
+use nestify::core::trace::TraceSink;
+use nestify::gui::audio::Audio;
 use nestify::gui::window::Window;
-use sdl2::event::Event;
 
-fn main() {
+const DEFAULT_ROM_PATH: &str = "super_mario.nes";
+const DEFAULT_FDS_BIOS_PATH: &str = "disksys.rom";
+const DEFAULT_NETPLAY_PORT: u16 = 7878;
+
+// Run-ahead is turned off when emulating a frame keeps taking longer than the console shows it
+const OVER_BUDGET_FRAMES: usize = 30;
+
+enum NetplayMode {
+    Host(u16),
+    Join(String),
+}
+
+struct Options {
+    rom_path: String,
+    fds_bios_path: String,
+    config: ConfigOverrides,
+    netplay: Option<NetplayMode>,
+}
+
+// nestify [ROM] [--fds-bios PATH] [--overclock SCANLINES] [--run-ahead FRAMES]
+//         [--host [PORT] | --join ADDRESS]
+fn parse_options(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        rom_path: DEFAULT_ROM_PATH.to_string(),
+        fds_bios_path: DEFAULT_FDS_BIOS_PATH.to_string(),
+        config: ConfigOverrides::default(),
+        netplay: None,
+    };
+    let mut args = args.peekable();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fds-bios" => {
+                options.fds_bios_path = args.next().ok_or("Missing FDS BIOS path!")?;
+            },
+            "--overclock" => {
+                let scanlines = args
+                    .next()
+                    .and_then(|scanlines| scanlines.parse().ok())
+                    .ok_or("Invalid amount of extra VBlank scanlines!")?;
+
+                options.config
+                    .set_extra_vblank_scanlines(scanlines)
+                    .map_err(|message| format!("Invalid `--overclock`, {}!", message))?;
+            },
+            "--run-ahead" => {
+                let frames = args
+                    .next()
+                    .and_then(|frames| frames.parse().ok())
+                    .ok_or("Invalid amount of run-ahead frames!")?;
+
+                options.config
+                    .set_run_ahead_frames(frames)
+                    .map_err(|message| format!("Invalid `--run-ahead`, {}!", message))?;
+            },
+            "--host" => {
+                let port = args
+                    .next_if(|port| !port.starts_with("--") && port.parse::<u16>().is_ok())
+                    .map(|port| port.parse().expect("Invalid netplay port!"))
+                    .unwrap_or(DEFAULT_NETPLAY_PORT);
+
+                options.netplay = Some(NetplayMode::Host(port));
+            },
+            "--join" => {
+                let address = args.next().ok_or("Missing netplay address to join!")?;
+                options.netplay = Some(NetplayMode::Join(address));
+            },
+            _ if arg.starts_with("--") => return Err(format!("Unknown option `{}`!", arg)),
+            _ => options.rom_path = arg,
+        }
+    }
+
+    Ok(options)
+}
+
+// `--host [PORT]` waits for a second player, `--join ADDRESS` connects to one
+fn start_netplay(mode: &NetplayMode, rom: &[u8]) -> Netplay {
+    let rom_hash = fnv1a(rom);
+
+    match mode {
+        NetplayMode::Host(port) => {
+            let listener = TcpListener::bind(("0.0.0.0", *port))
+                .expect("Unable to listen for netplay connection!");
+
+            let seed = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos() as u64)
+                .unwrap_or(0);
+
+            Netplay::host(&listener, rom_hash, seed, Netplay::DEFAULT_INPUT_DELAY)
+                .expect("Unable to start netplay session!")
+        },
+        NetplayMode::Join(address) => {
+            Netplay::join(address.as_str(), rom_hash)
+                .expect("Unable to join netplay session!")
+        },
+    }
+}
+
+struct TraceOptions {
+    rom_path: String,
+    start: Option<u16>,
+    instructions: Option<usize>,
+    output_path: Option<String>,
+    symbols_path: Option<String>,
+}
+
+// nestify trace <ROM> [--start C000] [--instructions N] [--output FILE] [--symbols FILE.nl]
+fn parse_trace_options(args: &[String]) -> Result<TraceOptions, String> {
+    let mut args = args.iter();
+    let mut options = TraceOptions {
+        rom_path: args.next().ok_or("Missing ROM to trace!")?.clone(),
+        start: None,
+        instructions: None,
+        output_path: None,
+        symbols_path: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for `{}`!", arg));
+
+        match arg.as_str() {
+            "--start" => {
+                let start = value()?;
+                let start = u16::from_str_radix(start.trim_start_matches('$'), 16)
+                    .map_err(|_| format!("Invalid start address `{}`!", start))?;
+
+                options.start = Some(start);
+            },
+            "--instructions" => {
+                let instructions = value()?;
+                let instructions = instructions
+                    .parse()
+                    .map_err(|_| format!("Invalid amount of instructions `{}`!", instructions))?;
+
+                options.instructions = Some(instructions);
+            },
+            "--output" => options.output_path = Some(value()?.clone()),
+            "--symbols" => options.symbols_path = Some(value()?.clone()),
+            _ => return Err(format!("Unknown trace option `{}`!", arg)),
+        }
+    }
+
+    Ok(options)
+}
+
+// Runs the CPU headless with the disassembler on, until N instructions or a KIL
+fn trace(args: &[String]) -> Result<(), String> {
+    let options = parse_trace_options(args)?;
+    let rom = std::fs::read(&options.rom_path)
+        .map_err(|error| format!("Unable to read `{}`: {}!", options.rom_path, error))?;
+    let cartridge = Cartridge::try_new(rom)
+        .map_err(|error| format!("Unable to load `{}`: {}", options.rom_path, error))?;
+    let output: Box<dyn Write> = match &options.output_path {
+        Some(path) => {
+            let file = File::create(path)
+                .map_err(|error| format!("Unable to create `{}`: {}!", path, error))?;
+
+            Box::new(BufWriter::new(file))
+        },
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+
+    let mut emulator = Emulator::new(&cartridge);
+    let cpu = emulator.cpu();
+
+    if let Some(path) = &options.symbols_path {
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| format!("Unable to read `{}`: {}!", path, error))?;
+        let symbols = SymbolTable::parse_nl(&source)
+            .map_err(|error| format!("Invalid symbols in `{}`: {}!", path, error))?;
+
+        cpu.set_symbols(symbols);
+    }
+
+    cpu.use_disassembler_with(TraceSink::writer(output));
+
+    if let Some(start) = options.start {
+        cpu.set_program_counter(start);
+    }
+
+    for _ in 0..options.instructions.unwrap_or(usize::MAX) {
+        if cpu.is_halted() {
+            break;
+        }
+
+        cpu.fetch();
+
+        if let Some(error) = cpu.take_trace_error() {
+            return trace_write_result(Err(error));
+        }
+    }
+
+    trace_write_result(cpu.flush_trace())
+}
+
+// A reader that quits early, like `nestify trace rom | head`, just ends the trace
+fn trace_write_result(result: io::Result<()>) -> Result<(), String> {
+    match result {
+        Err(error) if error.kind() != io::ErrorKind::BrokenPipe => Err(format!("Unable to write trace: {}!", error)),
+        _ => Ok(()),
+    }
+}
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    if args.first().map(String::as_str) == Some("trace") {
+        return match trace(&args[1..]) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("{}", error);
+                ExitCode::FAILURE
+            },
+        };
+    }
+
+    let options = match parse_options(args.into_iter()) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE;
+        },
+    };
     let mut window = Window::new();
-    let rom = std::fs::read("super_mario.nes").expect("Unable to read `nestest.nes`!");
-    let cartridge = Cartridge::new(rom);
-    let bus = Rc::new(RefCell::new(Bus::new(&cartridge)));
-    let ppu = Rc::new(RefCell::new(Ppu::new(&bus, cartridge.get_mirroring())));
-    let clock = Rc::new(RefCell::new(Clock::new(&ppu, move |ppu| {
-        window.render(ppu);
-        
-        for event in window.event_pump().poll_iter() {
-            match event {
-                Event::Quit { .. } => {
-                    std::process::exit(0);
-                },
-                _ => (),
+    let mut audio = Audio::new(window.context());
+    let rom = std::fs::read(&options.rom_path).expect("Unable to read ROM!");
+    let (mut config, warnings) = Config::load(Path::new(&options.rom_path), &rom, &options.config, &Window::is_key_name)
+        .unwrap_or_else(|error| panic!("Invalid game configuration, {}!", error));
+    warnings.iter().for_each(|warning| eprintln!("Warning: {}", warning));
+    window.set_key_bindings(&config.key_bindings);
+    let mut netplay = options.netplay
+        .as_ref()
+        .map(|mode| start_netplay(mode, &rom));
+    let cartridge = if Cartridge::is_fds_image(&rom) {
+        let bios = std::fs::read(&options.fds_bios_path).expect("Unable to read FDS BIOS!");
+        Cartridge::from_fds(rom, bios)
+    } else {
+        Cartridge::new(rom)
+    };
+    let mut emulator = match &netplay {
+        Some(netplay) => {
+            // Both peers have to start from the RAM the session seed gives
+            config.ram_init = None;
+            Emulator::new_deterministic(&cartridge, netplay.seed())
+        },
+        None => Emulator::new(&cartridge),
+    };
+    emulator.apply_config(&config);
+
+    let mut autosave = cartridge.has_battery().then(|| {
+        let save_file = SaveFile::new(Path::new(&options.rom_path).with_extension("sav"));
+        Autosave::new(save_file, AUTOSAVE_INTERVAL, Instant::now())
+    });
+
+    if let Some(autosave) = autosave.as_mut() {
+        autosave.load(&mut emulator).expect("Unable to read battery save!");
+    }
+
+    let frame_budget = cartridge.get_region().frame_duration();
+    let mut next_frame = Instant::now();
+    let mut over_budget_frames = 0;
+    let mut reported_halt = false;
+    let mut lost_netplay = false;
+
+    while !window.is_quit_requested() {
+        match netplay.as_mut() {
+            Some(netplay) => {
+                emulator.poll_input(&mut netplay.input(&mut window));
+
+                if let Some(error) = netplay.take_error() {
+                    eprintln!("Error: netplay session ended, {}", error);
+                    lost_netplay = true;
+                    break;
+                }
+            },
+            None => emulator.poll_input(&mut window),
+        }
+
+        let frame_start = Instant::now();
+
+        if !emulator.advance() {
+            emulator.present(&mut window, &mut audio);
+            continue;
+        }
+
+        if emulator.get_run_ahead() > 0 {
+            over_budget_frames = if frame_start.elapsed() > frame_budget { over_budget_frames + 1 } else { 0 };
+
+            if over_budget_frames >= OVER_BUDGET_FRAMES {
+                eprintln!("Warning: run-ahead is too slow on this machine, disabling it");
+                emulator.set_run_ahead(0);
             }
         }
-    })));
 
-    let mut cpu = Cpu::new(&bus, &clock);
+        if let Some(netplay) = netplay.as_mut() {
+            netplay
+                .check_frame(emulator.frame().hash())
+                .expect("Netplay session desynced!");
+        }
 
-    // cpu.use_disassembler(true);
-    // cpu.set_program_counter(0xC000);
-    cpu.reset();
+        if !reported_halt && emulator.cpu().is_halted() {
+            eprintln!("Error: the game executed a KIL opcode, the CPU is halted");
+            reported_halt = true;
+        }
 
-    loop {
-        cpu.fetch();
+        if let Some(autosave) = autosave.as_mut() {
+            if let Err(error) = autosave.update(&mut emulator, Instant::now()) {
+                eprintln!("Warning: unable to write battery save, {}", error);
+            }
+        }
+
+        emulator.present(&mut window, &mut audio);
+
+        // Vsync alone runs PAL games at the display's rate, so frames are paced to the console's too
+        next_frame += frame_budget;
+
+        match next_frame.checked_duration_since(Instant::now()) {
+            Some(wait) => std::thread::sleep(wait),
+            None => next_frame = Instant::now(),
+        }
+    }
+
+    audio.stop();
+
+    if let Some(autosave) = autosave.as_mut() {
+        if let Err(error) = autosave.flush(&mut emulator) {
+            eprintln!("Unable to write battery save, {}!", error);
+            return ExitCode::FAILURE;
+        }
     }
+
+    if lost_netplay {
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,15 @@
+#![cfg(feature = "test-roms")]
+
+mod common;
+
+use common::assert_blargg_passes;
+
+#[test]
+fn test_cpu_instrs() {
+    assert_blargg_passes("cpu_instrs/cpu_instrs.nes");
+}
+
+#[test]
+fn test_instr_timing() {
+    assert_blargg_passes("instr_timing/instr_timing.nes");
+}
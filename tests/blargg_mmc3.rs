@@ -0,0 +1,42 @@
+#![cfg(feature = "test-roms")]
+
+mod common;
+
+use common::assert_blargg_passes;
+
+// TODO: remove the ignores once mapper 4 and the PPU A12 notifications are in place
+#[test]
+#[ignore = "MMC3 (mapper 4) is not implemented yet"]
+fn test_mmc3_clocking() {
+    assert_blargg_passes("mmc3_test/1-clocking.nes");
+}
+
+#[test]
+#[ignore = "MMC3 (mapper 4) is not implemented yet"]
+fn test_mmc3_details() {
+    assert_blargg_passes("mmc3_test/2-details.nes");
+}
+
+#[test]
+#[ignore = "MMC3 (mapper 4) is not implemented yet"]
+fn test_mmc3_a12_clocking() {
+    assert_blargg_passes("mmc3_test/3-A12_clocking.nes");
+}
+
+#[test]
+#[ignore = "MMC3 (mapper 4) is not implemented yet"]
+fn test_mmc3_scanline_timing() {
+    assert_blargg_passes("mmc3_test/4-scanline_timing.nes");
+}
+
+#[test]
+#[ignore = "MMC3 (mapper 4) is not implemented yet"]
+fn test_mmc3() {
+    assert_blargg_passes("mmc3_test/5-MMC3.nes");
+}
+
+#[test]
+#[ignore = "MMC3 (mapper 4) is not implemented yet"]
+fn test_mmc3_alt() {
+    assert_blargg_passes("mmc3_test/6-MMC3_alt.nes");
+}
@@ -0,0 +1,55 @@
+#![cfg(feature = "test-roms")]
+
+mod common;
+
+use common::assert_blargg_passes;
+
+#[test]
+fn test_vbl_basics() {
+    assert_blargg_passes("ppu_vbl_nmi/rom_singles/01-vbl_basics.nes");
+}
+
+#[test]
+fn test_vbl_set_time() {
+    assert_blargg_passes("ppu_vbl_nmi/rom_singles/02-vbl_set_time.nes");
+}
+
+#[test]
+fn test_vbl_clear_time() {
+    assert_blargg_passes("ppu_vbl_nmi/rom_singles/03-vbl_clear_time.nes");
+}
+
+#[test]
+fn test_nmi_control() {
+    assert_blargg_passes("ppu_vbl_nmi/rom_singles/04-nmi_control.nes");
+}
+
+#[test]
+fn test_nmi_timing() {
+    assert_blargg_passes("ppu_vbl_nmi/rom_singles/05-nmi_timing.nes");
+}
+
+#[test]
+fn test_suppression() {
+    assert_blargg_passes("ppu_vbl_nmi/rom_singles/06-suppression.nes");
+}
+
+#[test]
+fn test_nmi_on_timing() {
+    assert_blargg_passes("ppu_vbl_nmi/rom_singles/07-nmi_on_timing.nes");
+}
+
+#[test]
+fn test_nmi_off_timing() {
+    assert_blargg_passes("ppu_vbl_nmi/rom_singles/08-nmi_off_timing.nes");
+}
+
+#[test]
+fn test_even_odd_frames() {
+    assert_blargg_passes("ppu_vbl_nmi/rom_singles/09-even_odd_frames.nes");
+}
+
+#[test]
+fn test_even_odd_timing() {
+    assert_blargg_passes("ppu_vbl_nmi/rom_singles/10-even_odd_timing.nes");
+}
@@ -0,0 +1,33 @@
+#![cfg(feature = "test-roms")]
+
+mod common;
+
+use common::assert_blargg_suite_passes;
+
+#[test]
+fn test_sprite_hit() {
+    assert_blargg_suite_passes("sprite_hit_tests", &[
+        "01.basics.nes",
+        "02.alignment.nes",
+        "03.corners.nes",
+        "04.flip.nes",
+        "05.left_clip.nes",
+        "06.right_edge.nes",
+        "07.screen_bottom.nes",
+        "08.double_height.nes",
+        "09.timing_basics.nes",
+        "10.timing_order.nes",
+        "11.edge_timing.nes",
+    ]);
+}
+
+#[test]
+fn test_sprite_overflow() {
+    assert_blargg_suite_passes("sprite_overflow_tests", &[
+        "1.Basics.nes",
+        "2.Details.nes",
+        "3.Timing.nes",
+        "4.Obscure.nes",
+        "5.Emulator.nes",
+    ]);
+}
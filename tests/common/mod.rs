@@ -0,0 +1,249 @@
+// Each test crate uses only part of these helpers
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use nestify::core::cartridge::Cartridge;
+use nestify::core::emulator::Emulator;
+use nestify::core::memory::Memory;
+
+const INSTRUCTIONS_PER_CHECK: usize = 10_000;
+const MAX_INSTRUCTIONS: usize = 500_000_000;
+// Roughly 100 msec of CPU time, test ROMs ask to wait at least that long before reset
+const RESET_DELAY_INSTRUCTIONS: usize = 100_000;
+
+const STATUS_RUNNING: u8 = 0x80;
+const STATUS_NEEDS_RESET: u8 = 0x81;
+
+/// 64KB of plain RAM for `Cpu::set_flat_memory`, clones share the bytes so tests
+/// can still reach them after handing one to the CPU.
+#[derive(Clone)]
+pub struct FlatMemory {
+    pub bytes: Rc<RefCell<Vec<u8>>>,
+}
+
+impl FlatMemory {
+    pub fn new() -> Self {
+        Self {
+            bytes: Rc::new(RefCell::new(vec![0x00; 0x10000])),
+        }
+    }
+
+    pub fn load(&self, address: u16, data: &[u8]) {
+        self.bytes.borrow_mut()[address as usize..address as usize + data.len()].copy_from_slice(data);
+    }
+}
+
+impl Memory for FlatMemory {
+    fn read(&self, address: u16) -> u8 {
+        self.bytes.borrow()[address as usize]
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.bytes.borrow_mut()[address as usize] = data;
+    }
+}
+
+pub struct TestRomResult {
+    pub status: u8,
+    pub output: String,
+}
+
+pub fn test_rom_path(relative_path: &str) -> Option<PathBuf> {
+    env::var("NESTIFY_TEST_ROMS")
+        .ok()
+        .map(|directory| PathBuf::from(directory).join(relative_path))
+}
+
+fn read(emulator: &Emulator, address: u16) -> u8 {
+    emulator.bus()
+        .borrow_mut()
+        .cpu_memory_map()
+        .read(address)
+}
+
+fn has_signature(emulator: &Emulator) -> bool {
+    [0xDE, 0xB0, 0x61]
+        .iter()
+        .enumerate()
+        .all(|(offset, byte)| read(emulator, 0x6001 + offset as u16) == *byte)
+}
+
+fn read_output(emulator: &Emulator) -> String {
+    (0x6004..0x8000)
+        .map(|address| read(emulator, address))
+        .take_while(|byte| *byte != 0x00)
+        .map(char::from)
+        .collect()
+}
+
+fn run_instructions(emulator: &mut Emulator, count: usize) {
+    for _ in 0..count {
+        emulator.cpu().fetch();
+    }
+}
+
+/// Runs a ROM which reports through blargg's protocol: status at $6000,
+/// signature $DE $B0 $61 at $6001 and zero-terminated text from $6004.
+pub fn run_blargg_rom(path: &Path) -> TestRomResult {
+    let rom = fs::read(path)
+        .unwrap_or_else(|_| panic!("Unable to read test ROM {}!", path.display()));
+
+    let cartridge = Cartridge::new(rom);
+    let mut emulator = Emulator::new(&cartridge);
+    let mut executed = 0;
+
+    while executed < MAX_INSTRUCTIONS {
+        run_instructions(&mut emulator, INSTRUCTIONS_PER_CHECK);
+        executed += INSTRUCTIONS_PER_CHECK;
+
+        if !has_signature(&emulator) {
+            continue;
+        }
+
+        match read(&emulator, 0x6000) {
+            STATUS_RUNNING => (),
+            STATUS_NEEDS_RESET => {
+                run_instructions(&mut emulator, RESET_DELAY_INSTRUCTIONS);
+                executed += RESET_DELAY_INSTRUCTIONS;
+
+                emulator.reset();
+            },
+            status => {
+                return TestRomResult {
+                    status,
+                    output: read_output(&emulator),
+                };
+            },
+        }
+    }
+
+    panic!("Test ROM {} didn't finish in time, output: {}", path.display(), read_output(&emulator));
+}
+
+fn check_blargg_rom(relative_path: &str, path: &Path) -> Result<(), String> {
+    let result = run_blargg_rom(path);
+
+    if result.status == 0x00 {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} failed with code {:#04X}: {}",
+            relative_path, result.status, result.output.trim()
+        ))
+    }
+}
+
+/// Skips the test when NESTIFY_TEST_ROMS isn't set.
+pub fn assert_blargg_passes(relative_path: &str) {
+    let Some(path) = test_rom_path(relative_path) else {
+        eprintln!("Skipping {}, NESTIFY_TEST_ROMS is not set", relative_path);
+        return;
+    };
+
+    if let Err(failure) = check_blargg_rom(relative_path, &path) {
+        panic!("{}", failure);
+    }
+}
+
+/// Runs every ROM of a suite and reports all failures at once.
+pub fn assert_blargg_suite_passes(directory: &str, roms: &[&str]) {
+    let failures = roms
+        .iter()
+        .map(|rom| format!("{}/{}", directory, rom))
+        .filter_map(|relative_path| {
+            let Some(path) = test_rom_path(&relative_path) else {
+                eprintln!("Skipping {}, NESTIFY_TEST_ROMS is not set", relative_path);
+                return None;
+            };
+
+            check_blargg_rom(&relative_path, &path).err()
+        })
+        .collect::<Vec<_>>();
+
+    assert!(failures.is_empty(), "{} of {} ROMs failed:\n{}", failures.len(), roms.len(), failures.join("\n"));
+}
+
+fn committed_rom_path(file_name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("roms")
+        .join(file_name)
+}
+
+fn parse_golden_hashes(golden: &str) -> BTreeMap<usize, u64> {
+    golden
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (frame, hash) = line
+                .split_once(' ')
+                .unwrap_or_else(|| panic!("Invalid golden hash line {:?}!", line));
+
+            let frame = frame.parse().expect("Invalid frame number in golden hashes!");
+            let hash = u64::from_str_radix(hash.trim_start_matches("0x"), 16)
+                .expect("Invalid hash in golden hashes!");
+
+            (frame, hash)
+        })
+        .collect()
+}
+
+/// Runs a ROM from tests/roms and compares palette-index frame hashes with
+/// tests/roms/<rom>.hashes. Run with UPDATE_GOLDEN=1 to regenerate them.
+pub fn assert_frame_hashes(rom_name: &str, frames: &[usize]) {
+    let rom = fs::read(committed_rom_path(&format!("{}.nes", rom_name)))
+        .unwrap_or_else(|_| panic!("Unable to read ROM {}!", rom_name));
+
+    let cartridge = Cartridge::new(rom);
+    let mut emulator = Emulator::new(&cartridge);
+    let last_frame = frames.iter().max().copied().unwrap_or(0);
+    let mut hashes = BTreeMap::new();
+
+    for frame in 1..=last_frame {
+        emulator.run_frame();
+
+        if frames.contains(&frame) {
+            hashes.insert(frame, emulator.frame().hash());
+        }
+    }
+
+    let golden_path = committed_rom_path(&format!("{}.hashes", rom_name));
+
+    if env::var("UPDATE_GOLDEN").as_deref() == Ok("1") {
+        let golden = hashes
+            .iter()
+            .map(|(frame, hash)| format!("{} {:#018X}\n", frame, hash))
+            .collect::<String>();
+
+        fs::write(&golden_path, format!("# frame hash\n{}", golden))
+            .expect("Unable to write golden hashes!");
+
+        return;
+    }
+
+    let golden = fs::read_to_string(&golden_path)
+        .unwrap_or_else(|_| panic!("Unable to read {}, run with UPDATE_GOLDEN=1 to create it!", golden_path.display()));
+
+    let expected = parse_golden_hashes(&golden);
+    let mismatches = hashes
+        .iter()
+        .filter(|(frame, hash)| expected.get(frame) != Some(hash))
+        .map(|(frame, hash)| match expected.get(frame) {
+            Some(expected) => format!("frame {}: expected {:#018X}, got {:#018X}", frame, expected, hash),
+            None => format!("frame {}: no golden hash, got {:#018X}", frame, hash),
+        })
+        .collect::<Vec<_>>();
+
+    assert!(
+        mismatches.is_empty(),
+        "{} renders different frames, rerun with UPDATE_GOLDEN=1 if that's intended:\n{}",
+        rom_name, mismatches.join("\n")
+    );
+}
@@ -0,0 +1,67 @@
+use std::fs;
+
+use nestify::core::cartridge::Cartridge;
+use nestify::core::emulator::Emulator;
+use nestify::core::frontend::InputState;
+use nestify::core::joypad::JoypadButton;
+
+const FRAMES: usize = 600;
+const SEED: u64 = 0x5EED_1234;
+
+struct RunSummary {
+    frame_hashes: Vec<u64>,
+    cpu_cycles: usize,
+    ppu_position: (isize, usize),
+}
+
+fn scripted_input(frame: usize) -> InputState {
+    let mut input = InputState::default();
+
+    input.set_button(0, JoypadButton::Start, frame % 120 == 60);
+    input.set_button(0, JoypadButton::A, frame % 7 < 3);
+    input.set_button(0, JoypadButton::Right, (frame / 50).is_multiple_of(2));
+    input.set_button(1, JoypadButton::B, frame.is_multiple_of(11));
+
+    input
+}
+
+fn run(rom: &[u8]) -> RunSummary {
+    let cartridge = Cartridge::new(rom.to_vec());
+    let mut emulator = Emulator::new_deterministic(&cartridge, SEED);
+
+    let frame_hashes = (0..FRAMES)
+        .map(|frame| {
+            emulator.set_input(scripted_input(frame));
+            emulator.run_frame();
+            emulator.frame().hash()
+        })
+        .collect();
+
+    let cpu_cycles = emulator.clock().borrow().get_cycles();
+    let ppu_position = {
+        let ppu = emulator.ppu().borrow();
+        (ppu.get_scanline(), ppu.get_cycles())
+    };
+
+    RunSummary {
+        frame_hashes,
+        cpu_cycles,
+        ppu_position,
+    }
+}
+
+#[test]
+fn test_independent_runs_are_identical() {
+    let rom = fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/roms/scroll_demo.nes"))
+        .expect("Unable to read scroll demo ROM!");
+
+    let first = run(&rom);
+    let second = run(&rom);
+
+    for (frame, (a, b)) in first.frame_hashes.iter().zip(second.frame_hashes.iter()).enumerate() {
+        assert_eq!(a, b, "Frame {} should be identical in both runs!", frame + 1);
+    }
+
+    assert_eq!(first.cpu_cycles, second.cpu_cycles, "CPU cycle counters should be identical!");
+    assert_eq!(first.ppu_position, second.ppu_position, "PPU scanline and dot should be identical!");
+}
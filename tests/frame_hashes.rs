@@ -0,0 +1,8 @@
+mod common;
+
+use common::assert_frame_hashes;
+
+#[test]
+fn test_scroll_demo_frames() {
+    assert_frame_hashes("scroll_demo", &[1, 2, 30, 60, 120]);
+}
@@ -0,0 +1,62 @@
+use std::fs;
+
+use nestify::core::cartridge::Cartridge;
+use nestify::core::emulator::Emulator;
+use nestify::core::frontend::InputState;
+use nestify::core::joypad::JoypadButton;
+use nestify::core::memory::Memory;
+
+// RAM layout of tests/roms/start_game.nes
+const GAME_STATE: u16 = 0x0300;
+const FRAME_COUNTER: u16 = 0x0301;
+const PLAYER_X: u16 = 0x0302;
+
+const STATE_TITLE: u8 = 0x00;
+const STATE_PLAYING: u8 = 0x01;
+
+fn read_ram(emulator: &Emulator, address: u16) -> u8 {
+    emulator.bus()
+        .borrow_mut()
+        .cpu_memory_map()
+        .read(address)
+}
+
+fn run_frames(emulator: &mut Emulator, frames: usize, input: InputState) {
+    emulator.set_input(input);
+
+    for _ in 0..frames {
+        emulator.run_frame();
+    }
+}
+
+fn press(buttons: &[JoypadButton]) -> InputState {
+    let mut input = InputState::default();
+    buttons
+        .iter()
+        .for_each(|button| input.set_button(0, *button, true));
+
+    input
+}
+
+#[test]
+fn test_start_game_leaves_title_screen() {
+    let rom = fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/roms/start_game.nes"))
+        .expect("Unable to read homebrew game ROM!");
+
+    let cartridge = Cartridge::new(rom);
+    let mut emulator = Emulator::new(&cartridge);
+
+    run_frames(&mut emulator, 120, InputState::default());
+
+    assert_eq!(read_ram(&emulator, GAME_STATE), STATE_TITLE, "Game should wait on the title screen!");
+    assert_eq!(read_ram(&emulator, FRAME_COUNTER), 118, "NMI handler should run once per frame!");
+    assert_eq!(emulator.frame().hash(), 0x691CEC183B03891D, "Title screen should render the recorded frame!");
+
+    run_frames(&mut emulator, 5, press(&[JoypadButton::Start]));
+    run_frames(&mut emulator, 60, press(&[JoypadButton::Right]));
+    run_frames(&mut emulator, 235, InputState::default());
+
+    assert_eq!(read_ram(&emulator, GAME_STATE), STATE_PLAYING, "Pressing Start should leave the title screen!");
+    assert_eq!(read_ram(&emulator, PLAYER_X), 180, "Holding Right for 60 frames should move the player by 60 pixels!");
+    assert_eq!(emulator.frame().hash(), 0x15D9D1AC462EDC7D, "Game screen should render the recorded frame!");
+}
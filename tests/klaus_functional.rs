@@ -0,0 +1,54 @@
+#![cfg(feature = "flat-memory")]
+
+mod common;
+
+use std::env;
+use std::fs;
+
+use common::FlatMemory;
+use nestify::core::cartridge::Cartridge;
+use nestify::core::emulator::Emulator;
+
+// Path to `6502_functional_test.bin` of Klaus Dormann's 6502 tests, assembled with default options
+const BINARY_VARIABLE: &str = "NESTIFY_KLAUS_FUNCTIONAL_TEST";
+const CODE_START: u16 = 0x0400;
+// Every check traps in a `JMP *`, this one is reached when all of them passed
+const SUCCESS_ADDRESS: u16 = 0x3469;
+const MAX_INSTRUCTIONS: usize = 100_000_000;
+
+/// Ignored as it takes minutes in debug builds, and skipped when NESTIFY_KLAUS_FUNCTIONAL_TEST isn't set.
+#[test]
+#[ignore]
+fn test_klaus_functional_test() {
+    let Ok(path) = env::var(BINARY_VARIABLE) else {
+        eprintln!("Skipping Klaus Dormann's functional test, {} is not set", BINARY_VARIABLE);
+        return;
+    };
+
+    let binary = fs::read(&path).unwrap_or_else(|_| panic!("Unable to read {}!", path));
+    let memory = FlatMemory::new();
+    memory.load(0x0000, &binary);
+
+    let mut emulator = Emulator::new(&Cartridge::empty());
+    let cpu = emulator.cpu();
+
+    cpu.set_flat_memory(Box::new(memory));
+    cpu.set_bcd_enabled(true);
+    cpu.reset_to(CODE_START);
+
+    for _ in 0..MAX_INSTRUCTIONS {
+        let program_counter = cpu.get_program_counter();
+        cpu.step();
+
+        if cpu.get_program_counter() == program_counter {
+            assert_eq!(
+                program_counter, SUCCESS_ADDRESS,
+                "Trapped at ${:04X}, look it up in the test's listing! A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+                program_counter, cpu.get_a(), cpu.get_x(), cpu.get_y(), cpu.get_status_byte(), cpu.get_sp()
+            );
+            return;
+        }
+    }
+
+    panic!("Functional test didn't trap within {} instructions!", MAX_INSTRUCTIONS);
+}
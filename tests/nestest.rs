@@ -0,0 +1,51 @@
+#![cfg(feature = "test-roms")]
+
+mod common;
+
+use std::cell::RefCell;
+use std::fs;
+use std::rc::Rc;
+
+use common::test_rom_path;
+use nestify::core::cartridge::Cartridge;
+use nestify::core::emulator::Emulator;
+use nestify::core::trace::TraceSink;
+
+// nestest's automated mode starts at $C000 instead of the reset vector
+const AUTOMATION_START: u16 = 0xC000;
+
+#[test]
+fn test_nestest_matches_golden_log() {
+    let (Some(rom_path), Some(log_path)) = (test_rom_path("nestest/nestest.nes"), test_rom_path("nestest/nestest.log")) else {
+        eprintln!("Skipping nestest, NESTIFY_TEST_ROMS is not set");
+        return;
+    };
+
+    let rom = fs::read(&rom_path).expect("Unable to read nestest ROM!");
+    let golden = fs::read_to_string(&log_path).expect("Unable to read nestest log!");
+    let golden = golden.lines().collect::<Vec<_>>();
+
+    let lines = Rc::new(RefCell::new(Vec::<String>::new()));
+    let mut emulator = Emulator::new(&Cartridge::new(rom));
+
+    // The emulator is already reset, only the entry point differs
+    let cpu = emulator.cpu();
+    cpu.set_program_counter(AUTOMATION_START);
+
+    {
+        let lines = lines.clone();
+        cpu.use_disassembler_with(TraceSink::callback(move |line| lines.borrow_mut().push(line.to_string())));
+    }
+
+    while lines.borrow().len() < golden.len() && !cpu.is_halted() {
+        cpu.fetch();
+    }
+
+    let lines = lines.borrow();
+
+    for (number, (line, expected)) in lines.iter().zip(golden.iter()).enumerate() {
+        assert_eq!(line, expected.trim_end(), "Trace differs from nestest.log on line {}!", number + 1);
+    }
+
+    assert_eq!(lines.len(), golden.len(), "CPU halted before the end of nestest.log!");
+}
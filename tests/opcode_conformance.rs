@@ -0,0 +1,273 @@
+#![cfg(feature = "flat-memory")]
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use common::FlatMemory;
+use nestify::core::cartridge::Cartridge;
+use nestify::core::cpu::{CpuState, StepResult};
+use nestify::core::emulator::Emulator;
+
+// Directory with the `nes6502` vectors of SingleStepTests, one `<opcode>.json` per opcode
+const FIXTURES_VARIABLE: &str = "NESTIFY_SINGLE_STEP_TESTS";
+const REPORTED_FAILURES: usize = 20;
+
+// KIL locks up the bus, the vectors record whatever the jammed chip kept doing
+const JAM_OPCODES: [u8; 12] = [0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2];
+
+#[derive(Debug)]
+enum Json {
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> &Json {
+        match self {
+            Json::Object(entries) => entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value)
+                .unwrap_or_else(|| panic!("Missing `{}` in test vector!", key)),
+            _ => panic!("Expected an object around `{}`!", key),
+        }
+    }
+
+    fn as_number(&self) -> i64 {
+        match self {
+            Json::Number(number) => *number,
+            _ => panic!("Expected a number, found {:?}!", self),
+        }
+    }
+
+    fn as_array(&self) -> &[Json] {
+        match self {
+            Json::Array(items) => items,
+            _ => panic!("Expected an array, found {:?}!", self),
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            Json::String(string) => string,
+            _ => panic!("Expected a string, found {:?}!", self),
+        }
+    }
+}
+
+// Just enough JSON for the test vectors: objects, arrays, strings and integers
+struct JsonParser<'a> {
+    source: &'a [u8],
+    position: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn parse(source: &'a str) -> Json {
+        let mut parser = Self { source: source.as_bytes(), position: 0 };
+        parser.parse_value()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.position < self.source.len() && self.source[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> u8 {
+        self.skip_whitespace();
+        *self.source.get(self.position).expect("Unexpected end of JSON!")
+    }
+
+    fn expect(&mut self, byte: u8) {
+        assert_eq!(self.peek(), byte, "Unexpected JSON at byte {}!", self.position);
+        self.position += 1;
+    }
+
+    fn parse_value(&mut self) -> Json {
+        match self.peek() {
+            b'{' => {
+                let mut entries = Vec::new();
+                self.expect(b'{');
+
+                while self.peek() != b'}' {
+                    let key = self.parse_string();
+                    self.expect(b':');
+                    entries.push((key, self.parse_value()));
+
+                    if self.peek() == b',' {
+                        self.position += 1;
+                    }
+                }
+
+                self.expect(b'}');
+                Json::Object(entries)
+            },
+            b'[' => {
+                let mut items = Vec::new();
+                self.expect(b'[');
+
+                while self.peek() != b']' {
+                    items.push(self.parse_value());
+
+                    if self.peek() == b',' {
+                        self.position += 1;
+                    }
+                }
+
+                self.expect(b']');
+                Json::Array(items)
+            },
+            b'"' => Json::String(self.parse_string()),
+            _ => {
+                let start = self.position;
+
+                while self.position < self.source.len() && matches!(self.source[self.position], b'-' | b'0'..=b'9') {
+                    self.position += 1;
+                }
+
+                let number = std::str::from_utf8(&self.source[start..self.position]).unwrap();
+                Json::Number(number.parse().unwrap_or_else(|_| panic!("Invalid JSON number at byte {}!", start)))
+            },
+        }
+    }
+
+    fn parse_string(&mut self) -> String {
+        let mut string = String::new();
+        self.expect(b'"');
+
+        loop {
+            match self.source[self.position] {
+                b'"' => break,
+                b'\\' => {
+                    self.position += 1;
+                    string.push(self.source[self.position] as char);
+                },
+                byte => string.push(byte as char),
+            }
+
+            self.position += 1;
+        }
+
+        self.position += 1;
+        string
+    }
+}
+
+fn read_state(state: &Json) -> CpuState {
+    CpuState {
+        a: state.get("a").as_number() as u8,
+        x: state.get("x").as_number() as u8,
+        y: state.get("y").as_number() as u8,
+        p: state.get("p").as_number() as u8,
+        sp: state.get("s").as_number() as u8,
+        pc: state.get("pc").as_number() as u16,
+        cycles: 0,
+        halted: false,
+        nmi_pending: false,
+        irq_requested: false,
+        delayed_interrupt_disable: None,
+    }
+}
+
+fn ram_cells(state: &Json) -> impl Iterator<Item = (u16, u8)> + '_ {
+    state.get("ram")
+        .as_array()
+        .iter()
+        .map(|cell| {
+            let cell = cell.as_array();
+            (cell[0].as_number() as u16, cell[1].as_number() as u8)
+        })
+}
+
+fn run_vector(vector: &Json) -> Result<(), String> {
+    let memory = FlatMemory::new();
+    let mut emulator = Emulator::new(&Cartridge::empty());
+    let cpu = emulator.cpu();
+    let initial = vector.get("initial");
+    let expected = vector.get("final");
+
+    cpu.set_flat_memory(Box::new(memory.clone()));
+    cpu.load_state(read_state(initial));
+
+    for (address, value) in ram_cells(initial) {
+        memory.bytes.borrow_mut()[address as usize] = value;
+    }
+
+    let cycles = match cpu.step() {
+        StepResult::Executed(cycles) => cycles,
+        result => return Err(format!("step returned {:?}", result)),
+    };
+
+    let state = cpu.state();
+    let expected_state = CpuState {
+        cycles: state.cycles,
+        delayed_interrupt_disable: state.delayed_interrupt_disable,
+        ..read_state(expected)
+    };
+
+    if state != expected_state {
+        return Err(format!("registers {:?}, expected {:?}", state, expected_state));
+    }
+
+    for (address, value) in ram_cells(expected) {
+        let actual = memory.bytes.borrow()[address as usize];
+
+        if actual != value {
+            return Err(format!("${:04X} is {:02X}, expected {:02X}", address, actual, value));
+        }
+    }
+
+    let expected_cycles = vector.get("cycles").as_array().len();
+
+    if cycles != expected_cycles {
+        return Err(format!("took {} cycles, expected {}", cycles, expected_cycles));
+    }
+
+    Ok(())
+}
+
+fn run_opcode_vectors(path: &Path, failures: &mut Vec<String>) -> usize {
+    let source = fs::read_to_string(path)
+        .unwrap_or_else(|_| panic!("Unable to read {}!", path.display()));
+    let vectors = JsonParser::parse(&source);
+
+    for vector in vectors.as_array() {
+        if let Err(failure) = run_vector(vector) {
+            failures.push(format!("{}: {}", vector.get("name").as_str(), failure));
+        }
+    }
+
+    vectors.as_array().len()
+}
+
+/// Skips the test when NESTIFY_SINGLE_STEP_TESTS isn't set.
+#[test]
+fn test_opcodes_match_single_step_tests() {
+    let Ok(directory) = env::var(FIXTURES_VARIABLE) else {
+        eprintln!("Skipping opcode conformance, {} is not set", FIXTURES_VARIABLE);
+        return;
+    };
+
+    let mut failures = Vec::new();
+    let mut vectors = 0;
+
+    for opcode in (0x00..=0xFF).filter(|opcode| !JAM_OPCODES.contains(opcode)) {
+        let path = Path::new(&directory).join(format!("{:02x}.json", opcode));
+
+        if path.exists() {
+            vectors += run_opcode_vectors(&path, &mut failures);
+        }
+    }
+
+    assert!(vectors > 0, "No test vectors were found in {}!", directory);
+    assert!(
+        failures.is_empty(),
+        "{} of {} vectors failed, first ones:\n{}",
+        failures.len(), vectors, failures.iter().take(REPORTED_FAILURES).cloned().collect::<Vec<_>>().join("\n")
+    );
+}
@@ -0,0 +1,96 @@
+// The binary needs SDL, even though trace mode never opens a window
+#![cfg(feature = "sdl")]
+
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+
+const ROM_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/roms/start_game.nes");
+
+fn nestify(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_nestify"))
+        .args(args)
+        .output()
+        .expect("Unable to run nestify!")
+}
+
+#[test]
+fn test_trace_runs_requested_instructions() {
+    let output = nestify(&["trace", ROM_PATH, "--instructions", "100"]);
+    let log = String::from_utf8(output.stdout).expect("Trace should be UTF-8!");
+    let lines = log.lines().collect::<Vec<_>>();
+
+    assert!(output.status.success(), "Trace should exit cleanly!");
+    assert_eq!(lines.len(), 100, "Trace should log one line per instruction!");
+    assert!(lines[0].starts_with("8000  78        SEI"), "Trace should start at the reset vector!");
+    assert!(lines[0].ends_with("A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7"), "Trace should log registers and cycles!");
+}
+
+#[test]
+fn test_trace_start_override_and_output_file() {
+    let path = std::env::temp_dir().join(format!("nestify_trace_{}.log", std::process::id()));
+    let output = nestify(&[
+        "trace", ROM_PATH,
+        "--start", "8005",
+        "--instructions", "3",
+        "--output", path.to_str().unwrap(),
+    ]);
+    let log = std::fs::read_to_string(&path).expect("Trace should be written to the output file!");
+    std::fs::remove_file(&path).ok();
+
+    assert!(output.status.success(), "Trace should exit cleanly!");
+    assert!(output.stdout.is_empty(), "Trace written to a file should keep stdout empty!");
+    assert_eq!(log.lines().count(), 3, "Trace should stop after 3 instructions!");
+    assert!(log.starts_with("8005  A9 00"), "Trace should start at the overridden address!");
+}
+
+#[test]
+fn test_trace_fails_on_missing_rom() {
+    let output = nestify(&["trace", "missing.nes"]);
+
+    assert!(!output.status.success(), "Trace without a readable ROM should fail!");
+}
+
+#[test]
+fn test_out_of_range_overclock_is_reported() {
+    let output = nestify(&[ROM_PATH, "--overclock", "101"]);
+    let stderr = String::from_utf8(output.stderr).expect("Error should be UTF-8!");
+
+    assert_eq!(output.status.code(), Some(1), "Invalid option should exit with an error code!");
+    assert!(stderr.contains("Invalid `--overclock`"), "Error should name the invalid option!");
+}
+
+#[test]
+fn test_trace_fails_on_unsupported_mapper() {
+    let mut rom = std::fs::read(ROM_PATH).expect("Unable to read test ROM!");
+    // Mapper 4 in the lower nybble of flags 6
+    rom[6] = (rom[6] & 0x0F) | 0x40;
+
+    let path = std::env::temp_dir().join(format!("nestify_mapper_{}.nes", std::process::id()));
+    std::fs::write(&path, rom).expect("Unable to write test ROM!");
+    let output = nestify(&["trace", path.to_str().unwrap(), "--instructions", "1"]);
+    std::fs::remove_file(&path).ok();
+    let stderr = String::from_utf8(output.stderr).expect("Error should be UTF-8!");
+
+    assert_eq!(output.status.code(), Some(1), "Unsupported mapper should exit with an error code!");
+    assert!(stderr.contains("Unsupported mapper 4"), "Error should name the mapper!");
+}
+
+#[test]
+fn test_trace_stops_when_reader_quits() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_nestify"))
+        .args(["trace", ROM_PATH])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Unable to run nestify!");
+
+    // Like `| head`, read a little and close the pipe
+    let mut stdout = child.stdout.take().unwrap();
+    stdout.read_exact(&mut [0; 64]).expect("Trace should reach stdout!");
+    drop(stdout);
+
+    let output = child.wait_with_output().expect("Unable to wait for nestify!");
+
+    assert!(output.status.success(), "Closed pipe should end the trace cleanly!");
+    assert!(output.stderr.is_empty(), "Closed pipe should not be reported!");
+}